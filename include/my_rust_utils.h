@@ -0,0 +1,47 @@
+/*
+    C declarations of the Kd-Tree functions of my_rust_utils, built with the `ffi` feature.
+
+    Trees are opaque handles created by kdtreeN_new and released by kdtreeN_free.
+    Points are identified by their index in the buffer given at creation.
+    Null pointers and internal errors give a null handle, false or 0.
+*/
+
+#ifndef MY_RUST_UTILS_H
+#define MY_RUST_UTILS_H
+
+#include <stdbool.h>
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct KdTree2Handle KdTree2Handle;
+typedef struct KdTree3Handle KdTree3Handle;
+
+/* Builds a tree from len points of interleaved coordinates (x0, y0, x1, y1, ...),
+   null if a coordinate is NaN or infinite or if the buffer can't be that large. */
+KdTree2Handle *kdtree2_new(const double *points, size_t len);
+
+/* Writes the index of the nearest point into out_index, false if the tree is empty. */
+bool kdtree2_nearest(const KdTree2Handle *handle, double x, double y, size_t *out_index);
+
+/* Writes at most cap indices of the points within radius into out_indices,
+   returns the total number of points found. */
+size_t kdtree2_within_radius(const KdTree2Handle *handle, double x, double y, double radius, size_t *out_indices, size_t cap);
+
+void kdtree2_free(KdTree2Handle *handle);
+
+KdTree3Handle *kdtree3_new(const double *points, size_t len);
+
+bool kdtree3_nearest(const KdTree3Handle *handle, double x, double y, double z, size_t *out_index);
+
+size_t kdtree3_within_radius(const KdTree3Handle *handle, double x, double y, double z, double radius, size_t *out_indices, size_t cap);
+
+void kdtree3_free(KdTree3Handle *handle);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,74 @@
+use super::{point_in_aabb, Columns, KdTree, KdTreePoint, Scalar};
+
+///An axis-aligned box, the boundary included
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb<const DIM: usize, S: Scalar = f64> {
+    ///Lowest coordinates of the box on each axis
+    pub min: [S; DIM],
+    ///Highest coordinates of the box on each axis
+    pub max: [S; DIM],
+}
+
+impl<const DIM: usize, S: Scalar> Aabb<DIM, S> {
+    ///Constructs a box from its corners
+    pub fn new(min: [S; DIM], max: [S; DIM]) -> Self {
+        Self { min, max }
+    }
+
+    ///Returns the box only holding the given point
+    pub fn from_point(point: &[S; DIM]) -> Self {
+        Self { min: *point, max: *point }
+    }
+
+    ///Returns true if the point lies inside the box or on its boundary
+    pub fn contains(&self, point: &[S; DIM]) -> bool {
+        point_in_aabb(point, &self.min, &self.max)
+    }
+
+    ///Returns the box grown by `margin` on every side, a negative margin shrinks it
+    pub fn expand(&self, margin: S) -> Self {
+        Self {
+            min: self.min.map(|x| x - margin),
+            max: self.max.map(|x| x + margin),
+        }
+    }
+
+    ///Returns the smallest box holding both boxes
+    pub fn union(&self, other: &Self) -> Self {
+        Self {
+            min: std::array::from_fn(|axis| self.min[axis].min(other.min[axis])),
+            max: std::array::from_fn(|axis| self.max[axis].max(other.max[axis])),
+        }
+    }
+
+    /// Grows the box to hold the given point.
+    pub(super) fn extend(&mut self, point: &[S; DIM]) {
+        *self = self.union(&Self::from_point(point));
+    }
+
+    /// Returns true if the point lies strictly inside the box, so removing it can't shrink the box.
+    pub(super) fn strictly_contains(&self, point: &[S; DIM]) -> bool {
+        (0..DIM).all(|axis| self.min[axis] < point[axis] && point[axis] < self.max[axis])
+    }
+
+    /// Returns the bounding box of the points of the columns, `None` if there is none.
+    pub(super) fn of_columns(columns: &Columns<'_, DIM, S>) -> Option<Self> {
+        if columns.len() == 0 {
+            return None;
+        }
+        let bound = |axis: usize, f: fn(S, S) -> S| (1..columns.len()).fold(columns.coord(axis, 0), |bound, i| f(bound, columns.coord(axis, i)));
+        Some(Self {
+            min: std::array::from_fn(|axis| bound(axis, S::min)),
+            max: std::array::from_fn(|axis| bound(axis, S::max)),
+        })
+    }
+}
+
+impl<const DIM: usize, POINT: KdTreePoint<DIM, S>, S: Scalar> KdTree<DIM, POINT, S> {
+    ///Returns the bounding box of the stored points, `None` if the tree is empty
+    ///
+    /// The box is kept up to date by every modification of the tree, reading it is free.
+    pub fn bounding_box(&self) -> Option<Aabb<DIM, S>> {
+        self.bounds
+    }
+}
@@ -0,0 +1,212 @@
+use std::io::{self, Read, Write};
+
+use super::{AxisStrategy, DecodeError, KdTree, KdTreeBuilder, KdTreeParts, MedianMode, Node, NodeArena, PartsNode, SplitRule, NONE};
+
+/// First bytes of every binary dump.
+pub const BINARY_MAGIC: [u8; 4] = *b"KDTR";
+/// Version of the format written by `write_binary`, the only one `read_binary` reads.
+pub const BINARY_VERSION: u16 = 1;
+
+const HAS_LEFT: u8 = 1;
+const HAS_RIGHT: u8 = 2;
+
+impl<const DIM: usize> Node<DIM, f64> {
+    /// Writes this subtree in pre-order, walking it with an explicit stack so that any depth fits.
+    fn write_binary<W: Write>(&self, nodes: &NodeArena<DIM, f64>, w: &mut W) -> io::Result<()> {
+        let mut stack = vec![self];
+        while let Some(node) = stack.pop() {
+            w.write_all(&(node.index as u64).to_le_bytes())?;
+            w.write_all(&(node.axis as u32).to_le_bytes())?;
+            w.write_all(&node.split.to_le_bytes())?;
+            let flags = if node.left != NONE { HAS_LEFT } else { 0 } | if node.right != NONE { HAS_RIGHT } else { 0 };
+            w.write_all(&[flags])?;
+            w.write_all(&(node.bucket.len() as u64).to_le_bytes())?;
+            for index in &node.bucket {
+                w.write_all(&(*index as u64).to_le_bytes())?;
+            }
+            stack.extend(nodes.get(node.right));
+            stack.extend(nodes.get(node.left));
+        }
+        Ok(())
+    }
+}
+
+/// Reads `N` bytes, a premature end of the input being a truncated dump.
+fn read_bytes<R: Read, const N: usize>(r: &mut R) -> Result<[u8; N], DecodeError> {
+    let mut bytes = [0; N];
+    r.read_exact(&mut bytes).map_err(|error| match error.kind() {
+        io::ErrorKind::UnexpectedEof => DecodeError::Truncated,
+        kind => DecodeError::Io(kind),
+    })?;
+    Ok(bytes)
+}
+
+fn read_u8<R: Read>(r: &mut R) -> Result<u8, DecodeError> {
+    Ok(read_bytes::<R, 1>(r)?[0])
+}
+
+fn read_u32<R: Read>(r: &mut R) -> Result<u32, DecodeError> {
+    Ok(u32::from_le_bytes(read_bytes(r)?))
+}
+
+fn read_u64<R: Read>(r: &mut R) -> Result<u64, DecodeError> {
+    Ok(u64::from_le_bytes(read_bytes(r)?))
+}
+
+/// Reads a length or an index, refusing values that don't fit in a `usize`.
+fn read_usize<R: Read>(r: &mut R, field: &'static str) -> Result<usize, DecodeError> {
+    let value = read_u64(r)?;
+    usize::try_from(value).map_err(|_| DecodeError::InvalidField { field, value })
+}
+
+fn read_f64<R: Read>(r: &mut R) -> Result<f64, DecodeError> {
+    Ok(f64::from_le_bytes(read_bytes(r)?))
+}
+
+/// Capacity reserved for `len` items read from the input, bounded so that a forged length can't exhaust the memory before the input runs out.
+fn bounded_capacity(len: usize) -> usize {
+    len.min(1 << 16)
+}
+
+fn write_builder<W: Write>(builder: &KdTreeBuilder, w: &mut W) -> io::Result<()> {
+    let axis_strategy: u8 = match builder.axis_strategy {
+        AxisStrategy::RoundRobin => 0,
+        AxisStrategy::MaxSpread => 1,
+        AxisStrategy::MaxVariance => 2,
+    };
+    let split_rule: u8 = match builder.split_rule {
+        SplitRule::Median => 0,
+        SplitRule::SlidingMidpoint => 1,
+    };
+    let (median_mode, sample_size): (u8, usize) = match builder.median_mode {
+        MedianMode::Exact => (0, 0),
+        MedianMode::Sampled { sample_size } => (1, sample_size),
+    };
+    w.write_all(&[axis_strategy, split_rule, median_mode])?;
+    w.write_all(&(sample_size as u64).to_le_bytes())?;
+    w.write_all(&(builder.leaf_size as u64).to_le_bytes())
+}
+
+fn read_builder<R: Read>(r: &mut R) -> Result<KdTreeBuilder, DecodeError> {
+    let axis_strategy = match read_u8(r)? {
+        0 => AxisStrategy::RoundRobin,
+        1 => AxisStrategy::MaxSpread,
+        2 => AxisStrategy::MaxVariance,
+        value => return Err(DecodeError::InvalidField { field: "axis strategy", value: value.into() }),
+    };
+    let split_rule = match read_u8(r)? {
+        0 => SplitRule::Median,
+        1 => SplitRule::SlidingMidpoint,
+        value => return Err(DecodeError::InvalidField { field: "split rule", value: value.into() }),
+    };
+    let median_mode = read_u8(r)?;
+    let sample_size = read_usize(r, "sample size")?;
+    let median_mode = match median_mode {
+        0 => MedianMode::Exact,
+        1 => MedianMode::Sampled { sample_size },
+        value => return Err(DecodeError::InvalidField { field: "median mode", value: value.into() }),
+    };
+    let leaf_size = read_usize(r, "leaf size")?;
+    Ok(KdTreeBuilder::new().axis_strategy(axis_strategy).split_rule(split_rule).median_mode(median_mode).leaf_size(leaf_size))
+}
+
+fn read_node<R: Read>(r: &mut R) -> Result<PartsNode, DecodeError> {
+    let index = read_usize(r, "point index")?;
+    let axis = read_u32(r)? as usize;
+    let split = read_f64(r)?;
+    let flags = read_u8(r)?;
+    if flags & !(HAS_LEFT | HAS_RIGHT) != 0 {
+        return Err(DecodeError::InvalidField { field: "node flags", value: flags.into() });
+    }
+    let len = read_usize(r, "bucket length")?;
+    let mut bucket = Vec::with_capacity(bounded_capacity(len));
+    for _ in 0..len {
+        bucket.push(read_usize(r, "point index")?);
+    }
+    Ok(PartsNode { index, axis, split, bucket, left: flags & HAS_LEFT != 0, right: flags & HAS_RIGHT != 0 })
+}
+
+impl<const DIM: usize> KdTree<DIM, [f64; DIM], f64> {
+    ///Writes the tree in a compact binary format, read back by `read_binary` without constructing the tree again
+    ///
+    /// The dump starts with a header (the magic bytes `KDTR`, the format version as a `u16`, `DIM`
+    /// as a `u32` and the number of points as a `u64`) followed by the options of the tree, the
+    /// coordinates of the points and the nodes in pre-order. Every field is little-endian with a
+    /// fixed width, indices and lengths on 64 bits.
+    ///
+    /// The tombstones left by `remove_lazy` are not written: the dump holds the tree as
+    /// `compact_tombstones` would leave it, so the indices of the points read back shift like
+    /// after a compaction.
+    ///
+    /// # Errors:
+    /// - Any error of the writer. Wrap it in a `BufWriter` when it's unbuffered, the fields are written one by one.
+    pub fn write_binary<W: Write>(&self, mut w: W) -> io::Result<()> {
+        if self.tombstone_count() > 0 {
+            let mut compacted = self.clone();
+            compacted.compact_tombstones();
+            return compacted.write_binary(w);
+        }
+        w.write_all(&BINARY_MAGIC)?;
+        w.write_all(&BINARY_VERSION.to_le_bytes())?;
+        w.write_all(&(DIM as u32).to_le_bytes())?;
+        w.write_all(&(self.points.len() as u64).to_le_bytes())?;
+        write_builder(&self.builder, &mut w)?;
+
+        for point in &self.points {
+            for x in point {
+                w.write_all(&x.to_le_bytes())?;
+            }
+        }
+        w.write_all(&(self.nodes.len() as u64).to_le_bytes())?;
+        if let Some(root) = self.nodes.root() {
+            root.write_binary(&self.nodes, &mut w)?;
+        }
+        w.flush()
+    }
+
+    ///Reads a tree written by `write_binary`
+    ///
+    /// The structure is validated like with `from_parts`, so a corrupted dump gives an error
+    /// instead of a tree that panics later.
+    ///
+    /// # Errors:
+    /// - `DecodeError::BadMagic` if the input doesn't start with the magic bytes.
+    /// - `DecodeError::UnsupportedVersion` if the dump was written by a later version of the format.
+    /// - `DecodeError::DimensionMismatch` if the dump holds a tree of another dimension than `DIM`.
+    /// - `DecodeError::Truncated` if the input ends before the end of the dump.
+    /// - `DecodeError::InvalidField` if an option or a node flag has an unknown value.
+    /// - `DecodeError::InvalidStructure` if the nodes don't describe a valid tree of the points.
+    /// - `DecodeError::Io` on any other error of the reader.
+    pub fn read_binary<R: Read>(mut r: R) -> Result<Self, DecodeError> {
+        let magic = read_bytes::<R, 4>(&mut r)?;
+        if magic != BINARY_MAGIC {
+            return Err(DecodeError::BadMagic { found: magic });
+        }
+        let version = u16::from_le_bytes(read_bytes(&mut r)?);
+        if version != BINARY_VERSION {
+            return Err(DecodeError::UnsupportedVersion { found: version, supported: BINARY_VERSION });
+        }
+        let dim = read_u32(&mut r)? as usize;
+        if dim != DIM {
+            return Err(DecodeError::DimensionMismatch { expected: DIM, found: dim });
+        }
+        let len = read_usize(&mut r, "point count")?;
+        let builder = read_builder(&mut r)?;
+
+        let mut points = Vec::with_capacity(bounded_capacity(len));
+        for _ in 0..len {
+            let mut point = [0.; DIM];
+            for x in &mut point {
+                *x = read_f64(&mut r)?;
+            }
+            points.push(point);
+        }
+        let node_count = read_usize(&mut r, "node count")?;
+        let mut nodes = Vec::with_capacity(bounded_capacity(node_count));
+        for _ in 0..node_count {
+            nodes.push(read_node(&mut r)?);
+        }
+
+        KdTree::from_parts(KdTreeParts { points, nodes, builder }).map_err(DecodeError::InvalidStructure)
+    }
+}
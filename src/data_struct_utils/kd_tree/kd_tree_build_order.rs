@@ -0,0 +1,81 @@
+use super::{Aabb, ColumnStore, Columns, KdTree, KdTreeBuilder, KdTreePoint, Node, NodeArena, NodeId, RebuildError, Scalar, NONE};
+
+impl<const DIM: usize, S: Scalar> Node<DIM, S> {
+    /// Constructs the balanced tree whose points are `order` in pre-order, with an explicit stack.
+    ///
+    /// Every subtree takes as many points as `construct_kdtree` would give it with the default
+    /// options: the median first, then `len / 2` points on the left and the rest on the right.
+    fn construct_from_order(columns: &Columns<'_, DIM, S>, order: &[usize], depth: usize, nodes: &mut NodeArena<DIM, S>) -> NodeId {
+        let mut root = NONE;
+        // Orders of the subtrees to construct with their depth and the parent linking to them, as a left child or not
+        let mut stack = vec![(order, depth, None)];
+        while let Some((order, depth, parent)) = stack.pop() {
+            let Some((&index, rest)) = order.split_first() else {
+                continue;
+            };
+            let (left, right) = rest.split_at(order.len() / 2);
+            let id = nodes.push(Self::leaf(columns, index, depth % DIM));
+            match parent {
+                None => root = id,
+                Some((parent, left)) => nodes.set_child(parent, left, id),
+            }
+            // The left subtree is constructed first, so that the nodes are pushed in pre-order
+            stack.push((right, depth + 1, Some((id, false))));
+            stack.push((left, depth + 1, Some((id, true))));
+        }
+        root
+    }
+}
+
+impl<const DIM: usize, POINT: KdTreePoint<DIM, S>, S: Scalar> KdTree<DIM, POINT, S> {
+    ///Returns the indices of the points in the pre-order of the tree `KdTree::from` builds from them
+    ///
+    /// Stored along with the points, the order lets `from_prebuilt_order` restore that tree in
+    /// linear time without selecting any median. The tree is constructed once to find the order,
+    /// whatever the options and the modifications of `self`.
+    pub fn to_build_order(&self) -> Vec<usize> {
+        let mut order = Vec::with_capacity(self.points.len());
+        if DIM > 0 {
+            let mut entries: Vec<_> = self.columns().entries(0..self.points.len()).collect();
+            let mut nodes = NodeArena::new();
+            nodes.root = Node::construct_kdtree(&mut entries, 0, &KdTreeBuilder::default(), &mut nodes);
+            if let Some(root) = nodes.root() {
+                root.collect_indices(&nodes, &mut order);
+            }
+        }
+        order
+    }
+
+    ///Restores the tree `KdTree::from(points)` in linear time from its build order, see `to_build_order`
+    ///
+    /// # Errors:
+    /// - `RebuildError::LengthMismatch` if `order` doesn't have one index per point.
+    /// - `RebuildError::IndexOutOfRange` or `RebuildError::DuplicateIndex` if `order` is not a permutation of the indices.
+    /// - `RebuildError::InvalidOrder` if the tree of `order` breaks the `check_invariants` of a Kd-Tree,
+    ///   when the order doesn't come from `to_build_order` on the same points.
+    pub fn from_prebuilt_order(points: Vec<POINT>, order: &[usize]) -> Result<Self, RebuildError> {
+        if order.len() != points.len() {
+            return Err(RebuildError::LengthMismatch { order: order.len(), points: points.len() });
+        }
+        let mut seen = vec![false; points.len()];
+        for (position, &index) in order.iter().enumerate() {
+            match seen.get_mut(index) {
+                None => return Err(RebuildError::IndexOutOfRange { position, index }),
+                Some(true) => return Err(RebuildError::DuplicateIndex { position, index }),
+                Some(seen) => *seen = true,
+            }
+        }
+
+        let store = ColumnStore::of_points(&points);
+        let columns = Columns::new(&store, &points);
+        let mut nodes = NodeArena::new();
+        if DIM > 0 {
+            nodes.nodes.reserve_exact(points.len());
+            nodes.root = Node::construct_from_order(&columns, order, 0, &mut nodes);
+        }
+        let bounds = Aabb::of_columns(&columns);
+        let tree = Self { nodes, bounds, points, store, builder: KdTreeBuilder::default(), scratch: Vec::new() };
+        tree.check_invariants().map_err(RebuildError::InvalidOrder)?;
+        Ok(tree)
+    }
+}
@@ -0,0 +1,160 @@
+use super::{Aabb, BuildEntry, ColumnStore, Columns, KdTree, KdTreePoint, Node, NodeArena, Scalar};
+
+///Strategy choosing the split axis of each node during construction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AxisStrategy {
+    ///Cycle through the axes with the depth of the node (`depth % DIM`)
+    #[default]
+    RoundRobin,
+    ///Split on the axis with the largest extent (max - min) of the subset
+    MaxSpread,
+    ///Split on the axis with the largest variance of the subset
+    MaxVariance,
+}
+
+///Rule choosing the split value of each node during construction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SplitRule {
+    ///Split on the median point of the subset, giving balanced trees
+    #[default]
+    Median,
+    ///Split on the midpoint of the extent of the subset, the node holding the point above it
+    ///nearest to the midpoint. It avoids long skinny cells on clustered data but may give
+    ///less balanced trees. Subsets without a finite extent on the axis use the median.
+    SlidingMidpoint,
+}
+
+///How the median of a subset is found by the `SplitRule::Median` rule
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MedianMode {
+    ///Select the exact median of every subset
+    #[default]
+    Exact,
+    ///Estimate the median of the subsets larger than `4 * sample_size` points from a sample of
+    ///`sample_size` of them, smaller subsets use the exact median. Much faster on very large
+    ///inputs, at the cost of slightly less balanced trees. The sample is drawn from a fixed
+    ///seed, so the same input always gives the same tree.
+    Sampled { sample_size: usize },
+}
+
+impl AxisStrategy {
+    /// Chooses the split axis of a subset of points.
+    ///
+    /// # Parameters:
+    /// - `entries`: The entries of the points of the subset.
+    /// - `depth`: The depth of the node to construct.
+    pub(super) fn split_axis<const DIM: usize, S: Scalar>(&self, entries: &[BuildEntry<DIM, S>], depth: usize) -> usize {
+        match self {
+            Self::RoundRobin => depth % DIM,
+            Self::MaxSpread => {
+                let mut min = [S::INFINITY; DIM];
+                let mut max = [S::NEG_INFINITY; DIM];
+                for entry in entries {
+                    for ((low, high), x) in min.iter_mut().zip(max.iter_mut()).zip(entry.position.iter()) {
+                        *low = low.min(*x);
+                        *high = high.max(*x);
+                    }
+                }
+                largest_axis::<DIM>(&std::array::from_fn(|axis| (max[axis] - min[axis]).to_f64()))
+            }
+            Self::MaxVariance => {
+                // Welford's algorithm, the variance is proportional to m2
+                let mut mean = [0.; DIM];
+                let mut m2 = [0.; DIM];
+                for (count, entry) in entries.iter().enumerate() {
+                    for ((mean, m2), x) in mean.iter_mut().zip(m2.iter_mut()).zip(entry.position.iter()) {
+                        let x = x.to_f64();
+                        let delta = x - *mean;
+                        *mean += delta / (count + 1) as f64;
+                        *m2 += delta * (x - *mean);
+                    }
+                }
+                largest_axis(&m2)
+            }
+        }
+    }
+}
+
+/// Returns the axis with the largest score, the first one on ties.
+pub(super) fn largest_axis<const DIM: usize>(scores: &[f64; DIM]) -> usize {
+    (1..DIM).fold(0, |best, axis| if scores[axis] > scores[best] { axis } else { best })
+}
+
+///Builder configuring the construction of a `KdTree`
+///
+/// The options are kept by the tree and reused whenever it rebuilds parts of itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KdTreeBuilder {
+    pub(crate) axis_strategy: AxisStrategy,
+    pub(crate) leaf_size: usize,
+    pub(crate) split_rule: SplitRule,
+    pub(crate) median_mode: MedianMode,
+}
+
+impl Default for KdTreeBuilder {
+    fn default() -> Self {
+        Self {
+            axis_strategy: AxisStrategy::default(),
+            leaf_size: 1,
+            split_rule: SplitRule::default(),
+            median_mode: MedianMode::default(),
+        }
+    }
+}
+
+impl KdTreeBuilder {
+    ///Returns a builder with the default options
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///Sets the strategy choosing the split axis of the nodes, `AxisStrategy::RoundRobin` by default
+    pub fn axis_strategy(mut self, axis_strategy: AxisStrategy) -> Self {
+        self.axis_strategy = axis_strategy;
+        self
+    }
+
+    ///Sets the maximum number of points stored in a leaf, 1 by default
+    ///
+    /// Leaves holding several points are scanned linearly by the queries, which saves nodes
+    /// and pointer chasing: sizes around 8 to 32 usually speed up both construction and queries.
+    /// `add_point` fills non-full leaves before creating new nodes. A size of 0 is treated as 1.
+    pub fn leaf_size(mut self, leaf_size: usize) -> Self {
+        self.leaf_size = leaf_size.max(1);
+        self
+    }
+
+    ///Sets the rule choosing the split values of the nodes, `SplitRule::Median` by default
+    pub fn split_rule(mut self, split_rule: SplitRule) -> Self {
+        self.split_rule = split_rule;
+        self
+    }
+
+    ///Sets how medians are found, `MedianMode::Exact` by default
+    pub fn median_mode(mut self, median_mode: MedianMode) -> Self {
+        self.median_mode = median_mode;
+        self
+    }
+
+    ///Constructs a Kd-Tree from a vector of points
+    pub fn build<const DIM: usize, POINT: KdTreePoint<DIM, S>, S: Scalar>(&self, points: Vec<POINT>) -> KdTree<DIM, POINT, S> {
+        let store = ColumnStore::of_points(&points);
+        let columns = Columns::new(&store, &points);
+        let mut nodes = NodeArena::new();
+        if DIM > 0 {
+            let mut entries = columns.entries(0..points.len()).collect::<Vec<_>>();
+            nodes.nodes.reserve_exact(points.len());
+            nodes.root = Node::construct_kdtree(&mut entries, 0, self, &mut nodes);
+        }
+
+        let bounds = Aabb::of_columns(&columns);
+        KdTree {
+            nodes,
+            points,
+            store,
+            builder: *self,
+            scratch: Vec::new(),
+            bounds,
+        }
+    }
+}
@@ -0,0 +1,134 @@
+use super::{may_reach, precedes, sum_over_axes, Columns, KdTree, KdTreePoint, Node, NodeArena, QueryStats, Scalar};
+
+/// Number of consecutive queries searched together by `nearest_batch_coherent`.
+const COHERENT_GROUP: usize = 8;
+
+/// A node left to search by a part of a group in `nearest_group`.
+struct GroupSearch<'a, const DIM: usize, S: Scalar> {
+    node: &'a Node<DIM, S>,
+    part: [usize; COHERENT_GROUP],       // positions in the group of the targets searching the node
+    len: usize,                          // number of targets in `part`
+    offsets: [[S; DIM]; COHERENT_GROUP], // squared offsets of each target to the region of the node, see `search_far_side`
+    far: bool,                           // whether the node is a far side, searched by the targets it may hold a better point for
+}
+
+impl<const DIM: usize, S: Scalar> Node<DIM, S> {
+    /// Finds the nearest points to a group of targets, the group visiting each node once.
+    ///
+    /// Every target visits the nodes in the order `nearest` would: the group is split between the
+    /// targets searching the left child first and the other ones, then each part searches its near
+    /// child and its far child with only the targets whose region distance is below their best.
+    /// The tree is walked with an explicit stack, so that any depth fits.
+    ///
+    /// # Parameters:
+    /// - `nodes`: The nodes of the tree.
+    /// - `columns`: The coordinates of the points of the tree.
+    /// - `targets`: The coordinates of the targets, at most `COHERENT_GROUP`.
+    /// - `best`: The best candidate of each target and its squared distance, indexed like `targets`.
+    /// - `stats`: The statistics of the batch, a node visited by several targets of a group counting once.
+    fn nearest_group(
+        &self,
+        nodes: &NodeArena<DIM, S>,
+        columns: &Columns<'_, DIM, S>,
+        targets: &[[S; DIM]],
+        best: &mut [Option<(usize, S)>],
+        stats: &mut QueryStats,
+    ) {
+        let mut stack = vec![GroupSearch {
+            node: self,
+            part: std::array::from_fn(|t| t),
+            len: targets.len(),
+            offsets: [[S::ZERO; DIM]; COHERENT_GROUP],
+            far: false,
+        }];
+        while let Some(GroupSearch { node, mut part, mut len, offsets, far }) = stack.pop() {
+            // A far side is only searched by the targets whose region distance is not above their best
+            if far {
+                let mut kept = 0;
+                for i in 0..len {
+                    let t = part[i];
+                    let region_distance = sum_over_axes::<DIM, S, _>(|axis| offsets[t][axis]);
+                    if best[t].is_none_or(|(_, best_distance)| may_reach(region_distance, best_distance)) {
+                        part[kept] = t;
+                        kept += 1;
+                    }
+                }
+                len = kept;
+                if len == 0 {
+                    continue;
+                }
+            }
+
+            stats.nodes_visited += 1;
+            for &t in &part[..len] {
+                let best = &mut best[t];
+                columns.for_each_squared_distance(node.live_points(columns), &targets[t], |candidate, distance| {
+                    if best.is_none_or(|best| precedes((candidate, distance), best)) {
+                        *best = Some((candidate, distance));
+                    }
+                });
+            }
+
+            // Pushed in reverse, so that the left part searches its near then its far child, then the right part
+            let axis = node.axis;
+            for goes_left in [false, true] {
+                let mut side = [0; COHERENT_GROUP];
+                let mut side_len = 0;
+                for &t in &part[..len] {
+                    if (targets[t][axis] < node.split) == goes_left {
+                        side[side_len] = t;
+                        side_len += 1;
+                    }
+                }
+                if side_len == 0 {
+                    continue;
+                }
+                let (next, opposite) = if goes_left { (node.left, node.right) } else { (node.right, node.left) };
+
+                // The offsets of the targets searching the far side are replaced like in `search_far_side`
+                if let Some(opposite) = nodes.get(opposite) {
+                    let mut far_offsets = offsets;
+                    for &t in &side[..side_len] {
+                        let delta = targets[t][axis] - node.split;
+                        far_offsets[t][axis] = delta * delta;
+                    }
+                    stack.push(GroupSearch { node: opposite, part: side, len: side_len, offsets: far_offsets, far: true });
+                }
+                if let Some(next) = nodes.get(next) {
+                    stack.push(GroupSearch { node: next, part: side, len: side_len, offsets, far: false });
+                }
+            }
+        }
+    }
+}
+
+impl<const DIM: usize, POINT: KdTreePoint<DIM, S>, S: Scalar> KdTree<DIM, POINT, S> {
+    ///Returns the index of the nearest point to each query like `nearest_batch`, searching consecutive queries together
+    ///
+    /// Groups of 8 consecutive queries go down the tree together, a node being visited once for
+    /// the queries of the group reaching it. A group splits where its queries fall on both sides
+    /// of a split, so this pays off for spatially coherent queries, in scanline order for example.
+    /// The results are exactly the ones of `nearest_by_coord`: if the squared distances of any
+    /// query may overflow, the queries are searched one by one like in `nearest_batch`.
+    pub fn nearest_batch_coherent(&self, queries: &[[S; DIM]]) -> Vec<Option<usize>> {
+        if queries.iter().any(|coord| self.distances_may_overflow(coord)) {
+            return self.nearest_batch(queries);
+        }
+        self.nearest_batch_coherent_with_stats(queries).0
+    }
+
+    ///Like `nearest_batch_coherent`, along with statistics on the whole batch
+    ///
+    /// A node visited by several queries of a group counts once in `nodes_visited`. The squared
+    /// distances are always computed plainly, even for coordinates making them overflow.
+    pub fn nearest_batch_coherent_with_stats(&self, queries: &[[S; DIM]]) -> (Vec<Option<usize>>, QueryStats) {
+        let mut stats = QueryStats::default();
+        let mut best = vec![None; queries.len()];
+        if let Some(root) = self.nodes.root() {
+            for (targets, best) in queries.chunks(COHERENT_GROUP).zip(best.chunks_mut(COHERENT_GROUP)) {
+                root.nearest_group(&self.nodes, &self.columns(), targets, best, &mut stats);
+            }
+        }
+        (best.into_iter().map(|best| best.map(|(index, _)| index)).collect(), stats)
+    }
+}
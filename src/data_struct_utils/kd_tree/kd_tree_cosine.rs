@@ -0,0 +1,90 @@
+use super::{BuildError, KdTree, KdTreePoint, PairPoint};
+
+///A Kd-Tree searching vectors by cosine similarity
+///
+/// Every vector is stored normalized in a Euclidean tree: for unit vectors the squared Euclidean
+/// distance is `2 - 2 cos`, so the nearest unit vector is the most similar one. Queries return the
+/// original points along with their cosine similarity to the query.
+#[derive(Debug, Clone)]
+pub struct CosineKdTree<const DIM: usize, POINT: KdTreePoint<DIM>> {
+    tree: KdTree<DIM, PairPoint<DIM, POINT>>,
+}
+
+/// Returns the vector scaled to a unit length, `None` for a zero (or non-finite) length.
+fn normalized<const DIM: usize>(v: &[f64; DIM]) -> Option<[f64; DIM]> {
+    let length = v.iter().map(|x| x * x).sum::<f64>().sqrt();
+    (length > 0. && length.is_finite()).then(|| v.map(|x| x / length))
+}
+
+/// Returns the dot product of two vectors.
+fn dot<const DIM: usize>(a: &[f64; DIM], b: &[f64; DIM]) -> f64 {
+    a.iter().zip(b).map(|(a, b)| a * b).sum()
+}
+
+impl<const DIM: usize, POINT: KdTreePoint<DIM>> Default for CosineKdTree<DIM, POINT> {
+    fn default() -> Self {
+        Self { tree: KdTree::new() }
+    }
+}
+
+impl<const DIM: usize, POINT: KdTreePoint<DIM>> CosineKdTree<DIM, POINT> {
+    ///Constructs a tree from a vector of points, their coordinates being the vectors to compare
+    ///
+    /// # Errors:
+    /// - `BuildError::ZeroVector` if a vector has a zero length.
+    pub fn try_from_points(points: Vec<POINT>) -> Result<Self, BuildError> {
+        let pairs = points
+            .into_iter()
+            .enumerate()
+            .map(|(index, point)| match normalized(&point.to_kdtree_point()) {
+                Some(direction) => Ok(PairPoint::new(direction, point)),
+                None => Err(BuildError::ZeroVector { index }),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self { tree: KdTree::from(pairs) })
+    }
+
+    ///Adds a point to the tree
+    ///
+    /// # Errors:
+    /// - `BuildError::ZeroVector` if the vector has a zero length, the tree is left untouched.
+    pub fn add_point(&mut self, point: POINT) -> Result<(), BuildError> {
+        let direction = normalized(&point.to_kdtree_point()).ok_or(BuildError::ZeroVector { index: self.size() })?;
+        self.tree.add_point(PairPoint::new(direction, point));
+        Ok(())
+    }
+
+    ///Returns a reference to the POINT most similar to the given vector, along with its cosine similarity
+    ///
+    /// Returns `None` if the tree is empty or the vector has a zero length.
+    pub fn nearest_cosine(&self, v: &[f64; DIM]) -> Option<(&POINT, f64)> {
+        let direction = normalized(v)?;
+        self.tree
+            .nearest_by_coord(&direction)
+            .map(|pair| (pair.payload(), dot(pair.coord(), &direction)))
+    }
+
+    ///Returns references to the `k` POINTs most similar to the given vector, along with their cosine similarity, the most similar first
+    ///
+    /// Returns nothing if the vector has a zero length.
+    pub fn nearest_k_cosine(&self, v: &[f64; DIM], k: usize) -> Vec<(&POINT, f64)> {
+        let Some(direction) = normalized(v) else {
+            return Vec::new();
+        };
+        self.tree
+            .nearest_k(&direction, k)
+            .into_iter()
+            .map(|pair| (pair.payload(), dot(pair.coord(), &direction)))
+            .collect()
+    }
+
+    ///Returns the number of points in the tree
+    pub fn size(&self) -> usize {
+        self.tree.size()
+    }
+
+    ///Returns true if the tree holds no point
+    pub fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+    }
+}
@@ -0,0 +1,102 @@
+use std::io::{self, BufRead, BufReader, Read, Write};
+
+use super::{CsvError, KdTree};
+
+///Options of the CSV import, see [`KdTree::from_csv`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CsvOptions<const DIM: usize> {
+    delimiter: char,
+    has_header: bool,
+    columns: [usize; DIM],
+}
+
+impl<const DIM: usize> Default for CsvOptions<DIM> {
+    fn default() -> Self {
+        Self { delimiter: ',', has_header: false, columns: std::array::from_fn(|axis| axis) }
+    }
+}
+
+impl<const DIM: usize> CsvOptions<DIM> {
+    ///Returns the default options
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///Sets the character separating the fields of a row, `,` by default
+    pub fn delimiter(mut self, delimiter: char) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    ///Sets whether the first line is a header to skip, false by default
+    pub fn has_header(mut self, has_header: bool) -> Self {
+        self.has_header = has_header;
+        self
+    }
+
+    ///Sets the column (from 0) read for each axis, the first `DIM` columns by default
+    ///
+    /// The other columns of the rows are ignored.
+    pub fn columns(mut self, columns: [usize; DIM]) -> Self {
+        self.columns = columns;
+        self
+    }
+}
+
+impl<const DIM: usize> KdTree<DIM, [f64; DIM], f64> {
+    ///Constructs a balanced Kd-Tree from the rows of a CSV input, one point per row
+    ///
+    /// Fields are trimmed and blank lines skipped. Quoted fields are not supported, the
+    /// coordinates being plain numbers. Errors locate the field by its line, from 1 with the
+    /// header included, and its column, from 0.
+    ///
+    /// # Errors:
+    /// - `CsvError::MissingColumn` if a row has no field for a column of `opts`.
+    /// - `CsvError::InvalidNumber` if a field read is not a finite number.
+    /// - `CsvError::Io` if the reader fails.
+    pub fn from_csv<R: Read>(reader: R, opts: CsvOptions<DIM>) -> Result<Self, CsvError> {
+        let mut points = Vec::new();
+        for (i, line) in BufReader::new(reader).lines().enumerate() {
+            let line = line.map_err(|error| CsvError::Io(error.kind()))?;
+            if (i == 0 && opts.has_header) || line.trim().is_empty() {
+                continue;
+            }
+            let fields: Vec<&str> = line.split(opts.delimiter).map(str::trim).collect();
+
+            let mut point = [0.; DIM];
+            for (x, &column) in point.iter_mut().zip(&opts.columns) {
+                let field = fields.get(column).ok_or(CsvError::MissingColumn { line: i + 1, column, found: fields.len() })?;
+                *x = field.parse::<f64>().ok().filter(|x| x.is_finite()).ok_or_else(|| CsvError::InvalidNumber {
+                    line: i + 1,
+                    column,
+                    field: field.to_string(),
+                })?;
+            }
+            points.push(point);
+        }
+        Ok(Self::from(points))
+    }
+
+    ///Writes the points as CSV rows `index,x0,x1,...` in index order, after a header line
+    ///
+    /// The coordinates are written with the shortest representation reading back to the same
+    /// value, so `from_csv` with a header and the columns `1..=DIM` restores the same points.
+    ///
+    /// # Errors:
+    /// - Any error of the writer.
+    pub fn write_points_csv<W: Write>(&self, mut w: W) -> io::Result<()> {
+        write!(w, "index")?;
+        for axis in 0..DIM {
+            write!(w, ",x{axis}")?;
+        }
+        writeln!(w)?;
+        for (index, point) in self.points.iter().enumerate() {
+            write!(w, "{index}")?;
+            for x in point {
+                write!(w, ",{x}")?;
+            }
+            writeln!(w)?;
+        }
+        w.flush()
+    }
+}
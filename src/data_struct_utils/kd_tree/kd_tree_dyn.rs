@@ -0,0 +1,232 @@
+use super::kd_tree_metric::{may_overflow, robust_distance, robust_split_distance};
+use super::{may_reach, precedes, BuildError, Scalar};
+
+///Node for the DynKdTree, only storing the index of its point like the nodes of `KdTree`
+#[derive(Debug, Clone)]
+struct DynNode {
+    index: usize,             // index of the stored point in the buffer of the tree
+    axis: usize,              // split axis of this node
+    split: f64,               // coordinate of the stored point on the axis
+    left: Option<Box<Self>>,  // left child, coordinates <= split
+    right: Option<Box<Self>>, // right child, coordinates >= split
+}
+
+/// A Kd-Tree whose dimension is only known at runtime.
+///
+/// The coordinates are stored in a flat interleaved buffer `[x0, y0, z0, x1, y1, z1, ...]` and
+/// the queries take slices, checked against the dimension of the tree. Points are identified by
+/// their index in the buffer, use `point` to read their coordinates.
+#[derive(Debug, Clone)]
+pub struct DynKdTree {
+    dim: usize,
+    coords: Vec<f64>,
+    root: Option<Box<DynNode>>,
+    magnitude: f64, // largest absolute coordinate, to detect the squared distances that may overflow
+}
+
+fn squared_distance(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(a, b)| (a - b) * (a - b)).sum()
+}
+
+/// Returns the distance between two points, the `RobustEuclidean` one if `robust`, squared otherwise.
+fn distance(a: &[f64], b: &[f64], robust: bool) -> f64 {
+    match robust {
+        true => robust_distance(a, b),
+        false => squared_distance(a, b),
+    }
+}
+
+/// Returns the lower bound of the distance from the target to the other side of a split, like `distance`.
+fn split_distance(coord: f64, split: f64, robust: bool) -> f64 {
+    match robust {
+        true => robust_split_distance(coord, split),
+        false => (coord - split) * (coord - split),
+    }
+}
+
+impl DynNode {
+    /// Constructs a balanced subtree from the points `indices`, splitting on the median of each axis in turn.
+    ///
+    /// The coordinates follow `f64::total_cmp` and ties are broken by index, like in `KdTree`.
+    fn construct(coords: &[f64], dim: usize, indices: &mut [usize], depth: usize) -> Option<Box<Self>> {
+        if indices.is_empty() {
+            return None;
+        }
+        let axis = depth % dim;
+        let coord = |i: &usize| coords[i * dim + axis];
+        let median = indices.len() / 2;
+        indices.select_nth_unstable_by(median, |i1, i2| {
+            Scalar::total_cmp(&coord(i1), &coord(i2)).then(i1.cmp(i2))
+        });
+
+        let index = indices[median];
+        let (left, right) = indices.split_at_mut(median);
+        Some(Box::new(Self {
+            index,
+            axis,
+            split: coord(&index),
+            left: Self::construct(coords, dim, left, depth + 1),
+            right: Self::construct(coords, dim, &mut right[1..], depth + 1),
+        }))
+    }
+
+    /// Recursively collects the `k` nearest points to the target into `best`, in the order of `precedes`.
+    fn nearest_k(&self, coords: &[f64], dim: usize, target: &[f64], robust: bool, k: usize, best: &mut Vec<(usize, f64)>) {
+        let candidate = (self.index, distance(&coords[self.index * dim..(self.index + 1) * dim], target, robust));
+        if best.len() < k || precedes(candidate, best[best.len() - 1]) {
+            let position = best.partition_point(|&found| precedes(found, candidate));
+            best.insert(position, candidate);
+            best.truncate(k);
+        }
+
+        let (next, opposite_branch) = if target[self.axis] < self.split {
+            (&self.left, &self.right)
+        } else {
+            (&self.right, &self.left)
+        };
+
+        if let Some(next) = next {
+            next.nearest_k(coords, dim, target, robust, k, best);
+        }
+        // The opposite subtree can only improve the result if the split plane is not farther than
+        // the worst candidate, a plane exactly as far may hold a tie with a smaller index
+        if let Some(opposite_branch) = opposite_branch {
+            if best.len() < k || may_reach(split_distance(target[self.axis], self.split, robust), best[best.len() - 1].1) {
+                opposite_branch.nearest_k(coords, dim, target, robust, k, best);
+            }
+        }
+    }
+
+    /// Recursively collects the indices of the points lying within a radius of the target point.
+    ///
+    /// `bound` is the radius compared with the distances, squared unless `robust`.
+    fn within_radius(&self, coords: &[f64], dim: usize, target: &[f64], robust: bool, bound: f64, found: &mut Vec<usize>) {
+        if distance(&coords[self.index * dim..(self.index + 1) * dim], target, robust) <= bound {
+            found.push(self.index);
+        }
+
+        let delta = target[self.axis] - self.split;
+        let reachable = may_reach(split_distance(target[self.axis], self.split, robust), bound);
+        if let Some(left) = &self.left {
+            if delta <= 0. || reachable {
+                left.within_radius(coords, dim, target, robust, bound, found);
+            }
+        }
+        if let Some(right) = &self.right {
+            if delta >= 0. || reachable {
+                right.within_radius(coords, dim, target, robust, bound, found);
+            }
+        }
+    }
+}
+
+impl DynKdTree {
+    ///Constructs a balanced Kd-Tree of dimension `dim` from a flat buffer of interleaved coordinates
+    ///
+    /// Coordinates are not validated, like with `KdTree::from_flat`. A tree of dimension 0 is always empty.
+    ///
+    /// # Errors:
+    /// - `BuildError::FlatLengthMismatch` if the length of the buffer is not a multiple of `dim`.
+    pub fn from_flat(dim: usize, coords: Vec<f64>) -> Result<Self, BuildError> {
+        let len = coords.len();
+        // Only an empty buffer is a multiple of 0
+        if !len.is_multiple_of(dim) {
+            return Err(BuildError::FlatLengthMismatch { len, dim });
+        }
+        let count = len.checked_div(dim).unwrap_or(0);
+
+        let mut indices: Vec<usize> = (0..count).collect();
+        let root = DynNode::construct(&coords, dim, &mut indices, 0);
+        let magnitude = coords.iter().fold(0., |magnitude: f64, x| magnitude.max(x.abs()));
+        Ok(Self { dim, coords, root, magnitude })
+    }
+
+    ///Constructs a balanced Kd-Tree of dimension `dim` from one vector of coordinates per point
+    ///
+    /// # Errors:
+    /// - `BuildError::DimensionMismatch` if a row doesn't have `dim` coordinates.
+    pub fn from_rows(dim: usize, rows: Vec<Vec<f64>>) -> Result<Self, BuildError> {
+        let mut coords = Vec::with_capacity(rows.len() * dim);
+        for row in rows {
+            if row.len() != dim {
+                return Err(BuildError::DimensionMismatch { expected: dim, found: row.len() });
+            }
+            coords.extend(row);
+        }
+        Self::from_flat(dim, coords)
+    }
+
+    /// Returns true if the squared distances between `coord` and the points may overflow, like in `KdTree`.
+    fn distances_may_overflow(&self, coord: &[f64]) -> bool {
+        may_overflow(self.dim, std::iter::once(self.magnitude).chain(coord.iter().copied()))
+    }
+
+    /// Checks that a query has the dimension of the tree.
+    fn check_query(&self, coord: &[f64]) -> Result<(), BuildError> {
+        match coord.len() == self.dim {
+            true => Ok(()),
+            false => Err(BuildError::DimensionMismatch { expected: self.dim, found: coord.len() }),
+        }
+    }
+
+    ///Returns the index of the nearest point to the given coordinates
+    ///
+    /// # Errors:
+    /// - `BuildError::DimensionMismatch` if `coord` doesn't have the dimension of the tree.
+    pub fn nearest(&self, coord: &[f64]) -> Result<Option<usize>, BuildError> {
+        Ok(self.nearest_k(coord, 1)?.first().copied())
+    }
+
+    ///Returns the indices of the `k` nearest points to the given coordinates, the nearest first
+    ///
+    /// Fewer indices are returned if the tree holds less than `k` points. Ties are broken by the
+    /// smallest index and NaN distances come last, like with `KdTree`. Coordinates making the
+    /// squared distances overflow switch the search to `RobustEuclidean`, like with `KdTree`
+    /// too, and so does `within_radius`.
+    ///
+    /// # Errors:
+    /// - `BuildError::DimensionMismatch` if `coord` doesn't have the dimension of the tree.
+    pub fn nearest_k(&self, coord: &[f64], k: usize) -> Result<Vec<usize>, BuildError> {
+        self.check_query(coord)?;
+        let mut best = Vec::with_capacity(k.min(self.len()) + 1);
+        if let (Some(root), true) = (&self.root, k > 0) {
+            root.nearest_k(&self.coords, self.dim, coord, self.distances_may_overflow(coord), k, &mut best);
+        }
+        Ok(best.into_iter().map(|(index, _)| index).collect())
+    }
+
+    ///Returns the indices of every point within `radius` of the given coordinates (boundary included)
+    ///
+    /// # Errors:
+    /// - `BuildError::DimensionMismatch` if `coord` doesn't have the dimension of the tree.
+    pub fn within_radius(&self, coord: &[f64], radius: f64) -> Result<Vec<usize>, BuildError> {
+        self.check_query(coord)?;
+        let mut found = Vec::new();
+        if let (Some(root), true) = (&self.root, radius >= 0.) {
+            let robust = self.distances_may_overflow(coord);
+            let bound = if robust { radius } else { radius * radius };
+            root.within_radius(&self.coords, self.dim, coord, robust, bound, &mut found);
+        }
+        Ok(found)
+    }
+
+    ///Returns the coordinates of the point `index`, if it exists
+    pub fn point(&self, index: usize) -> Option<&[f64]> {
+        (index < self.len()).then(|| &self.coords[index * self.dim..(index + 1) * self.dim])
+    }
+
+    ///Returns the number of coordinates of the points
+    pub fn dim(&self) -> usize {
+        self.dim
+    }
+
+    ///Returns the number of points in the tree
+    pub fn len(&self) -> usize {
+        self.coords.len().checked_div(self.dim).unwrap_or(0)
+    }
+
+    ///Returns true if the tree holds no point
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+}
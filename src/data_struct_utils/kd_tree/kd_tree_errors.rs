@@ -0,0 +1,458 @@
+use std::fmt;
+
+use super::Scalar;
+
+///Error returned by the fallible constructors and queries of `KdTree`, and by the queries of `DynKdTree`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BuildError {
+    ///A coordinate of the input is NaN or infinite
+    NonFiniteCoordinate {
+        index: usize, //Index of the offending point
+        axis: usize,  //Axis of the offending coordinate
+        value: f64,   //Offending coordinate, converted to f64
+    },
+    ///The coordinate columns of the input don't all have the same length
+    ColumnLengthMismatch {
+        axis: usize,     //Axis of the offending column
+        expected: usize, //Length of the first column
+        found: usize,    //Length of the offending column
+    },
+    ///The length of a flat buffer of coordinates is not a multiple of the dimension
+    FlatLengthMismatch {
+        len: usize, //Length of the buffer
+        dim: usize, //Number of coordinates per point
+    },
+    ///The input has points, or a query coordinates, of another dimension than the one of the tree
+    DimensionMismatch {
+        expected: usize, //Dimension of the tree
+        found: usize,    //Dimension of the input
+    },
+    ///A coefficient of the linear transform of a metric is NaN or infinite
+    NonFiniteTransform {
+        row: usize,    //Row of the offending coefficient
+        column: usize, //Column of the offending coefficient
+        value: f64,    //Offending coefficient
+    },
+    ///A vector of the input has a zero length and no direction
+    ZeroVector {
+        index: usize, //Index of the offending vector
+    },
+    ///A coordinate of a query is NaN
+    NanQuery {
+        axis: usize, //Axis of the offending coordinate
+    },
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NonFiniteCoordinate { index, axis, value } => {
+                write!(f, "point {index} has a non-finite coordinate {value} on axis {axis}")
+            }
+            Self::ColumnLengthMismatch { axis, expected, found } => {
+                write!(f, "column of axis {axis} has {found} coordinates, expected {expected}")
+            }
+            Self::FlatLengthMismatch { len, dim } => {
+                write!(f, "flat buffer of {len} coordinates can't be split into points of dimension {dim}")
+            }
+            Self::DimensionMismatch { expected, found } => {
+                write!(f, "points of dimension {found} can't be stored in a tree of dimension {expected}")
+            }
+            Self::NonFiniteTransform { row, column, value } => {
+                write!(f, "transform has a non-finite coefficient {value} at row {row} and column {column}")
+            }
+            Self::ZeroVector { index } => {
+                write!(f, "vector {index} has a zero length and no direction")
+            }
+            Self::NanQuery { axis } => {
+                write!(f, "query has a NaN coordinate on axis {axis}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for BuildError {}
+
+///Broken invariant of a Kd-Tree found by `KdTree::check_invariants` or `KdTree::from_parts`
+///
+/// `path` locates the offending node from the root, one `L` or `R` per step, empty for the root.
+#[derive(Debug, Clone, PartialEq)]
+pub enum InvariantViolation {
+    ///A node refers to a point index past the end of the points
+    IndexOutOfRange {
+        path: String,  //Path of the offending node
+        index: usize,  //Offending index
+        len: usize,    //Number of points
+    },
+    ///Several nodes, or a node and its bucket, refer to the same point
+    DuplicateIndex {
+        path: String, //Path of the second node referring to the point
+        index: usize, //Index of the point
+    },
+    ///A point has a NaN or infinite coordinate
+    NonFinitePosition {
+        path: String, //Path of the node of the point
+        index: usize, //Index of the point
+        axis: usize,  //Axis of the offending coordinate
+    },
+    ///A point lies on the wrong side of the split of an ancestor
+    MisplacedPoint {
+        path: String, //Path of the node of the point
+        index: usize, //Index of the point
+        axis: usize,  //Axis on which the point is out of its cell
+    },
+    ///The coordinates read by the queries are not the ones of the points
+    StaleCoordinates {
+        index: usize, //Index of the first point whose coordinates differ, or the number of coordinates stored if some are missing
+        axis: usize,  //Axis of the offending coordinate
+    },
+    ///The nodes don't refer to every point
+    CountMismatch {
+        nodes: usize,  //Number of points referred to by the nodes
+        points: usize, //Number of stored points
+    },
+    ///A node splits on an axis past the dimension of the tree
+    AxisOutOfRange {
+        path: String, //Path of the offending node
+        axis: usize,  //Offending axis
+        dim: usize,   //Dimension of the tree
+    },
+    ///The nodes given to `KdTree::from_parts` end before the last announced child
+    TruncatedStructure {
+        nodes: usize, //Number of nodes given
+    },
+    ///The nodes given to `KdTree::from_parts` go on after the last node of the tree
+    TrailingNodes {
+        used: usize,  //Number of nodes in the tree
+        nodes: usize, //Number of nodes given
+    },
+}
+
+impl fmt::Display for InvariantViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::IndexOutOfRange { path, index, len } => {
+                write!(f, "node at {path:?} refers to point {index} out of {len} points")
+            }
+            Self::DuplicateIndex { path, index } => {
+                write!(f, "node at {path:?} refers to point {index} which is already in another node")
+            }
+            Self::NonFinitePosition { path, index, axis } => {
+                write!(f, "point {index} of the node at {path:?} has a non-finite coordinate on axis {axis}")
+            }
+            Self::MisplacedPoint { path, index, axis } => {
+                write!(f, "point {index} of the node at {path:?} is on the wrong side of a split on axis {axis}")
+            }
+            Self::StaleCoordinates { index, axis } => {
+                write!(f, "the stored coordinate of point {index} on axis {axis} is not the one of the point")
+            }
+            Self::CountMismatch { nodes, points } => {
+                write!(f, "nodes refer to {nodes} points but the tree stores {points}")
+            }
+            Self::AxisOutOfRange { path, axis, dim } => {
+                write!(f, "node at {path:?} splits on axis {axis} of a tree of dimension {dim}")
+            }
+            Self::TruncatedStructure { nodes } => {
+                write!(f, "the structure announces more children than its {nodes} nodes")
+            }
+            Self::TrailingNodes { used, nodes } => {
+                write!(f, "the tree ends after {used} of the {nodes} nodes of the structure")
+            }
+        }
+    }
+}
+
+impl std::error::Error for InvariantViolation {}
+
+///Error returned by `KdTree::read_binary` on an input that is not a valid binary dump
+#[derive(Debug, Clone, PartialEq)]
+pub enum DecodeError {
+    ///The input doesn't start with the magic bytes of the format
+    BadMagic {
+        found: [u8; 4], //First bytes of the input
+    },
+    ///The dump was written by another version of the format
+    UnsupportedVersion {
+        found: u16,     //Version of the dump
+        supported: u16, //Version read by this crate
+    },
+    ///The dump holds a tree of another dimension
+    DimensionMismatch {
+        expected: usize, //Dimension of the tree read
+        found: usize,    //Dimension of the dump
+    },
+    ///The input ends before the end of the dump
+    Truncated,
+    ///A field of the dump has a value the format doesn't define
+    InvalidField {
+        field: &'static str, //Name of the offending field
+        value: u64,          //Offending value
+    },
+    ///The nodes of the dump don't describe a valid tree of its points
+    InvalidStructure(InvariantViolation),
+    ///The reader failed
+    Io(std::io::ErrorKind),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BadMagic { found } => write!(f, "input starts with {found:?} instead of the magic bytes of a dump"),
+            Self::UnsupportedVersion { found, supported } => {
+                write!(f, "dump has version {found} of the format, only version {supported} is supported")
+            }
+            Self::DimensionMismatch { expected, found } => {
+                write!(f, "dump holds a tree of dimension {found}, expected {expected}")
+            }
+            Self::Truncated => write!(f, "input ends before the end of the dump"),
+            Self::InvalidField { field, value } => write!(f, "invalid {field} {value} in the dump"),
+            Self::InvalidStructure(violation) => write!(f, "invalid structure in the dump: {violation}"),
+            Self::Io(kind) => write!(f, "failed to read the dump: {kind}"),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::InvalidStructure(violation) => Some(violation),
+            _ => None,
+        }
+    }
+}
+
+///Error returned by `KdTree::from_csv` on a row that can't be read as a point
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CsvError {
+    ///A row has no field for a column to read
+    MissingColumn {
+        line: usize,   //Line of the row, from 1
+        column: usize, //Missing column, from 0
+        found: usize,  //Number of fields of the row
+    },
+    ///A field is not a finite number
+    InvalidNumber {
+        line: usize,   //Line of the row, from 1
+        column: usize, //Column of the field, from 0
+        field: String, //Offending field
+    },
+    ///The reader failed
+    Io(std::io::ErrorKind),
+}
+
+impl fmt::Display for CsvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingColumn { line, column, found } => {
+                write!(f, "line {line} has {found} fields, column {column} is missing")
+            }
+            Self::InvalidNumber { line, column, field } => {
+                write!(f, "line {line}, column {column}: {field:?} is not a finite number")
+            }
+            Self::Io(kind) => write!(f, "failed to read the CSV: {kind}"),
+        }
+    }
+}
+
+impl std::error::Error for CsvError {}
+
+///Error returned by `KdTree::from_ply` and `KdTree::from_xyz` on a file that can't be read as a point cloud
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PointCloudError {
+    ///The header of a PLY file is malformed or declares an unsupported format
+    InvalidHeader {
+        line: usize,    //Line of the header, from 1
+        reason: String, //What is wrong with the line
+    },
+    ///A PLY file has no `vertex` element with `x`, `y` and `z` properties
+    MissingVertexCoordinates,
+    ///An element of a PLY file can't be read
+    InvalidElement {
+        element: String, //Name of the element
+        index: usize,    //Index of the element, from 0
+        reason: String,  //What is wrong with the element
+    },
+    ///A PLY file ends before its last vertex
+    Truncated {
+        element: String, //Name of the first missing element
+        index: usize,    //Index of the first missing element, from 0
+    },
+    ///A line of an XYZ file has less than three columns
+    MissingCoordinates {
+        line: usize,  //Line, from 1
+        found: usize, //Number of columns of the line
+    },
+    ///A coordinate of an XYZ file is not a finite number
+    InvalidLine {
+        line: usize,   //Line, from 1
+        column: usize, //Column of the coordinate, from 0
+        field: String, //Offending field
+    },
+    ///The reader failed
+    Io(std::io::ErrorKind),
+}
+
+impl fmt::Display for PointCloudError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidHeader { line, reason } => write!(f, "invalid PLY header at line {line}: {reason}"),
+            Self::MissingVertexCoordinates => write!(f, "the PLY file has no vertex element with x, y and z properties"),
+            Self::InvalidElement { element, index, reason } => write!(f, "invalid {element} {index}: {reason}"),
+            Self::Truncated { element, index } => write!(f, "the PLY file ends before {element} {index}"),
+            Self::MissingCoordinates { line, found } => write!(f, "line {line} has {found} columns, expected at least 3"),
+            Self::InvalidLine { line, column, field } => {
+                write!(f, "line {line}, column {column}: {field:?} is not a finite number")
+            }
+            Self::Io(kind) => write!(f, "failed to read the point cloud: {kind}"),
+        }
+    }
+}
+
+impl std::error::Error for PointCloudError {}
+
+///Error returned by `KdTree::from_geojson_points` on an input that can't be read as Point features
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GeoJsonError {
+    ///The input is not valid JSON
+    InvalidJson {
+        offset: usize,  //Byte offset of the error in the input
+        reason: String, //What is wrong at the offset
+    },
+    ///The input is not a FeatureCollection with a `features` array
+    NotAFeatureCollection,
+    ///A feature has no geometry, or a Point geometry without valid coordinates
+    InvalidFeature {
+        feature: usize, //Index of the feature in the collection
+        reason: String, //What is wrong with the feature
+    },
+    ///A feature has another geometry than a Point, and they are not skipped
+    NonPointGeometry {
+        feature: usize, //Index of the feature in the collection
+        kind: String,   //Type of the geometry, `null` for a null geometry
+    },
+}
+
+impl fmt::Display for GeoJsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidJson { offset, reason } => write!(f, "invalid JSON at byte {offset}: {reason}"),
+            Self::NotAFeatureCollection => write!(f, "the input is not a GeoJSON FeatureCollection"),
+            Self::InvalidFeature { feature, reason } => write!(f, "invalid feature {feature}: {reason}"),
+            Self::NonPointGeometry { feature, kind } => write!(f, "feature {feature} has a {kind} geometry instead of a Point"),
+        }
+    }
+}
+
+impl std::error::Error for GeoJsonError {}
+
+///Error returned by `KdTree::from_prebuilt_order` on an order that doesn't restore a tree
+#[derive(Debug, Clone, PartialEq)]
+pub enum RebuildError {
+    ///The order doesn't have one index per point
+    LengthMismatch {
+        order: usize,  //Length of the order
+        points: usize, //Number of points
+    },
+    ///The order has an index past the end of the points
+    IndexOutOfRange {
+        position: usize, //Position of the index in the order
+        index: usize,    //Offending index
+    },
+    ///The order has an index twice
+    DuplicateIndex {
+        position: usize, //Position of the second occurrence in the order
+        index: usize,    //Offending index
+    },
+    ///The tree of the order is not a valid Kd-Tree of the points
+    InvalidOrder(InvariantViolation),
+}
+
+impl fmt::Display for RebuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::LengthMismatch { order, points } => write!(f, "order of {order} indices for {points} points"),
+            Self::IndexOutOfRange { position, index } => write!(f, "index {index} at position {position} is out of the points"),
+            Self::DuplicateIndex { position, index } => write!(f, "index {index} at position {position} is already in the order"),
+            Self::InvalidOrder(violation) => write!(f, "the order doesn't give a valid tree: {violation}"),
+        }
+    }
+}
+
+impl std::error::Error for RebuildError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::InvalidOrder(violation) => Some(violation),
+            _ => None,
+        }
+    }
+}
+
+///Error returned by `KdTree::from_npy` on a file that can't be read as an array of points
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NpyError {
+    ///The input doesn't start with the magic bytes of a `.npy` file
+    BadMagic,
+    ///The file has a version of the format that can't be read
+    UnsupportedVersion {
+        major: u8, //Major version of the file
+        minor: u8, //Minor version of the file
+    },
+    ///The header can't be parsed
+    InvalidHeader {
+        reason: String, //What is wrong with the header
+    },
+    ///The array is not of little-endian float64 or float32
+    UnsupportedDtype {
+        descr: String, //Type of the array, as in the header
+    },
+    ///The array is in Fortran (column-major) order
+    FortranOrder,
+    ///The array is not of shape `(n, DIM)`
+    ShapeMismatch {
+        shape: Vec<usize>, //Shape of the array
+        dim: usize,        //Dimension of the tree
+    },
+    ///A coordinate is NaN or infinite
+    NonFiniteCoordinate {
+        row: usize,    //Row of the coordinate
+        column: usize, //Column of the coordinate
+    },
+    ///The input ends before the end of the array
+    Truncated,
+    ///The reader failed
+    Io(std::io::ErrorKind),
+}
+
+impl fmt::Display for NpyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BadMagic => write!(f, "the input is not a .npy file"),
+            Self::UnsupportedVersion { major, minor } => write!(f, "unsupported .npy version {major}.{minor}"),
+            Self::InvalidHeader { reason } => write!(f, "invalid .npy header: {reason}"),
+            Self::UnsupportedDtype { descr } => write!(f, "unsupported dtype {descr:?}, expected '<f8' or '<f4'"),
+            Self::FortranOrder => write!(f, "the array is in Fortran order, expected C order"),
+            Self::ShapeMismatch { shape, dim } => write!(f, "array of shape {shape:?}, expected (n, {dim})"),
+            Self::NonFiniteCoordinate { row, column } => write!(f, "non-finite coordinate at row {row} and column {column}"),
+            Self::Truncated => write!(f, "the input ends before the end of the array"),
+            Self::Io(kind) => write!(f, "failed to read the .npy file: {kind}"),
+        }
+    }
+}
+
+impl std::error::Error for NpyError {}
+
+/// Checks that no coordinate of the query `coord` is NaN.
+pub(crate) fn check_query<const DIM: usize, S: Scalar>(coord: &[S; DIM]) -> Result<(), BuildError> {
+    match coord.iter().position(|x| x.to_f64().is_nan()) {
+        Some(axis) => Err(BuildError::NanQuery { axis }),
+        None => Ok(()),
+    }
+}
+
+/// Checks that every coordinate of `position` is finite, `index` is the index reported on error.
+pub(crate) fn check_finite<const DIM: usize, S: Scalar>(position: &[S; DIM], index: usize) -> Result<(), BuildError> {
+    match position.iter().position(|x| !x.is_finite()) {
+        Some(axis) => Err(BuildError::NonFiniteCoordinate { index, axis, value: position[axis].to_f64() }),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,116 @@
+//! C interface for 2-D and 3-D trees of `f64` coordinates, enabled by the `ffi` feature.
+//!
+//! Trees are handed out as opaque handles, created by `kdtree2_new` or `kdtree3_new` and
+//! released by `kdtree2_free` or `kdtree3_free`. Points are identified by their index in the
+//! buffer given at creation. Every function tolerates null pointers and never unwinds into C:
+//! a null pointer or a panic gives a null handle, `false` or `0`. The matching C declarations
+//! are in `include/my_rust_utils.h`.
+
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use super::KdTree;
+
+///Opaque handle on a 2-D tree, created by `kdtree2_new`
+pub struct KdTree2Handle(KdTree<2, [f64; 2]>);
+
+///Opaque handle on a 3-D tree, created by `kdtree3_new`
+pub struct KdTree3Handle(KdTree<3, [f64; 3]>);
+
+/// Runs `f`, giving `default` instead of unwinding if it panics.
+fn guarded<T, F: FnOnce() -> T>(default: T, f: F) -> T {
+    catch_unwind(AssertUnwindSafe(f)).unwrap_or(default)
+}
+
+macro_rules! impl_ffi {
+    ($dim:literal, $handle:ident, $new:ident, $nearest:ident, $within_radius:ident, $free:ident, ($($axis:ident),+)) => {
+        ///Builds a tree from `len` points of interleaved coordinates
+        ///
+        /// Returns null if `points` is null while `len` isn't 0, if the coordinates of `len` points
+        /// would take more than `isize::MAX` bytes, or if a coordinate is NaN or infinite.
+        ///
+        /// # Safety
+        /// `points` must be null or point to the readable coordinates of `len` points.
+        #[no_mangle]
+        pub unsafe extern "C" fn $new(points: *const f64, len: usize) -> *mut $handle {
+            if points.is_null() && len > 0 {
+                return std::ptr::null_mut();
+            }
+            // No buffer can be larger, `from_raw_parts` requires it
+            let max_count = isize::MAX as usize / std::mem::size_of::<f64>();
+            let Some(count) = len.checked_mul($dim).filter(|&count| count <= max_count) else {
+                return std::ptr::null_mut();
+            };
+            guarded(std::ptr::null_mut(), || {
+                let coords = match len {
+                    0 => &[][..],
+                    _ => std::slice::from_raw_parts(points, count),
+                };
+                let points = coords.chunks_exact($dim).map(|c| std::array::from_fn(|axis| c[axis])).collect();
+                match KdTree::try_from_points(points) {
+                    Ok(tree) => Box::into_raw(Box::new($handle(tree))),
+                    Err(_) => std::ptr::null_mut(),
+                }
+            })
+        }
+
+        ///Writes the index of the nearest point to the given coordinates into `out_index`
+        ///
+        /// Returns false, leaving `out_index` untouched, if a pointer is null or the tree is empty.
+        ///
+        /// # Safety
+        /// `handle` must be null or a live handle, `out_index` null or writable.
+        #[no_mangle]
+        pub unsafe extern "C" fn $nearest(handle: *const $handle, $($axis: f64,)+ out_index: *mut usize) -> bool {
+            if handle.is_null() || out_index.is_null() {
+                return false;
+            }
+            guarded(false, || {
+                let tree = &(*handle).0;
+                match tree.nearest_index(&[$($axis),+]) {
+                    Some(index) => {
+                        *out_index = index;
+                        true
+                    }
+                    None => false,
+                }
+            })
+        }
+
+        ///Writes the indices of the points within `radius` of the given coordinates into `out_indices`
+        ///
+        /// At most `cap` indices are written, in no particular order, but the total number of points
+        /// found is returned so that a caller can retry with a larger buffer. Returns 0 if `handle`
+        /// is null, and only counts the points if `out_indices` is null.
+        ///
+        /// # Safety
+        /// `handle` must be null or a live handle, `out_indices` null or writable for `cap` indices.
+        #[no_mangle]
+        pub unsafe extern "C" fn $within_radius(handle: *const $handle, $($axis: f64,)+ radius: f64, out_indices: *mut usize, cap: usize) -> usize {
+            if handle.is_null() {
+                return 0;
+            }
+            guarded(0, || {
+                let found = (*handle).0.within_radius_indices(&[$($axis),+], radius);
+                if !out_indices.is_null() {
+                    let written = found.len().min(cap);
+                    std::ptr::copy_nonoverlapping(found.as_ptr(), out_indices, written);
+                }
+                found.len()
+            })
+        }
+
+        ///Releases a tree, does nothing if `handle` is null
+        ///
+        /// # Safety
+        /// `handle` must be null or a live handle, which can't be used afterwards.
+        #[no_mangle]
+        pub unsafe extern "C" fn $free(handle: *mut $handle) {
+            if !handle.is_null() {
+                guarded((), || drop(Box::from_raw(handle)));
+            }
+        }
+    };
+}
+
+impl_ffi!(2, KdTree2Handle, kdtree2_new, kdtree2_nearest, kdtree2_within_radius, kdtree2_free, (x, y));
+impl_ffi!(3, KdTree3Handle, kdtree3_new, kdtree3_nearest, kdtree3_within_radius, kdtree3_free, (x, y, z));
@@ -0,0 +1,68 @@
+use std::fmt;
+use std::sync::Arc;
+
+use super::{BuildError, KdTree, KdTreePoint};
+
+///A point of a flat interleaved buffer `[x0, y0, z0, x1, y1, z1, ...]`, built by `KdTree::from_flat`
+///
+/// Every point shares the same buffer and only stores its index, nothing is allocated per point.
+#[derive(Clone)]
+pub struct FlatPoint<const DIM: usize> {
+    buffer: Arc<[f64]>,
+    index: usize,
+}
+
+impl<const DIM: usize> FlatPoint<DIM> {
+    ///Returns the index of the point in the buffer, the point `i` starting at `buffer[i * DIM]`
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    ///Returns the coordinates of the point
+    pub fn coord(&self) -> &[f64; DIM] {
+        self.buffer[self.index * DIM..(self.index + 1) * DIM]
+            .try_into()
+            .expect("the buffer holds DIM coordinates per point")
+    }
+}
+
+impl<const DIM: usize> fmt::Debug for FlatPoint<DIM> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FlatPoint")
+            .field("index", &self.index)
+            .field("coord", self.coord())
+            .finish()
+    }
+}
+
+impl<const DIM: usize> PartialEq for FlatPoint<DIM> {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index && self.coord() == other.coord()
+    }
+}
+
+impl<const DIM: usize> KdTreePoint<DIM> for FlatPoint<DIM> {
+    fn to_kdtree_point(&self) -> [f64; DIM] {
+        *self.coord()
+    }
+}
+
+impl<const DIM: usize> KdTree<DIM, FlatPoint<DIM>> {
+    ///Constructs a Kd-Tree from a flat buffer of interleaved coordinates `[x0, y0, z0, x1, y1, z1, ...]`
+    ///
+    /// Coordinates are not validated, like with `From<Vec<POINT>>`.
+    ///
+    /// # Errors:
+    /// - `BuildError::FlatLengthMismatch` if the length of the buffer is not a multiple of `DIM`.
+    pub fn from_flat(buffer: Vec<f64>) -> Result<Self, BuildError> {
+        let len = buffer.len();
+        // Only an empty buffer is a multiple of 0
+        if !len.is_multiple_of(DIM) {
+            return Err(BuildError::FlatLengthMismatch { len, dim: DIM });
+        }
+        let count = len.checked_div(DIM).unwrap_or(0);
+
+        let buffer: Arc<[f64]> = buffer.into();
+        Ok(Self::from_iter_with_capacity((0..count).map(|index| FlatPoint { buffer: buffer.clone(), index }), count))
+    }
+}
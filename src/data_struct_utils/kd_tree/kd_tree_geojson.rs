@@ -0,0 +1,328 @@
+use super::{GeoJsonError, KdTree, KdTreePoint};
+
+///JSON value of a GeoJSON input, as kept in the `id` and the properties of a `GeoFeature`
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonValue {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<JsonValue>),
+    ///Members in the order of the input
+    Object(Vec<(String, JsonValue)>),
+}
+
+impl JsonValue {
+    ///Returns the member `key` of an object, `None` for other values or missing members
+    pub fn get(&self, key: &str) -> Option<&JsonValue> {
+        match self {
+            Self::Object(members) => members.iter().find(|(k, _)| k == key).map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    ///Returns the string of a `JsonValue::String`
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    ///Returns the number of a `JsonValue::Number`
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Self::Number(x) => Some(*x),
+            _ => None,
+        }
+    }
+}
+
+/// Deepest nesting of objects and arrays accepted by `JsonParser`, so that a forged input can't overflow the stack.
+const MAX_JSON_DEPTH: usize = 128;
+
+/// Minimal recursive descent JSON parser, errors give the byte offset of the offending character.
+struct JsonParser<'a> {
+    input: &'a [u8],
+    position: usize,
+    depth: usize, //Number of objects and arrays being parsed
+}
+
+impl JsonParser<'_> {
+    fn error(&self, reason: &str) -> GeoJsonError {
+        GeoJsonError::InvalidJson { offset: self.position, reason: reason.to_string() }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.input.get(self.position).is_some_and(|c| c.is_ascii_whitespace()) {
+            self.position += 1;
+        }
+    }
+
+    fn expect(&mut self, literal: &str, value: JsonValue) -> Result<JsonValue, GeoJsonError> {
+        match self.input[self.position..].starts_with(literal.as_bytes()) {
+            true => {
+                self.position += literal.len();
+                Ok(value)
+            }
+            false => Err(self.error("invalid literal")),
+        }
+    }
+
+    fn value(&mut self) -> Result<JsonValue, GeoJsonError> {
+        self.skip_whitespace();
+        match self.input.get(self.position) {
+            None => Err(self.error("unexpected end of input")),
+            Some(b'{') => self.object(),
+            Some(b'[') => self.array(),
+            Some(b'"') => Ok(JsonValue::String(self.string()?)),
+            Some(b't') => self.expect("true", JsonValue::Bool(true)),
+            Some(b'f') => self.expect("false", JsonValue::Bool(false)),
+            Some(b'n') => self.expect("null", JsonValue::Null),
+            Some(b'-' | b'0'..=b'9') => self.number(),
+            Some(_) => Err(self.error("unexpected character")),
+        }
+    }
+
+    /// Parses the members or items separated by commas up to `close`, the opening bracket being consumed.
+    fn sequence<F: FnMut(&mut Self) -> Result<(), GeoJsonError>>(&mut self, close: u8, mut item: F) -> Result<(), GeoJsonError> {
+        if self.depth == MAX_JSON_DEPTH {
+            return Err(self.error(&format!("more than {MAX_JSON_DEPTH} nested objects and arrays")));
+        }
+        self.depth += 1;
+        let result = self.items(close, &mut item);
+        self.depth -= 1;
+        result
+    }
+
+    /// Parses the content of a sequence, see `sequence`.
+    fn items<F: FnMut(&mut Self) -> Result<(), GeoJsonError>>(&mut self, close: u8, item: &mut F) -> Result<(), GeoJsonError> {
+        self.position += 1;
+        self.skip_whitespace();
+        if self.input.get(self.position) == Some(&close) {
+            self.position += 1;
+            return Ok(());
+        }
+        loop {
+            item(self)?;
+            self.skip_whitespace();
+            match self.input.get(self.position) {
+                Some(b',') => self.position += 1,
+                Some(c) if *c == close => {
+                    self.position += 1;
+                    return Ok(());
+                }
+                _ => return Err(self.error("expected a comma or a closing bracket")),
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<JsonValue, GeoJsonError> {
+        let mut members = Vec::new();
+        self.sequence(b'}', |parser| {
+            parser.skip_whitespace();
+            if parser.input.get(parser.position) != Some(&b'"') {
+                return Err(parser.error("expected a member name"));
+            }
+            let key = parser.string()?;
+            parser.skip_whitespace();
+            if parser.input.get(parser.position) != Some(&b':') {
+                return Err(parser.error("expected a colon"));
+            }
+            parser.position += 1;
+            members.push((key, parser.value()?));
+            Ok(())
+        })?;
+        Ok(JsonValue::Object(members))
+    }
+
+    fn array(&mut self) -> Result<JsonValue, GeoJsonError> {
+        let mut items = Vec::new();
+        self.sequence(b']', |parser| {
+            items.push(parser.value()?);
+            Ok(())
+        })?;
+        Ok(JsonValue::Array(items))
+    }
+
+    fn hex4(&mut self) -> Result<u32, GeoJsonError> {
+        let digits = self.input.get(self.position..self.position + 4).ok_or_else(|| self.error("truncated escape"))?;
+        let code = std::str::from_utf8(digits).ok().and_then(|d| u32::from_str_radix(d, 16).ok()).ok_or_else(|| self.error("invalid escape"))?;
+        self.position += 4;
+        Ok(code)
+    }
+
+    fn string(&mut self) -> Result<String, GeoJsonError> {
+        self.position += 1;
+        let mut bytes = Vec::new();
+        loop {
+            let c = *self.input.get(self.position).ok_or_else(|| self.error("unterminated string"))?;
+            self.position += 1;
+            match c {
+                b'"' => break,
+                b'\\' => {
+                    let escape = *self.input.get(self.position).ok_or_else(|| self.error("unterminated string"))?;
+                    self.position += 1;
+                    let decoded = match escape {
+                        b'"' => '"',
+                        b'\\' => '\\',
+                        b'/' => '/',
+                        b'b' => '\u{8}',
+                        b'f' => '\u{c}',
+                        b'n' => '\n',
+                        b'r' => '\r',
+                        b't' => '\t',
+                        b'u' => {
+                            let mut code = self.hex4()?;
+                            // A high surrogate is followed by the escape of the low one
+                            if (0xD800..0xDC00).contains(&code) && self.input[self.position..].starts_with(b"\\u") {
+                                self.position += 2;
+                                let low = self.hex4()?;
+                                if !(0xDC00..0xE000).contains(&low) {
+                                    return Err(self.error("invalid surrogate pair"));
+                                }
+                                code = 0x10000 + ((code - 0xD800) << 10) + (low - 0xDC00);
+                            }
+                            char::from_u32(code).ok_or_else(|| self.error("invalid escape"))?
+                        }
+                        _ => return Err(self.error("invalid escape")),
+                    };
+                    bytes.extend(decoded.encode_utf8(&mut [0; 4]).as_bytes());
+                }
+                c => bytes.push(c),
+            }
+        }
+        String::from_utf8(bytes).map_err(|_| self.error("invalid UTF-8 in string"))
+    }
+
+    fn number(&mut self) -> Result<JsonValue, GeoJsonError> {
+        let start = self.position;
+        while self.input.get(self.position).is_some_and(|c| matches!(c, b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9')) {
+            self.position += 1;
+        }
+        let text = std::str::from_utf8(&self.input[start..self.position]).unwrap_or_default();
+        text.parse().map(JsonValue::Number).map_err(|_| GeoJsonError::InvalidJson { offset: start, reason: format!("invalid number {text:?}") })
+    }
+}
+
+/// Parses a whole JSON document.
+fn parse_json(input: &str) -> Result<JsonValue, GeoJsonError> {
+    let mut parser = JsonParser { input: input.as_bytes(), position: 0, depth: 0 };
+    let value = parser.value()?;
+    parser.skip_whitespace();
+    match parser.position == input.len() {
+        true => Ok(value),
+        false => Err(parser.error("trailing characters")),
+    }
+}
+
+///Feature of a GeoJSON input with a Point geometry, stored at its (longitude, latitude)
+///
+/// The coordinates keep the GeoJSON order, longitude first. Query them with `HaversineLonLat`
+/// for great-circle distances, `Haversine` takes the latitude first.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeoFeature {
+    coord: [f64; 2],
+    ///The `id` of the feature, if it has one
+    pub id: Option<JsonValue>,
+    ///The `properties` of the feature, `JsonValue::Null` if it has none
+    pub properties: JsonValue,
+}
+
+impl GeoFeature {
+    ///Returns the longitude of the point
+    pub fn lon(&self) -> f64 {
+        self.coord[0]
+    }
+
+    ///Returns the latitude of the point
+    pub fn lat(&self) -> f64 {
+        self.coord[1]
+    }
+}
+
+impl KdTreePoint<2> for GeoFeature {
+    fn to_kdtree_point(&self) -> [f64; 2] {
+        self.coord
+    }
+}
+
+///Options of the GeoJSON import, see [`KdTree::from_geojson_points`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GeoJsonOptions {
+    skip_non_points: bool,
+}
+
+impl Default for GeoJsonOptions {
+    fn default() -> Self {
+        Self { skip_non_points: true }
+    }
+}
+
+impl GeoJsonOptions {
+    ///Returns the default options
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///Sets whether features without a Point geometry are skipped or refused with `GeoJsonError::NonPointGeometry`, skipped by default
+    pub fn skip_non_points(mut self, skip_non_points: bool) -> Self {
+        self.skip_non_points = skip_non_points;
+        self
+    }
+}
+
+impl KdTree<2, GeoFeature> {
+    ///Constructs a balanced Kd-Tree of the Point features of a GeoJSON FeatureCollection
+    ///
+    /// Every feature keeps its `id` and its `properties`, so that the results of the queries can be
+    /// told apart. Features are numbered from 0 in the errors, in the order of the collection.
+    ///
+    /// # Errors:
+    /// - `GeoJsonError::InvalidJson` if the input is not valid JSON.
+    /// - `GeoJsonError::NotAFeatureCollection` if the input is not a FeatureCollection with a `features` array.
+    /// - `GeoJsonError::InvalidFeature` if a feature has no `geometry`, or a Point geometry with invalid coordinates.
+    /// - `GeoJsonError::NonPointGeometry` if a feature has another geometry and `opts` doesn't skip them.
+    pub fn from_geojson_points(input: &str, opts: GeoJsonOptions) -> Result<Self, GeoJsonError> {
+        let json = parse_json(input)?;
+        let features = match (json.get("type").and_then(JsonValue::as_str), json.get("features")) {
+            (Some("FeatureCollection"), Some(JsonValue::Array(features))) => features,
+            _ => return Err(GeoJsonError::NotAFeatureCollection),
+        };
+
+        let mut points = Vec::with_capacity(features.len());
+        for (feature, value) in features.iter().enumerate() {
+            let invalid = |reason: &str| GeoJsonError::InvalidFeature { feature, reason: reason.to_string() };
+            let geometry = value.get("geometry").ok_or_else(|| invalid("missing geometry"))?;
+            let kind = match geometry {
+                JsonValue::Null => "null",
+                geometry => geometry.get("type").and_then(JsonValue::as_str).ok_or_else(|| invalid("geometry without a type"))?,
+            };
+            if kind != "Point" {
+                match opts.skip_non_points {
+                    true => continue,
+                    false => return Err(GeoJsonError::NonPointGeometry { feature, kind: kind.to_string() }),
+                }
+            }
+
+            let coord = match geometry.get("coordinates") {
+                Some(JsonValue::Array(coordinates)) if coordinates.len() >= 2 => {
+                    let lon = coordinates[0].as_f64().ok_or_else(|| invalid("non-numeric longitude"))?;
+                    let lat = coordinates[1].as_f64().ok_or_else(|| invalid("non-numeric latitude"))?;
+                    [lon, lat]
+                }
+                Some(_) => return Err(invalid("coordinates are not a position")),
+                None => return Err(invalid("missing coordinates")),
+            };
+            if coord.iter().any(|x| !x.is_finite()) {
+                return Err(invalid("non-finite coordinates"));
+            }
+            points.push(GeoFeature {
+                coord,
+                id: value.get("id").cloned(),
+                properties: value.get("properties").cloned().unwrap_or(JsonValue::Null),
+            });
+        }
+        Ok(Self::from(points))
+    }
+}
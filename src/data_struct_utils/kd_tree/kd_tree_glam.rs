@@ -0,0 +1,56 @@
+//! `KdTreePoint` implementations for the `glam` vectors, enabled by the `glam` feature.
+//!
+//! The f32 vectors are points of `f32` trees and the f64 ones of `f64` trees, so the
+//! coordinates are never converted. Use `as_dvec3` and the like to store f32 vectors in an
+//! `f64` tree.
+
+use glam::{DVec2, DVec3, Vec2, Vec3, Vec3A};
+
+use super::{KdTree, KdTreePoint, KdTreePointMut};
+
+macro_rules! impl_glam_point {
+    ($($vec:ty => $dim:literal, $scalar:ty;)*) => {$(
+        impl KdTreePoint<$dim, $scalar> for $vec {
+            fn to_kdtree_point(&self) -> [$scalar; $dim] {
+                *self.as_ref()
+            }
+        }
+
+        impl KdTreePointMut<$dim, $scalar> for $vec {
+            fn set_kdtree_point(&mut self, coords: &[$scalar; $dim]) {
+                *self = <$vec>::from_array(*coords);
+            }
+        }
+    )*};
+}
+
+impl_glam_point! {
+    Vec2 => 2, f32;
+    Vec3 => 3, f32;
+    Vec3A => 3, f32;
+    DVec2 => 2, f64;
+    DVec3 => 3, f64;
+}
+
+macro_rules! impl_glam_queries {
+    ($($vec:ty => $dim:literal, $scalar:ty, $nearest:ident, $within_radius:ident;)*) => {$(
+        impl<POINT: KdTreePoint<$dim, $scalar>> KdTree<$dim, POINT, $scalar> {
+            #[doc = concat!("Returns a reference to the nearest POINT to a `", stringify!($vec), "`")]
+            pub fn $nearest(&self, v: $vec) -> Option<&POINT> {
+                self.nearest_by_coord(v.as_ref())
+            }
+
+            #[doc = concat!("Returns references to every POINT within `radius` of a `", stringify!($vec), "` (boundary included)")]
+            pub fn $within_radius(&self, v: $vec, radius: $scalar) -> Vec<&POINT> {
+                self.within_radius(v.as_ref(), radius)
+            }
+        }
+    )*};
+}
+
+impl_glam_queries! {
+    Vec2 => 2, f32, nearest_by_vec2, within_radius_vec2;
+    Vec3 => 3, f32, nearest_by_vec3, within_radius_vec3;
+    DVec2 => 2, f64, nearest_by_dvec2, within_radius_dvec2;
+    DVec3 => 3, f64, nearest_by_dvec3, within_radius_dvec3;
+}
@@ -0,0 +1,43 @@
+use std::ops::{Deref, DerefMut};
+
+use super::{KdTree, KdTreePoint, Scalar};
+
+///Mutable access to a POINT stored in a `KdTree`, see [`KdTree::get_mut`]
+///
+/// The coordinates of the point are captured when the guard is created. When the guard is
+/// dropped, they are compared to the current ones: if they changed, the node of the point is
+/// moved so the tree stays correct, otherwise nothing is done.
+pub struct PointGuard<'a, const DIM: usize, POINT: KdTreePoint<DIM, S>, S: Scalar = f64> {
+    tree: &'a mut KdTree<DIM, POINT, S>,
+    index: usize,
+    position: [S; DIM], // coordinates of the point when the guard was created
+}
+
+impl<'a, const DIM: usize, POINT: KdTreePoint<DIM, S>, S: Scalar> PointGuard<'a, DIM, POINT, S> {
+    pub(super) fn new(tree: &'a mut KdTree<DIM, POINT, S>, index: usize) -> Self {
+        let position = tree.columns().position(index);
+        Self { tree, index, position }
+    }
+}
+
+impl<const DIM: usize, POINT: KdTreePoint<DIM, S>, S: Scalar> Deref for PointGuard<'_, DIM, POINT, S> {
+    type Target = POINT;
+
+    fn deref(&self) -> &POINT {
+        &self.tree.points[self.index]
+    }
+}
+
+impl<const DIM: usize, POINT: KdTreePoint<DIM, S>, S: Scalar> DerefMut for PointGuard<'_, DIM, POINT, S> {
+    fn deref_mut(&mut self) -> &mut POINT {
+        &mut self.tree.points[self.index]
+    }
+}
+
+impl<const DIM: usize, POINT: KdTreePoint<DIM, S>, S: Scalar> Drop for PointGuard<'_, DIM, POINT, S> {
+    fn drop(&mut self) {
+        if self.tree.points[self.index].to_kdtree_point() != self.position {
+            self.tree.relocate(self.index, &self.position);
+        }
+    }
+}
@@ -0,0 +1,230 @@
+use std::marker::PhantomData;
+
+use super::kd_tree_metric::{box_may_overflow, robust_split_distance};
+use super::{may_reach, precedes, squared_distance, Aabb, Columns, KdTree, KdTreePoint, Metric, RobustEuclidean, Scalar};
+
+///A static Kd-Tree stored in heap order, built by [`KdTree::freeze_implicit`]
+///
+/// The points are stored in a single array where the children of the node `i` are the nodes
+/// `2i + 1` and `2i + 2`, so no link is stored at all. The tree is complete: every level is full
+/// but the last one, filled from the left. Every node holds one point and splits on the axis
+/// `depth % DIM` at the coordinate of its point.
+///
+/// The queries give the same results as the ones of the `KdTree` it was frozen from, points at
+/// the same distance of a query may be found in another order.
+#[derive(Debug, Clone)]
+pub struct ImplicitKdTree<const DIM: usize, POINT: KdTreePoint<DIM, S>, S: Scalar = f64> {
+    points: Vec<POINT>, //Points in heap order
+
+    bounds: Option<Aabb<DIM, S>>, //Bounding box of the points
+
+    scalar: PhantomData<S>, //Type of the coordinates, only read from the points
+}
+
+/// Returns the number of nodes of the left subtree of a complete tree of `len` nodes.
+fn left_len(len: usize) -> usize {
+    if len <= 1 {
+        return 0;
+    }
+    // Levels above the last one are full, the left subtree takes up to half of the last level
+    let levels = usize::BITS - len.leading_zeros();
+    let half_level = 1 << (levels - 2);
+    let last_level = len - (2 * half_level - 1);
+    half_level - 1 + last_level.min(half_level)
+}
+
+impl<const DIM: usize, POINT: KdTreePoint<DIM, S>, S: Scalar> ImplicitKdTree<DIM, POINT, S> {
+    /// Recursively stores the points of `indices` in `order`, the root of the subtree being at `position`.
+    fn fill_order(columns: &Columns<'_, DIM, S>, indices: &mut [usize], position: usize, depth: usize, order: &mut [usize]) {
+        if indices.is_empty() {
+            return;
+        }
+        let axis = depth % DIM;
+        let coord = |i: &usize| columns.coord(axis, *i);
+        // Ties are broken by index so that the same input always gives the same tree
+        let cmp = |i1: &usize, i2: &usize| coord(i1).total_cmp(&coord(i2)).then(i1.cmp(i2));
+
+        let (left, index, right) = indices.select_nth_unstable_by(left_len(indices.len()), cmp);
+        order[position] = *index;
+        Self::fill_order(columns, left, 2 * position + 1, depth + 1, order);
+        Self::fill_order(columns, right, 2 * position + 2, depth + 1, order);
+    }
+
+    /// Returns the number of nodes, the points of a tree of dimension 0 are in no node.
+    fn node_count(&self) -> usize {
+        if DIM == 0 { 0 } else { self.points.len() }
+    }
+
+    /// Returns the split axis and value of the node at `position`.
+    fn split(&self, position: usize) -> (usize, S) {
+        let axis = (usize::BITS - 1 - (position + 1).leading_zeros()) as usize % DIM;
+        (axis, self.points[position].to_kdtree_point()[axis])
+    }
+
+    /// Returns the distance between the point at `position` and the target, the `RobustEuclidean` one if `robust`, squared otherwise.
+    fn distance(&self, position: usize, target: &[S; DIM], robust: bool) -> S {
+        let point = self.points[position].to_kdtree_point();
+        match robust {
+            true => RobustEuclidean.distance(&point, target),
+            false => squared_distance(&point, target),
+        }
+    }
+
+    /// Returns the lower bound of the distance from the target to the other side of `split` on `axis`, like `distance`.
+    fn split_distance(target: &[S; DIM], axis: usize, split: S, robust: bool) -> S {
+        match robust {
+            true => robust_split_distance(target[axis], split),
+            false => (target[axis] - split) * (target[axis] - split),
+        }
+    }
+
+    /// Recursively finds the nearest point to the target in the subtree rooted at `position`.
+    fn nearest_from(&self, position: usize, target: &[S; DIM], robust: bool, best: Option<(usize, S)>) -> Option<(usize, S)> {
+        if position >= self.node_count() {
+            return best;
+        }
+        let distance = self.distance(position, target, robust);
+        let best = match best {
+            Some(best) if !precedes((position, distance), best) => Some(best),
+            _ => Some((position, distance)),
+        };
+
+        let (axis, split) = self.split(position);
+        let (next, opposite) = if target[axis] < split {
+            (2 * position + 1, 2 * position + 2)
+        } else {
+            (2 * position + 2, 2 * position + 1)
+        };
+        let best = self.nearest_from(next, target, robust, best);
+
+        match best {
+            Some((_, best_distance)) if may_reach(Self::split_distance(target, axis, split, robust), best_distance) => {
+                self.nearest_from(opposite, target, robust, best)
+            }
+            best => best,
+        }
+    }
+
+    /// Recursively collects the `k` nearest points to the target in the subtree rooted at `position`, sorted by distance.
+    fn nearest_k_from(&self, position: usize, target: &[S; DIM], robust: bool, k: usize, best: &mut Vec<(usize, S)>) {
+        if position >= self.node_count() {
+            return;
+        }
+        let distance = self.distance(position, target, robust);
+        if best.len() < k || precedes((position, distance), best[best.len() - 1]) {
+            let insert = best.partition_point(|&found| precedes(found, (position, distance)));
+            best.insert(insert, (position, distance));
+            best.truncate(k);
+        }
+
+        let (axis, split) = self.split(position);
+        let (next, opposite) = if target[axis] < split {
+            (2 * position + 1, 2 * position + 2)
+        } else {
+            (2 * position + 2, 2 * position + 1)
+        };
+        self.nearest_k_from(next, target, robust, k, best);
+
+        if best.len() < k || may_reach(Self::split_distance(target, axis, split, robust), best[best.len() - 1].1) {
+            self.nearest_k_from(opposite, target, robust, k, best);
+        }
+    }
+
+    /// Recursively collects the points within a radius of the target in the subtree rooted at `position`.
+    ///
+    /// `bound` is the radius compared with the distances, squared unless `robust`.
+    fn within_radius_from<'a>(&'a self, position: usize, target: &[S; DIM], robust: bool, bound: S, found: &mut Vec<&'a POINT>) {
+        if position >= self.node_count() {
+            return;
+        }
+        if self.distance(position, target, robust) <= bound {
+            found.push(&self.points[position]);
+        }
+
+        let (axis, split) = self.split(position);
+        let delta = target[axis] - split;
+        let reachable = may_reach(Self::split_distance(target, axis, split, robust), bound);
+        if delta <= S::ZERO || reachable {
+            self.within_radius_from(2 * position + 1, target, robust, bound, found);
+        }
+        if delta >= S::ZERO || reachable {
+            self.within_radius_from(2 * position + 2, target, robust, bound, found);
+        }
+    }
+
+    ///Returns a reference to the nearest POINT using given coordinates
+    ///
+    /// Switches to `RobustEuclidean` for coordinates making the squared distances overflow, like
+    /// `KdTree::nearest_by_coord`. So do `nearest_k` and `within_radius`.
+    pub fn nearest_by_coord(&self, coord: &[S; DIM]) -> Option<&POINT> {
+        self.nearest_from(0, coord, box_may_overflow(self.bounds.as_ref(), coord), None).map(|(position, _)| &self.points[position])
+    }
+
+    ///Returns a reference to the nearest POINT using another POINT
+    pub fn nearest(&self, target: &POINT) -> Option<&POINT> {
+        self.nearest_by_coord(&target.to_kdtree_point())
+    }
+
+    ///Returns references to the `k` nearest POINTs to the given coordinates, the nearest first
+    ///
+    /// Fewer points are returned if the tree holds less than `k` points.
+    pub fn nearest_k(&self, coord: &[S; DIM], k: usize) -> Vec<&POINT> {
+        let mut best = Vec::with_capacity(k.min(self.points.len()) + 1);
+        if k > 0 {
+            self.nearest_k_from(0, coord, box_may_overflow(self.bounds.as_ref(), coord), k, &mut best);
+        }
+        best.into_iter().map(|(position, _)| &self.points[position]).collect()
+    }
+
+    ///Returns references to every POINT within `radius` of the given coordinates (boundary included)
+    pub fn within_radius(&self, coord: &[S; DIM], radius: S) -> Vec<&POINT> {
+        let mut found = Vec::new();
+        if radius >= S::ZERO {
+            let robust = box_may_overflow(self.bounds.as_ref(), coord);
+            let bound = if robust { radius } else { radius * radius };
+            self.within_radius_from(0, coord, robust, bound, &mut found);
+        }
+        found
+    }
+
+    ///Returns the points of the tree, in heap order
+    pub fn points(&self) -> &[POINT] {
+        &self.points
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    pub fn size(&self) -> usize {
+        self.points.len()
+    }
+
+    ///Returns the height of the tree, the number of levels of the complete tree, 0 for a tree of dimension 0
+    pub fn height(&self) -> usize {
+        (usize::BITS - self.node_count().leading_zeros()) as usize
+    }
+}
+
+impl<const DIM: usize, POINT: KdTreePoint<DIM, S>, S: Scalar> KdTree<DIM, POINT, S> {
+    ///Freezes the tree into an `ImplicitKdTree`, which stores its points in heap order without any link
+    ///
+    /// The points are reordered into a complete tree, whatever the options and the modifications
+    /// of `self`: axes are chosen round robin and nodes hold a single point, like with the default
+    /// `KdTreeBuilder`. Suited to trees built once and only queried. The tombstones left by
+    /// `remove_lazy` are compacted first.
+    pub fn freeze_implicit(mut self) -> ImplicitKdTree<DIM, POINT, S> {
+        self.compact_tombstones();
+        let mut order = (0..self.points.len()).collect::<Vec<_>>();
+        if DIM > 0 {
+            let mut indices = order.clone();
+            ImplicitKdTree::<DIM, POINT, S>::fill_order(&self.columns(), &mut indices, 0, 0, &mut order);
+        }
+        let mut points: Vec<Option<POINT>> = self.points.into_iter().map(Some).collect();
+        ImplicitKdTree {
+            points: order.into_iter().map(|i| points[i].take().unwrap()).collect(),
+            bounds: self.bounds,
+            scalar: PhantomData,
+        }
+    }
+}
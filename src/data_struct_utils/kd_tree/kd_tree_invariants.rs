@@ -0,0 +1,128 @@
+use super::{ColumnStore, InvariantViolation, KdTree, KdTreePoint, Node, NodeArena, Scalar};
+
+impl<const DIM: usize, S: Scalar> Node<DIM, S> {
+    /// Checks the points of this node, which must lie in the cell `[cell_min, cell_max]`.
+    ///
+    /// # Parameters:
+    /// - `values`: The points of the tree.
+    /// - `path`: The path of this node from the root.
+    /// - `finite`: Whether infinite coordinates are refused, NaN always is.
+    /// - `seen`: The points already found in a node.
+    /// - `count`: The number of points found so far, updated while checking.
+    #[allow(clippy::too_many_arguments)]
+    fn check_points<POINT: KdTreePoint<DIM, S>>(
+        &self,
+        values: &[POINT],
+        cell_min: &[S; DIM],
+        cell_max: &[S; DIM],
+        path: &str,
+        finite: bool,
+        seen: &mut [bool],
+        count: &mut usize,
+    ) -> Result<(), InvariantViolation> {
+        for index in self.node_points() {
+            if index >= values.len() {
+                return Err(InvariantViolation::IndexOutOfRange { path: path.to_string(), index, len: values.len() });
+            }
+            if std::mem::replace(&mut seen[index], true) {
+                return Err(InvariantViolation::DuplicateIndex { path: path.to_string(), index });
+            }
+            *count += 1;
+
+            let position = values[index].to_kdtree_point();
+            if let Some(axis) = position.iter().position(|x| if finite { !x.is_finite() } else { x.to_f64().is_nan() }) {
+                return Err(InvariantViolation::NonFinitePosition { path: path.to_string(), index, axis });
+            }
+            if let Some(axis) = (0..DIM).find(|&axis| position[axis] < cell_min[axis] || position[axis] > cell_max[axis]) {
+                return Err(InvariantViolation::MisplacedPoint { path: path.to_string(), index, axis });
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks the invariants of the subtree in pre-order, walking it with an explicit stack so that any depth fits.
+    fn check_invariants<POINT: KdTreePoint<DIM, S>>(&self, nodes: &NodeArena<DIM, S>, values: &[POINT], finite: bool, seen: &mut [bool], count: &mut usize) -> Result<(), InvariantViolation> {
+        let mut path = String::new();
+        // Nodes to check with their cell, their depth and the last step of their path
+        let mut stack = vec![(self, [S::NEG_INFINITY; DIM], [S::INFINITY; DIM], 0usize, None)];
+        while let Some((node, cell_min, cell_max, depth, step)) = stack.pop() {
+            path.truncate(depth.saturating_sub(1));
+            path.extend(step);
+            node.check_points(values, &cell_min, &cell_max, &path, finite, seen, count)?;
+
+            let (axis, split) = (node.axis, node.split);
+            if let Some(right) = nodes.get(node.right) {
+                let mut right_min = cell_min;
+                right_min[axis] = split;
+                stack.push((right, right_min, cell_max, depth + 1, Some('R')));
+            }
+            if let Some(left) = nodes.get(node.left) {
+                let mut left_max = cell_max;
+                left_max[axis] = split;
+                stack.push((left, cell_min, left_max, depth + 1, Some('L')));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<const DIM: usize, S: Scalar> ColumnStore<DIM, S> {
+    /// Returns the point and the axis of the first coordinate of the store which is not the one of `points`.
+    fn first_stale<POINT: KdTreePoint<DIM, S>>(&self, points: &[POINT]) -> Option<(usize, usize)> {
+        if self.len != points.len() {
+            return Some((self.len.min(points.len()), 0));
+        }
+        if self.in_place {
+            return None;
+        }
+        if let Some(axis) = self.columns.iter().position(|column| column.len() != points.len()) {
+            return Some((self.columns[axis].len().min(points.len()), axis));
+        }
+        points.iter().enumerate().find_map(|(index, point)| {
+            let position = point.to_kdtree_point();
+            (0..DIM).find(|&axis| self.columns[axis][index].total_cmp(&position[axis]).is_ne()).map(|axis| (index, axis))
+        })
+    }
+}
+
+impl<const DIM: usize, POINT: KdTreePoint<DIM, S>, S: Scalar> KdTree<DIM, POINT, S> {
+    ///Checks that the tree is a valid Kd-Tree
+    ///
+    /// Every point must be referred to by exactly one node, have finite coordinates, and lie on
+    /// the side of the split of each of its ancestors given by the convention of the nodes: the
+    /// left subtree holds coordinates `<= split` and the right one coordinates `>= split`. The
+    /// coordinates copied for the queries must be the ones of the points.
+    ///
+    /// # Errors:
+    /// - The first `InvariantViolation` found, in pre-order.
+    pub fn check_invariants(&self) -> Result<(), InvariantViolation> {
+        self.check_invariants_with(true)
+    }
+
+    /// Checks the invariants of `check_invariants`, infinite coordinates included.
+    ///
+    /// `From` places infinite coordinates like the other ones, only NaN has no place: this is
+    /// what `from_parts` checks, so that any tree built by `From` without NaN round trips.
+    pub(super) fn check_structure(&self) -> Result<(), InvariantViolation> {
+        self.check_invariants_with(false)
+    }
+
+    /// Checks the invariants, refusing infinite coordinates if `finite` is true.
+    fn check_invariants_with(&self, finite: bool) -> Result<(), InvariantViolation> {
+        let mut count = 0;
+        if let Some(root) = self.nodes.root() {
+            let mut seen = vec![false; self.points.len()];
+            root.check_invariants(&self.nodes, &self.points, finite, &mut seen, &mut count)?;
+        }
+
+        // Trees of dimension 0 have no node
+        let expected = if DIM == 0 { 0 } else { self.points.len() };
+        if count != expected {
+            return Err(InvariantViolation::CountMismatch { nodes: count, points: expected });
+        }
+        if let Some((index, axis)) = self.store.first_stale(&self.points) {
+            return Err(InvariantViolation::StaleCoordinates { index, axis });
+        }
+        Ok(())
+    }
+}
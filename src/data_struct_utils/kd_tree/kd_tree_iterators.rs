@@ -1,19 +1,314 @@
 use std::collections::VecDeque;
+use std::iter::FusedIterator;
 
-use super::{KdTree, KdTreePoint, Node};
+use super::{KdTree, KdTreePoint, Node, Scalar};
 
-struct KdTreePrefixIterator<'a,const DIM :usize,POINT:KdTreePoint<DIM>>{
-    tree : &'a KdTree<DIM,POINT>,
-    iterator_stack : VecDeque<&'a Node<DIM>>,
+///Iterator over the points of a Kd-Tree in pre-order, built by `KdTree::iter_prefix`
+///
+/// Every node yields its points before the points of its left subtree, then of its right subtree,
+/// so the first point is the one of the root.
+pub struct KdTreePrefixIterator<'a,const DIM :usize,POINT:KdTreePoint<DIM, S>,S:Scalar = f64>{
+    tree : &'a KdTree<DIM,POINT,S>,
+    iterator_stack : VecDeque<&'a Node<DIM, S>>,
+    bucket : std::slice::Iter<'a, usize>, //Remaining bucket points of the last visited node
+    remaining : usize,                    //Number of points left to yield
 }
 
-impl<'a,const DIM :usize,POINT:KdTreePoint<DIM>> Iterator for KdTreePrefixIterator<'a,DIM,POINT> {
-    type Item = &'a POINT;
+impl<'a,const DIM :usize,POINT:KdTreePoint<DIM, S>,S:Scalar> KdTreePrefixIterator<'a,DIM,POINT,S> {
+    /// Returns the next point of the traversal.
+    fn advance(&mut self) -> Option<&'a POINT> {
+        if let Some(&index) = self.bucket.next() {
+            return Some(&self.tree.points[index]);
+        }
 
-    fn next(&mut self) -> Option<Self::Item> {
         let curr_node = self.iterator_stack.pop_front()?;
-        
+        if let Some(right) = self.tree.nodes.get(curr_node.right) {
+            self.iterator_stack.push_front(right);
+        }
+        if let Some(left) = self.tree.nodes.get(curr_node.left) {
+            self.iterator_stack.push_front(left);
+        }
+        self.bucket = curr_node.bucket.iter();
+        Some(&self.tree.points[curr_node.index])
+    }
+}
+
+///Iterator over the points of a Kd-Tree in order, built by `KdTree::iter_in_order`
+///
+/// Every node yields its points after the points of its left subtree and before the points of its
+/// right subtree. The points of a leaf bucket are yielded in storage order.
+pub struct KdTreeInOrderIterator<'a,const DIM :usize,POINT:KdTreePoint<DIM, S>,S:Scalar = f64>{
+    tree : &'a KdTree<DIM,POINT,S>,
+    iterator_stack : Vec<&'a Node<DIM, S>>, //Nodes whose left subtree is being visited
+    bucket : std::slice::Iter<'a, usize>,   //Remaining bucket points of the last visited node
+    remaining : usize,                      //Number of points left to yield
+}
+
+impl<'a,const DIM :usize,POINT:KdTreePoint<DIM, S>,S:Scalar> KdTreeInOrderIterator<'a,DIM,POINT,S> {
+    /// Pushes the node and its chain of left children on the stack.
+    fn push_left_branch(&mut self, mut node: Option<&'a Node<DIM, S>>) {
+        while let Some(curr_node) = node {
+            self.iterator_stack.push(curr_node);
+            node = self.tree.nodes.get(curr_node.left);
+        }
+    }
+}
+
+impl<'a,const DIM :usize,POINT:KdTreePoint<DIM, S>,S:Scalar> KdTreeInOrderIterator<'a,DIM,POINT,S> {
+    /// Returns the next point of the traversal.
+    fn advance(&mut self) -> Option<&'a POINT> {
+        if let Some(&index) = self.bucket.next() {
+            return Some(&self.tree.points[index]);
+        }
+
+        let curr_node = self.iterator_stack.pop()?;
+        self.push_left_branch(self.tree.nodes.get(curr_node.right));
+        self.bucket = curr_node.bucket.iter();
+        Some(&self.tree.points[curr_node.index])
+    }
+}
+
+///Iterator over the points of a Kd-Tree in post-order with their depth, built by `KdTree::iter_post_order`
+///
+/// Every node yields its points after the points of its left subtree, then of its right subtree,
+/// so the children always appear before their parent and the point of the root comes last.
+pub struct KdTreePostOrderIterator<'a,const DIM :usize,POINT:KdTreePoint<DIM, S>,S:Scalar = f64>{
+    tree : &'a KdTree<DIM,POINT,S>,
+    iterator_stack : Vec<(&'a Node<DIM, S>, usize, bool)>, //Nodes left to visit, with their depth and whether their children were pushed
+    bucket : std::slice::Iter<'a, usize>,                  //Remaining bucket points of the last visited node
+    bucket_depth : usize,                                  //Depth of the last visited node
+    remaining : usize,                                     //Number of points left to yield
+}
+
+impl<'a,const DIM :usize,POINT:KdTreePoint<DIM, S>,S:Scalar> KdTreePostOrderIterator<'a,DIM,POINT,S> {
+    /// Returns the next point of the traversal.
+    fn advance(&mut self) -> Option<(&'a POINT, usize)> {
+        if let Some(&index) = self.bucket.next() {
+            return Some((&self.tree.points[index], self.bucket_depth));
+        }
+
+        // Push the children until the top of the stack is a node whose children were visited
+        loop {
+            let (curr_node, depth, expanded) = self.iterator_stack.last_mut()?;
+            if *expanded {
+                break;
+            }
+            *expanded = true;
+            let (curr_node, depth) = (*curr_node, *depth);
+            if let Some(right) = self.tree.nodes.get(curr_node.right) {
+                self.iterator_stack.push((right, depth + 1, false));
+            }
+            if let Some(left) = self.tree.nodes.get(curr_node.left) {
+                self.iterator_stack.push((left, depth + 1, false));
+            }
+        }
+
+        let (curr_node, depth, _) = self.iterator_stack.pop()?;
+        self.bucket = curr_node.bucket.iter();
+        self.bucket_depth = depth;
+        Some((&self.tree.points[curr_node.index], depth))
+    }
+}
+
+///Iterator over the points of a Kd-Tree level by level with their depth, built by `KdTree::iter_bfs`
+///
+/// The root comes first, then the nodes of depth 1 from left to right, and so on.
+pub struct KdTreeBfsIterator<'a,const DIM :usize,POINT:KdTreePoint<DIM, S>,S:Scalar = f64>{
+    tree : &'a KdTree<DIM,POINT,S>,
+    iterator_queue : VecDeque<(&'a Node<DIM, S>, usize)>, //Nodes left to visit, with their depth
+    bucket : std::slice::Iter<'a, usize>,                 //Remaining bucket points of the last visited node
+    bucket_depth : usize,                                 //Depth of the last visited node
+    remaining : usize,                                    //Number of points left to yield
+}
+
+impl<'a,const DIM :usize,POINT:KdTreePoint<DIM, S>,S:Scalar> KdTreeBfsIterator<'a,DIM,POINT,S> {
+    /// Returns the next point of the traversal.
+    fn advance(&mut self) -> Option<(usize, &'a POINT)> {
+        if let Some(&index) = self.bucket.next() {
+            return Some((self.bucket_depth, &self.tree.points[index]));
+        }
+
+        let (curr_node, depth) = self.iterator_queue.pop_front()?;
+        if let Some(left) = self.tree.nodes.get(curr_node.left) {
+            self.iterator_queue.push_back((left, depth + 1));
+        }
+        if let Some(right) = self.tree.nodes.get(curr_node.right) {
+            self.iterator_queue.push_back((right, depth + 1));
+        }
+        self.bucket = curr_node.bucket.iter();
+        self.bucket_depth = depth;
+        Some((depth, &self.tree.points[curr_node.index]))
+    }
+}
+
+///A point stored in a Kd-Tree along with its index and coordinates, yielded by `KdTree::iter_entries`
+pub struct Entry<'a,const DIM :usize,POINT:KdTreePoint<DIM, S>,S:Scalar = f64>{
+    tree : &'a KdTree<DIM,POINT,S>,
+    index : usize,
+}
+
+impl<'a,const DIM :usize,POINT:KdTreePoint<DIM, S>,S:Scalar> Entry<'a,DIM,POINT,S> {
+    ///Returns the index of the point in the tree
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    ///Returns the coordinates of the point
+    ///
+    /// Nodes don't keep a copy of the coordinates, they are read from the point through `to_kdtree_point`.
+    pub fn position(&self) -> [S; DIM] {
+        self.point().to_kdtree_point()
+    }
+
+    ///Returns the point
+    pub fn point(&self) -> &'a POINT {
+        &self.tree.points[self.index]
+    }
+}
+
+impl<const DIM :usize,POINT:KdTreePoint<DIM, S>,S:Scalar> Clone for Entry<'_,DIM,POINT,S> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<const DIM :usize,POINT:KdTreePoint<DIM, S>,S:Scalar> Copy for Entry<'_,DIM,POINT,S> {}
+
+impl<const DIM :usize,POINT:KdTreePoint<DIM, S> + std::fmt::Debug,S:Scalar> std::fmt::Debug for Entry<'_,DIM,POINT,S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Entry")
+            .field("index", &self.index)
+            .field("point", self.point())
+            .finish()
+    }
+}
+
+// The traversals count the points they yield, so they know exactly how many are left
+macro_rules! impl_traversal_iterator {
+    ($($iterator:ident => $item:ty),*) => {$(
+        impl<'a,const DIM :usize,POINT:KdTreePoint<DIM, S>,S:Scalar> Iterator for $iterator<'a,DIM,POINT,S> {
+            type Item = $item;
+
+            fn next(&mut self) -> Option<Self::Item> {
+                let item = self.advance()?;
+                self.remaining -= 1;
+                Some(item)
+            }
+
+            fn size_hint(&self) -> (usize, Option<usize>) {
+                (self.remaining, Some(self.remaining))
+            }
+        }
+
+        impl<'a,const DIM :usize,POINT:KdTreePoint<DIM, S>,S:Scalar> ExactSizeIterator for $iterator<'a,DIM,POINT,S> {}
+
+        impl<'a,const DIM :usize,POINT:KdTreePoint<DIM, S>,S:Scalar> FusedIterator for $iterator<'a,DIM,POINT,S> {}
+    )*};
+}
+
+impl_traversal_iterator!(
+    KdTreePrefixIterator => &'a POINT,
+    KdTreeInOrderIterator => &'a POINT,
+    KdTreePostOrderIterator => (&'a POINT, usize),
+    KdTreeBfsIterator => (usize, &'a POINT)
+);
+
+impl<const DIM :usize,POINT:KdTreePoint<DIM, S>,S:Scalar> IntoIterator for KdTree<DIM,POINT,S> {
+    type Item = POINT;
+    type IntoIter = std::vec::IntoIter<POINT>;
+
+    /// Consumes the tree into its points in index order, the nodes are dropped right away.
+    fn into_iter(self) -> Self::IntoIter {
+        let KdTree { nodes, points, .. } = self;
+        drop(nodes);
+        points.into_iter()
+    }
+}
+
+impl<'a,const DIM :usize,POINT:KdTreePoint<DIM, S>,S:Scalar> IntoIterator for &'a KdTree<DIM,POINT,S> {
+    type Item = &'a POINT;
+    type IntoIter = std::slice::Iter<'a, POINT>;
 
-        todo!()
+    /// Iterates over the points of the tree in index order.
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
     }
-}
\ No newline at end of file
+}
+
+impl<const DIM :usize,POINT:KdTreePoint<DIM, S>,S:Scalar> KdTree<DIM,POINT,S> {
+    /// Returns the number of points yielded by the traversals, the points of a tree of dimension 0 are in no node.
+    fn traversal_len(&self) -> usize {
+        if self.nodes.root().is_some() { self.points.len() } else { 0 }
+    }
+
+    ///Returns an iterator over the points of the tree in index order
+    ///
+    /// The iterator knows its exact length and can be walked from both ends.
+    pub fn iter(&self) -> std::slice::Iter<'_, POINT> {
+        self.points.iter()
+    }
+
+    ///Returns an iterator over the entries of the tree in index order, giving the index, coordinates and point of each
+    pub fn iter_entries(&self) -> impl ExactSizeIterator<Item = Entry<'_,DIM,POINT,S>> + DoubleEndedIterator {
+        (0..self.points.len()).map(|index| Entry { tree: self, index })
+    }
+
+    ///Gives `f` an iterator over mutable references to the points in index order, then rebuilds the tree
+    ///
+    /// Since the coordinates may change, the node structure is always rebuilt from scratch once
+    /// `f` returns, like with `rebuild_from`. To move a few points, `get_mut` is cheaper.
+    ///
+    /// # Returns:
+    /// - The value returned by `f`.
+    pub fn iter_mut_then_rebuild<R, F: FnOnce(std::slice::IterMut<'_, POINT>) -> R>(&mut self, f: F) -> R {
+        let result = f(self.points.iter_mut());
+        self.rebuild();
+        result
+    }
+
+    ///Returns an iterator over the points of the tree in pre-order, the point of the root first
+    pub fn iter_prefix(&self) -> KdTreePrefixIterator<'_,DIM,POINT,S> {
+        KdTreePrefixIterator {
+            tree: self,
+            iterator_stack: self.nodes.root().into_iter().collect(),
+            bucket: [].iter(),
+            remaining: self.traversal_len(),
+        }
+    }
+
+    ///Returns an iterator over the points of the tree in order, left subtrees first
+    ///
+    /// For a 1-D tree with leaves of one point (the default) the points are sorted by coordinate.
+    pub fn iter_in_order(&self) -> KdTreeInOrderIterator<'_,DIM,POINT,S> {
+        let mut iterator = KdTreeInOrderIterator { tree: self, iterator_stack: Vec::new(), bucket: [].iter(), remaining: self.traversal_len() };
+        iterator.push_left_branch(self.nodes.root());
+        iterator
+    }
+
+    ///Returns an iterator over the points of the tree in post-order along with the depth of their node, the root at depth 0
+    ///
+    /// Children always appear before their parent, which allows computing aggregates per subtree.
+    /// The traversal doesn't recurse, so it can walk degenerate trees of any depth.
+    pub fn iter_post_order(&self) -> KdTreePostOrderIterator<'_,DIM,POINT,S> {
+        KdTreePostOrderIterator {
+            tree: self,
+            iterator_stack: self.nodes.root().map(|root| (root, 0, false)).into_iter().collect(),
+            bucket: [].iter(),
+            bucket_depth: 0,
+            remaining: self.traversal_len(),
+        }
+    }
+
+    ///Returns an iterator over the points of the tree level by level, along with the depth of their node
+    ///
+    /// The root is at depth 0 and the deepest points are at depth `height() - 1`.
+    pub fn iter_bfs(&self) -> KdTreeBfsIterator<'_,DIM,POINT,S> {
+        KdTreeBfsIterator {
+            tree: self,
+            iterator_queue: self.nodes.root().map(|root| (root, 0)).into_iter().collect(),
+            bucket: [].iter(),
+            bucket_depth: 0,
+            remaining: self.traversal_len(),
+        }
+    }
+}
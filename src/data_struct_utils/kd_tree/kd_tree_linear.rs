@@ -0,0 +1,123 @@
+use std::cmp::Ordering;
+use std::marker::PhantomData;
+
+use super::kd_tree_metric::may_overflow;
+use super::{point_in_aabb, precedes, squared_distance, KdTreePoint, Metric, RobustEuclidean, Scalar};
+
+///Points searched by scanning all of them, with the same queries as a `KdTree`
+///
+/// Every query reads every point, so nothing is built and the points can be added freely. It is
+/// the recommended fallback below a couple hundred points, where the nodes of a `KdTree` cost
+/// more than they save, and a trivially correct reference to check the trees against.
+///
+/// The results are the ones of a `KdTree` holding the same points: ties are broken by the
+/// smallest index, NaN distances come last and coordinates large enough for the squared
+/// distances to overflow switch to `RobustEuclidean`. `within_radius` and `points_in_aabb`
+/// return the points in index order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinearSearch<const DIM: usize, POINT: KdTreePoint<DIM, S>, S: Scalar = f64> {
+    points: Vec<POINT>,
+
+    scalar: PhantomData<S>, //Type of the coordinates, only read from the points
+}
+
+impl<const DIM: usize, POINT: KdTreePoint<DIM, S>, S: Scalar> LinearSearch<DIM, POINT, S> {
+    ///Creates an empty search
+    pub fn new() -> Self {
+        Self { points: Vec::new(), scalar: PhantomData }
+    }
+
+    /// Returns true if the squared distances between `coord` and the points may overflow, like in `KdTree`.
+    fn distances_may_overflow(&self, coord: &[S; DIM]) -> bool {
+        may_overflow(DIM, self.points.iter().flat_map(|point| point.to_kdtree_point()).chain(coord.iter().copied()))
+    }
+
+    /// Returns the indices of the points with their distance to `coord`, the `RobustEuclidean` one if `robust`, squared otherwise.
+    fn distances<'a>(&'a self, coord: &'a [S; DIM], robust: bool) -> impl Iterator<Item = (usize, S)> + 'a {
+        self.points.iter().enumerate().map(move |(i, point)| {
+            let position = point.to_kdtree_point();
+            match robust {
+                true => (i, RobustEuclidean.distance(&position, coord)),
+                false => (i, squared_distance(&position, coord)),
+            }
+        })
+    }
+
+    ///Returns a reference to the nearest POINT using given coordinates
+    pub fn nearest_by_coord(&self, coord: &[S; DIM]) -> Option<&POINT> {
+        self.distances(coord, self.distances_may_overflow(coord))
+            .reduce(|best, candidate| if precedes(candidate, best) { candidate } else { best })
+            .map(|(i, _)| &self.points[i])
+    }
+
+    ///Returns a reference to the nearest POINT using another POINT
+    pub fn nearest(&self, target: &POINT) -> Option<&POINT> {
+        self.nearest_by_coord(&target.to_kdtree_point())
+    }
+
+    ///Returns references to the `k` nearest POINTs to the given coordinates, the nearest first
+    ///
+    /// Fewer points are returned if there are less than `k` points.
+    pub fn nearest_k(&self, coord: &[S; DIM], k: usize) -> Vec<&POINT> {
+        let mut found: Vec<(usize, S)> = self.distances(coord, self.distances_may_overflow(coord)).collect();
+        // `precedes` is a strict total order, the indices being distinct
+        found.sort_unstable_by(|a, b| if precedes(*a, *b) { Ordering::Less } else { Ordering::Greater });
+        found.into_iter().take(k).map(|(i, _)| &self.points[i]).collect()
+    }
+
+    ///Returns references to every POINT within `radius` of the given coordinates (boundary included)
+    pub fn within_radius(&self, coord: &[S; DIM], radius: S) -> Vec<&POINT> {
+        if radius < S::ZERO {
+            return Vec::new();
+        }
+        let robust = self.distances_may_overflow(coord);
+        let bound = if robust { radius } else { radius * radius };
+        self.distances(coord, robust)
+            .filter(|(_, distance)| *distance <= bound)
+            .map(|(i, _)| &self.points[i])
+            .collect()
+    }
+
+    ///Returns references to every POINT inside the box `[min, max]` (boundary included)
+    ///
+    /// A box with `min[axis] > max[axis]` on any axis is empty.
+    pub fn points_in_aabb(&self, min: &[S; DIM], max: &[S; DIM]) -> Vec<&POINT> {
+        self.points.iter().filter(|point| point_in_aabb(&point.to_kdtree_point(), min, max)).collect()
+    }
+
+    ///Adds a point, its index being the number of points before it
+    pub fn add_point(&mut self, point: POINT) {
+        self.points.push(point);
+    }
+
+    ///Returns the points, in index order
+    pub fn points(&self) -> &[POINT] {
+        &self.points
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    pub fn size(&self) -> usize {
+        self.points.len()
+    }
+}
+
+impl<const DIM: usize, POINT: KdTreePoint<DIM, S>, S: Scalar> Default for LinearSearch<DIM, POINT, S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const DIM: usize, POINT: KdTreePoint<DIM, S>, S: Scalar> From<Vec<POINT>> for LinearSearch<DIM, POINT, S> {
+    fn from(points: Vec<POINT>) -> Self {
+        Self { points, scalar: PhantomData }
+    }
+}
+
+impl<const DIM: usize, POINT: KdTreePoint<DIM, S>, S: Scalar> FromIterator<POINT> for LinearSearch<DIM, POINT, S> {
+    fn from_iter<I: IntoIterator<Item = POINT>>(iter: I) -> Self {
+        Self::from(iter.into_iter().collect::<Vec<_>>())
+    }
+}
@@ -0,0 +1,100 @@
+use super::{KdTree, PairPoint};
+
+///A spatial map, associating values to coordinates with at most one value per exact coordinates
+///
+/// It is a `KdTree` of `PairPoint`s whose insertions replace the value of an existing key
+/// instead of adding a duplicate point. `-0.` and `0.` are the same coordinate, a NaN coordinate
+/// only matches a NaN of the same bits.
+#[derive(Debug, Clone)]
+pub struct KdTreeMap<const DIM: usize, V> {
+    tree: KdTree<DIM, PairPoint<DIM, V>>,
+}
+
+impl<const DIM: usize, V> Default for KdTreeMap<DIM, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const DIM: usize, V> FromIterator<([f64; DIM], V)> for KdTreeMap<DIM, V> {
+    /// Inserts the entries in order, so a later entry replaces an earlier one with the same key.
+    fn from_iter<I: IntoIterator<Item = ([f64; DIM], V)>>(iter: I) -> Self {
+        let mut map = Self::new();
+        for (coord, value) in iter {
+            map.insert(coord, value);
+        }
+        map
+    }
+}
+
+impl<const DIM: usize, V> KdTreeMap<DIM, V> {
+    ///Constructs an empty map
+    pub fn new() -> Self {
+        Self { tree: KdTree::new() }
+    }
+
+    /// Returns the index of the entry whose key is exactly `coord`, through `KdTree::find_index`.
+    ///
+    /// No comparison places a NaN in the tree, such keys are compared by bits over all the entries.
+    fn find(&self, coord: &[f64; DIM]) -> Option<usize> {
+        if !coord.iter().any(|x| x.is_nan()) {
+            return self.tree.find_index(coord);
+        }
+        let same = |key: &[f64; DIM]| key.iter().zip(coord).all(|(a, b)| a == b || a.to_bits() == b.to_bits());
+        self.tree.points.iter().position(|p| same(p.coord()))
+    }
+
+    ///Inserts a value at the given coordinates
+    ///
+    /// # Returns:
+    /// - The previous value if the map already had an entry with exactly these coordinates, it is replaced.
+    pub fn insert(&mut self, coord: [f64; DIM], value: V) -> Option<V> {
+        match self.find(&coord) {
+            Some(index) => Some(std::mem::replace(self.tree.points[index].payload_mut(), value)),
+            None => {
+                self.tree.add_point(PairPoint::new(coord, value));
+                None
+            }
+        }
+    }
+
+    ///Returns the value stored at exactly the given coordinates
+    pub fn get(&self, coord: &[f64; DIM]) -> Option<&V> {
+        self.find(coord).map(|index| self.tree.points[index].payload())
+    }
+
+    ///Returns the entry whose key is the nearest to the given coordinates
+    pub fn get_nearest(&self, coord: &[f64; DIM]) -> Option<(&[f64; DIM], &V)> {
+        self.tree.nearest_by_coord(coord).map(|p| (p.coord(), p.payload()))
+    }
+
+    ///Returns the entry whose key is the nearest to the given coordinates, with mutable access to its value
+    pub fn get_nearest_mut(&mut self, coord: &[f64; DIM]) -> Option<(&[f64; DIM], &mut V)> {
+        let index = self.tree.nearest_index(coord)?;
+        Some(self.tree.points[index].parts_mut())
+    }
+
+    ///Returns every entry whose key is within `radius` of the given coordinates (boundary included)
+    pub fn within_radius(&self, coord: &[f64; DIM], radius: f64) -> Vec<(&[f64; DIM], &V)> {
+        self.tree.within_radius(coord, radius).into_iter().map(|p| (p.coord(), p.payload())).collect()
+    }
+
+    ///Removes the entry at exactly the given coordinates and returns its value
+    pub fn remove(&mut self, coord: &[f64; DIM]) -> Option<V> {
+        let index = self.find(coord)?;
+        self.tree.remove_indices(&[index]).pop().map(|p| p.into_inner().1)
+    }
+
+    pub fn len(&self) -> usize {
+        self.tree.size()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tree.size() == 0
+    }
+
+    ///Returns an iterator over the entries, in insertion order
+    pub fn iter(&self) -> impl Iterator<Item = (&[f64; DIM], &V)> {
+        self.tree.points.iter().map(|p| (p.coord(), p.payload()))
+    }
+}
@@ -0,0 +1,467 @@
+use super::{may_reach, precedes, squared_distance, sum_over_axes, Aabb, Columns, KdTree, KdTreePoint, KdTreeRef, Node, NodeArena, QueryStats, Scalar, SearchStack};
+
+///Distance used by the `*_with_metric` queries of `KdTree`
+///
+/// The queries skip a subtree when the lower bound of the distance to its side of a split is
+/// larger than the best distance found so far, so `axis_lower_bound` must never exceed the
+/// distance between two points whose coordinates differ by `axis_delta` on one axis. Metrics
+/// for which this bound depends on the position of the target override `split_lower_bound`.
+pub trait Metric<const DIM: usize, S: Scalar = f64> {
+    ///Returns the distance between two points
+    fn distance(&self, a: &[S; DIM], b: &[S; DIM]) -> S;
+
+    ///Returns a lower bound of the distance between two points differing by `axis_delta` on an axis
+    fn axis_lower_bound(&self, axis_delta: S) -> S;
+
+    ///Returns a lower bound of the distance from `target` to any point on the other side of
+    ///the split value `split` on `axis`, `axis_lower_bound(target[axis] - split)` by default
+    fn split_lower_bound(&self, target: &[S; DIM], axis: usize, split: S) -> S {
+        self.axis_lower_bound(target[axis] - split)
+    }
+}
+
+///The usual straight-line distance (L2)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Euclidean;
+
+///The straight-line distance (L2) computed without overflow, for coordinates of extreme magnitudes
+///
+/// The differences are taken between halved coordinates and divided by the largest of them
+/// before being squared, so any distance up to the largest finite value is computed instead of
+/// overflowing to infinity as soon as a difference goes past the square root of it. Slower than
+/// `Euclidean`, the plain queries of `KdTree` switch to it by themselves when the bounding box of
+/// the points or the target holds coordinates large enough to overflow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RobustEuclidean;
+
+///The sum of the absolute differences of the coordinates (L1)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Manhattan;
+
+///The largest absolute difference of the coordinates (L∞)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Chebyshev;
+
+///The Lp distance of the given order `p`, which must be at least 1 to be a metric
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Minkowski(pub f64);
+
+impl<const DIM: usize, S: Scalar> Metric<DIM, S> for Euclidean {
+    fn distance(&self, a: &[S; DIM], b: &[S; DIM]) -> S {
+        squared_distance(a, b).sqrt()
+    }
+
+    fn axis_lower_bound(&self, axis_delta: S) -> S {
+        axis_delta.abs()
+    }
+}
+
+impl<const DIM: usize, S: Scalar> Metric<DIM, S> for RobustEuclidean {
+    fn distance(&self, a: &[S; DIM], b: &[S; DIM]) -> S {
+        robust_distance(a, b)
+    }
+
+    fn axis_lower_bound(&self, axis_delta: S) -> S {
+        axis_delta.abs()
+    }
+
+    /// Halved like in `distance`, so that a split far from the target doesn't overflow either.
+    fn split_lower_bound(&self, target: &[S; DIM], axis: usize, split: S) -> S {
+        robust_split_distance(target[axis], split)
+    }
+}
+
+/// Computes the `RobustEuclidean` distance between two points of the same number of coordinates.
+pub(super) fn robust_distance<S: Scalar>(a: &[S], b: &[S]) -> S {
+    let (half, two) = (S::from_f64(0.5), S::from_f64(2.));
+    let deltas = || a.iter().zip(b).map(|(x, y)| *x * half - *y * half);
+    let largest = deltas().fold(S::ZERO, |largest, delta| largest.max(delta.abs()));
+    // Equal points and infinite coordinates give 0, NaN or infinity like `Euclidean`
+    if largest == S::ZERO || largest == S::INFINITY {
+        return deltas().fold(S::ZERO, |acc, delta| acc + delta * delta).sqrt() * two;
+    }
+    // Every ratio is at most 1 and one of them is 1, so the result is never below `largest`
+    deltas().fold(S::ZERO, |acc, delta| acc + (delta / largest) * (delta / largest)).sqrt() * largest * two
+}
+
+/// Computes the `RobustEuclidean` distance between a coordinate and a split value on the same axis.
+pub(super) fn robust_split_distance<S: Scalar>(coord: S, split: S) -> S {
+    let half = S::from_f64(0.5);
+    (coord * half - split * half).abs() * S::from_f64(2.)
+}
+
+impl<const DIM: usize, S: Scalar> Metric<DIM, S> for Manhattan {
+    fn distance(&self, a: &[S; DIM], b: &[S; DIM]) -> S {
+        sum_over_axes::<DIM, S, _>(|axis| (a[axis] - b[axis]).abs())
+    }
+
+    fn axis_lower_bound(&self, axis_delta: S) -> S {
+        axis_delta.abs()
+    }
+}
+
+impl<const DIM: usize, S: Scalar> Metric<DIM, S> for Chebyshev {
+    fn distance(&self, a: &[S; DIM], b: &[S; DIM]) -> S {
+        a.iter().zip(b).fold(S::ZERO, |acc, (x, y)| acc.max((*x - *y).abs()))
+    }
+
+    fn axis_lower_bound(&self, axis_delta: S) -> S {
+        axis_delta.abs()
+    }
+}
+
+impl<const DIM: usize, S: Scalar> Metric<DIM, S> for Minkowski {
+    fn distance(&self, a: &[S; DIM], b: &[S; DIM]) -> S {
+        let p = S::from_f64(self.0);
+        a.iter()
+            .zip(b)
+            .fold(S::ZERO, |acc, (x, y)| acc + (*x - *y).abs().powf(p))
+            .powf(S::from_f64(1. / self.0))
+    }
+
+    fn axis_lower_bound(&self, axis_delta: S) -> S {
+        axis_delta.abs()
+    }
+}
+
+///The great-circle distance between `[latitude, longitude]` points in degrees
+///
+/// Latitudes are expected in `[-90, 90]` and longitudes in `[-180, 180]`, points on both sides
+/// of the antimeridian are near each other. The distance is expressed in the unit of `radius`,
+/// kilometers on the Earth for the default value.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Haversine {
+    pub radius: f64,
+}
+
+impl Haversine {
+    ///Mean radius of the Earth in kilometers
+    pub const EARTH_RADIUS_KM: f64 = 6371.0088;
+}
+
+impl Default for Haversine {
+    fn default() -> Self {
+        Self { radius: Self::EARTH_RADIUS_KM }
+    }
+}
+
+impl<S: Scalar> Metric<2, S> for Haversine {
+    fn distance(&self, a: &[S; 2], b: &[S; 2]) -> S {
+        let (lat_a, lat_b) = (a[0].to_f64().to_radians(), b[0].to_f64().to_radians());
+        let delta_lat = lat_b - lat_a;
+        let delta_lon = (b[1].to_f64() - a[1].to_f64()).to_radians();
+        let h = (delta_lat / 2.).sin().powi(2) + lat_a.cos() * lat_b.cos() * (delta_lon / 2.).sin().powi(2);
+        S::from_f64(2. * self.radius * h.sqrt().min(1.).asin())
+    }
+
+    /// The bound of a longitude split depends on the latitude of the target, see `split_lower_bound`.
+    fn axis_lower_bound(&self, _axis_delta: S) -> S {
+        S::ZERO
+    }
+
+    fn split_lower_bound(&self, target: &[S; 2], axis: usize, split: S) -> S {
+        let (lat, lon, split) = (target[0].to_f64(), target[1].to_f64(), split.to_f64());
+        let bound = if axis == 0 {
+            // Any point beyond a parallel is at least the latitude difference away
+            (lat - split).abs().to_radians()
+        } else {
+            // The other side reaches the target through the split meridian or around the antimeridian
+            let gap = if lon < split {
+                (split - lon).min(lon + 180.)
+            } else {
+                (lon - split).min(180. - lon)
+            };
+            // Distance to a meridian `gap` degrees away, or to the pole past 90 degrees
+            (lat.to_radians().cos() * gap.clamp(0., 90.).to_radians().sin()).min(1.).asin()
+        };
+        // Slightly lowered so that rounding never prunes a branch holding the answer
+        S::from_f64(self.radius * bound * (1. - 1e-12))
+    }
+}
+
+///The great-circle distance between `[longitude, latitude]` points in degrees, like `Haversine`
+///
+/// The order of the GeoJSON coordinates and of `GeoFeature`, so that the trees built by
+/// `KdTree::from_geojson_points` can be queried directly.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HaversineLonLat {
+    pub radius: f64,
+}
+
+impl HaversineLonLat {
+    /// Returns the `Haversine` metric on the same sphere, taking the latitude first.
+    fn lat_lon(&self) -> Haversine {
+        Haversine { radius: self.radius }
+    }
+}
+
+impl Default for HaversineLonLat {
+    fn default() -> Self {
+        Self { radius: Haversine::EARTH_RADIUS_KM }
+    }
+}
+
+impl<S: Scalar> Metric<2, S> for HaversineLonLat {
+    fn distance(&self, a: &[S; 2], b: &[S; 2]) -> S {
+        self.lat_lon().distance(&[a[1], a[0]], &[b[1], b[0]])
+    }
+
+    fn axis_lower_bound(&self, _axis_delta: S) -> S {
+        S::ZERO
+    }
+
+    fn split_lower_bound(&self, target: &[S; 2], axis: usize, split: S) -> S {
+        self.lat_lon().split_lower_bound(&[target[1], target[0]], 1 - axis, split)
+    }
+}
+
+impl<const DIM: usize, S: Scalar> Node<DIM, S> {
+    /// Finds the nearest neighbor to the target point under a metric, walking the tree with an explicit stack.
+    ///
+    /// # Parameters:
+    /// - `nodes`: The nodes of the tree.
+    /// - `columns`: The coordinates of the points of the tree.
+    /// - `target`: The coordinates of the target point.
+    /// - `metric`: The distance to minimize.
+    ///
+    /// # Returns:
+    /// - An `Option` containing the index of the nearest point and its distance.
+    fn nearest_with_metric<M: Metric<DIM, S>>(
+        &self,
+        nodes: &NodeArena<DIM, S>,
+        columns: &Columns<'_, DIM, S>,
+        target: &[S; DIM],
+        metric: &M,
+    ) -> Option<(usize, S)> {
+        let mut best: Option<(usize, S)> = None;
+        // Nodes to search with, for the far sides, the lower bound of the distance of their points to the target
+        let mut stack = vec![(self, None)];
+        while let Some((node, lower_bound)) = stack.pop() {
+            if let (Some(lower_bound), Some((_, best_distance))) = (lower_bound, best) {
+                if !may_reach(lower_bound, best_distance) {
+                    continue;
+                }
+            }
+            for candidate in node.live_points(columns) {
+                let distance = metric.distance(&columns.position(candidate), target);
+                if best.is_none_or(|best| precedes((candidate, distance), best)) {
+                    best = Some((candidate, distance));
+                }
+            }
+
+            let delta = target[node.axis] - node.split;
+            let (next, opposite_branch) = if delta < S::ZERO {
+                (node.left, node.right)
+            } else {
+                (node.right, node.left)
+            };
+            if let Some(opposite_branch) = nodes.get(opposite_branch) {
+                stack.push((opposite_branch, Some(metric.split_lower_bound(target, node.axis, node.split))));
+            }
+            stack.extend(nodes.get(next).map(|next| (next, None)));
+        }
+        best
+    }
+
+    /// Collects the `k` nearest points to the target point under a metric, walking the tree with an explicit stack.
+    ///
+    /// `best` holds the indices of the best candidates found so far and their distances to the target, sorted like in `nearest_k`.
+    fn nearest_k_with_metric<M: Metric<DIM, S>>(
+        &self,
+        nodes: &NodeArena<DIM, S>,
+        columns: &Columns<'_, DIM, S>,
+        target: &[S; DIM],
+        k: usize,
+        metric: &M,
+        best: &mut Vec<(usize, S)>,
+    ) {
+        // Nodes to search with, for the far sides, the lower bound of the distance of their points to the target
+        let mut stack = vec![(self, None)];
+        while let Some((node, lower_bound)) = stack.pop() {
+            if lower_bound.is_some_and(|lower_bound| best.len() >= k && !may_reach(lower_bound, best[best.len() - 1].1)) {
+                continue;
+            }
+            for candidate in node.live_points(columns) {
+                let distance = metric.distance(&columns.position(candidate), target);
+                if best.len() < k || precedes((candidate, distance), best[best.len() - 1]) {
+                    let position = best.partition_point(|&found| precedes(found, (candidate, distance)));
+                    best.insert(position, (candidate, distance));
+                    best.truncate(k);
+                }
+            }
+
+            let (next, opposite_branch) = if target[node.axis] < node.split {
+                (node.left, node.right)
+            } else {
+                (node.right, node.left)
+            };
+            if let Some(opposite_branch) = nodes.get(opposite_branch) {
+                stack.push((opposite_branch, Some(metric.split_lower_bound(target, node.axis, node.split))));
+            }
+            stack.extend(nodes.get(next).map(|next| (next, None)));
+        }
+    }
+
+    /// Collects the indices of the points within a radius of the target point under a metric, walking the tree with an explicit stack.
+    fn within_radius_with_metric<M: Metric<DIM, S>>(
+        &self,
+        nodes: &NodeArena<DIM, S>,
+        columns: &Columns<'_, DIM, S>,
+        target: &[S; DIM],
+        radius: S,
+        metric: &M,
+        found: &mut Vec<usize>,
+    ) {
+        let mut stack = vec![self];
+        while let Some(node) = stack.pop() {
+            for index in node.live_points(columns) {
+                if metric.distance(&columns.position(index), target) <= radius {
+                    found.push(index);
+                }
+            }
+
+            // The right child is pushed first so that the left one is searched first
+            let delta = target[node.axis] - node.split;
+            let reachable = metric.split_lower_bound(target, node.axis, node.split) <= radius;
+            if let Some(right) = nodes.get(node.right) {
+                if delta >= S::ZERO || reachable {
+                    stack.push(right);
+                }
+            }
+            if let Some(left) = nodes.get(node.left) {
+                if delta <= S::ZERO || reachable {
+                    stack.push(left);
+                }
+            }
+        }
+    }
+
+    /// Returns the index of the nearest point to `coord` under `metric` in the tree of `nodes`.
+    fn nearest_index_with_metric<M: Metric<DIM, S>>(nodes: &NodeArena<DIM, S>, columns: &Columns<'_, DIM, S>, coord: &[S; DIM], metric: &M) -> Option<usize> {
+        nodes.root().and_then(|n| n.nearest_with_metric(nodes, columns, coord, metric)).map(|(index, _)| index)
+    }
+
+    /// Returns the indices of the `k` nearest points to `coord` under `metric` in the tree of `nodes`, the nearest first.
+    fn nearest_k_indices_with_metric<M: Metric<DIM, S>>(nodes: &NodeArena<DIM, S>, columns: &Columns<'_, DIM, S>, coord: &[S; DIM], k: usize, metric: &M) -> Vec<usize> {
+        let mut best = Vec::with_capacity(k.min(columns.len()) + 1);
+        if let (Some(root), true) = (nodes.root(), k > 0) {
+            root.nearest_k_with_metric(nodes, columns, coord, k, metric, &mut best);
+        }
+        best.into_iter().map(|(index, _)| index).collect()
+    }
+
+    /// Returns the indices of the points within `radius` of `coord` under `metric` in the tree of `nodes`.
+    fn within_radius_indices_with_metric<M: Metric<DIM, S>>(nodes: &NodeArena<DIM, S>, columns: &Columns<'_, DIM, S>, coord: &[S; DIM], radius: S, metric: &M) -> Vec<usize> {
+        let mut found = Vec::new();
+        if radius >= S::ZERO {
+            if let Some(root) = nodes.root() {
+                root.within_radius_with_metric(nodes, columns, coord, radius, metric, &mut found);
+            }
+        }
+        found
+    }
+}
+
+/// Returns true if squared distances between points of `DIM` coordinates among `coords` may overflow.
+///
+/// They can't while every coordinate is at most `sqrt(MAX / DIM) / 2` in magnitude, infinite
+/// coordinates giving infinite distances anyway.
+pub(super) fn may_overflow<S: Scalar>(dim: usize, coords: impl Iterator<Item = S>) -> bool {
+    let magnitude = coords.fold(0., |magnitude: f64, x| magnitude.max(x.to_f64().abs()));
+    magnitude.is_finite() && magnitude > (S::MAX.to_f64() / dim as f64).sqrt() / 2.
+}
+
+/// Returns true if the squared distances between `coord` and points inside `bounds` may overflow, false without points.
+///
+/// The box of the points bounds their coordinates, see `may_overflow`.
+pub(super) fn box_may_overflow<const DIM: usize, S: Scalar>(bounds: Option<&Aabb<DIM, S>>, coord: &[S; DIM]) -> bool {
+    bounds.is_some_and(|bounds| may_overflow(DIM, bounds.min.iter().chain(&bounds.max).chain(coord).copied()))
+}
+
+impl<const DIM: usize, POINT: KdTreePoint<DIM, S>, S: Scalar> KdTree<DIM, POINT, S> {
+    /// Returns true if the squared distances between `coord` and the points may overflow, so that the queries use `RobustEuclidean`.
+    pub(super) fn distances_may_overflow(&self, coord: &[S; DIM]) -> bool {
+        box_may_overflow(self.bounds.as_ref(), coord)
+    }
+
+    /// Returns the index of the nearest point to `coord`, searched with `RobustEuclidean` if the squared distances may overflow.
+    pub(super) fn nearest_index(&self, coord: &[S; DIM]) -> Option<usize> {
+        if self.distances_may_overflow(coord) {
+            return Node::nearest_index_with_metric(&self.nodes, &self.columns(), coord, &RobustEuclidean);
+        }
+        Node::nearest_index(&self.nodes, &self.columns(), coord, &mut QueryStats::default())
+    }
+
+    /// Replaces the content of `best` by the `k` nearest points to `coord`, the nearest first, like `nearest_index`.
+    ///
+    /// The distances are the robust ones, not squared, when the search switches to `RobustEuclidean`.
+    /// The plain search runs with `stack`, so that batches and workspaces reuse it.
+    pub(super) fn nearest_k_candidates(&self, coord: &[S; DIM], k: usize, best: &mut Vec<(usize, S)>, stack: &mut SearchStack<S>) {
+        if !self.distances_may_overflow(coord) {
+            return Node::nearest_k_candidates(&self.nodes, &self.columns(), coord, k, best, stack, &mut QueryStats::default());
+        }
+        best.clear();
+        if let (Some(root), true) = (self.nodes.root(), k > 0) {
+            root.nearest_k_with_metric(&self.nodes, &self.columns(), coord, k, &RobustEuclidean, best);
+        }
+    }
+
+    /// Pushes the indices of the points within `radius` of `coord` into `found`, like `nearest_index`, searching with `stack`.
+    pub(super) fn within_radius_into(&self, coord: &[S; DIM], radius: S, found: &mut Vec<usize>, stack: &mut SearchStack<S>) {
+        if !self.distances_may_overflow(coord) {
+            return Node::within_radius_into(&self.nodes, &self.columns(), coord, radius, found, stack, &mut QueryStats::default());
+        }
+        if let (Some(root), true) = (self.nodes.root(), radius >= S::ZERO) {
+            root.within_radius_with_metric(&self.nodes, &self.columns(), coord, radius, &RobustEuclidean, found);
+        }
+    }
+
+    ///Returns a reference to the nearest POINT to the given coordinates under `metric`
+    pub fn nearest_by_coord_with_metric<M: Metric<DIM, S>>(&self, coord: &[S; DIM], metric: &M) -> Option<&POINT> {
+        Node::nearest_index_with_metric(&self.nodes, &self.columns(), coord, metric).map(|i| &self.points[i])
+    }
+
+    ///Returns references to the `k` nearest POINTs to the given coordinates under `metric`, the nearest first
+    pub fn nearest_k_with_metric<M: Metric<DIM, S>>(&self, coord: &[S; DIM], k: usize, metric: &M) -> Vec<&POINT> {
+        Node::nearest_k_indices_with_metric(&self.nodes, &self.columns(), coord, k, metric)
+            .into_iter()
+            .map(|i| &self.points[i])
+            .collect()
+    }
+
+    ///Returns references to every POINT within `radius` of the given coordinates under `metric` (boundary included)
+    pub fn within_radius_with_metric<M: Metric<DIM, S>>(&self, coord: &[S; DIM], radius: S, metric: &M) -> Vec<&POINT> {
+        Node::within_radius_indices_with_metric(&self.nodes, &self.columns(), coord, radius, metric)
+            .into_iter()
+            .map(|i| &self.points[i])
+            .collect()
+    }
+}
+
+impl<'a, const DIM: usize, POINT: KdTreePoint<DIM, S>, S: Scalar> KdTreeRef<'a, DIM, POINT, S> {
+    /// Returns true if the squared distances between `coord` and the points may overflow, like in `KdTree`.
+    pub(super) fn distances_may_overflow(&self, coord: &[S; DIM]) -> bool {
+        box_may_overflow(self.bounds.as_ref(), coord)
+    }
+
+    ///Returns a reference to the nearest POINT to the given coordinates under `metric`
+    pub fn nearest_by_coord_with_metric<M: Metric<DIM, S>>(&self, coord: &[S; DIM], metric: &M) -> Option<&'a POINT> {
+        Node::nearest_index_with_metric(&self.nodes, &self.columns(), coord, metric).map(|i| &self.points[i])
+    }
+
+    ///Returns references to the `k` nearest POINTs to the given coordinates under `metric`, the nearest first
+    pub fn nearest_k_with_metric<M: Metric<DIM, S>>(&self, coord: &[S; DIM], k: usize, metric: &M) -> Vec<&'a POINT> {
+        Node::nearest_k_indices_with_metric(&self.nodes, &self.columns(), coord, k, metric)
+            .into_iter()
+            .map(|i| &self.points[i])
+            .collect()
+    }
+
+    ///Returns references to every POINT within `radius` of the given coordinates under `metric` (boundary included)
+    pub fn within_radius_with_metric<M: Metric<DIM, S>>(&self, coord: &[S; DIM], radius: S, metric: &M) -> Vec<&'a POINT> {
+        Node::within_radius_indices_with_metric(&self.nodes, &self.columns(), coord, radius, metric)
+            .into_iter()
+            .map(|i| &self.points[i])
+            .collect()
+    }
+}
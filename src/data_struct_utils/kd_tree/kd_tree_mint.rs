@@ -0,0 +1,28 @@
+//! `KdTreePoint` implementations for the `mint` points and vectors, enabled by the `mint` feature.
+
+use mint::{Point2, Point3, Vector2, Vector3};
+
+use super::{KdTreePoint, KdTreePointMut, Scalar};
+
+macro_rules! impl_mint_point {
+    ($($point:ident => $dim:literal;)*) => {$(
+        impl<S: Scalar> KdTreePoint<$dim, S> for $point<S> {
+            fn to_kdtree_point(&self) -> [S; $dim] {
+                *self.as_ref()
+            }
+        }
+
+        impl<S: Scalar> KdTreePointMut<$dim, S> for $point<S> {
+            fn set_kdtree_point(&mut self, coords: &[S; $dim]) {
+                *self = (*coords).into();
+            }
+        }
+    )*};
+}
+
+impl_mint_point! {
+    Point2 => 2;
+    Point3 => 3;
+    Vector2 => 2;
+    Vector3 => 3;
+}
@@ -0,0 +1,45 @@
+//! `KdTreePoint` implementations for the `nalgebra` points and vectors, enabled by the `nalgebra` feature.
+
+use nalgebra::{DMatrix, Point, SVector};
+
+use super::{BuildError, KdTree, KdTreePoint, KdTreePointMut, Scalar};
+
+impl<const DIM: usize, S: Scalar + nalgebra::Scalar> KdTreePoint<DIM, S> for SVector<S, DIM> {
+    fn to_kdtree_point(&self) -> [S; DIM] {
+        self.data.0[0]
+    }
+}
+
+impl<const DIM: usize, S: Scalar + nalgebra::Scalar> KdTreePointMut<DIM, S> for SVector<S, DIM> {
+    fn set_kdtree_point(&mut self, coords: &[S; DIM]) {
+        self.data.0[0] = *coords;
+    }
+}
+
+impl<const DIM: usize, S: Scalar + nalgebra::Scalar> KdTreePoint<DIM, S> for Point<S, DIM> {
+    fn to_kdtree_point(&self) -> [S; DIM] {
+        self.coords.to_kdtree_point()
+    }
+}
+
+impl<const DIM: usize, S: Scalar + nalgebra::Scalar> KdTreePointMut<DIM, S> for Point<S, DIM> {
+    fn set_kdtree_point(&mut self, coords: &[S; DIM]) {
+        self.coords.set_kdtree_point(coords);
+    }
+}
+
+impl<const DIM: usize, S: Scalar + nalgebra::Scalar> KdTree<DIM, SVector<S, DIM>, S> {
+    ///Constructs a Kd-Tree whose points are the columns of a matrix
+    ///
+    /// # Errors:
+    /// - `BuildError::DimensionMismatch` if the matrix doesn't have `DIM` rows.
+    pub fn from_matrix_columns(matrix: &DMatrix<S>) -> Result<Self, BuildError> {
+        if matrix.nrows() != DIM {
+            return Err(BuildError::DimensionMismatch { expected: DIM, found: matrix.nrows() });
+        }
+        Ok(Self::from_iter_with_capacity(
+            matrix.column_iter().map(|column| SVector::from_iterator(column.iter().copied())),
+            matrix.ncols(),
+        ))
+    }
+}
@@ -0,0 +1,154 @@
+use std::io::{self, Read, Write};
+
+use super::{KdTree, NpyError};
+
+/// First bytes of every `.npy` file.
+const NPY_MAGIC: &[u8; 6] = b"\x93NUMPY";
+
+/// Returns the value of `key` in the Python dict literal of a `.npy` header, up to the next top-level comma.
+fn header_value<'a>(header: &'a str, key: &str) -> Result<&'a str, NpyError> {
+    let missing = || NpyError::InvalidHeader { reason: format!("missing {key}") };
+    let start = header.find(&format!("'{key}'")).ok_or_else(missing)? + key.len() + 2;
+    let rest = header[start..].trim_start().strip_prefix(':').ok_or_else(missing)?.trim_start();
+    // Values are strings, booleans or tuples, only tuples contain commas
+    let end = match rest.starts_with('(') {
+        true => rest.find(')').map(|i| i + 1),
+        false => rest.find([',', '}']),
+    };
+    Ok(rest[..end.ok_or_else(missing)?].trim())
+}
+
+/// Element type of the arrays read by `from_npy`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NpyFloat {
+    F32,
+    F64,
+}
+
+/// Parses the header of a `.npy` file, returning the element type and the shape.
+fn read_npy_header<R: Read>(r: &mut R) -> Result<(NpyFloat, Vec<usize>), NpyError> {
+    let mut preamble = [0; 8];
+    read_npy_bytes(r, &mut preamble)?;
+    if &preamble[..6] != NPY_MAGIC {
+        return Err(NpyError::BadMagic);
+    }
+    let (major, minor) = (preamble[6], preamble[7]);
+    let len = match major {
+        1 => {
+            let mut len = [0; 2];
+            read_npy_bytes(r, &mut len)?;
+            u16::from_le_bytes(len) as usize
+        }
+        2 | 3 => {
+            let mut len = [0; 4];
+            read_npy_bytes(r, &mut len)?;
+            u32::from_le_bytes(len) as usize
+        }
+        _ => return Err(NpyError::UnsupportedVersion { major, minor }),
+    };
+    let mut header = vec![0; len];
+    read_npy_bytes(r, &mut header)?;
+    let header = String::from_utf8(header).map_err(|_| NpyError::InvalidHeader { reason: "not UTF-8".into() })?;
+
+    let descr = header_value(&header, "descr")?.trim_matches(['\'', '"']);
+    let dtype = match descr {
+        "<f8" => NpyFloat::F64,
+        "<f4" => NpyFloat::F32,
+        descr => return Err(NpyError::UnsupportedDtype { descr: descr.to_string() }),
+    };
+    match header_value(&header, "fortran_order")? {
+        "False" => {}
+        "True" => return Err(NpyError::FortranOrder),
+        value => return Err(NpyError::InvalidHeader { reason: format!("invalid fortran_order {value}") }),
+    }
+    let shape = header_value(&header, "shape")?;
+    let shape = shape
+        .trim_start_matches('(')
+        .trim_end_matches(')')
+        .split(',')
+        .map(str::trim)
+        .filter(|dim| !dim.is_empty())
+        .map(|dim| dim.parse().map_err(|_| NpyError::InvalidHeader { reason: format!("invalid shape {shape}") }))
+        .collect::<Result<Vec<usize>, _>>()?;
+    Ok((dtype, shape))
+}
+
+/// Reads exactly `bytes.len()` bytes, a premature end of the input being a truncated file.
+fn read_npy_bytes<R: Read>(r: &mut R, bytes: &mut [u8]) -> Result<(), NpyError> {
+    r.read_exact(bytes).map_err(|error| match error.kind() {
+        io::ErrorKind::UnexpectedEof => NpyError::Truncated,
+        kind => NpyError::Io(kind),
+    })
+}
+
+/// Writes a version 1.0 header, padded with spaces so that the data starts on a multiple of 64 bytes.
+fn write_npy_header<W: Write>(w: &mut W, descr: &str, shape: &str) -> io::Result<()> {
+    let mut header = format!("{{'descr': '{descr}', 'fortran_order': False, 'shape': {shape}, }}");
+    let unpadded = NPY_MAGIC.len() + 2 + 2 + header.len() + 1;
+    header.extend(std::iter::repeat_n(' ', unpadded.next_multiple_of(64) - unpadded));
+    header.push('\n');
+    w.write_all(NPY_MAGIC)?;
+    w.write_all(&[1, 0])?;
+    w.write_all(&(header.len() as u16).to_le_bytes())?;
+    w.write_all(header.as_bytes())
+}
+
+///Writes indices as a `.npy` file holding a 1-D `uint64` array, to hand query results back to NumPy
+///
+/// # Errors:
+/// - Any error of the writer.
+pub fn write_indices_npy<W: Write>(indices: &[u64], mut w: W) -> io::Result<()> {
+    write_npy_header(&mut w, "<u8", &format!("({},)", indices.len()))?;
+    for index in indices {
+        w.write_all(&index.to_le_bytes())?;
+    }
+    w.flush()
+}
+
+impl<const DIM: usize> KdTree<DIM, [f64; DIM], f64> {
+    ///Constructs a balanced Kd-Tree from a `.npy` file holding an array of shape `(n, DIM)`
+    ///
+    /// The array must be in C order, of little-endian `float64` or `float32` converted to `f64`.
+    ///
+    /// # Errors:
+    /// - `NpyError::BadMagic` if the input is not a `.npy` file.
+    /// - `NpyError::UnsupportedVersion` if the file has another version than 1.0, 2.0 or 3.0.
+    /// - `NpyError::InvalidHeader` if the header can't be parsed.
+    /// - `NpyError::UnsupportedDtype` if the array is not of `<f8` or `<f4`.
+    /// - `NpyError::FortranOrder` if the array is in Fortran order.
+    /// - `NpyError::ShapeMismatch` if the array is not of shape `(n, DIM)`.
+    /// - `NpyError::NonFiniteCoordinate` if a coordinate is NaN or infinite.
+    /// - `NpyError::Truncated` if the input ends before the end of the array.
+    /// - `NpyError::Io` on any other error of the reader.
+    pub fn from_npy<R: Read>(mut r: R) -> Result<Self, NpyError> {
+        let (dtype, shape) = read_npy_header(&mut r)?;
+        let len = match shape.as_slice() {
+            [len, dim] if *dim == DIM => *len,
+            _ => return Err(NpyError::ShapeMismatch { shape, dim: DIM }),
+        };
+
+        let mut points = Vec::with_capacity(len.min(1 << 16));
+        for row in 0..len {
+            let mut point = [0.; DIM];
+            for (column, x) in point.iter_mut().enumerate() {
+                *x = match dtype {
+                    NpyFloat::F64 => {
+                        let mut bytes = [0; 8];
+                        read_npy_bytes(&mut r, &mut bytes)?;
+                        f64::from_le_bytes(bytes)
+                    }
+                    NpyFloat::F32 => {
+                        let mut bytes = [0; 4];
+                        read_npy_bytes(&mut r, &mut bytes)?;
+                        f32::from_le_bytes(bytes) as f64
+                    }
+                };
+                if !x.is_finite() {
+                    return Err(NpyError::NonFiniteCoordinate { row, column });
+                }
+            }
+            points.push(point);
+        }
+        Ok(Self::from(points))
+    }
+}
@@ -0,0 +1,62 @@
+use super::{KdTree, KdTreePoint, KdTreePointMut};
+
+///A point associated with a payload, to store any data in a Kd-Tree without implementing `KdTreePoint`
+#[derive(Debug, Clone, PartialEq)]
+pub struct PairPoint<const DIM: usize, T> {
+    coord: [f64; DIM],
+    payload: T,
+}
+
+impl<const DIM: usize, T> PairPoint<DIM, T> {
+    pub fn new(coord: [f64; DIM], payload: T) -> Self {
+        Self { coord, payload }
+    }
+
+    ///Returns the coordinates of the point
+    pub fn coord(&self) -> &[f64; DIM] {
+        &self.coord
+    }
+
+    pub fn payload(&self) -> &T {
+        &self.payload
+    }
+
+    pub fn payload_mut(&mut self) -> &mut T {
+        &mut self.payload
+    }
+
+    ///Returns the coordinates along with mutable access to the payload
+    pub fn parts_mut(&mut self) -> (&[f64; DIM], &mut T) {
+        (&self.coord, &mut self.payload)
+    }
+
+    ///Returns the coordinates and the payload of the point
+    pub fn into_inner(self) -> ([f64; DIM], T) {
+        (self.coord, self.payload)
+    }
+}
+
+impl<const DIM: usize, T> From<([f64; DIM], T)> for PairPoint<DIM, T> {
+    fn from((coord, payload): ([f64; DIM], T)) -> Self {
+        Self::new(coord, payload)
+    }
+}
+
+impl<const DIM: usize, T> KdTreePoint<DIM> for PairPoint<DIM, T> {
+    fn to_kdtree_point(&self) -> [f64; DIM] {
+        self.coord
+    }
+}
+
+impl<const DIM: usize, T> KdTreePointMut<DIM> for PairPoint<DIM, T> {
+    fn set_kdtree_point(&mut self, coords: &[f64; DIM]) {
+        self.coord = *coords;
+    }
+}
+
+impl<const DIM: usize, T> KdTree<DIM, PairPoint<DIM, T>> {
+    ///Constructs a Kd-Tree from (coordinates, payload) pairs
+    pub fn from_pairs(pairs: Vec<([f64; DIM], T)>) -> Self {
+        pairs.into_iter().map(PairPoint::from).collect()
+    }
+}
@@ -0,0 +1,139 @@
+//! Parallel versions of the construction and queries, enabled by the `rayon` feature.
+
+use rayon::iter::IntoParallelIterator;
+use rayon::prelude::{IntoParallelRefIterator, IntoParallelRefMutIterator, ParallelIterator};
+
+use super::{Aabb, BuildEntry, ColumnStore, Columns, KdTree, KdTreeBuilder, KdTreePoint, Node, NodeArena, NodeId, Scalar, SearchStack, NONE};
+
+/// Subsets smaller than this are constructed sequentially, spawning tasks for them costs more than it saves.
+const PARALLEL_THRESHOLD: usize = 4096;
+
+impl<const DIM: usize, S: Scalar> NodeArena<DIM, S> {
+    /// Moves the nodes of a freshly constructed arena after the nodes of this one, returning the new position of its root.
+    fn append(&mut self, other: Self) -> NodeId {
+        let offset = self.nodes.len() as NodeId;
+        let shift = |id: NodeId| if id == NONE { NONE } else { id + offset };
+        self.nodes.extend(other.nodes.into_iter().map(|mut node| {
+            (node.left, node.right) = (shift(node.left), shift(node.right));
+            node
+        }));
+        shift(other.root)
+    }
+}
+
+impl<const DIM: usize, S: Scalar> Node<DIM, S> {
+    /// Constructs a Kd-Tree recursively, large subsets have their two subtrees constructed in parallel.
+    ///
+    /// The two recursive calls work on disjoint slices of entries and arenas, which are appended
+    /// to `nodes` in pre-order, so the result is identical to the one of `construct_kdtree`.
+    fn construct_kdtree_par(entries: &mut [BuildEntry<DIM, S>], depth: usize, builder: &KdTreeBuilder, nodes: &mut NodeArena<DIM, S>) -> NodeId {
+        if entries.len() < PARALLEL_THRESHOLD || entries.len() <= builder.leaf_size {
+            return Self::construct_kdtree(entries, depth, builder, nodes);
+        }
+        let axis = builder.axis_strategy.split_axis(entries, depth);
+
+        let (left, index, right, split) = Self::partition(entries, axis, builder);
+        let id = nodes.push(Self { index, axis, split, bucket: Vec::new(), left: NONE, right: NONE });
+
+        let (left, right) = rayon::join(
+            || {
+                let mut nodes = NodeArena::new();
+                nodes.root = Self::construct_kdtree_par(left, depth + 1, builder, &mut nodes);
+                nodes
+            },
+            || {
+                let mut nodes = NodeArena::new();
+                nodes.root = Self::construct_kdtree_par(right, depth + 1, builder, &mut nodes);
+                nodes
+            },
+        );
+
+        let (left, right) = (nodes.append(left), nodes.append(right));
+        let node = &mut nodes.nodes[id as usize];
+        (node.left, node.right) = (left, right);
+        id
+    }
+}
+
+impl KdTreeBuilder {
+    ///Constructs a balanced Kd-Tree from a vector of points using the rayon thread pool
+    ///
+    /// The resulting tree is identical to the one returned by [`KdTreeBuilder::build`].
+    pub fn build_par<const DIM: usize, POINT: KdTreePoint<DIM, S> + Sync, S: Scalar>(&self, points: Vec<POINT>) -> KdTree<DIM, POINT, S> {
+        let store = ColumnStore::of_points(&points);
+        let columns = Columns::new(&store, &points);
+        let mut nodes = NodeArena::new();
+        if DIM > 0 {
+            let mut entries = columns.entries(0..points.len()).collect::<Vec<_>>();
+            nodes.nodes.reserve_exact(points.len());
+            nodes.root = Node::construct_kdtree_par(&mut entries, 0, self, &mut nodes);
+        }
+
+        let bounds = Aabb::of_columns(&columns);
+        KdTree {
+            nodes,
+            points,
+            store,
+            builder: *self,
+            scratch: Vec::new(),
+            bounds,
+        }
+    }
+}
+
+impl<const DIM: usize, POINT: KdTreePoint<DIM, S> + Sync, S: Scalar> KdTree<DIM, POINT, S> {
+    ///Constructs a Kd-Tree from a vector of points using the rayon thread pool
+    ///
+    /// The resulting tree is identical to the one returned by `From<Vec<POINT>>`.
+    pub fn from_par(points: Vec<POINT>) -> Self {
+        KdTreeBuilder::new().build_par(points)
+    }
+
+    ///Gives `f` a parallel iterator over mutable references to the points in index order, then rebuilds the tree
+    ///
+    /// Like `iter_mut_then_rebuild`, the node structure is always rebuilt once `f` returns since the
+    /// coordinates may change.
+    ///
+    /// # Returns:
+    /// - The value returned by `f`.
+    pub fn par_iter_mut_then_rebuild<R, F: FnOnce(rayon::slice::IterMut<'_, POINT>) -> R>(&mut self, f: F) -> R
+    where
+        POINT: Send,
+    {
+        let result = f(self.points.par_iter_mut());
+        self.rebuild();
+        result
+    }
+
+    ///Returns the index of the nearest point to each query, the queries being split across the rayon thread pool
+    ///
+    /// The result is in the order of the queries, identical to the one of `nearest_batch`.
+    pub fn nearest_batch_par(&self, queries: &[[S; DIM]]) -> Vec<Option<usize>> {
+        queries.par_iter()
+            .map(|coord| self.nearest_index(coord))
+            .collect()
+    }
+
+    ///Returns the indices of the `k` nearest points to each query, the queries being split across the rayon thread pool
+    ///
+    /// Every worker reuses its own candidates and search buffers. The result is in the order of
+    /// the queries, identical to the one of `nearest_k_batch`.
+    pub fn nearest_k_batch_par(&self, queries: &[[S; DIM]], k: usize) -> Vec<Vec<usize>> {
+        queries.par_iter()
+            .map_init(|| (Vec::new(), SearchStack::new()), |(best, stack), coord| {
+                self.nearest_k_candidates(coord, k, best, stack);
+                best.iter().map(|(index, _)| *index).collect()
+            })
+            .collect()
+    }
+}
+
+impl<'a, const DIM: usize, POINT: KdTreePoint<DIM, S> + Sync, S: Scalar> IntoParallelIterator for &'a KdTree<DIM, POINT, S> {
+    type Item = &'a POINT;
+    type Iter = rayon::slice::Iter<'a, POINT>;
+
+    /// Iterates over the points of the tree in parallel, in index order, which gives `par_iter`.
+    fn into_par_iter(self) -> Self::Iter {
+        self.points.par_iter()
+    }
+}
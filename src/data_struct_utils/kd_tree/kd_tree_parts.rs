@@ -0,0 +1,142 @@
+use super::{Aabb, ColumnStore, Columns, InvariantViolation, KdTree, KdTreeBuilder, KdTreePoint, Node, NodeArena, NodeId, Scalar, NONE};
+
+///Node of a `KdTreeParts`, listed in pre-order with flags telling which children follow it
+#[derive(Debug, Clone, PartialEq)]
+pub struct PartsNode<S: Scalar = f64> {
+    ///Index of the point stored in the node
+    pub index: usize,
+    ///Split axis of the node
+    pub axis: usize,
+    ///Split value on the axis
+    pub split: S,
+    ///Indices of the other points stored in the node when it is a leaf bucket
+    pub bucket: Vec<usize>,
+    ///True if the left subtree follows the node
+    pub left: bool,
+    ///True if the right subtree follows the node, after the left one
+    pub right: bool,
+}
+
+///Points and structure of a `KdTree`, to store a built tree and restore it without constructing it again
+///
+/// This is the representation a serialization format reads and writes: plain vectors, with the
+/// nodes in pre-order. `KdTree::from_parts` validates it before restoring the tree.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KdTreeParts<POINT, S: Scalar = f64> {
+    ///The points of the tree, in index order
+    pub points: Vec<POINT>,
+    ///The nodes of the tree in pre-order, empty for an empty tree
+    pub nodes: Vec<PartsNode<S>>,
+    ///Options of the tree, reused when it rebuilds parts of itself
+    pub builder: KdTreeBuilder,
+}
+
+impl<const DIM: usize, S: Scalar> Node<DIM, S> {
+    /// Appends this subtree to `parts` in pre-order, walking it with an explicit stack so that any depth fits.
+    fn write_parts(&self, nodes: &NodeArena<DIM, S>, parts: &mut Vec<PartsNode<S>>) {
+        let mut stack = vec![self];
+        while let Some(node) = stack.pop() {
+            parts.push(PartsNode {
+                index: node.index,
+                axis: node.axis,
+                split: node.split,
+                bucket: node.bucket.clone(),
+                left: node.left != NONE,
+                right: node.right != NONE,
+            });
+            stack.extend(nodes.get(node.right));
+            stack.extend(nodes.get(node.left));
+        }
+    }
+
+    /// Reads the next node of `parts` into `nodes`, returns its id and whether its left and right subtrees follow.
+    fn read_part(parts: &mut std::vec::IntoIter<PartsNode<S>>, nodes: &mut NodeArena<DIM, S>, path: &str, count: &mut usize) -> Result<(NodeId, bool, bool), InvariantViolation> {
+        let part = parts.next().ok_or(InvariantViolation::TruncatedStructure { nodes: *count })?;
+        *count += 1;
+        if part.axis >= DIM {
+            return Err(InvariantViolation::AxisOutOfRange { path: path.to_string(), axis: part.axis, dim: DIM });
+        }
+        let id = nodes.push(Node { index: part.index, axis: part.axis, split: part.split, bucket: part.bucket, left: NONE, right: NONE });
+        Ok((id, part.left, part.right))
+    }
+
+    /// Reads a subtree from the remaining `parts` into `nodes`, `count` is the number of nodes read so far.
+    ///
+    /// The nodes are read with an explicit stack, so that a forged chain of any depth can't
+    /// overflow the call stack. Only the shape is checked here, the placement of the points is
+    /// left to `check_invariants`.
+    fn read_parts(parts: &mut std::vec::IntoIter<PartsNode<S>>, nodes: &mut NodeArena<DIM, S>, count: &mut usize) -> Result<NodeId, InvariantViolation> {
+        let mut path = String::new();
+        let (root, left, right) = Self::read_part(parts, nodes, &path, count)?;
+        // Nodes whose subtrees are being read, with the subtrees still to read
+        let mut stack = vec![(root, left, right)];
+        while let Some((parent, left, right)) = stack.last_mut() {
+            let parent = *parent;
+            let step = if std::mem::take(left) {
+                'L'
+            } else if std::mem::take(right) {
+                'R'
+            } else {
+                stack.pop();
+                path.pop();
+                continue;
+            };
+            path.push(step);
+            let (id, left, right) = Self::read_part(parts, nodes, &path, count)?;
+            let node = &mut nodes.nodes[parent as usize];
+            match step {
+                'L' => node.left = id,
+                _ => node.right = id,
+            }
+            stack.push((id, left, right));
+        }
+        Ok(root)
+    }
+}
+
+impl<const DIM: usize, POINT: KdTreePoint<DIM, S>, S: Scalar> KdTree<DIM, POINT, S> {
+    ///Returns the points and the structure of the tree, to restore it later with `from_parts`
+    ///
+    /// The tombstones left by `remove_lazy` are compacted first.
+    pub fn into_parts(mut self) -> KdTreeParts<POINT, S> {
+        self.compact_tombstones();
+        let mut parts = Vec::with_capacity(self.nodes.len());
+        if let Some(root) = self.nodes.root() {
+            root.write_parts(&self.nodes, &mut parts);
+        }
+        KdTreeParts { points: self.points, nodes: parts, builder: self.builder }
+    }
+
+    ///Restores a tree from its points and structure without constructing it again
+    ///
+    /// The structure is validated first, so that a corrupted or forged input is refused instead
+    /// of giving a tree whose queries panic or miss points.
+    ///
+    /// # Errors:
+    /// - `InvariantViolation::TruncatedStructure` or `InvariantViolation::TrailingNodes` if the
+    ///   flags of the nodes don't describe exactly `nodes`.
+    /// - `InvariantViolation::AxisOutOfRange` if a node splits on an axis past `DIM`.
+    /// - Any other `InvariantViolation` reported by `check_invariants` on the restored tree,
+    ///   except for infinite coordinates: they are placed like the other ones by `From`, so a
+    ///   tree holding some is restored, only NaN is refused.
+    pub fn from_parts(parts: KdTreeParts<POINT, S>) -> Result<Self, InvariantViolation> {
+        let KdTreeParts { points, nodes, builder } = parts;
+        let (len, mut count) = (nodes.len(), 0);
+        let mut arena = NodeArena::new();
+        if !nodes.is_empty() {
+            let mut parts = nodes.into_iter();
+            arena.nodes.reserve_exact(len);
+            arena.root = Node::read_parts(&mut parts, &mut arena, &mut count)?;
+            if parts.next().is_some() {
+                return Err(InvariantViolation::TrailingNodes { used: count, nodes: len });
+            }
+        }
+
+        let store = ColumnStore::of_points(&points);
+        let columns = Columns::new(&store, &points);
+        let bounds = Aabb::of_columns(&columns);
+        let tree = Self { bounds, nodes: arena, store, points, builder, scratch: Vec::new() };
+        tree.check_structure()?;
+        Ok(tree)
+    }
+}
@@ -0,0 +1,276 @@
+use std::io::{BufRead, BufReader, Read};
+
+use super::{KdTree, PointCloudError};
+
+/// Type of a scalar property of a PLY element.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PlyType {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl PlyType {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "char" | "int8" => Self::I8,
+            "uchar" | "uint8" => Self::U8,
+            "short" | "int16" => Self::I16,
+            "ushort" | "uint16" => Self::U16,
+            "int" | "int32" => Self::I32,
+            "uint" | "uint32" => Self::U32,
+            "float" | "float32" => Self::F32,
+            "double" | "float64" => Self::F64,
+            _ => return None,
+        })
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Self::I8 | Self::U8 => 1,
+            Self::I16 | Self::U16 => 2,
+            Self::I32 | Self::U32 | Self::F32 => 4,
+            Self::F64 => 8,
+        }
+    }
+
+    /// Decodes a little-endian value of `self.size()` bytes.
+    fn decode(self, bytes: &[u8]) -> f64 {
+        match self {
+            Self::I8 => bytes[0] as i8 as f64,
+            Self::U8 => bytes[0] as f64,
+            Self::I16 => i16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+            Self::U16 => u16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+            Self::I32 => i32::from_le_bytes(bytes[..4].try_into().unwrap()) as f64,
+            Self::U32 => u32::from_le_bytes(bytes[..4].try_into().unwrap()) as f64,
+            Self::F32 => f32::from_le_bytes(bytes[..4].try_into().unwrap()) as f64,
+            Self::F64 => f64::from_le_bytes(bytes[..8].try_into().unwrap()),
+        }
+    }
+}
+
+/// Property of a PLY element, a list being stored as its count followed by its items.
+#[derive(Debug, Clone, PartialEq)]
+enum PlyProperty {
+    Scalar(String, PlyType),
+    List(PlyType, PlyType),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct PlyElement {
+    name: String,
+    count: usize,
+    properties: Vec<PlyProperty>,
+}
+
+/// Reads a line of the header, counted in `line`.
+fn read_header_line<R: BufRead>(r: &mut R, line: &mut usize) -> Result<String, PointCloudError> {
+    let mut text = String::new();
+    *line += 1;
+    match r.read_line(&mut text) {
+        Ok(0) => Err(PointCloudError::InvalidHeader { line: *line, reason: "missing end_header".into() }),
+        Ok(_) => Ok(text.trim_end().to_string()),
+        Err(error) => Err(PointCloudError::Io(error.kind())),
+    }
+}
+
+/// Reads the header of a PLY file, returning whether the body is binary and its elements.
+fn read_ply_header<R: BufRead>(r: &mut R) -> Result<(bool, Vec<PlyElement>), PointCloudError> {
+    let mut line = 0;
+    let invalid = |line: usize, reason: String| PointCloudError::InvalidHeader { line, reason };
+    if read_header_line(r, &mut line)? != "ply" {
+        return Err(invalid(line, "not a PLY file".into()));
+    }
+
+    let (mut binary, mut format_found) = (false, false);
+    let mut elements: Vec<PlyElement> = Vec::new();
+    loop {
+        let text = read_header_line(r, &mut line)?;
+        let words: Vec<&str> = text.split_whitespace().collect();
+        match words.as_slice() {
+            ["end_header"] => break,
+            [] | ["comment", ..] | ["obj_info", ..] => {}
+            ["format", format, _version] => {
+                binary = match *format {
+                    "ascii" => false,
+                    "binary_little_endian" => true,
+                    format => return Err(invalid(line, format!("unsupported format {format}"))),
+                };
+                format_found = true;
+            }
+            ["element", name, count] => {
+                let count = count.parse().map_err(|_| invalid(line, format!("invalid element count {count}")))?;
+                elements.push(PlyElement { name: name.to_string(), count, properties: Vec::new() });
+            }
+            ["property", "list", count_type, item_type, _name] => {
+                let (Some(count_type), Some(item_type)) = (PlyType::parse(count_type), PlyType::parse(item_type)) else {
+                    return Err(invalid(line, format!("unknown type in {text:?}")));
+                };
+                let element = elements.last_mut().ok_or_else(|| invalid(line, "property outside of an element".into()))?;
+                element.properties.push(PlyProperty::List(count_type, item_type));
+            }
+            ["property", ty, name] => {
+                let ty = PlyType::parse(ty).ok_or_else(|| invalid(line, format!("unknown type {ty}")))?;
+                let element = elements.last_mut().ok_or_else(|| invalid(line, "property outside of an element".into()))?;
+                element.properties.push(PlyProperty::Scalar(name.to_string(), ty));
+            }
+            _ => return Err(invalid(line, format!("unexpected line {text:?}"))),
+        }
+    }
+    if !format_found {
+        return Err(invalid(line, "missing format".into()));
+    }
+    Ok((binary, elements))
+}
+
+/// Reads the values of one element of an ASCII body, lists flattened after their count.
+fn read_ascii_element<R: BufRead>(r: &mut R, element: &PlyElement, values: &mut Vec<f64>) -> Result<bool, String> {
+    let mut text = String::new();
+    if r.read_line(&mut text).map_err(|error| error.to_string())? == 0 {
+        return Ok(false);
+    }
+    values.clear();
+    let mut fields = text.split_whitespace();
+    let mut next = || -> Result<f64, String> {
+        let field = fields.next().ok_or("missing value")?;
+        field.parse().map_err(|_| format!("invalid value {field:?}"))
+    };
+    for property in &element.properties {
+        match property {
+            PlyProperty::Scalar(..) => values.push(next()?),
+            PlyProperty::List(..) => {
+                let count = next()?;
+                values.push(count);
+                for _ in 0..count as usize {
+                    values.push(next()?);
+                }
+            }
+        }
+    }
+    Ok(true)
+}
+
+/// Reads the values of one element of a binary little-endian body, lists flattened after their count.
+fn read_binary_element<R: Read>(r: &mut R, element: &PlyElement, values: &mut Vec<f64>) -> std::io::Result<()> {
+    let mut bytes = [0; 8];
+    let mut read = |r: &mut R, ty: PlyType| -> std::io::Result<f64> {
+        r.read_exact(&mut bytes[..ty.size()])?;
+        Ok(ty.decode(&bytes))
+    };
+    values.clear();
+    for property in &element.properties {
+        match *property {
+            PlyProperty::Scalar(_, ty) => values.push(read(r, ty)?),
+            PlyProperty::List(count_type, item_type) => {
+                let count = read(r, count_type)?;
+                values.push(count);
+                for _ in 0..count as usize {
+                    values.push(read(r, item_type)?);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+impl KdTree<3, [f64; 3]> {
+    ///Constructs a balanced Kd-Tree from the vertices of a PLY file, in ASCII or binary little-endian format
+    ///
+    /// The points are read from the `x`, `y` and `z` properties of the `vertex` element, of any
+    /// numeric type. The other properties (colors, normals...) and elements (faces...) are skipped.
+    /// Errors locate the offending element by its name and its index from 0.
+    ///
+    /// # Errors:
+    /// - `PointCloudError::InvalidHeader` if the header is malformed or declares another format.
+    /// - `PointCloudError::MissingVertexCoordinates` if there is no `vertex` element with `x`, `y` and `z` properties.
+    /// - `PointCloudError::InvalidElement` if an element can't be read or a vertex has a non-finite coordinate.
+    /// - `PointCloudError::Truncated` if the file ends before the last vertex.
+    /// - `PointCloudError::Io` if the reader fails.
+    pub fn from_ply<R: Read>(r: R) -> Result<Self, PointCloudError> {
+        let mut r = BufReader::new(r);
+        let (binary, elements) = read_ply_header(&mut r)?;
+        let vertex = elements.iter().position(|element| element.name == "vertex").ok_or(PointCloudError::MissingVertexCoordinates)?;
+        let property = |name: &str| {
+            elements[vertex].properties.iter().position(|property| matches!(property, PlyProperty::Scalar(n, _) if n == name))
+        };
+        let (Some(x), Some(y), Some(z)) = (property("x"), property("y"), property("z")) else {
+            return Err(PointCloudError::MissingVertexCoordinates);
+        };
+        // Position of a property among the values of a vertex, the lists before it taking their count plus one values
+        let value = |values: &[f64], property: usize| {
+            let position = elements[vertex].properties[..property].iter().fold(0, |position, p| match p {
+                PlyProperty::Scalar(..) => position + 1,
+                PlyProperty::List(..) => position + 1 + values[position] as usize,
+            });
+            values[position]
+        };
+
+        // The elements before the vertices are read and dropped, the ones after them are not read
+        let mut points = Vec::with_capacity(elements[vertex].count.min(1 << 16));
+        let mut values = Vec::new();
+        for element in &elements[..=vertex] {
+            for index in 0..element.count {
+                let truncated = || PointCloudError::Truncated { element: element.name.clone(), index };
+                let invalid = |reason: String| PointCloudError::InvalidElement { element: element.name.clone(), index, reason };
+                match binary {
+                    true => read_binary_element(&mut r, element, &mut values).map_err(|error| match error.kind() {
+                        std::io::ErrorKind::UnexpectedEof => truncated(),
+                        kind => PointCloudError::Io(kind),
+                    })?,
+                    false => match read_ascii_element(&mut r, element, &mut values) {
+                        Ok(true) => {}
+                        Ok(false) => return Err(truncated()),
+                        Err(reason) => return Err(invalid(reason)),
+                    },
+                }
+                if element.name == "vertex" {
+                    let point = [value(&values, x), value(&values, y), value(&values, z)];
+                    if point.iter().any(|x| !x.is_finite()) {
+                        return Err(invalid("non-finite coordinate".into()));
+                    }
+                    points.push(point);
+                }
+            }
+        }
+        Ok(Self::from(points))
+    }
+
+    ///Constructs a balanced Kd-Tree from an XYZ file, one point per line as whitespace-separated coordinates
+    ///
+    /// Columns after the third one (colors, intensities...) are ignored, as well as blank lines
+    /// and lines starting with `#`. Lines are numbered from 1 and columns from 0 in the errors.
+    ///
+    /// # Errors:
+    /// - `PointCloudError::MissingCoordinates` if a line has less than three columns.
+    /// - `PointCloudError::InvalidLine` if a coordinate is not a finite number.
+    /// - `PointCloudError::Io` if the reader fails.
+    pub fn from_xyz<R: Read>(r: R) -> Result<Self, PointCloudError> {
+        let mut points = Vec::new();
+        for (i, text) in BufReader::new(r).lines().enumerate() {
+            let text = text.map_err(|error| PointCloudError::Io(error.kind()))?;
+            let text = text.trim();
+            if text.is_empty() || text.starts_with('#') {
+                continue;
+            }
+            let fields: Vec<&str> = text.split_whitespace().take(3).collect();
+            if fields.len() < 3 {
+                return Err(PointCloudError::MissingCoordinates { line: i + 1, found: fields.len() });
+            }
+            let mut point = [0.; 3];
+            for (column, (x, field)) in point.iter_mut().zip(&fields).enumerate() {
+                *x = field.parse::<f64>().ok().filter(|x| x.is_finite()).ok_or_else(|| PointCloudError::InvalidLine {
+                    line: i + 1,
+                    column,
+                    field: field.to_string(),
+                })?;
+            }
+            points.push(point);
+        }
+        Ok(Self::from(points))
+    }
+}
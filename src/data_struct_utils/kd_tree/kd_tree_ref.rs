@@ -0,0 +1,141 @@
+use super::{Aabb, ColumnStore, Columns, KdTreeBuilder, KdTreePoint, Node, NodeArena, QueryStats, RobustEuclidean, Scalar};
+
+/// A Kd-Tree borrowing its points from a slice instead of owning them.
+///
+/// It shares the construction and the queries of `KdTree`, the returned references borrow
+/// the slice rather than the tree, so they can outlive it.
+///
+/// # Type Parameters:
+/// - `'a`: The lifetime of the borrowed slice.
+/// - `DIM`: The number of dimensions.
+/// - `POINT`: The type of point stored in the slice, which must implement `KdTreePoint`.
+/// - `S`: The type of the coordinates, `f64` by default.
+#[derive(Debug)]
+pub struct KdTreeRef<'a, const DIM: usize, POINT: KdTreePoint<DIM, S>, S: Scalar = f64> {
+    pub(super) nodes: NodeArena<DIM, S>, //Nodes of the Kd-Tree
+
+    pub(super) points: &'a [POINT],
+
+    pub(super) store: ColumnStore<DIM, S>, //Coordinates of the points not read in place, one array per axis
+
+    pub(super) bounds: Option<Aabb<DIM, S>>, //Bounding box of the points
+}
+
+impl<const DIM: usize, POINT: KdTreePoint<DIM, S>, S: Scalar> Clone for KdTreeRef<'_, DIM, POINT, S> {
+    fn clone(&self) -> Self {
+        Self {
+            nodes: self.nodes.clone(),
+            points: self.points,
+            store: self.store.clone(),
+            bounds: self.bounds,
+        }
+    }
+}
+
+impl<'a, const DIM: usize, POINT: KdTreePoint<DIM, S>, S: Scalar> From<&'a [POINT]> for KdTreeRef<'a, DIM, POINT, S> {
+    /// Constructs a Kd-Tree over a slice of points.
+    fn from(points: &'a [POINT]) -> Self {
+        KdTreeBuilder::new().build_ref(points)
+    }
+}
+
+impl KdTreeBuilder {
+    ///Constructs a balanced Kd-Tree borrowing a slice of points
+    pub fn build_ref<'a, const DIM: usize, POINT: KdTreePoint<DIM, S>, S: Scalar>(&self, points: &'a [POINT]) -> KdTreeRef<'a, DIM, POINT, S> {
+        let store = ColumnStore::of_points(points);
+        let mut nodes = NodeArena::new();
+        if DIM > 0 {
+            let mut entries = Columns::new(&store, points).entries(0..points.len()).collect::<Vec<_>>();
+            nodes.nodes.reserve_exact(points.len());
+            nodes.root = Node::construct_kdtree(&mut entries, 0, self, &mut nodes);
+        }
+
+        let bounds = Aabb::of_columns(&Columns::new(&store, points));
+        KdTreeRef { nodes, points, store, bounds }
+    }
+}
+
+impl<'a, const DIM: usize, POINT: KdTreePoint<DIM, S>, S: Scalar> KdTreeRef<'a, DIM, POINT, S> {
+    /// Returns the coordinates of the points, as read by the queries.
+    pub(super) fn columns(&self) -> Columns<'_, DIM, S> {
+        Columns::new(&self.store, self.points)
+    }
+
+    ///Constructs a Kd-Tree over a slice of points
+    pub fn from_slice(points: &'a [POINT]) -> Self {
+        Self::from(points)
+    }
+
+    ///Returns a reference to the nearest POINT using given coordinates
+    ///
+    /// Switches to `RobustEuclidean` for coordinates making the squared distances overflow, like
+    /// `KdTree::nearest_by_coord`. So do `nearest_k` and `within_radius`.
+    pub fn nearest_by_coord(&self, coord: &[S; DIM]) -> Option<&'a POINT> {
+        if self.distances_may_overflow(coord) {
+            return self.nearest_by_coord_with_metric(coord, &RobustEuclidean);
+        }
+        self.nearest_by_coord_with_stats(coord).0
+    }
+
+    ///Returns a reference to the nearest POINT using given coordinates, along with statistics on the search
+    ///
+    /// The squared distances are always computed plainly, even for coordinates making them overflow.
+    pub fn nearest_by_coord_with_stats(&self, coord: &[S; DIM]) -> (Option<&'a POINT>, QueryStats) {
+        let mut stats = QueryStats::default();
+        let index = Node::nearest_index(&self.nodes, &self.columns(), coord, &mut stats);
+        (index.map(|i| &self.points[i]), stats)
+    }
+
+    ///Returns a reference to the nearest POINT using another POINT
+    pub fn nearest(&self, target: &POINT) -> Option<&'a POINT> {
+        self.nearest_by_coord(&target.to_kdtree_point())
+    }
+
+    ///Returns references to the `k` nearest POINTs to the given coordinates, the nearest first
+    pub fn nearest_k(&self, coord: &[S; DIM], k: usize) -> Vec<&'a POINT> {
+        if self.distances_may_overflow(coord) {
+            return self.nearest_k_with_metric(coord, k, &RobustEuclidean);
+        }
+        Node::nearest_k_indices(&self.nodes, &self.columns(), coord, k, &mut QueryStats::default())
+            .into_iter()
+            .map(|i| &self.points[i])
+            .collect()
+    }
+
+    ///Returns references to every POINT within `radius` of the given coordinates (boundary included)
+    pub fn within_radius(&self, coord: &[S; DIM], radius: S) -> Vec<&'a POINT> {
+        if self.distances_may_overflow(coord) {
+            return self.within_radius_with_metric(coord, radius, &RobustEuclidean);
+        }
+        Node::within_radius_indices(&self.nodes, &self.columns(), coord, radius, &mut QueryStats::default())
+            .into_iter()
+            .map(|i| &self.points[i])
+            .collect()
+    }
+
+    ///Returns references to every POINT inside the box `[min, max]` (boundary included)
+    pub fn points_in_aabb(&self, min: &[S; DIM], max: &[S; DIM]) -> Vec<&'a POINT> {
+        Node::in_aabb_indices(&self.nodes, &self.columns(), self.points, min, max, &mut QueryStats::default())
+            .into_iter()
+            .map(|i| &self.points[i])
+            .collect()
+    }
+
+    ///Returns the borrowed slice of points
+    pub fn points(&self) -> &'a [POINT] {
+        self.points
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    pub fn size(&self) -> usize {
+        self.points.len()
+    }
+
+    ///Returns the height of the tree, the number of nodes on its longest branch, like `KdTree::height`
+    pub fn height(&self) -> usize {
+        Node::tree_height(&self.nodes)
+    }
+}
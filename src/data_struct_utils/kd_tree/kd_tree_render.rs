@@ -0,0 +1,308 @@
+use std::fmt::{self, Write};
+
+use super::{KdTree, KdTreePoint, Node, NodeArena, Scalar};
+
+///Options of the Graphviz export, see [`KdTree::to_dot`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DotOptions {
+    precision: usize,
+    coordinates: bool,
+    highlight: Vec<usize>,
+}
+
+impl Default for DotOptions {
+    fn default() -> Self {
+        Self { precision: 3, coordinates: true, highlight: Vec::new() }
+    }
+}
+
+impl DotOptions {
+    ///Returns the default options
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///Sets the number of decimals of the coordinates in the labels, 3 by default
+    pub fn precision(mut self, precision: usize) -> Self {
+        self.precision = precision;
+        self
+    }
+
+    ///Sets whether the labels show the coordinates of the points, true by default
+    pub fn coordinates(mut self, coordinates: bool) -> Self {
+        self.coordinates = coordinates;
+        self
+    }
+
+    ///Sets the indices of the points whose nodes are filled in another color, none by default
+    pub fn highlight<I: IntoIterator<Item = usize>>(mut self, indices: I) -> Self {
+        self.highlight = indices.into_iter().collect();
+        self
+    }
+}
+
+impl<const DIM: usize, S: Scalar> Node<DIM, S> {
+    /// Recursively draws the subtree, one node per line.
+    ///
+    /// # Parameters:
+    /// - `nodes`: The nodes of the tree.
+    /// - `values`: The points of the tree.
+    /// - `lead`: The start of the line of this node, with the branches drawn so far.
+    /// - `prefix`: The start of the lines of the children.
+    /// - `depth`, `max_depth`: The depth of this node and the depth of the deepest nodes to draw.
+    /// - `out`: The string the lines are pushed into.
+    #[allow(clippy::too_many_arguments)]
+    fn write_ascii<POINT: KdTreePoint<DIM, S>>(&self, nodes: &NodeArena<DIM, S>, values: &[POINT], lead: &str, prefix: &str, depth: usize, max_depth: Option<usize>, out: &mut String) {
+        let _ = write!(out, "{lead}axis={} {:?} #{}", self.axis, values[self.index].to_kdtree_point(), self.index);
+        for &index in &self.bucket {
+            let _ = write!(out, " + {:?} #{index}", values[index].to_kdtree_point());
+        }
+        out.push('\n');
+
+        let children: Vec<(&str, &Self)> = [("L", self.left), ("R", self.right)]
+            .into_iter()
+            .filter_map(|(side, child)| nodes.get(child).map(|child| (side, child)))
+            .collect();
+        if children.is_empty() {
+            return;
+        }
+        if max_depth.is_some_and(|max_depth| depth >= max_depth) {
+            let hidden: usize = children.iter().map(|(_, child)| child.node_count(nodes)).sum();
+            let _ = writeln!(out, "{prefix}└── … {hidden} more nodes");
+            return;
+        }
+
+        let last = children.len() - 1;
+        for (i, (side, child)) in children.into_iter().enumerate() {
+            let (connector, indent) = if i == last { ("└── ", "    ") } else { ("├── ", "│   ") };
+            child.write_ascii(nodes, values, &format!("{prefix}{connector}{side} "), &format!("{prefix}{indent}"), depth + 1, max_depth, out);
+        }
+    }
+
+    /// Returns the number of nodes of the subtree.
+    pub(super) fn node_count(&self, nodes: &NodeArena<DIM, S>) -> usize {
+        1 + nodes.get(self.left).map_or(0, |n| n.node_count(nodes)) + nodes.get(self.right).map_or(0, |n| n.node_count(nodes))
+    }
+}
+
+impl<const DIM: usize, S: Scalar> Node<DIM, S> {
+    /// Recursively writes the nodes and edges of the subtree in the DOT language.
+    fn write_dot<POINT: KdTreePoint<DIM, S>>(&self, nodes: &NodeArena<DIM, S>, values: &[POINT], opts: &DotOptions, out: &mut String) {
+        let mut label = String::new();
+        for index in self.node_points() {
+            if !label.is_empty() {
+                label.push_str("\\n");
+            }
+            let _ = write!(label, "#{index}");
+            if opts.coordinates {
+                let coords: Vec<String> = values[index].to_kdtree_point().iter()
+                    .map(|x| format!("{:.*}", opts.precision, x.to_f64()))
+                    .collect();
+                let _ = write!(label, " ({})", coords.join(", "));
+            }
+        }
+        let highlighted = self.node_points().any(|index| opts.highlight.contains(&index));
+        let style = if highlighted { ", style=filled, fillcolor=orange" } else { "" };
+        let _ = writeln!(out, "    n{} [label=\"{label}\\naxis={}\"{style}];", self.index, self.axis);
+
+        if let Some(left) = nodes.get(self.left) {
+            let _ = writeln!(out, "    n{} -> n{} [label=\"L\"];", self.index, left.index);
+            left.write_dot(nodes, values, opts, out);
+        }
+        if let Some(right) = nodes.get(self.right) {
+            let _ = writeln!(out, "    n{} -> n{} [label=\"R\", style=dashed];", self.index, right.index);
+            right.write_dot(nodes, values, opts, out);
+        }
+    }
+}
+
+impl<const DIM: usize, S: Scalar> Node<DIM, S> {
+    /// Recursively writes the fields of the nodes of the subtree, one node per line indented by its depth.
+    fn write_structure(&self, nodes: &NodeArena<DIM, S>, side: &str, depth: usize, max_depth: Option<usize>, out: &mut String) {
+        let indent = "    ".repeat(depth);
+        let _ = writeln!(
+            out,
+            "{indent}{side}Node {{ index: {}, axis: {}, split: {:?}, bucket: {:?} }}",
+            self.index, self.axis, self.split, self.bucket
+        );
+        if self.is_leaf() {
+            return;
+        }
+        if max_depth.is_some_and(|max_depth| depth >= max_depth) {
+            let _ = writeln!(out, "{indent}    …");
+            return;
+        }
+        if let Some(left) = nodes.get(self.left) {
+            left.write_structure(nodes, "left: ", depth + 1, max_depth, out);
+        }
+        if let Some(right) = nodes.get(self.right) {
+            right.write_structure(nodes, "right: ", depth + 1, max_depth, out);
+        }
+    }
+}
+
+impl<const DIM: usize, POINT: KdTreePoint<DIM, S>, S: Scalar> KdTree<DIM, POINT, S> {
+    ///Draws the tree as text, one node per line, for debugging small trees
+    ///
+    /// Every line shows the split axis, the coordinates and the index of the point of a node,
+    /// followed by the points of its leaf bucket, e.g. `axis=1 [3.0, 4.0] #2`. Children are
+    /// drawn below their parent, marked `L` or `R`.
+    ///
+    /// # Parameters:
+    /// - `max_depth`: The depth of the deepest nodes to draw, the root being at depth 0. Deeper
+    ///   subtrees are summarized by their number of nodes. `None` draws the whole tree.
+    pub fn render_ascii(&self, max_depth: Option<usize>) -> String {
+        let mut out = String::new();
+        match self.nodes.root() {
+            Some(root) => root.write_ascii(&self.nodes, &self.points, "", "", 0, max_depth, &mut out),
+            None => out.push_str("(empty)\n"),
+        }
+        out
+    }
+}
+
+impl<const DIM: usize, POINT: KdTreePoint<DIM, S>, S: Scalar> KdTree<DIM, POINT, S> {
+    ///Exports the tree as a Graphviz digraph, to be rendered with `dot`
+    ///
+    /// Every node is named `n<index>` after the index of its point and labeled with the indices,
+    /// and optionally the coordinates, of its points and with its split axis. Edges to left
+    /// children are labeled `L`, edges to right children `R` and dashed.
+    pub fn to_dot(&self, opts: DotOptions) -> String {
+        let mut out = String::from("digraph KdTree {\n    node [shape=box];\n");
+        if let Some(root) = self.nodes.root() {
+            root.write_dot(&self.nodes, &self.points, &opts, &mut out);
+        }
+        out.push_str("}\n");
+        out
+    }
+}
+
+/// Node of the JSON export along with its depth and the ids of its children.
+struct JsonNode<'a, const DIM: usize, S: Scalar> {
+    node: &'a Node<DIM, S>,
+    depth: usize,
+    left: Option<usize>,
+    right: Option<usize>,
+}
+
+/// Writes a coordinate as a JSON number, NaN and infinities as `null`.
+fn write_json_number<S: Scalar>(x: S, out: &mut String) {
+    let x = x.to_f64();
+    let _ = match x.is_finite() {
+        true => write!(out, "{x}"),
+        false => write!(out, "null"),
+    };
+}
+
+/// Writes coordinates as a JSON array.
+fn write_json_coordinates<S: Scalar>(coordinates: &[S], out: &mut String) {
+    out.push('[');
+    for (i, x) in coordinates.iter().enumerate() {
+        if i > 0 {
+            out.push_str(", ");
+        }
+        write_json_number(*x, out);
+    }
+    out.push(']');
+}
+
+impl<const DIM: usize, POINT: KdTreePoint<DIM, S>, S: Scalar> KdTree<DIM, POINT, S> {
+    ///Exports the tree as JSON, to draw it with visualization tools
+    ///
+    /// The output is an object with the fields:
+    /// - `dim`: The number of dimensions.
+    /// - `bounding_box`: `{"min": [...], "max": [...]}` of the points, `null` for an empty tree.
+    /// - `root`: The id of the root, `0`, or `null` for an empty tree.
+    /// - `nodes`: The nodes in pre-order, each one an object with its `id` (its position in
+    ///   `nodes`), the `index` and the `position` of its point, its split `axis` and `split`
+    ///   value, its `depth` from 0 at the root, the indices of the other points of its leaf
+    ///   `bucket`, and the ids of its `left` and `right` children or `null`.
+    ///
+    /// NaN and infinite coordinates are written as `null`. The output is meant to be drawn, use
+    /// `into_parts` or `write_binary` to store a tree and restore it.
+    pub fn to_visualization_json(&self) -> String {
+        // Pre-order walk without recursion, ids being the positions in the walk
+        let mut nodes: Vec<JsonNode<'_, DIM, S>> = Vec::new();
+        let mut stack = Vec::new();
+        if let Some(root) = self.nodes.root() {
+            stack.push((root, 0, None::<(usize, bool)>));
+        }
+        while let Some((node, depth, parent)) = stack.pop() {
+            let id = nodes.len();
+            match parent {
+                Some((parent, true)) => nodes[parent].left = Some(id),
+                Some((parent, false)) => nodes[parent].right = Some(id),
+                None => {}
+            }
+            nodes.push(JsonNode { node, depth, left: None, right: None });
+            stack.extend(self.nodes.get(node.right).map(|right| (right, depth + 1, Some((id, false)))));
+            stack.extend(self.nodes.get(node.left).map(|left| (left, depth + 1, Some((id, true)))));
+        }
+
+        let mut out = format!("{{\n  \"dim\": {DIM},\n  \"bounding_box\": ");
+        match self.bounding_box() {
+            Some(aabb) => {
+                out.push_str("{\"min\": ");
+                write_json_coordinates(&aabb.min, &mut out);
+                out.push_str(", \"max\": ");
+                write_json_coordinates(&aabb.max, &mut out);
+                out.push('}');
+            }
+            None => out.push_str("null"),
+        }
+        out.push_str(if nodes.is_empty() { ",\n  \"root\": null,\n  \"nodes\": [" } else { ",\n  \"root\": 0,\n  \"nodes\": [" });
+
+        let id_or_null = |id: Option<usize>| id.map_or("null".to_string(), |id| id.to_string());
+        for (id, JsonNode { node, depth, left, right }) in nodes.iter().enumerate() {
+            out.push_str(if id == 0 { "\n    " } else { ",\n    " });
+            let _ = write!(out, "{{\"id\": {id}, \"index\": {}, \"position\": ", node.index);
+            write_json_coordinates(&self.points[node.index].to_kdtree_point(), &mut out);
+            let _ = write!(out, ", \"axis\": {}, \"split\": ", node.axis);
+            write_json_number(node.split, &mut out);
+            let _ = write!(
+                out,
+                ", \"depth\": {depth}, \"bucket\": {:?}, \"left\": {}, \"right\": {}}}",
+                node.bucket,
+                id_or_null(*left),
+                id_or_null(*right)
+            );
+        }
+        out.push_str(if nodes.is_empty() { "]\n}\n" } else { "\n  ]\n}\n" });
+        out
+    }
+}
+
+impl<const DIM: usize, POINT: KdTreePoint<DIM, S>, S: Scalar> KdTree<DIM, POINT, S> {
+    ///Dumps the fields of the nodes, one node per line indented by its depth, for debugging the structure
+    ///
+    /// `Debug` only prints a summary of the tree, since the whole structure of a large tree is huge.
+    ///
+    /// # Parameters:
+    /// - `max_depth`: The depth of the deepest nodes to dump, the root being at depth 0. `None` dumps every node.
+    pub fn debug_structure(&self, max_depth: Option<usize>) -> String {
+        let mut out = String::new();
+        match self.nodes.root() {
+            Some(root) => root.write_structure(&self.nodes, "", 0, max_depth, &mut out),
+            None => out.push_str("(empty)\n"),
+        }
+        out
+    }
+}
+
+impl<const DIM: usize, POINT: KdTreePoint<DIM, S>, S: Scalar> fmt::Debug for KdTree<DIM, POINT, S> {
+    /// Prints a summary of the tree, see [`KdTree::debug_structure`] for the nodes.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KdTree")
+            .field("dim", &DIM)
+            .field("size", &self.size())
+            .field("height", &self.height())
+            .finish()
+    }
+}
+
+impl<const DIM: usize, POINT: KdTreePoint<DIM, S>, S: Scalar> fmt::Display for KdTree<DIM, POINT, S> {
+    /// Draws the whole tree, see [`KdTree::render_ascii`].
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.render_ascii(None))
+    }
+}
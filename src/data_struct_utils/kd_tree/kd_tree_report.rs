@@ -0,0 +1,255 @@
+use std::fmt;
+
+use super::kd_tree_builder::largest_axis;
+use super::{KdTree, KdTreePoint, Node, NodeArena, Scalar};
+
+///Report on the balance of a Kd-Tree, see [`KdTree::balance_report`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BalanceReport {
+    ///Number of points in the tree
+    pub size: usize,
+    ///Height of the tree, see [`KdTree::height`]
+    pub height: usize,
+    ///Height of a perfectly balanced tree of one point per node, `ceil(log2(size + 1))`
+    pub ideal_height: usize,
+    ///Number of points of the largest subtree of the root
+    pub largest_root_subtree: usize,
+    ///Number of points of the smallest subtree of the root
+    pub smallest_root_subtree: usize,
+}
+
+impl BalanceReport {
+    ///Returns true if the height is at most `alpha` times the ideal height
+    ///
+    /// `alpha` is the tolerance chosen by the caller, `1.0` only accepting perfectly balanced trees.
+    pub fn is_balanced(&self, alpha: f64) -> bool {
+        self.height as f64 <= alpha * self.ideal_height as f64
+    }
+}
+
+impl fmt::Display for BalanceReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} points, height {} (ideal {}), root subtrees of {} and {} points",
+            self.size, self.height, self.ideal_height, self.largest_root_subtree, self.smallest_root_subtree
+        )
+    }
+}
+
+///Statistics on the depths of the nodes of a Kd-Tree, see [`KdTree::depth_stats`]
+///
+/// Depths start at 0 for the root, a leaf is a node without children.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct DepthStats {
+    ///Depth of the shallowest leaf
+    pub min_leaf_depth: usize,
+    ///Depth of the deepest leaf
+    pub max_leaf_depth: usize,
+    ///Mean depth of the leaves
+    pub mean_leaf_depth: f64,
+    ///Number of nodes, a leaf bucket counting as one node
+    pub node_count: usize,
+    ///Number of nodes at each depth
+    pub nodes_per_level: Vec<usize>,
+}
+
+///Estimated number of bytes the allocator uses on top of each heap allocation, for `MemoryUsage`
+pub const ALLOCATION_OVERHEAD: usize = 2 * std::mem::size_of::<usize>();
+
+///Estimate of the memory used by a Kd-Tree, see [`KdTree::memory_usage`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MemoryUsage {
+    ///Number of nodes
+    pub node_count: usize,
+    ///Bytes used by the nodes and their buckets, allocation overheads included
+    pub node_bytes: usize,
+    ///Number of stored points
+    pub points_len: usize,
+    ///Number of points the storage can hold without reallocating
+    pub points_capacity: usize,
+    ///Size of a point, `size_of::<POINT>()`
+    pub point_size: usize,
+    ///Bytes used by the tree itself and its construction buffers
+    pub other_bytes: usize,
+}
+
+impl MemoryUsage {
+    ///Returns the number of bytes used by the storage of the points, spare capacity and allocation overhead included
+    pub fn points_bytes(&self) -> usize {
+        match self.points_capacity * self.point_size {
+            0 => 0,
+            bytes => bytes + ALLOCATION_OVERHEAD,
+        }
+    }
+
+    ///Returns the total number of bytes used by the tree
+    pub fn total(&self) -> usize {
+        self.node_bytes + self.points_bytes() + self.other_bytes
+    }
+}
+
+impl fmt::Display for MemoryUsage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mib = |bytes: usize| bytes as f64 / (1024. * 1024.);
+        write!(
+            f,
+            "{} points of {} bytes ({} allocated): {:.2} MiB, {} nodes: {:.2} MiB, total {:.2} MiB",
+            self.points_len,
+            self.point_size,
+            self.points_capacity,
+            mib(self.points_bytes()),
+            self.node_count,
+            mib(self.node_bytes),
+            mib(self.total())
+        )
+    }
+}
+
+///Statistics of the coordinates of the stored points on one axis, see [`KdTree::axis_stats`]
+///
+/// The values are computed in f64, they are all 0 for an empty tree.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct AxisStats {
+    ///Lowest coordinate
+    pub min: f64,
+    ///Highest coordinate
+    pub max: f64,
+    ///Mean of the coordinates
+    pub mean: f64,
+    ///Population variance of the coordinates
+    pub variance: f64,
+}
+
+impl AxisStats {
+    ///Returns the extent of the coordinates, `max - min`
+    pub fn spread(&self) -> f64 {
+        self.max - self.min
+    }
+}
+
+///Distribution of the nodes of a Kd-Tree over the split axes and the depths, see [`KdTree::structure_histogram`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StructureHistogram<const DIM: usize> {
+    ///Number of nodes with children splitting on each axis, leaves don't split
+    pub splits_per_axis: [usize; DIM],
+    ///Number of nodes at each depth, the root at depth 0
+    pub nodes_per_depth: Vec<usize>,
+}
+
+impl<const DIM: usize, S: Scalar> Node<DIM, S> {
+    /// Returns the number of points in the subtree, walked without recursion.
+    fn subtree_size(&self, nodes: &NodeArena<DIM, S>) -> usize {
+        let mut size = 0;
+        let mut stack = vec![self];
+        while let Some(node) = stack.pop() {
+            size += 1 + node.bucket.len();
+            stack.extend(nodes.get(node.left));
+            stack.extend(nodes.get(node.right));
+        }
+        size
+    }
+}
+
+impl<const DIM: usize, POINT: KdTreePoint<DIM, S>, S: Scalar> KdTree<DIM, POINT, S> {
+    ///Returns a report on the balance of the tree, to detect when insertions degraded it enough to rebuild
+    pub fn balance_report(&self) -> BalanceReport {
+        let (left, right) = self.nodes.root().map_or((0, 0), |root| {
+            (
+                self.nodes.get(root.left).map_or(0, |n| n.subtree_size(&self.nodes)),
+                self.nodes.get(root.right).map_or(0, |n| n.subtree_size(&self.nodes)),
+            )
+        });
+        BalanceReport {
+            size: self.size(),
+            height: self.height(),
+            ideal_height: (self.size() + 1).next_power_of_two().trailing_zeros() as usize,
+            largest_root_subtree: left.max(right),
+            smallest_root_subtree: left.min(right),
+        }
+    }
+
+    ///Returns statistics on the depths of the nodes, walked without recursion
+    pub fn depth_stats(&self) -> DepthStats {
+        let mut stats = DepthStats { min_leaf_depth: usize::MAX, ..DepthStats::default() };
+        let mut leaves = 0;
+        let mut leaf_depths = 0;
+        let mut stack: Vec<(&Node<DIM, S>, usize)> = self.nodes.root().map(|root| (root, 0)).into_iter().collect();
+        while let Some((node, depth)) = stack.pop() {
+            stats.node_count += 1;
+            if stats.nodes_per_level.len() <= depth {
+                stats.nodes_per_level.push(0);
+            }
+            stats.nodes_per_level[depth] += 1;
+            if node.is_leaf() {
+                leaves += 1;
+                leaf_depths += depth;
+                stats.min_leaf_depth = stats.min_leaf_depth.min(depth);
+                stats.max_leaf_depth = stats.max_leaf_depth.max(depth);
+            }
+            stack.extend(self.nodes.get(node.left).map(|n| (n, depth + 1)));
+            stack.extend(self.nodes.get(node.right).map(|n| (n, depth + 1)));
+        }
+
+        if leaves == 0 {
+            stats.min_leaf_depth = 0;
+        } else {
+            stats.mean_leaf_depth = leaf_depths as f64 / leaves as f64;
+        }
+        stats
+    }
+
+    ///Returns the statistics of the coordinates on each axis, computed in a single pass over the points
+    ///
+    /// The variance uses Welford's algorithm, which stays accurate for large coordinates and is
+    /// exactly 0 on an axis where all the coordinates are equal.
+    pub fn axis_stats(&self) -> [AxisStats; DIM] {
+        let mut stats = [AxisStats { min: f64::INFINITY, max: f64::NEG_INFINITY, mean: 0., variance: 0. }; DIM];
+        for (count, point) in self.points.iter().enumerate() {
+            for (stats, x) in stats.iter_mut().zip(point.to_kdtree_point().iter()) {
+                let x = x.to_f64();
+                stats.min = stats.min.min(x);
+                stats.max = stats.max.max(x);
+                // The variance holds the sum of the squared deviations until the end
+                let delta = x - stats.mean;
+                stats.mean += delta / (count + 1) as f64;
+                stats.variance += delta * (x - stats.mean);
+            }
+        }
+
+        if self.points.is_empty() {
+            return [AxisStats::default(); DIM];
+        }
+        stats.map(|stats| AxisStats { variance: stats.variance / self.points.len() as f64, ..stats })
+    }
+
+    ///Returns the axis on which the coordinates have the largest spread, the first one on ties
+    ///
+    /// Returns `None` if the tree is empty or of dimension 0.
+    pub fn largest_spread_axis(&self) -> Option<usize> {
+        if self.points.is_empty() || DIM == 0 {
+            return None;
+        }
+        Some(largest_axis(&self.axis_stats().map(|stats| stats.spread())))
+    }
+
+    ///Returns how the nodes are distributed over the split axes and the depths, walked without recursion
+    ///
+    /// The axes are read from the nodes, so the histogram holds for any `AxisStrategy`.
+    pub fn structure_histogram(&self) -> StructureHistogram<DIM> {
+        let mut histogram = StructureHistogram { splits_per_axis: [0; DIM], nodes_per_depth: Vec::new() };
+        let mut stack: Vec<(&Node<DIM, S>, usize)> = self.nodes.root().map(|root| (root, 0)).into_iter().collect();
+        while let Some((node, depth)) = stack.pop() {
+            if histogram.nodes_per_depth.len() <= depth {
+                histogram.nodes_per_depth.push(0);
+            }
+            histogram.nodes_per_depth[depth] += 1;
+            if !node.is_leaf() {
+                histogram.splits_per_axis[node.axis] += 1;
+            }
+            stack.extend(self.nodes.get(node.left).map(|n| (n, depth + 1)));
+            stack.extend(self.nodes.get(node.right).map(|n| (n, depth + 1)));
+        }
+        histogram
+    }
+}
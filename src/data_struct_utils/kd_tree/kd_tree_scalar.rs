@@ -0,0 +1,110 @@
+use std::fmt::Debug;
+use std::ops::{Add, Div, Mul, Sub};
+
+///Floating point type of the coordinates of a `KdTree`, implemented for `f32` and `f64`
+pub trait Scalar:
+    Copy
+    + PartialOrd
+    + Debug
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Send
+    + Sync
+    + 'static
+{
+    const ZERO: Self;
+    const INFINITY: Self;
+    const NEG_INFINITY: Self;
+    ///Largest finite value
+    const MAX: Self;
+
+    fn min(self, other: Self) -> Self;
+    fn max(self, other: Self) -> Self;
+    fn is_finite(self) -> bool;
+    fn abs(self) -> Self;
+    fn sqrt(self) -> Self;
+    fn powf(self, n: Self) -> Self;
+    ///Total order of the values, NaN being after `+inf` (before `-inf` if negative) and `-0` before `+0`
+    fn total_cmp(&self, other: &Self) -> std::cmp::Ordering;
+
+    ///Converts to `f64`, lossless for `f32` and `f64`
+    fn to_f64(self) -> f64;
+    ///Converts from `f64`, rounding to the nearest value for `f32`
+    fn from_f64(value: f64) -> Self;
+
+    ///Computes the squared distances between `target` and 4 points, `coords[axis][lane]` being the coordinate of the point `lane` on `axis`
+    ///
+    /// Every lane sums its terms in axis order starting from zero, like the distances of the
+    /// queries, so the results are bit-identical to computing them one by one. With the `simd`
+    /// feature, `f32` and `f64` compute the 4 lanes at once with the vectors of the `wide` crate.
+    fn squared_distances_x4<const DIM: usize>(coords: &[[Self; 4]; DIM], target: &[Self; DIM]) -> [Self; 4] {
+        let mut sums = [Self::ZERO; 4];
+        for (coords, y) in coords.iter().zip(target) {
+            for (sum, x) in sums.iter_mut().zip(coords) {
+                *sum = *sum + (*x - *y) * (*x - *y);
+            }
+        }
+        sums
+    }
+}
+
+macro_rules! impl_scalar {
+    ($($t:ident => $simd:ident),*) => {$(
+        impl Scalar for $t {
+            const ZERO: Self = 0.;
+            const INFINITY: Self = <$t>::INFINITY;
+            const NEG_INFINITY: Self = <$t>::NEG_INFINITY;
+            const MAX: Self = <$t>::MAX;
+
+            fn min(self, other: Self) -> Self {
+                <$t>::min(self, other)
+            }
+
+            fn max(self, other: Self) -> Self {
+                <$t>::max(self, other)
+            }
+
+            fn is_finite(self) -> bool {
+                <$t>::is_finite(self)
+            }
+
+            fn abs(self) -> Self {
+                <$t>::abs(self)
+            }
+
+            fn total_cmp(&self, other: &Self) -> std::cmp::Ordering {
+                <$t>::total_cmp(self, other)
+            }
+
+            fn sqrt(self) -> Self {
+                <$t>::sqrt(self)
+            }
+
+            fn powf(self, n: Self) -> Self {
+                <$t>::powf(self, n)
+            }
+
+            fn to_f64(self) -> f64 {
+                self as f64
+            }
+
+            fn from_f64(value: f64) -> Self {
+                value as $t
+            }
+
+            #[cfg(feature = "simd")]
+            fn squared_distances_x4<const DIM: usize>(coords: &[[Self; 4]; DIM], target: &[Self; DIM]) -> [Self; 4] {
+                let mut sums = wide::$simd::splat(0.);
+                for (coords, y) in coords.iter().zip(target) {
+                    let delta = wide::$simd::new(*coords) - wide::$simd::splat(*y);
+                    sums += delta * delta;
+                }
+                sums.to_array()
+            }
+        }
+    )*};
+}
+
+impl_scalar!(f32 => f32x4, f64 => f64x4);
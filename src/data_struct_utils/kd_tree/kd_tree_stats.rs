@@ -0,0 +1,6 @@
+///Statistics gathered while answering a query
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct QueryStats {
+    ///Number of nodes whose points were compared to the target
+    pub nodes_visited: usize,
+}
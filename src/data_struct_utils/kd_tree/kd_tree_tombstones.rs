@@ -0,0 +1,78 @@
+use super::{KdTree, KdTreePoint, Scalar};
+
+/// The tombstones are compacted by `remove_lazy` once they outnumber this fraction of the live points.
+const MAX_TOMBSTONE_RATIO: f64 = 0.25;
+
+///Index given by the mappings of `remove_lazy` and `shrink_to_fit` to the points they dropped
+pub const REMOVED_INDEX: usize = usize::MAX;
+
+///Outcome of `KdTree::remove_lazy`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LazyRemoval {
+    ///There is no such point or it already is a tombstone, nothing changed
+    NotFound,
+    ///The point became a tombstone, every index is unchanged
+    Buried,
+    ///The point became a tombstone and the tombstones were compacted, shifting the indices
+    Compacted {
+        mapping: Vec<usize>, //New index of the point previously at each index, `REMOVED_INDEX` for the tombstones
+    },
+}
+
+impl LazyRemoval {
+    ///Returns true if the point was removed, whether the tombstones were compacted or not
+    pub fn is_removed(&self) -> bool {
+        !matches!(self, Self::NotFound)
+    }
+}
+
+impl<const DIM: usize, POINT: KdTreePoint<DIM, S>, S: Scalar> KdTree<DIM, POINT, S> {
+    ///Removes the point `index` without repairing the nodes
+    ///
+    /// The point becomes a tombstone: it stays stored and its node still splits the space, but the
+    /// queries (`nearest`, `nearest_k`, `within_radius`, `points_in_aabb` and their variants) never
+    /// return it and `size` doesn't count it. The iterators, `bounding_box` and the exports by
+    /// reference still see it until it is compacted. Once the tombstones outnumber a quarter of
+    /// the live points, they are compacted by `compact_tombstones` and the indices of the
+    /// remaining points shift: the result is then `LazyRemoval::Compacted` with the new index of
+    /// every point, so that the indices held by the caller can be updated.
+    pub fn remove_lazy(&mut self, index: usize) -> LazyRemoval {
+        if index >= self.points.len() || !self.store.bury(index) {
+            return LazyRemoval::NotFound;
+        }
+        if self.tombstone_count() as f64 > MAX_TOMBSTONE_RATIO * self.size() as f64 {
+            return LazyRemoval::Compacted { mapping: self.compact_tombstones_mapped() };
+        }
+        LazyRemoval::Buried
+    }
+
+    ///Returns the number of points removed by `remove_lazy` that are still stored
+    pub fn tombstone_count(&self) -> usize {
+        self.store.tombstone_count
+    }
+
+    ///Removes the tombstones for good and repairs the nodes, returns the removed points in index order
+    ///
+    /// Indices of the remaining points shift like with [`KdTree::remove_within_radius`].
+    pub fn compact_tombstones(&mut self) -> Vec<POINT> {
+        let tombstones: Vec<usize> = (0..self.points.len()).filter(|i| self.store.is_tombstone(*i)).collect();
+        self.store.clear_tombstones();
+        self.remove_indices(&tombstones)
+    }
+
+    /// Compacts the tombstones like `compact_tombstones`, returns the new index of the point previously at each index.
+    pub(super) fn compact_tombstones_mapped(&mut self) -> Vec<usize> {
+        let mut next = 0;
+        let mapping = (0..self.points.len())
+            .map(|i| {
+                if self.store.is_tombstone(i) {
+                    return REMOVED_INDEX;
+                }
+                next += 1;
+                next - 1
+            })
+            .collect();
+        self.compact_tombstones();
+        mapping
+    }
+}
@@ -1,35 +1,143 @@
 use std::borrow::Cow;
+use std::rc::Rc;
+use std::sync::Arc;
 
-pub trait KdTreePoint<const DIM:usize>{
-    fn as_kdtree_point(&self)->Cow<[f64;DIM]>;
-}
+use super::Scalar;
 
-impl<const DIM:usize> KdTreePoint<DIM> for [f64;DIM]{
-    fn as_kdtree_point(&self)->Cow<[f64;DIM]> {
-        Cow::Borrowed(self)
+///Points which can be stored in a `KdTree`, giving their coordinates of type `S` (`f64` by default)
+///
+/// Implementors provide `to_kdtree_point`, returning the coordinates by value. Implementations
+/// written against the old `as_kdtree_point` keep working through the default bridge between the
+/// two methods until they are migrated.
+///
+/// Each of the two methods is provided on top of the other, so one of them must be overridden:
+/// an implementation defining neither compiles, but calling either method then recurses until
+/// the stack overflows.
+pub trait KdTreePoint<const DIM:usize, S: Scalar = f64>{
+    ///Returns the coordinates of the point, `as_kdtree_point` by default
+    ///
+    /// To be overridden by every new implementation, see the documentation of the trait.
+    fn to_kdtree_point(&self)->[S;DIM] {
+        #[allow(deprecated)]
+        self.as_kdtree_point().into_owned()
     }
-}
 
-impl KdTreePoint<1> for f64{
-    fn as_kdtree_point(&self)->Cow<[f64;1]> {
-        Cow::Owned([*self])
+    ///Returns `points` as their coordinates if the points are arrays of coordinates, `None` by default
+    ///
+    /// A tree reads the coordinates of such points in place, instead of keeping a copy of them
+    /// next to the points. The array `i` must be `to_kdtree_point` of the point `i`.
+    fn as_positions(points: &[Self]) -> Option<&[[S;DIM]]> where Self: Sized {
+        let _ = points;
+        None
     }
-}
 
-impl KdTreePoint<2> for (f64,f64){
-    fn as_kdtree_point(&self)->Cow<[f64;2]> {
-        Cow::Owned([self.0,self.1])
+    ///Returns the coordinates of the point, borrowed or owned, `to_kdtree_point` by default
+    #[deprecated(note = "implement and call `to_kdtree_point` instead, the coordinates are returned by value")]
+    fn as_kdtree_point(&self)->Cow<'_, [S;DIM]> {
+        Cow::Owned(self.to_kdtree_point())
     }
 }
 
-impl KdTreePoint<3> for (f64,f64,f64){
-    fn as_kdtree_point(&self)->Cow<[f64;3]> {
-        Cow::Owned([self.0,self.1,self.2])
+impl<const DIM:usize, S: Scalar> KdTreePoint<DIM, S> for [S;DIM]{
+    fn to_kdtree_point(&self)->[S;DIM] {
+        *self
     }
+
+    fn as_positions(points: &[Self]) -> Option<&[[S;DIM]]> {
+        Some(points)
+    }
+}
+
+// References and smart pointers to points are points, so shared points can be stored without a newtype
+macro_rules! impl_pointer_points {
+    ($($pointer:ty),*) => {$(
+        impl<const DIM:usize, S: Scalar, P: KdTreePoint<DIM, S> + ?Sized> KdTreePoint<DIM, S> for $pointer{
+            fn to_kdtree_point(&self)->[S;DIM] {
+                (**self).to_kdtree_point()
+            }
+        }
+    )*};
 }
 
-impl KdTreePoint<4> for (f64,f64,f64,f64){
-    fn as_kdtree_point(&self)->Cow<[f64;4]> {
-        Cow::Owned([self.0,self.1,self.2,self.3])
+impl_pointer_points!(&P, Box<P>, Rc<P>, Arc<P>);
+
+///Points whose coordinates can be written back, needed by the methods moving stored points
+pub trait KdTreePointMut<const DIM:usize, S: Scalar = f64> : KdTreePoint<DIM, S>{
+    fn set_kdtree_point(&mut self, coords:&[S;DIM]);
+}
+
+impl<const DIM:usize, S: Scalar> KdTreePointMut<DIM, S> for [S;DIM]{
+    fn set_kdtree_point(&mut self, coords:&[S;DIM]) {
+        *self = *coords;
     }
-}
\ No newline at end of file
+}
+
+// Scalars are 1-D points, for both f32 and f64
+macro_rules! impl_scalar_points {
+    ($($t:ty),*) => {$(
+        impl KdTreePoint<1, $t> for $t{
+            fn to_kdtree_point(&self)->[$t;1] {
+                [*self]
+            }
+
+            fn as_positions(points: &[Self]) -> Option<&[[$t;1]]> {
+                Some(points.as_chunks().0)
+            }
+        }
+
+        impl KdTreePointMut<1, $t> for $t{
+            fn set_kdtree_point(&mut self, coords:&[$t;1]) {
+                *self = coords[0];
+            }
+        }
+    )*};
+}
+
+impl_scalar_points!(f32, f64);
+
+// The type of a tuple field, one `S` per field index
+macro_rules! tuple_field {
+    ($field:tt) => { S };
+}
+
+// Tuples of scalars are points of their arity, the fields giving the coordinates in order
+macro_rules! impl_tuple_points {
+    ($($dim:literal => ($($field:tt),+);)*) => {$(
+        impl<S: Scalar> KdTreePoint<$dim, S> for ($(tuple_field!($field),)+){
+            fn to_kdtree_point(&self)->[S;$dim] {
+                [$(self.$field),+]
+            }
+        }
+
+        impl<S: Scalar> KdTreePointMut<$dim, S> for ($(tuple_field!($field),)+){
+            fn set_kdtree_point(&mut self, coords:&[S;$dim]) {
+                *self = ($(coords[$field],)+);
+            }
+        }
+    )*};
+}
+
+impl_tuple_points! {
+    2 => (0, 1);
+    3 => (0, 1, 2);
+    4 => (0, 1, 2, 3);
+    5 => (0, 1, 2, 3, 4);
+    6 => (0, 1, 2, 3, 4, 5);
+    7 => (0, 1, 2, 3, 4, 5, 6);
+    8 => (0, 1, 2, 3, 4, 5, 6, 7);
+}
+
+// Integer arrays are converted to f64 coordinates, exactly for every type up to 32 bits.
+// i64 and u64 values beyond 2^53 in magnitude are rounded to the nearest f64, so distinct
+// cells may then share the same coordinates.
+macro_rules! impl_integer_points {
+    ($($t:ty),*) => {$(
+        impl<const DIM:usize> KdTreePoint<DIM> for [$t;DIM]{
+            fn to_kdtree_point(&self)->[f64;DIM] {
+                self.map(|x| x as f64)
+            }
+        }
+    )*};
+}
+
+impl_integer_points!(i8, i16, i32, i64, u8, u16, u32, u64);
@@ -0,0 +1,81 @@
+use super::{BuildError, KdTree, KdTreePoint, PairPoint};
+
+///A Kd-Tree searching under the metric `sqrt((x - y)ᵀ M (x - y))` with `M = L Lᵀ`, built by `KdTree::with_metric_transform`
+///
+/// Every point is stored at `Lᵀ x` in a Euclidean tree, where the Euclidean distance is the
+/// distance of the metric (a Mahalanobis distance when `M` is an inverse covariance matrix and `L`
+/// its Cholesky factor). Queries take raw coordinates and return the original points.
+#[derive(Debug, Clone)]
+pub struct TransformedKdTree<const DIM: usize, POINT: KdTreePoint<DIM>> {
+    transform: [[f64; DIM]; DIM],
+    tree: KdTree<DIM, PairPoint<DIM, POINT>>,
+}
+
+impl<const DIM: usize, POINT: KdTreePoint<DIM>> KdTree<DIM, POINT> {
+    ///Constructs a Kd-Tree searching under the metric `sqrt((x - y)ᵀ L Lᵀ (x - y))`
+    ///
+    /// # Parameters:
+    /// - `points`: The points of the tree, returned untouched by the queries.
+    /// - `l`: The factor `L` of the metric, by rows (`l[i][j]` is the coefficient of row `i` and column `j`),
+    ///   for instance the Cholesky factor of a positive-definite `M`.
+    ///
+    /// # Errors:
+    /// - `BuildError::NonFiniteTransform` if a coefficient of `l` is NaN or infinite.
+    pub fn with_metric_transform(points: Vec<POINT>, l: [[f64; DIM]; DIM]) -> Result<TransformedKdTree<DIM, POINT>, BuildError> {
+        for (row, coefficients) in l.iter().enumerate() {
+            if let Some(column) = coefficients.iter().position(|x| !x.is_finite()) {
+                return Err(BuildError::NonFiniteTransform { row, column, value: coefficients[column] });
+            }
+        }
+
+        let tree = points
+            .into_iter()
+            .map(|point| PairPoint::new(apply_transform(&l, &point.to_kdtree_point()), point))
+            .collect();
+        Ok(TransformedKdTree { transform: l, tree })
+    }
+}
+
+/// Returns `Lᵀ coord`.
+fn apply_transform<const DIM: usize>(l: &[[f64; DIM]; DIM], coord: &[f64; DIM]) -> [f64; DIM] {
+    std::array::from_fn(|column| (0..DIM).map(|row| l[row][column] * coord[row]).sum())
+}
+
+impl<const DIM: usize, POINT: KdTreePoint<DIM>> TransformedKdTree<DIM, POINT> {
+    ///Returns the distance between two raw coordinates under the metric of the tree
+    pub fn distance(&self, a: &[f64; DIM], b: &[f64; DIM]) -> f64 {
+        let delta: [f64; DIM] = std::array::from_fn(|axis| a[axis] - b[axis]);
+        apply_transform(&self.transform, &delta).iter().map(|x| x * x).sum::<f64>().sqrt()
+    }
+
+    ///Returns a reference to the nearest POINT to the given raw coordinates under the metric of the tree
+    pub fn nearest_by_coord(&self, coord: &[f64; DIM]) -> Option<&POINT> {
+        self.tree
+            .nearest_by_coord(&apply_transform(&self.transform, coord))
+            .map(PairPoint::payload)
+    }
+
+    ///Returns a reference to the nearest POINT to the target under the metric of the tree
+    pub fn nearest(&self, target: &POINT) -> Option<&POINT> {
+        self.nearest_by_coord(&target.to_kdtree_point())
+    }
+
+    ///Returns references to every POINT within `radius` of the given raw coordinates under the metric of the tree (boundary included)
+    pub fn within_radius(&self, coord: &[f64; DIM], radius: f64) -> Vec<&POINT> {
+        self.tree
+            .within_radius(&apply_transform(&self.transform, coord), radius)
+            .into_iter()
+            .map(PairPoint::payload)
+            .collect()
+    }
+
+    ///Returns the number of points in the tree
+    pub fn size(&self) -> usize {
+        self.tree.size()
+    }
+
+    ///Returns true if the tree holds no point
+    pub fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+    }
+}
@@ -0,0 +1,37 @@
+use super::{KdTree, KdTreePoint, Scalar};
+
+///Outcome of `KdTree::add_or_update`
+#[derive(Debug, Clone, PartialEq)]
+pub enum UpsertResult<POINT> {
+    ///No point had the same coordinates, the point was added at `index`
+    Inserted {
+        index: usize, //Index of the new point
+    },
+    ///A point had the same coordinates, it was replaced in place
+    Updated {
+        index: usize, //Index of the replaced point, kept by the new one
+        old: POINT,   //Replaced point
+    },
+}
+
+impl<const DIM: usize, POINT: KdTreePoint<DIM, S>, S: Scalar> KdTree<DIM, POINT, S> {
+    ///Replaces the point at exactly the same coordinates as `point`, or adds it if there is none
+    ///
+    /// The match is found through the tree by `find_index`, tombstones excluded. If several points
+    /// already share these coordinates, the one of smallest index is replaced. A replaced point keeps its index and its node, so repeated
+    /// updates of the same location don't grow the tree.
+    pub fn add_or_update(&mut self, point: POINT) -> UpsertResult<POINT> {
+        let position = point.to_kdtree_point();
+        match self.find_index(&position) {
+            Some(index) => {
+                // Equal coordinates may still differ by the sign of a zero
+                self.store.set(index, &position);
+                UpsertResult::Updated { index, old: std::mem::replace(&mut self.points[index], point) }
+            }
+            None => {
+                self.add_point(point);
+                UpsertResult::Inserted { index: self.points.len() - 1 }
+            }
+        }
+    }
+}
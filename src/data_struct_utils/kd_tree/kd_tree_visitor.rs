@@ -0,0 +1,100 @@
+use super::{point_in_aabb, KdTree, KdTreePoint, Node, NodeArena, QueryStats, Scalar};
+
+///What a traversal does after a `KdVisitor` visited a point
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraversalControl {
+    ///Visits both subtrees of the node
+    Continue,
+    ///Doesn't visit the left subtree of the node, holding coordinates <= split on its axis
+    SkipLeft,
+    ///Doesn't visit the right subtree of the node, holding coordinates >= split on its axis
+    SkipRight,
+    ///Doesn't visit any subtree of the node
+    SkipBoth,
+    ///Ends the traversal
+    Stop,
+}
+
+///Custom traversal of a Kd-Tree, see [`KdTree::traverse`]
+pub trait KdVisitor<const DIM: usize, POINT: KdTreePoint<DIM, S>, S: Scalar = f64> {
+    ///Visits a point of the tree and decides which subtrees of its node are visited next
+    ///
+    /// # Parameters:
+    /// - `index`: The index of the point in the tree.
+    /// - `point`: The point.
+    /// - `depth`: The depth of the node holding the point, 0 for the root.
+    /// - `axis`, `split`: The split axis and value of the node.
+    fn visit(&mut self, index: usize, point: &POINT, depth: usize, axis: usize, split: S) -> TraversalControl;
+}
+
+impl<const DIM: usize, S: Scalar> Node<DIM, S> {
+    /// Visits the tree in pre-order with an explicit stack, following the controls returned by the visitor.
+    ///
+    /// The points of a leaf bucket are visited one after the other with the axis and split of
+    /// their node; a leaf has no subtree so only `Stop` matters for them.
+    ///
+    /// # Returns:
+    /// - `false` if the visitor stopped the traversal.
+    pub(super) fn traverse<POINT: KdTreePoint<DIM, S>, V: KdVisitor<DIM, POINT, S>>(&self, nodes: &NodeArena<DIM, S>, values: &[POINT], depth: usize, visitor: &mut V, stats: &mut QueryStats) -> bool {
+        let mut stack = vec![(self, depth)];
+        while let Some((node, depth)) = stack.pop() {
+            stats.nodes_visited += 1;
+            let control = visitor.visit(node.index, &values[node.index], depth, node.axis, node.split);
+            if control == TraversalControl::Stop {
+                return false;
+            }
+            for &index in &node.bucket {
+                if visitor.visit(index, &values[index], depth, node.axis, node.split) == TraversalControl::Stop {
+                    return false;
+                }
+            }
+
+            let (visit_left, visit_right) = match control {
+                TraversalControl::Continue => (true, true),
+                TraversalControl::SkipLeft => (false, true),
+                TraversalControl::SkipRight => (true, false),
+                TraversalControl::SkipBoth | TraversalControl::Stop => (false, false),
+            };
+            if let (Some(right), true) = (nodes.get(node.right), visit_right) {
+                stack.push((right, depth + 1));
+            }
+            if let (Some(left), true) = (nodes.get(node.left), visit_left) {
+                stack.push((left, depth + 1));
+            }
+        }
+        true
+    }
+}
+
+/// Visitor collecting the indices of the points inside an axis-aligned box, used by `points_in_aabb`.
+pub(super) struct AabbVisitor<'b, const DIM: usize, S: Scalar> {
+    pub(super) min: &'b [S; DIM],
+    pub(super) max: &'b [S; DIM],
+    pub(super) found: Vec<usize>,
+}
+
+impl<const DIM: usize, POINT: KdTreePoint<DIM, S>, S: Scalar> KdVisitor<DIM, POINT, S> for AabbVisitor<'_, DIM, S> {
+    fn visit(&mut self, index: usize, point: &POINT, _depth: usize, axis: usize, split: S) -> TraversalControl {
+        if point_in_aabb(&point.to_kdtree_point(), self.min, self.max) {
+            self.found.push(index);
+        }
+        match (self.min[axis] <= split, self.max[axis] >= split) {
+            (true, true) => TraversalControl::Continue,
+            (false, true) => TraversalControl::SkipLeft,
+            (true, false) => TraversalControl::SkipRight,
+            (false, false) => TraversalControl::SkipBoth,
+        }
+    }
+}
+
+impl<const DIM: usize, POINT: KdTreePoint<DIM, S>, S: Scalar> KdTree<DIM, POINT, S> {
+    ///Visits the points of the tree in pre-order, the visitor deciding which subtrees are visited
+    ///
+    /// Every node calls `visitor.visit` on its points, then visits its left and right subtrees
+    /// unless the returned `TraversalControl` skips them; `Stop` ends the whole traversal.
+    pub fn traverse<V: KdVisitor<DIM, POINT, S>>(&self, visitor: &mut V) {
+        if let Some(root) = self.nodes.root() {
+            root.traverse(&self.nodes, &self.points, 0, visitor, &mut QueryStats::default());
+        }
+    }
+}
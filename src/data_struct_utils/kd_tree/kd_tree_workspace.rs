@@ -0,0 +1,49 @@
+use super::{KdTree, KdTreePoint, Scalar, SearchStack};
+
+///Buffers reused by the `*_with` queries of `KdTree`, so that a steady stream of queries doesn't allocate
+///
+/// The buffers grow to the largest result seen so far and are kept between the queries. A
+/// workspace can be used with any tree of the same scalar type, and each thread of a pool can own
+/// one since it is `Send`.
+#[derive(Debug, Clone)]
+pub struct QueryWorkspace<S: Scalar = f64> {
+    candidates: Vec<(usize, S)>, // candidates of `nearest_k_with` and their distances to the target
+    indices: Vec<usize>,         // indices returned by the last query
+    stack: SearchStack<S>,       // nodes left to search by the current query
+}
+
+impl<S: Scalar> Default for QueryWorkspace<S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S: Scalar> QueryWorkspace<S> {
+    ///Returns a workspace without buffers, they are allocated by the first queries
+    pub fn new() -> Self {
+        Self { candidates: Vec::new(), indices: Vec::new(), stack: SearchStack::new() }
+    }
+}
+
+impl<const DIM: usize, POINT: KdTreePoint<DIM, S>, S: Scalar> KdTree<DIM, POINT, S> {
+    ///Returns the indices of the `k` nearest points to the given coordinates, the nearest first, using the buffers of `ws`
+    ///
+    /// The points are the ones returned by `nearest_k`, in the same order. The indices are
+    /// borrowed from `ws` until its next query.
+    pub fn nearest_k_with<'w>(&self, ws: &'w mut QueryWorkspace<S>, coord: &[S; DIM], k: usize) -> &'w [usize] {
+        self.nearest_k_candidates(coord, k, &mut ws.candidates, &mut ws.stack);
+        ws.indices.clear();
+        ws.indices.extend(ws.candidates.iter().map(|(index, _)| *index));
+        &ws.indices
+    }
+
+    ///Returns the indices of every point within `radius` of the given coordinates (boundary included), using the buffers of `ws`
+    ///
+    /// The points are the ones returned by `within_radius`, in the same order. The indices are
+    /// borrowed from `ws` until its next query.
+    pub fn within_radius_with<'w>(&self, ws: &'w mut QueryWorkspace<S>, coord: &[S; DIM], radius: S) -> &'w [usize] {
+        ws.indices.clear();
+        self.within_radius_into(coord, radius, &mut ws.indices, &mut ws.stack);
+        &ws.indices
+    }
+}
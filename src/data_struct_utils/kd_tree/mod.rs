@@ -2,32 +2,599 @@
 //! The purpose of this structure is to organize K-dimensional points
 //!
 //! # Features 
-//! - Construction of a Kd-Tree from a set of points
-//! - `nearest` function to find the nearest point to a given one
+//! - Construction of a Kd-Tree from a set of points, configurable with `KdTreeBuilder` (split axis, leaf size, median or sliding-midpoint splits, exact or sampled medians)
+//! - Coordinates of any `Scalar` type, `f64` by default or `f32` to halve the memory
+//! - Points giving their coordinates by value through `KdTreePoint::to_kdtree_point`, the former `as_kdtree_point` being deprecated
+//! - `KdTreePoint` implementations for references, `Box`, `Rc` and `Arc` of points
+//! - `KdTreePoint` implementations for integer arrays, converted to f64 coordinates
+//! - Leaf buckets scanned 4 points at a time with the vectors of the `wide` crate with the `simd` feature
+//! - Parallel construction (`from_par`), iteration (`par_iter`) and batch queries (`nearest_batch_par`) with the `rayon` feature
+//! - `KdTreePoint` implementations for the `glam` vectors with the `glam` feature
+//! - `KdTreePoint` implementations for the `nalgebra` points and vectors with the `nalgebra` feature
+//! - `KdTreePoint` implementations for the `mint` and `cgmath` points and vectors with the `mint` and `cgmath` features
+//! - C functions for 2-D and 3-D trees of `f64` with the `ffi` feature, declared in `include/my_rust_utils.h`
+//! - `KdTree::from_pairs` to store a payload with each point, as a `PairPoint`
+//! - `KdTree::from_coordinate_columns` to build from one slice of coordinates per axis
+//! - `KdTreeMap` associating values to coordinates, with map-like insertions and removals
+//! - `KdTree::from_flat` to build from a flat buffer of interleaved coordinates, as `FlatPoint`s
+//! - `DynKdTree` for a dimension only known at runtime, with coordinates given as slices
+//! - `render_ascii` (or `Display`) to draw the tree as text, `to_dot` to export it to Graphviz, `to_visualization_json` for drawing tools
+//! - `axis_stats` of the coordinates on each axis, to choose an `AxisStrategy`
+//! - `check_invariants` to validate the structure of the tree
+//! - `into_parts` and `from_parts` to store a built tree and restore it without constructing it again
+//! - `to_build_order` and `from_prebuilt_order` to store only the points, in an order restoring the tree in linear time
+//! - `freeze_implicit` to store a tree that is only queried in heap order, as an `ImplicitKdTree` without child links
+//! - `write_binary` and `read_binary` to dump trees of `[f64; DIM]` in a compact versioned binary format
+//! - `from_csv` to build a tree from CSV rows, `write_points_csv` to export its points
+//! - `from_ply` and `from_xyz` to build 3-D trees from point cloud files
+//! - `from_npy` to build a tree from a NumPy array of shape `(n, DIM)`, `write_indices_npy` to hand query results back
+//! - `from_geojson_points` to build a 2-D tree of the Point features of a GeoJSON FeatureCollection, as `GeoFeature`s
+//! - `balance_report`, `depth_stats` and `structure_histogram` to tell when insertions degraded the tree enough to rebuild it
+//! - Nodes only store indices, all the nodes share a single allocation, and the coordinates are read from one contiguous array per axis (see `memory_usage`)
+//! - `iter` over the points in index order, `iter_entries` along with their indices and coordinates, `iter_mut_then_rebuild` to edit them all
+//! - `iter_prefix`, `iter_in_order`, `iter_post_order` and `iter_bfs` to iterate over the points in pre-order, in order, in post-order and level by level
+//! - `nearest` function to find the nearest point to a given one, `nearest_k` for the `k` nearest ones, ties giving the smallest index
+//! - `nearest_batch` to answer many queries at once, `nearest_batch_coherent` to search groups of spatially coherent queries together
+//! - `*_with_metric` variants of the queries for other distances (`Manhattan`, `Chebyshev`, `Minkowski`, `Haversine` on latitudes and longitudes or `HaversineLonLat` on longitudes and latitudes, any `Metric`)
+//! - `RobustEuclidean` distances computed without overflow, used by the queries on coordinates of extreme magnitudes
+//! - `KdTree::with_metric_transform` for Mahalanobis-like distances `(x - y)ᵀ M (x - y)`, searched in a transformed space
+//! - `CosineKdTree` to find the most similar vectors by cosine similarity
+//! - `within_radius` function to find every point within a radius, `remove_within_radius` to delete them
+//! - `nearest_k_with` and `within_radius_with` reusing the buffers of a `QueryWorkspace`, without allocating once it has grown
+//! - `traverse` to walk the tree with a custom `KdVisitor`, which can skip subtrees or stop
+//! - `bounding_box` of the stored points as an `Aabb`, kept up to date
+//! - `points_in_aabb` function to find every point inside a box, `remove_in_aabb` and `crop_to_aabb` to delete them
+//! - `remove_lazy` to mark points as tombstones skipped by the queries, compacted once they are too many
+//! - `add_or_update` replacing the point at the same coordinates instead of adding a duplicate
+//! - `get`, `position`, `points` and indexing to read the stored points, `find_index` to look a point up by its exact coordinates
+//! - `LinearSearch`, the same queries by scanning every point, for tiny sets of points and as a reference
+//! - `add_point` rebuilding the subtrees left unbalanced by the insertions, so that adding sorted points keeps a logarithmic depth
 
 
+pub mod kd_tree_scalar;
 pub mod kd_tree_traits;
 pub mod kd_tree_iterators;
+pub mod kd_tree_guard;
+pub mod kd_tree_errors;
+pub mod kd_tree_builder;
+pub mod kd_tree_stats;
+pub mod kd_tree_report;
+pub mod kd_tree_aabb;
+pub mod kd_tree_render;
+pub mod kd_tree_invariants;
+pub mod kd_tree_parts;
+pub mod kd_tree_build_order;
+pub mod kd_tree_implicit;
+pub mod kd_tree_binary;
+pub mod kd_tree_csv;
+pub mod kd_tree_point_cloud;
+pub mod kd_tree_geojson;
+pub mod kd_tree_npy;
+pub mod kd_tree_metric;
+pub mod kd_tree_ref;
+pub mod kd_tree_pair;
+pub mod kd_tree_flat;
+pub mod kd_tree_map;
+pub mod kd_tree_dyn;
+pub mod kd_tree_transform;
+pub mod kd_tree_cosine;
+pub mod kd_tree_visitor;
+pub mod kd_tree_tombstones;
+pub mod kd_tree_workspace;
+pub mod kd_tree_coherent;
+pub mod kd_tree_upsert;
+pub mod kd_tree_linear;
+#[cfg(feature = "rayon")]
+pub mod kd_tree_parallel;
+#[cfg(feature = "glam")]
+pub mod kd_tree_glam;
+#[cfg(feature = "nalgebra")]
+pub mod kd_tree_nalgebra;
+#[cfg(feature = "mint")]
+pub mod kd_tree_mint;
+#[cfg(feature = "cgmath")]
+pub mod kd_tree_cgmath;
+#[cfg(feature = "ffi")]
+pub mod kd_tree_ffi;
 
 #[cfg(test)]
 pub mod tests;
 
 
-pub use kd_tree_traits::KdTreePoint;
+pub use kd_tree_scalar::Scalar;
+pub use kd_tree_traits::{KdTreePoint, KdTreePointMut};
+pub use kd_tree_iterators::{Entry, KdTreeBfsIterator, KdTreeInOrderIterator, KdTreePostOrderIterator, KdTreePrefixIterator};
+pub use kd_tree_guard::PointGuard;
+pub use kd_tree_errors::{BuildError, CsvError, DecodeError, GeoJsonError, InvariantViolation, NpyError, PointCloudError, RebuildError};
+pub use kd_tree_builder::{AxisStrategy, KdTreeBuilder, MedianMode, SplitRule};
+pub use kd_tree_stats::QueryStats;
+pub use kd_tree_report::{AxisStats, BalanceReport, DepthStats, MemoryUsage, StructureHistogram, ALLOCATION_OVERHEAD};
+pub use kd_tree_aabb::Aabb;
+pub use kd_tree_render::DotOptions;
+pub use kd_tree_parts::{KdTreeParts, PartsNode};
+pub use kd_tree_binary::{BINARY_MAGIC, BINARY_VERSION};
+pub use kd_tree_implicit::ImplicitKdTree;
+pub use kd_tree_csv::CsvOptions;
+pub use kd_tree_geojson::{GeoFeature, GeoJsonOptions, JsonValue};
+pub use kd_tree_npy::write_indices_npy;
+pub use kd_tree_metric::{Chebyshev, Euclidean, Haversine, HaversineLonLat, Manhattan, Metric, Minkowski, RobustEuclidean};
+pub use kd_tree_ref::KdTreeRef;
+pub use kd_tree_pair::PairPoint;
+pub use kd_tree_flat::FlatPoint;
+pub use kd_tree_map::KdTreeMap;
+pub use kd_tree_dyn::DynKdTree;
+pub use kd_tree_transform::TransformedKdTree;
+pub use kd_tree_cosine::CosineKdTree;
+pub use kd_tree_visitor::{KdVisitor, TraversalControl};
+pub use kd_tree_workspace::QueryWorkspace;
+pub use kd_tree_tombstones::{LazyRemoval, REMOVED_INDEX};
+pub use kd_tree_upsert::UpsertResult;
+pub use kd_tree_linear::LinearSearch;
+
+use kd_tree_errors::{check_finite, check_query};
+use kd_tree_visitor::AabbVisitor;
+
+#[cfg(test)]
+thread_local! {
+    /// Number of subsets partitioned by the constructions of this thread, counted by the tests
+    static PARTITIONS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+    /// Number of distances between a point and a target computed by the Euclidean queries of this thread, counted by the tests
+    static DISTANCES: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+/// Position of a node in the arena of its tree.
+type NodeId = u32;
+
+/// Link to a missing child, or root of an empty tree.
+const NONE: NodeId = u32::MAX;
+
+/// Largest share of the points of a node that the subtree of one of its children may hold before
+/// `add_point` rebuilds it, which keeps the depth of the nodes below `log(n) / log(1 / MAX_CHILD_SHARE)`.
+const MAX_CHILD_SHARE: f64 = 0.75;
 
 ///Node for the KdTree
+///
+/// Nodes only store the indices of their points, the coordinates are read from the points of
+/// the tree so that they are not duplicated. Children are linked by their position in the
+/// `NodeArena` of the tree.
+///
+/// On the split axis, points are ordered by coordinate then by index, the coordinates following
+/// `Scalar::total_cmp` so that NaN has a place too: the left subtree holds the
+/// points before the ones of the node and the right subtree the points after them, so points
+/// whose coordinate equals the split may lie on both sides. `construct_kdtree` partitions and
+/// `add_node` inserts following that order, a new point equal to the split going to the right
+/// since its index is the largest. The queries know no index: a target on the split descends to
+/// the right first, and the left side is searched as its region distance is 0 on that axis.
+#[derive(Debug,Clone,PartialEq)]
+struct Node<const DIM: usize, S: Scalar> {
+    index: usize,           // index of the stored point in the points of the tree
+    axis: usize,            // split axis of this node
+    split: S,               // split value on the axis, the left subtree holds coordinates <= split and the right one coordinates >= split
+    bucket: Vec<usize>,     // indices of the other points stored in this node when it is a leaf bucket
+    left: NodeId,           // left child, NONE if there is none
+    right: NodeId,          // right child, NONE if there is none
+}
+
+/// Storage of the nodes of a tree, held in a single `Vec` instead of one allocation per node.
+///
+/// A construction pushes the nodes in pre-order, so that a subtree is stored contiguously.
+/// The nodes of removed subtrees are kept in a free list and reused by the next insertions.
+#[derive(Debug,Clone)]
+struct NodeArena<const DIM: usize, S: Scalar> {
+    nodes: Vec<Node<DIM, S>>, // nodes of the tree, released ones included
+    root: NodeId,             // root node, NONE for an empty tree
+    free: Vec<NodeId>,        // positions of the released nodes
+}
+
+impl<const DIM: usize, S: Scalar> NodeArena<DIM, S> {
+    /// Returns an arena without nodes.
+    fn new() -> Self {
+        Self { nodes: Vec::new(), root: NONE, free: Vec::new() }
+    }
+
+    /// Returns the node at `id`, `None` for `NONE`.
+    fn get(&self, id: NodeId) -> Option<&Node<DIM, S>> {
+        (id != NONE).then(|| &self.nodes[id as usize])
+    }
+
+    /// Returns the node at `id` mutably, `None` for `NONE`.
+    fn get_mut(&mut self, id: NodeId) -> Option<&mut Node<DIM, S>> {
+        (id != NONE).then(|| &mut self.nodes[id as usize])
+    }
+
+    /// Returns the root node, `None` for an empty tree.
+    fn root(&self) -> Option<&Node<DIM, S>> {
+        self.get(self.root)
+    }
+
+    /// Makes `child` the left child of `parent` if `left` is true, its right child otherwise.
+    fn set_child(&mut self, parent: NodeId, left: bool, child: NodeId) {
+        let parent = &mut self.nodes[parent as usize];
+        if left {
+            parent.left = child;
+        } else {
+            parent.right = child;
+        }
+    }
+
+    /// Stores a node, in a released position if there is one, and returns its position.
+    ///
+    /// # Panics:
+    /// - If the arena already holds `u32::MAX` nodes.
+    fn push(&mut self, node: Node<DIM, S>) -> NodeId {
+        if let Some(id) = self.free.pop() {
+            self.nodes[id as usize] = node;
+            return id;
+        }
+        let id = NodeId::try_from(self.nodes.len()).ok().filter(|id| *id != NONE).expect("too many nodes in the Kd-Tree");
+        self.nodes.push(node);
+        id
+    }
+
+    /// Releases every node of the subtree rooted at `id`, their positions are reused by the next insertions.
+    fn release(&mut self, id: NodeId) {
+        let mut stack = vec![id];
+        while let Some(id) = stack.pop() {
+            if let Some(node) = self.get_mut(id) {
+                node.bucket = Vec::new();
+                stack.push(node.left);
+                stack.push(node.right);
+                self.free.push(id);
+            }
+        }
+    }
+
+    /// Returns the number of points stored in the subtree rooted at `id`.
+    fn subtree_len(&self, id: NodeId) -> usize {
+        let mut len = 0;
+        let mut stack = vec![id];
+        while let Some(id) = stack.pop() {
+            if let Some(node) = self.get(id) {
+                len += 1 + node.bucket.len();
+                stack.push(node.left);
+                stack.push(node.right);
+            }
+        }
+        len
+    }
+
+    /// Removes every node, keeping the allocated storage.
+    fn clear(&mut self) {
+        self.nodes.clear();
+        self.free.clear();
+        self.root = NONE;
+    }
+
+    /// Returns the number of nodes of the tree, released ones excluded.
+    fn len(&self) -> usize {
+        self.nodes.len() - self.free.len()
+    }
+
+    /// Stores the nodes of the tree contiguously in pre-order, dropping the released ones and every spare capacity.
+    fn compact(&mut self) {
+        let mut order = Vec::with_capacity(self.len());
+        let mut new_ids = vec![NONE; self.nodes.len()];
+        let mut stack = vec![self.root];
+        while let Some(id) = stack.pop() {
+            if let Some(node) = self.get(id) {
+                new_ids[id as usize] = order.len() as NodeId;
+                order.push(id);
+                stack.push(node.right);
+                stack.push(node.left);
+            }
+        }
+
+        let remap = |id: NodeId| if id == NONE { NONE } else { new_ids[id as usize] };
+        let mut old: Vec<Option<Node<DIM, S>>> = std::mem::take(&mut self.nodes).into_iter().map(Some).collect();
+        self.nodes = Vec::with_capacity(order.len());
+        for id in order {
+            let mut node = old[id as usize].take().unwrap();
+            (node.left, node.right) = (remap(node.left), remap(node.right));
+            node.bucket.shrink_to_fit();
+            self.nodes.push(node);
+        }
+        self.root = remap(self.root);
+        self.free = Vec::new();
+    }
+}
+
+impl<const DIM: usize, S: Scalar> PartialEq for NodeArena<DIM, S> {
+    /// Structural equality: the nodes reachable from both roots are equal with the same links,
+    /// wherever they are stored.
+    fn eq(&self, other: &Self) -> bool {
+        let mut stack = vec![(self.root, other.root)];
+        while let Some((lhs, rhs)) = stack.pop() {
+            match (self.get(lhs), other.get(rhs)) {
+                (None, None) => {}
+                (Some(a), Some(b)) => {
+                    if (a.index, a.axis, &a.bucket) != (b.index, b.axis, &b.bucket) || a.split != b.split {
+                        return false;
+                    }
+                    stack.push((a.left, b.left));
+                    stack.push((a.right, b.right));
+                }
+                _ => return false,
+            }
+        }
+        true
+    }
+}
+
+/// Nodes left to search by a query, which walks the tree with an explicit stack so that any depth fits.
+///
+/// Each node comes with the squared offsets of the target to its region on each axis, see
+/// `Node::search_far_side`, stored `DIM` by `DIM` in `offsets` so that a `QueryWorkspace` can
+/// keep the buffers whatever the dimension. The far sides also come with the squared distance of
+/// the target to their region, compared to the best candidates when they are popped.
 #[derive(Debug,Clone)]
-struct Node<const DIM: usize> {
-    point: Point<DIM>,      // the stored point in this node
-    left: Option<Box<Self>>, // left child
-    right: Option<Box<Self>>,// right child
+struct SearchStack<S: Scalar> {
+    entries: Vec<(NodeId, Option<S>)>, // nodes to search and the region distances of the far sides
+    offsets: Vec<S>,                   // offsets of the entries, DIM per entry
+}
+
+impl<S: Scalar> SearchStack<S> {
+    /// Returns a stack without buffers.
+    fn new() -> Self {
+        Self { entries: Vec::new(), offsets: Vec::new() }
+    }
+
+    /// Pushes the node `id` to search, with the offsets of the target to its region.
+    fn push<const DIM: usize>(&mut self, id: NodeId, offsets: &[S; DIM], region_distance: Option<S>) {
+        self.entries.push((id, region_distance));
+        self.offsets.extend_from_slice(offsets);
+    }
+
+    /// Pops the last node pushed, with the offsets of the target to its region.
+    fn pop<const DIM: usize>(&mut self) -> Option<(NodeId, [S; DIM], Option<S>)> {
+        let (id, region_distance) = self.entries.pop()?;
+        let start = self.offsets.len() - DIM;
+        let offsets = std::array::from_fn(|axis| self.offsets[start + axis]);
+        self.offsets.truncate(start);
+        Some((id, offsets, region_distance))
+    }
+}
+
+/// Coordinates of the points of a tree which are not arrays of coordinates, along with the tombstones.
+///
+/// `columns[axis][i]` is the coordinate of the point `i` on `axis`. Points which are arrays of
+/// coordinates, see [`KdTreePoint::as_positions`], are read in place and nothing is copied: the
+/// columns stay empty. The other points are copied once, so that `to_kdtree_point` is called once
+/// per point and scanning many points reads each axis sequentially.
+#[derive(Debug,Clone,PartialEq)]
+struct ColumnStore<const DIM: usize, S: Scalar> {
+    columns: [Vec<S>; DIM], // coordinates on each axis, indexed like the points, empty if they are read in place
+    in_place: bool,         // true if the points are arrays of coordinates, read in place
+    len: usize,             // number of points, also known when DIM is 0
+    tombstones: Vec<bool>,  // points removed by `KdTree::remove_lazy` but still stored, empty while there is none
+    tombstone_count: usize, // number of true values of `tombstones`
+}
+
+impl<const DIM: usize, S: Scalar> ColumnStore<DIM, S> {
+    /// Returns the store of the coordinates of `points`, copying them only if they can't be read in place.
+    fn of_points<POINT: KdTreePoint<DIM, S>>(points: &[POINT]) -> Self {
+        let in_place = POINT::as_positions(points).is_some();
+        let mut store = Self { columns: std::array::from_fn(|_| Vec::new()), in_place, len: 0, tombstones: Vec::new(), tombstone_count: 0 };
+        store.assign(points);
+        store
+    }
+
+    /// Replaces the coordinates by the ones of `points`, keeping the allocated storage.
+    ///
+    /// The tombstones are kept, `points` must then be the same number of points.
+    fn assign<POINT: KdTreePoint<DIM, S>>(&mut self, points: &[POINT]) {
+        if self.in_place {
+            self.len = points.len();
+            return;
+        }
+        self.len = 0;
+        for column in &mut self.columns {
+            column.clear();
+            column.reserve_exact(points.len());
+        }
+        for point in points {
+            self.push(&point.to_kdtree_point());
+        }
+    }
+
+    /// Appends the coordinates of a new point.
+    fn push(&mut self, position: &[S;DIM]) {
+        if !self.in_place {
+            for (column, x) in self.columns.iter_mut().zip(position) {
+                column.push(*x);
+            }
+        }
+        if !self.tombstones.is_empty() {
+            self.tombstones.push(false);
+        }
+        self.len += 1;
+    }
+
+    /// Replaces the coordinates of the point `index`.
+    fn set(&mut self, index: usize, position: &[S;DIM]) {
+        if !self.in_place {
+            for (column, x) in self.columns.iter_mut().zip(position) {
+                column[index] = *x;
+            }
+        }
+    }
+
+    /// Reserves room in the columns for at least `additional` more points, they stay empty if read in place.
+    fn reserve(&mut self, additional: usize) {
+        if !self.in_place {
+            for column in &mut self.columns {
+                column.reserve(additional);
+            }
+        }
+    }
+
+    /// Returns the number of points the columns can hold without reallocating, `usize::MAX` if read in place.
+    fn capacity(&self) -> usize {
+        match self.in_place {
+            true => usize::MAX,
+            false => self.columns.iter().map(Vec::capacity).min().unwrap_or(usize::MAX),
+        }
+    }
+
+    /// Releases the spare capacity of the columns.
+    fn shrink_to_fit(&mut self) {
+        for column in &mut self.columns {
+            column.shrink_to_fit();
+        }
+        self.tombstones.shrink_to_fit();
+    }
+
+    /// Removes the coordinates of the points marked in `removed`, the other ones keep their order.
+    fn remove(&mut self, removed: &[bool]) {
+        self.len -= removed.iter().filter(|removed| **removed).count();
+        if !self.in_place {
+            for column in &mut self.columns {
+                let mut i = 0;
+                column.retain(|_| {
+                    i += 1;
+                    !removed[i - 1]
+                });
+            }
+        }
+        if self.tombstone_count > 0 {
+            let mut removed = removed.iter();
+            self.tombstones.retain(|_| !removed.next().unwrap());
+            self.tombstone_count = self.tombstones.iter().filter(|tombstone| **tombstone).count();
+        }
+    }
+
+    /// Returns true if the point `index` was removed by `KdTree::remove_lazy`.
+    fn is_tombstone(&self, index: usize) -> bool {
+        self.tombstone_count > 0 && self.tombstones[index]
+    }
+
+    /// Marks the point `index` as a tombstone, returns false if it already was one.
+    fn bury(&mut self, index: usize) -> bool {
+        if self.tombstones.is_empty() {
+            self.tombstones.resize(self.len, false);
+        }
+        let buried = !std::mem::replace(&mut self.tombstones[index], true);
+        self.tombstone_count += buried as usize;
+        buried
+    }
+
+    /// Forgets every tombstone.
+    fn clear_tombstones(&mut self) {
+        self.tombstones.clear();
+        self.tombstone_count = 0;
+    }
+
+    /// Returns the number of bytes used by the columns and the tombstones, allocation overheads included.
+    fn memory_usage(&self) -> usize {
+        let allocation = |bytes: usize| if bytes > 0 { bytes + ALLOCATION_OVERHEAD } else { 0 };
+        self.columns.iter()
+            .map(|column| allocation(column.capacity() * std::mem::size_of::<S>()))
+            .sum::<usize>() + allocation(self.tombstones.capacity())
+    }
+}
+
+/// Coordinates of the points of a tree as read by the constructions and the queries.
+///
+/// They are the points themselves when these are arrays of coordinates, the columns of the
+/// `ColumnStore` otherwise.
+#[derive(Clone,Copy)]
+struct Columns<'a, const DIM: usize, S: Scalar> {
+    store: &'a ColumnStore<DIM, S>,    // copied coordinates, if any, and tombstones
+    positions: Option<&'a [[S; DIM]]>, // the points when they are read in place
+}
+
+impl<'a, const DIM: usize, S: Scalar> Columns<'a, DIM, S> {
+    /// Returns the coordinates of `points`, whose store is `store`.
+    fn new<POINT: KdTreePoint<DIM, S>>(store: &'a ColumnStore<DIM, S>, points: &'a [POINT]) -> Self {
+        Self { store, positions: POINT::as_positions(points) }
+    }
+
+    /// Returns the number of points.
+    fn len(&self) -> usize {
+        self.store.len
+    }
+
+    /// Returns true if the point `index` was removed by `KdTree::remove_lazy`.
+    fn is_tombstone(&self, index: usize) -> bool {
+        self.store.is_tombstone(index)
+    }
+
+    /// Returns the coordinate of the point `index` on `axis`.
+    fn coord(&self, axis: usize, index: usize) -> S {
+        match self.positions {
+            Some(positions) => positions[index][axis],
+            None => self.store.columns[axis][index],
+        }
+    }
+
+    /// Returns the coordinates of the point `index`.
+    fn position(&self, index: usize) -> [S;DIM] {
+        match self.positions {
+            Some(positions) => positions[index],
+            None => std::array::from_fn(|axis| self.store.columns[axis][index]),
+        }
+    }
+
+    /// Returns the entries of the points `indices`, to be partitioned by a construction.
+    fn entries<I: IntoIterator<Item = usize> + 'a>(self, indices: I) -> impl Iterator<Item = BuildEntry<DIM, S>> + 'a {
+        indices.into_iter().map(move |index| BuildEntry { position: self.position(index), index })
+    }
+
+    /// Computes the squared Euclidean distance between the point `index` and `target`, like `squared_distance`.
+    fn squared_distance(&self, index: usize, target: &[S;DIM]) -> S {
+        #[cfg(test)]
+        DISTANCES.with(|count| count.set(count.get() + 1));
+        let position = self.position(index);
+        sum_over_axes::<DIM, S, _>(|axis| {
+            let delta = position[axis] - target[axis];
+            delta * delta
+        })
+    }
+
+    /// Calls `f` with each point of `indices` and its squared distance to `target`, like `squared_distance`.
+    ///
+    /// With the `simd` feature, the distances are computed 4 at a time by `Scalar::squared_distances_x4`.
+    fn for_each_squared_distance<I: Iterator<Item = usize>, F: FnMut(usize, S)>(&self, indices: I, target: &[S;DIM], mut f: F) {
+        #[cfg(feature = "simd")]
+        {
+            let mut lanes = [0; 4];
+            let mut len = 0;
+            for index in indices {
+                lanes[len] = index;
+                len += 1;
+                if len == lanes.len() {
+                    #[cfg(test)]
+                    DISTANCES.with(|count| count.set(count.get() + lanes.len()));
+                    let mut coords = [[S::ZERO; 4]; DIM];
+                    for (lane, i) in lanes.into_iter().enumerate() {
+                        for (coords, x) in coords.iter_mut().zip(self.position(i)) {
+                            coords[lane] = x;
+                        }
+                    }
+                    for (index, distance) in lanes.into_iter().zip(S::squared_distances_x4(&coords, target)) {
+                        f(index, distance);
+                    }
+                    len = 0;
+                }
+            }
+            for index in &lanes[..len] {
+                f(*index, self.squared_distance(*index, target));
+            }
+        }
+        #[cfg(not(feature = "simd"))]
+        for index in indices {
+            f(index, self.squared_distance(index, target));
+        }
+    }
 }
 
-///Structure that represent a k-dimensional point
-#[derive(Debug, Clone,Copy)]
-pub(crate)struct Point<const DIM: usize> {
-    pub(crate) position: [f64; DIM], //Coordinates of the point
-    index:usize //Index of the point in the original input list
+/// Point of a subset partitioned by a construction, its coordinates stored along with its index.
+///
+/// The constructions move the entries around rather than bare indices, so that comparing two
+/// points reads the entries themselves instead of looking their coordinates up in the columns.
+#[derive(Debug,Clone,Copy,PartialEq)]
+struct BuildEntry<const DIM: usize, S: Scalar> {
+    position: [S; DIM], // coordinates of the point
+    index: usize,       // index of the point in the tree
 }
 
 /// A Kd-Tree data structure for partitioning a k-dimensional space.
@@ -38,224 +605,1373 @@ pub(crate)struct Point<const DIM: usize> {
 /// # Type Parameters:
 /// - `DIM`: The number of dimensions.
 /// - `POINT`: The type of point stored in the tree, which must implement `KdTreePoint`.
+/// - `S`: The type of the coordinates, `f64` by default.
 /// 
 
-#[derive(Debug,Clone)]
-pub struct KdTree<const DIM: usize,POINT: KdTreePoint<DIM>> {
-    root: Option<Box<Node<DIM>>>, //Root node of the Kd-Tree
+#[derive(Clone)]
+pub struct KdTree<const DIM: usize,POINT: KdTreePoint<DIM, S>, S: Scalar = f64> {
+    nodes: NodeArena<DIM, S>, //Nodes of the Kd-Tree
+
+    points : Vec<POINT>,
+
+    store: ColumnStore<DIM, S>, //Coordinates of the points not read in place, one array per axis, and the tombstones
 
-    points : Vec<POINT>
+    builder: KdTreeBuilder, //Options used to construct the tree, reused when rebuilding
+
+    scratch: Vec<BuildEntry<DIM, S>>, //Entries buffer kept by `rebuild_from` to rebuild without allocating
+
+    bounds: Option<Aabb<DIM, S>>, //Bounding box of the points, kept up to date by every modification
 }
 
-impl<const DIM: usize, POINT:KdTreePoint<DIM>> From<Vec<POINT>> for KdTree<DIM,POINT> {
+impl<const DIM: usize, POINT:KdTreePoint<DIM, S>, S: Scalar> From<Vec<POINT>> for KdTree<DIM,POINT,S> {
     /// Constructs a Kd-Tree from a vector of points.
+    ///
+    /// Coordinates are not validated, use [`KdTree::try_from_points`] to reject NaN and infinite
+    /// ones. With infinite coordinates the queries stay exact: distances are infinite, or NaN for a
+    /// point and a target infinite on the same axis, which come after every other distance. With
+    /// NaN coordinates the queries may return wrong results, the construction itself stays
+    /// deterministic, NaN being ordered after every other coordinate by `Scalar::total_cmp`.
     fn from(value: Vec<POINT>) -> Self {
-        if DIM == 0{
-            return Self{
-                root : None,
-                points : value,
-            };
-        }
+        KdTreeBuilder::new().build(value)
+    }
+}
 
-        let mut indices = (0..value.len()).collect::<Vec<_>>();       
+impl<const DIM: usize, POINT:KdTreePoint<DIM, S> + PartialEq, S: Scalar> PartialEq for KdTree<DIM,POINT,S> {
+    /// Content equality: two trees are equal if they hold the same multiset of points, whatever
+    /// their order and the layout of the nodes.
+    ///
+    /// Trees built from the same points in different orders are equal, but may be structurally
+    /// different, see [`KdTree::structurally_equal`].
+    fn eq(&self, other: &Self) -> bool {
+        if self.points.len() != other.points.len() {
+            return false;
+        }
+        // A total order, so that the sort is consistent with NaN coordinates. `-0` and `+0` are
+        // merged, being equal coordinates
+        let cmp = |a: &POINT, b: &POINT| {
+            let (a, b) = (a.to_kdtree_point(), b.to_kdtree_point());
+            let key = |x: S| if x == S::ZERO { S::ZERO } else { x };
+            (0..DIM).map(|axis| key(a[axis]).total_cmp(&key(b[axis])))
+                .find(|ordering| ordering.is_ne())
+                .unwrap_or(std::cmp::Ordering::Equal)
+        };
+        let mut lhs: Vec<&POINT> = self.points.iter().collect();
+        let mut rhs: Vec<&POINT> = other.points.iter().collect();
+        lhs.sort_by(|a, b| cmp(a, b));
+        rhs.sort_by(|a, b| cmp(a, b));
 
-        Self {
-            root: Node::<DIM>::construct_kdtree(&value,&mut indices.as_mut_slice(), 0),
-            points : value
+        // Points sharing coordinates may differ otherwise, they are matched one by one within
+        // each run of equal coordinates
+        let mut start = 0;
+        while start < lhs.len() {
+            let end = start + lhs[start..].iter().take_while(|p| cmp(p, lhs[start]).is_eq()).count();
+            let mut unmatched: Vec<&POINT> = rhs[start..end].to_vec();
+            for point in &lhs[start..end] {
+                match unmatched.iter().position(|other| other == point) {
+                    Some(position) => { unmatched.swap_remove(position); }
+                    None => return false,
+                }
+            }
+            start = end;
         }
+        true
+    }
+}
+
+impl<const DIM: usize, POINT:KdTreePoint<DIM, S>, S: Scalar> Default for KdTree<DIM,POINT,S> {
+    /// Constructs an empty Kd-Tree, see [`KdTree::new`].
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const DIM: usize, POINT:KdTreePoint<DIM, S>, S: Scalar> FromIterator<POINT> for KdTree<DIM,POINT,S> {
+    /// Constructs a balanced Kd-Tree from an iterator of points, see [`KdTree::from_iter_with_capacity`].
+    fn from_iter<I: IntoIterator<Item = POINT>>(iter: I) -> Self {
+        Self::from(iter.into_iter().collect::<Vec<_>>())
+    }
+}
+
+impl<const DIM: usize, POINT:KdTreePoint<DIM, S>, S: Scalar> std::ops::Index<usize> for KdTree<DIM,POINT,S> {
+    type Output = POINT;
+
+    /// Returns the POINT of the given index, see [`KdTree::get`].
+    ///
+    /// # Panics:
+    /// If `index` is out of range, like the slices.
+    fn index(&self, index: usize) -> &POINT {
+        &self.points[index]
+    }
+}
+
+/// Sums `term(axis)` over the axes, in axis order starting from zero.
+///
+/// The sum is unrolled for the dimensions 1 to 4, `DIM` being known at compile time the match
+/// is resolved by the optimizer. The additions are done in the same order as the generic fold,
+/// so the result is bit-identical whatever the dimension.
+#[inline(always)]
+fn sum_over_axes<const DIM: usize, S: Scalar, F: Fn(usize) -> S>(term: F) -> S {
+    match DIM {
+        1 => S::ZERO + term(0),
+        2 => S::ZERO + term(0) + term(1),
+        3 => S::ZERO + term(0) + term(1) + term(2),
+        4 => S::ZERO + term(0) + term(1) + term(2) + term(3),
+        _ => (0..DIM).fold(S::ZERO, |acc, axis| acc + term(axis)),
     }
 }
 
-impl<const DIM: usize> Point<DIM> {
-    /// Computes the squared Euclidean distance between this point and another point.
-    fn squared_distance(&self, other: &[f64;DIM]) -> f64 {
-        self.position
-            .iter()
-            .zip(other.iter())
-            .fold(0., |acc, (x, y)| acc + (x - y) * (x - y))
+/// Computes the squared Euclidean distance between two points.
+fn squared_distance<const DIM: usize, S: Scalar>(a: &[S;DIM], b: &[S;DIM]) -> S {
+    sum_over_axes::<DIM, S, _>(|axis| (a[axis] - b[axis]) * (a[axis] - b[axis]))
+}
+
+/// Returns true if the candidate `(index, distance)` comes before `best`: nearer, or as near with a smaller index.
+///
+/// This is the order of the results of the nearest neighbor queries, so that ties between
+/// points at the same distance always give the smallest index, whatever the layout of the nodes.
+/// NaN distances, from infinite coordinates of the point and the target on the same axis, come
+/// after all the other ones.
+fn precedes<S: Scalar>(candidate: (usize, S), best: (usize, S)) -> bool {
+    match candidate.1.partial_cmp(&best.1) {
+        Some(order) => order.then(candidate.0.cmp(&best.0)).is_lt(),
+        None => best.1.to_f64().is_nan() && (!candidate.1.to_f64().is_nan() || candidate.0 < best.0),
     }
 }
 
-impl<'a,const DIM: usize> Node<DIM> {
-    /// Recursively finds the nearest neighbor to the target point.
+/// Returns false if the squared distance of a region is known to be above `bound`, so that its points can be skipped.
+///
+/// A NaN distance or bound, from infinite coordinates, never skips anything.
+fn may_reach<S: Scalar>(region_distance: S, bound: S) -> bool {
+    !matches!(region_distance.partial_cmp(&bound), Some(std::cmp::Ordering::Greater))
+}
+
+/// Returns true if `position` lies inside the box `[min, max]` (boundary included).
+fn point_in_aabb<const DIM: usize, S: Scalar>(position: &[S;DIM], min: &[S;DIM], max: &[S;DIM]) -> bool {
+    (0..DIM).all(|axis| min[axis] <= position[axis] && position[axis] <= max[axis])
+}
+
+impl<const DIM: usize, S: Scalar> Node<DIM, S> {
+    /// Returns a leaf holding the single point `index`.
+    fn leaf(columns: &Columns<'_, DIM, S>, index: usize, axis: usize) -> Self {
+        let split = columns.coord(axis, index);
+        Self { index, axis, split, bucket: Vec::new(), left: NONE, right: NONE }
+    }
+
+    /// Searches the far side of the split of this node, with the offset of the target to the region on the split axis replaced by `delta`.
+    ///
+    /// `offsets` holds the squared offset of the target to the region of the node on each axis,
+    /// `search` is given them along with the squared distance of the target to the far region,
+    /// their sum: a lower bound of the distance of its points to the target. Only the term of the
+    /// split axis changes, the sum is taken in axis order so that it can't exceed the squared
+    /// distance of any point of the region, which `squared_distance` sums in the same order.
+    fn search_far_side<R, F: FnOnce(&mut [S;DIM], S) -> R>(offsets: &mut [S;DIM], axis: usize, delta: S, search: F) -> R {
+        let offset = std::mem::replace(&mut offsets[axis], delta * delta);
+        let region_distance = sum_over_axes::<DIM, S, _>(|axis| offsets[axis]);
+        let result = search(offsets, region_distance);
+        offsets[axis] = offset;
+        result
+    }
+
+    /// Returns the indices of the points stored in this node, `index` followed by the bucket.
+    fn node_points(&self) -> impl Iterator<Item = usize> + '_ {
+        std::iter::once(self.index).chain(self.bucket.iter().copied())
+    }
+
+    /// Returns the points of this node that are candidates of the queries, without the tombstones.
+    fn live_points<'a>(&'a self, columns: &'a Columns<'_, DIM, S>) -> impl Iterator<Item = usize> + 'a {
+        self.node_points().filter(|i| !columns.is_tombstone(*i))
+    }
+
+    /// Finds the nearest neighbor to the target point in the subtree of `id`, walking it with an explicit stack.
     ///
     /// # Parameters:
+    /// - `nodes`: The nodes of the tree.
+    /// - `columns`: The coordinates of the points of the tree.
     /// - `target`: The coordinates of the target point.
-    /// - `depth`: The current depth in the tree (used to determine the split axis).
-    /// - `best`: The best candidate node found so far.
+    /// - `stack`: The stack of the nodes left to search, empty before and after the search.
+    /// - `stats`: The statistics of the query, updated while searching.
     ///
     /// # Returns:
-    /// - An `Option` containing a reference to the nearest node.
+    /// - An `Option` containing the index of the nearest point and its squared distance.
     fn nearest(
-        &'a self,
-        target: &[f64;DIM],
-        depth: usize,
-        best: Option<&'a Node<DIM>>,
-    ) -> Option<&'a Self> {
-        let point = &self.point;
-
+        nodes: &NodeArena<DIM, S>,
+        id: NodeId,
+        columns: &Columns<'_, DIM, S>,
+        target: &[S;DIM],
+        stack: &mut SearchStack<S>,
+        stats: &mut QueryStats,
+    ) -> Option<(usize, S)> {
+        let mut best: Option<(usize, S)> = None;
+        stack.push(id, &[S::ZERO; DIM], None);
+        while let Some((id, mut offsets, region_distance)) = stack.pop::<DIM>() {
+            let Some(node) = nodes.get(id) else {
+                continue;
+            };
+            // A far side is searched if its region is not farther than the best point, or if only tombstones were found.
+            // A region exactly as far as the best may hold a tie with a smaller index, see `precedes`
+            if let (Some(region_distance), Some((_, best_distance))) = (region_distance, best) {
+                if !may_reach(region_distance, best_distance) {
+                    continue;
+                }
+            }
+            stats.nodes_visited += 1;
 
-        let self_distance = point.squared_distance(target);
-        let best_distance = best.map_or(f64::INFINITY, |b| b.point.squared_distance(target));
+            // Update the best point if a point of this node is closer
+            columns.for_each_squared_distance(node.live_points(columns), target, |candidate, distance| {
+                if best.is_none_or(|best| precedes((candidate, distance), best)) {
+                    best = Some((candidate, distance));
+                }
+            });
 
-        // Update the best node if this node is closer
-        let best = if self_distance < best_distance { self } else { best.unwrap_or(self) };
-    
+            let axis = node.axis;
 
-        let axis = depth % DIM;// Determine the splitting axis
+            // Determine the next subtree to search, a target on the split goes to the right, see `Node`
+            let (next, opposite_branch) = if target[axis] < node.split {
+                (node.left, node.right)
+            } else {
+                (node.right, node.left)
+            };
 
-        // Determine the next subtree to search
-        let (next, opposite_branch) = if target[axis] < point.position[axis] {
-            (
-                (self.left).as_ref().map(|n| n.as_ref()),
-                (self.right).as_ref().map(|n| n.as_ref()),
-            )
-        } else {
-            (
-                (self.right).as_ref().map(|n| n.as_ref()),
-                (self.left).as_ref().map(|n| n.as_ref()),
-            )
-        };
+            // The opposite subtree is pushed first to be searched once the next one is done, whose
+            // region is as far from the target as the one of this node
+            if opposite_branch != NONE {
+                Self::search_far_side(&mut offsets, axis, target[axis] - node.split, |offsets, region_distance| {
+                    stack.push(opposite_branch, offsets, Some(region_distance));
+                });
+            }
+            if next != NONE {
+                stack.push(next, &offsets, None);
+            }
+        }
+        best
+    }
 
-        // Search the next subtree
-        let candidate = next.and_then(|n| n.nearest(target, depth + 1, Some(best)));
-        let best = candidate.unwrap_or(best);
+    /// Collects the `k` nearest points to the target point in the subtree of `id`, walking it with an explicit stack.
+    ///
+    /// # Parameters:
+    /// - `nodes`: The nodes of the tree.
+    /// - `columns`: The coordinates of the points of the tree.
+    /// - `target`: The coordinates of the target point.
+    /// - `k`: The number of points to find.
+    /// - `best`: The indices of the best candidates found so far and their squared distances to the target, sorted by distance.
+    /// - `stack`: The stack of the nodes left to search, empty before and after the search.
+    /// - `stats`: The statistics of the query, updated while searching.
+    #[allow(clippy::too_many_arguments)]
+    fn nearest_k(nodes: &NodeArena<DIM, S>, id: NodeId, columns: &Columns<'_, DIM, S>, target: &[S;DIM], k: usize, best: &mut Vec<(usize, S)>, stack: &mut SearchStack<S>, stats: &mut QueryStats){
+        stack.push(id, &[S::ZERO; DIM], None);
+        while let Some((id, mut offsets, region_distance)) = stack.pop::<DIM>() {
+            let Some(node) = nodes.get(id) else {
+                continue;
+            };
+            // A far side is searched if its region is not farther than the worst candidate, like in `nearest`
+            if region_distance.is_some_and(|region_distance| best.len() >= k && !may_reach(region_distance, best[best.len() - 1].1)) {
+                continue;
+            }
+            stats.nodes_visited += 1;
 
-        // Check if we need to search the opposite subtree
-        if (target[axis] - self.point.position[axis]).powi(2) < best.point.squared_distance(&target){
-            return opposite_branch
-              .and_then(|n| n.nearest(target, depth + 1, Some(best)))
-              .or(Some(best));
+            // Insert the points of this node before the worst candidate, in the order of `precedes`
+            columns.for_each_squared_distance(node.live_points(columns), target, |candidate, distance| {
+                if best.len() < k || precedes((candidate, distance), best[best.len() - 1]) {
+                    let position = best.partition_point(|&found| precedes(found, (candidate, distance)));
+                    best.insert(position, (candidate, distance));
+                    best.truncate(k);
+                }
+            });
 
+            let axis = node.axis;
+            let (next, opposite_branch) = if target[axis] < node.split {
+                (node.left, node.right)
+            } else {
+                (node.right, node.left)
+            };
+            if opposite_branch != NONE {
+                Self::search_far_side(&mut offsets, axis, target[axis] - node.split, |offsets, region_distance| {
+                    stack.push(opposite_branch, offsets, Some(region_distance));
+                });
+            }
+            if next != NONE {
+                stack.push(next, &offsets, None);
+            }
         }
-        Some(best)
     }
 
     /// Constructs a Kd-Tree recursively.
     ///
     /// # Parameters:
-    /// - `values`: The input points.
-    /// - `indices`: Mutable slice of point indices to sort and partition.
+    /// - `entries`: Mutable slice of the entries of the points to sort and partition.
     /// - `depth`: The current depth in the tree.
+    /// - `builder`: The construction options.
+    /// - `nodes`: The arena the nodes are pushed into, in pre-order.
     ///
     /// # Returns:
-    /// - An `Option<Rc<Node<DIM>>>` representing the root of the constructed subtree.
-    fn construct_kdtree<POINT:KdTreePoint<DIM>>(values:&[POINT],indices: &mut [usize], depth: usize) -> Option<Box<Self>> {
-        if indices.is_empty() {
-            return None;
+    /// - The position of the root of the constructed subtree, `NONE` if `entries` is empty.
+    fn construct_kdtree(entries: &mut [BuildEntry<DIM, S>], depth: usize, builder: &KdTreeBuilder, nodes: &mut NodeArena<DIM, S>) -> NodeId {
+        let Some(first) = entries.first() else {
+            return NONE;
+        };
+        let axis = builder.axis_strategy.split_axis(entries, depth); //DIM != 0 because the condition is verify into the build function
+
+        // Small enough subsets are stored in a single leaf bucket
+        if entries.len() > 1 && entries.len() <= builder.leaf_size {
+            let bucket = entries[1..].iter().map(|entry| entry.index).collect();
+            return nodes.push(Self { index: first.index, axis, split: first.position[axis], bucket, left: NONE, right: NONE });
         }
-        let axis = depth % DIM; //DIM != 0 because the condition is verify into the from function
 
-        // Find the median index
-        let median = indices.len() / 2;
-        let (left, index, right) = indices.select_nth_unstable_by(median, |p1, p2| 
-            values[*p1].as_kdtree_point()[axis].partial_cmp(&values[*p2].as_kdtree_point()[axis]).unwrap_or(std::cmp::Ordering::Equal));
+        let (left, index, right, split) = Self::partition(entries, axis, builder);
+        let id = nodes.push(Self { index, axis, split, bucket: Vec::new(), left: NONE, right: NONE });
 
         // Recursively construct left and right subtrees
-        let left = Self::construct_kdtree(values,left, depth + 1);
-        let right = Self::construct_kdtree(values,right, depth + 1);
-        
-        let point = Point{
-            position : *values[*index].as_kdtree_point(),
-            index:*index
-        };
+        let left = Self::construct_kdtree(left, depth + 1, builder, nodes);
+        let right = Self::construct_kdtree(right, depth + 1, builder, nodes);
 
-        Some(Box::new(Self { point, left, right }))
+        let node = &mut nodes.nodes[id as usize];
+        (node.left, node.right) = (left, right);
+        id
     }
 
-    fn add_node(&mut self,new_node:Self,depth: usize){
-        let axis = depth % DIM;
-        
-        if self.point.position[axis] < new_node.point.position[axis]{
-            if let Some(right) = &mut self.right{
-                right.add_node(new_node, depth+1);
-                
-            }else {
-                self.right = Some(Box::new(new_node));
-                
+    /// Partitions a subset of points on the given axis according to the split rule of the builder.
+    ///
+    /// # Returns:
+    /// - The entries of the points of the left subtree, the index of the point of the node,
+    ///   the entries of the points of the right subtree and the split value.
+    #[allow(clippy::type_complexity)]
+    fn partition<'b>(entries: &'b mut [BuildEntry<DIM, S>], axis: usize, builder: &KdTreeBuilder) -> (&'b mut [BuildEntry<DIM, S>], usize, &'b mut [BuildEntry<DIM, S>], S) {
+        #[cfg(test)]
+        PARTITIONS.with(|count| count.set(count.get() + 1));
+        let coord = |entry: &BuildEntry<DIM, S>| entry.position[axis];
+        // A total order, ties broken by index, so that the same input always gives the same tree, NaN included
+        let cmp = |e1: &BuildEntry<DIM, S>, e2: &BuildEntry<DIM, S>| coord(e1).total_cmp(&coord(e2))
+            .then(e1.index.cmp(&e2.index));
+
+        if builder.split_rule == SplitRule::SlidingMidpoint {
+            let (min, max) = entries.iter()
+                .map(coord)
+                .fold((S::INFINITY, S::NEG_INFINITY), |(min, max), x| (min.min(x), max.max(x)));
+            let two = S::from_f64(2.);
+            let split = min / two + max / two;
+
+            // Without a finite extent (equal or infinite coordinates), the median split is used
+            if min < max && split.is_finite() {
+                // Points below the split go first
+                let mut below = 0;
+                for i in 0..entries.len() {
+                    if coord(&entries[i]) < split {
+                        entries.swap(i, below);
+                        below += 1;
+                    }
+                }
+
+                // The node holds the point above the split nearest to it, there is at least the max one
+                let nearest = (below..entries.len())
+                    .min_by(|a, b| cmp(&entries[*a], &entries[*b]))
+                    .unwrap_or(below);
+                entries.swap(nearest, below);
+
+                let (left, rest) = entries.split_at_mut(below);
+                let (entry, right) = rest.split_first_mut().unwrap();
+                return (left, entry.index, right, split);
             }
-        }else{
-            if let Some(left) = &mut self.left{
-                left.add_node(new_node, depth+1);
-                
-            }else {
-                self.left = Some(Box::new(new_node));
-                
+        }
+
+        if let MedianMode::Sampled { sample_size } = builder.median_mode {
+            if sample_size > 0 && entries.len() > sample_size.saturating_mul(4) {
+                let pivot = Self::sampled_median(entries, sample_size, cmp);
+
+                // Points before the pivot go first, followed by the pivot
+                let mut below = 0;
+                for i in 0..entries.len() {
+                    if cmp(&entries[i], &pivot).is_lt() {
+                        entries.swap(i, below);
+                        below += 1;
+                    }
+                }
+                let position = (below..entries.len()).find(|i| entries[*i].index == pivot.index).unwrap();
+                entries.swap(position, below);
+
+                let (left, rest) = entries.split_at_mut(below);
+                let (entry, right) = rest.split_first_mut().unwrap();
+                return (left, entry.index, right, coord(entry));
             }
         }
+
+        let median = entries.len() / 2;
+        let (left, entry, right) = entries.select_nth_unstable_by(median, cmp);
+        (left, entry.index, right, coord(entry))
+    }
+
+    /// Returns the entry of the median of a sample of `sample_size` points of the subset.
+    ///
+    /// The sample is drawn with a xorshift generator seeded from the size of the subset, so
+    /// that the same input always gives the same tree.
+    fn sampled_median<F: Fn(&BuildEntry<DIM, S>, &BuildEntry<DIM, S>) -> std::cmp::Ordering>(entries: &[BuildEntry<DIM, S>], sample_size: usize, cmp: F) -> BuildEntry<DIM, S> {
+        let mut state = 0x9E37_79B9_7F4A_7C15 ^ entries.len() as u64;
+        let mut sample: Vec<BuildEntry<DIM, S>> = (0..sample_size).map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            entries[(state % entries.len() as u64) as usize]
+        }).collect();
+        let median = sample.len() / 2;
+        *sample.select_nth_unstable_by(median, cmp).1
+    }
+
+    /// Returns true if the point `index`, not stored in this node, belongs to the right subtree.
+    fn goes_right(&self, columns: &Columns<'_, DIM, S>, index: usize) -> bool {
+        // Same order as in `partition`
+        let coord = columns.coord(self.axis, index);
+        self.split.total_cmp(&coord).then(self.index.cmp(&index)).is_lt()
+    }
+
+    /// Inserts the point `index` into the subtree rooted at `id`.
+    ///
+    /// The point is appended to the bucket of a non-full leaf, otherwise it is stored in a new
+    /// leaf whose split axis follows the one of its parent. As at build time, a point equal to the
+    /// split goes right if its index is greater than the one of the node.
+    ///
+    /// # Returns:
+    /// - The depth of the node holding the point, relative to the one of `id`.
+    fn add_node(nodes: &mut NodeArena<DIM, S>, mut id: NodeId, columns: &Columns<'_, DIM, S>, index: usize, leaf_size: usize) -> usize {
+        for depth in 0.. {
+            let node = &mut nodes.nodes[id as usize];
+            if node.is_leaf() && node.bucket.len() + 1 < leaf_size {
+                node.bucket.push(index);
+                return depth;
+            }
+
+            let goes_right = node.goes_right(columns, index);
+            let (axis, child) = (node.axis, if goes_right { node.right } else { node.left });
+
+            if child == NONE {
+                let leaf = nodes.push(Self::leaf(columns, index, (axis + 1) % DIM));
+                let node = &mut nodes.nodes[id as usize];
+                if goes_right {
+                    node.right = leaf;
+                } else {
+                    node.left = leaf;
+                }
+                return depth + 1;
+            }
+            id = child;
+        }
+        unreachable!()
+    }
+
+    /// Returns the nodes from `id` down to the one holding the point `index`, inserted by `add_node`.
+    fn path_to(nodes: &NodeArena<DIM, S>, mut id: NodeId, columns: &Columns<'_, DIM, S>, index: usize) -> Vec<NodeId> {
+        let mut path = Vec::new();
+        while let Some(node) = nodes.get(id) {
+            path.push(id);
+            if node.node_points().any(|i| i == index) {
+                break;
+            }
+            id = if node.goes_right(columns, index) { node.right } else { node.left };
+        }
+        path
+    }
+
+    /// Rebuilds the subtree of the lowest unbalanced node on `path`, from the root to a node inserted too deep.
+    ///
+    /// This is the rebuild of a scapegoat tree: a node is unbalanced when the subtree of its child
+    /// on the path holds more than `MAX_CHILD_SHARE` of its points. The subtree is built again with
+    /// the options of the tree, at its depth.
+    fn rebuild_scapegoat(nodes: &mut NodeArena<DIM, S>, path: &[NodeId], columns: &Columns<'_, DIM, S>, builder: &KdTreeBuilder) {
+        let Some((&last, _)) = path.split_last() else {
+            return;
+        };
+        let mut child_len = nodes.subtree_len(last);
+        for depth in (0..path.len() - 1).rev() {
+            let node = &nodes.nodes[path[depth] as usize];
+            let sibling = if node.left == path[depth + 1] { node.right } else { node.left };
+            let len = child_len + 1 + node.bucket.len() + nodes.subtree_len(sibling);
+            if child_len as f64 > MAX_CHILD_SHARE * len as f64 {
+                let rebuilt = Self::rebuild_subtree(nodes, path[depth], columns, |_| true, depth, builder);
+                match depth.checked_sub(1).map(|parent| &mut nodes.nodes[path[parent] as usize]) {
+                    Some(parent) if parent.left == path[depth] => parent.left = rebuilt,
+                    Some(parent) => parent.right = rebuilt,
+                    None => nodes.root = rebuilt,
+                }
+                return;
+            }
+            child_len = len;
+        }
     }
 
     fn is_leaf(&self)->bool{
-        self.left.is_none() && self.right.is_none()
+        self.left == NONE && self.right == NONE
+    }
+
+    /// Collects the indices of the points of the subtree of `id` lying within a radius of the target point, walking it with an explicit stack.
+    ///
+    /// # Parameters:
+    /// - `nodes`: The nodes of the tree.
+    /// - `columns`: The coordinates of the points of the tree.
+    /// - `target`: The coordinates of the target point.
+    /// - `squared_radius`: The squared search radius (the boundary is included).
+    /// - `found`: The vector the matching indices are pushed into.
+    /// - `stack`: The stack of the nodes left to search, empty before and after the search.
+    /// - `stats`: The statistics of the query, updated while searching.
+    #[allow(clippy::too_many_arguments)]
+    fn within_radius(nodes: &NodeArena<DIM, S>, id: NodeId, columns: &Columns<'_, DIM, S>, target: &[S;DIM], squared_radius: S, found: &mut Vec<usize>, stack: &mut SearchStack<S>, stats: &mut QueryStats){
+        stack.push(id, &[S::ZERO; DIM], None);
+        while let Some((id, mut offsets, _)) = stack.pop::<DIM>() {
+            let Some(node) = nodes.get(id) else {
+                continue;
+            };
+            stats.nodes_visited += 1;
+            columns.for_each_squared_distance(node.live_points(columns), target, |index, distance| {
+                if distance <= squared_radius {
+                    found.push(index);
+                }
+            });
+
+            let axis = node.axis;
+            let delta = target[axis] - node.split;
+
+            // A child on the side of the target is searched, the other one if its region is within
+            // the radius. The right child is pushed first so that the left one is searched first
+            for (child, near) in [(node.right, delta >= S::ZERO), (node.left, delta <= S::ZERO)] {
+                if child == NONE {
+                    continue;
+                }
+                if near {
+                    stack.push(child, &offsets, None);
+                    continue;
+                }
+                Self::search_far_side(&mut offsets, axis, delta, |offsets, region_distance| {
+                    if may_reach(region_distance, squared_radius) {
+                        stack.push(child, offsets, None);
+                    }
+                });
+            }
+        }
+    }
+
+    /// Collects the indices of the points of this subtree lying outside an axis-aligned box, walking it with an explicit stack.
+    ///
+    /// Subtrees whose region lies entirely inside the box are skipped.
+    ///
+    /// # Parameters:
+    /// - `nodes`: The nodes of the tree.
+    /// - `columns`: The coordinates of the points of the tree.
+    /// - `min`, `max`: The corners of the box (the boundary is considered inside).
+    /// - `found`: The vector the matching indices are pushed into.
+    fn outside_aabb(&self, nodes: &NodeArena<DIM, S>, columns: &Columns<'_, DIM, S>, min: &[S;DIM], max: &[S;DIM], found: &mut Vec<usize>){
+        // Nodes to search with the bounds of the region covered by their subtree
+        let mut stack = vec![(self, [S::NEG_INFINITY; DIM], [S::INFINITY; DIM])];
+        while let Some((node, cell_min, cell_max)) = stack.pop() {
+            if point_in_aabb(&cell_min, min, max) && point_in_aabb(&cell_max, min, max) {
+                continue;
+            }
+            for index in node.live_points(columns) {
+                if !point_in_aabb(&columns.position(index), min, max) {
+                    found.push(index);
+                }
+            }
+
+            let (axis, split) = (node.axis, node.split);
+            if let Some(right) = nodes.get(node.right) {
+                let mut right_min = cell_min;
+                right_min[axis] = split;
+                stack.push((right, right_min, cell_max));
+            }
+            if let Some(left) = nodes.get(node.left) {
+                let mut left_max = cell_max;
+                left_max[axis] = split;
+                stack.push((left, cell_min, left_max));
+            }
+        }
+    }
+
+    /// Checks that every point lies inside the region delimited by the splits of its ancestors, walking the tree with an explicit stack.
+    ///
+    /// # Parameters:
+    /// - `nodes`: The nodes of the tree.
+    /// - `columns`: The coordinates of the points of the tree.
+    fn is_valid(&self, nodes: &NodeArena<DIM, S>, columns: &Columns<'_, DIM, S>) -> bool {
+        // Nodes to check with the bounds of the region covered by their subtree
+        let mut stack = vec![(self, [S::NEG_INFINITY; DIM], [S::INFINITY; DIM])];
+        while let Some((node, cell_min, cell_max)) = stack.pop() {
+            if !node.node_points().all(|i| point_in_aabb(&columns.position(i), &cell_min, &cell_max)) {
+                return false;
+            }
+
+            let (axis, split) = (node.axis, node.split);
+            if let Some(right) = nodes.get(node.right) {
+                let mut right_min = cell_min;
+                right_min[axis] = split;
+                stack.push((right, right_min, cell_max));
+            }
+            if let Some(left) = nodes.get(node.left) {
+                let mut left_max = cell_max;
+                left_max[axis] = split;
+                stack.push((left, cell_min, left_max));
+            }
+        }
+        true
+    }
+
+    /// Moves every split of the subtree rooted at `id` onto the current position of the point of its node.
+    ///
+    /// This is only valid for trees whose splits lie on the points of their nodes (`SplitRule::Median`).
+    fn reset_splits(nodes: &mut NodeArena<DIM, S>, id: NodeId, columns: &Columns<'_, DIM, S>){
+        let mut stack = vec![id];
+        while let Some(id) = stack.pop() {
+            if let Some(node) = nodes.get_mut(id) {
+                node.split = columns.coord(node.axis, node.index);
+                stack.push(node.left);
+                stack.push(node.right);
+            }
+        }
+    }
+
+    /// Pushes the indices of every point of this subtree into `indices`, in pre-order.
+    fn collect_indices(&self, nodes: &NodeArena<DIM, S>, indices: &mut Vec<usize>){
+        let mut stack = vec![self];
+        while let Some(node) = stack.pop() {
+            indices.extend(node.node_points());
+            stack.extend(nodes.get(node.right));
+            stack.extend(nodes.get(node.left));
+        }
+    }
+
+    /// Releases the subtree rooted at `id` and constructs a new one from its points kept by `keep`.
+    ///
+    /// # Returns:
+    /// - The position of the root of the new subtree, `NONE` if no point is kept.
+    fn rebuild_subtree<F: Fn(usize) -> bool>(nodes: &mut NodeArena<DIM, S>, id: NodeId, columns: &Columns<'_, DIM, S>, keep: F, depth: usize, builder: &KdTreeBuilder) -> NodeId {
+        let mut survivors = Vec::new();
+        nodes.nodes[id as usize].collect_indices(nodes, &mut survivors);
+        let mut survivors: Vec<_> = columns.entries(survivors.into_iter().filter(|i| keep(*i))).collect();
+        nodes.release(id);
+        Self::construct_kdtree(&mut survivors, depth, builder, nodes)
     }
 
-    fn height(&self,depth: usize)->usize{
-        if self.is_leaf(){
-            return depth+1;
-        }else {
-            return usize::max(
-                (self.right.as_ref()).map(|r|r.height(depth+1)).unwrap_or(0), 
-                (self.left.as_ref()).map(|r|r.height(depth+1)).unwrap_or(0));
+    /// Removes the marked points from the subtree rooted at `id`.
+    ///
+    /// Subtrees free of marked points are kept as they are, a subtree whose root is marked
+    /// is rebuilt from its remaining points. The subtrees are repaired in pre-order with an
+    /// explicit stack, so that any depth fits.
+    ///
+    /// # Parameters:
+    /// - `nodes`: The nodes of the tree.
+    /// - `id`: The position of the root of the subtree.
+    /// - `columns`: The coordinates of the points of the tree, indexed like `removed`.
+    /// - `removed`: `removed[i]` is true if the point of index `i` has to be removed.
+    /// - `depth`: The current depth in the tree.
+    /// - `builder`: The construction options used to rebuild subtrees.
+    ///
+    /// # Returns:
+    /// - The position of the repaired subtree, `NONE` if every point was removed.
+    fn without(nodes: &mut NodeArena<DIM, S>, id: NodeId, columns: &Columns<'_, DIM, S>, removed: &[bool], depth: usize, builder: &KdTreeBuilder) -> NodeId {
+        let mut root = id;
+        // Subtrees to repair with their depth and the parent linking to them, as a left child or not
+        let mut stack = vec![(id, depth, None)];
+        while let Some((id, depth, parent)) = stack.pop() {
+            let Some(node) = nodes.get_mut(id) else {
+                continue;
+            };
+            if !removed[node.index] {
+                node.bucket.retain(|i| !removed[*i]);
+                stack.push((node.right, depth + 1, Some((id, false))));
+                stack.push((node.left, depth + 1, Some((id, true))));
+                continue;
+            }
+            let rebuilt = Self::rebuild_subtree(nodes, id, columns, |i| !removed[i], depth, builder);
+            match parent {
+                None => root = rebuilt,
+                Some((parent, left)) => nodes.set_child(parent, left, rebuilt),
+            }
         }
+        root
     }
 
-    //TODO, iterate over nodes (prefix, infix, postfix, breast)
+    /// Removes the node holding the point `index` from the subtree rooted at `id`, the subtree rooted at it is rebuilt.
+    ///
+    /// The node is searched in pre-order with an explicit stack, so that any depth fits.
+    ///
+    /// # Parameters:
+    /// - `nodes`: The nodes of the tree.
+    /// - `id`: The position of the root of the subtree.
+    /// - `columns`: The coordinates of the points of the tree.
+    /// - `index`: The index of the point to remove.
+    /// - `position`: The position the point had when it was inserted, used to find it.
+    /// - `depth`: The current depth in the tree.
+    /// - `builder`: The construction options used to rebuild the subtree.
+    ///
+    /// # Returns:
+    /// - The position of the repaired subtree, and whether the node was found in it.
+    #[allow(clippy::too_many_arguments)]
+    fn without_index(nodes: &mut NodeArena<DIM, S>, id: NodeId, columns: &Columns<'_, DIM, S>, index: usize, position: &[S;DIM], depth: usize, builder: &KdTreeBuilder) -> (NodeId, bool) {
+        // Subtrees to search with their depth and the parent linking to them, as a left child or not
+        let mut stack = vec![(id, depth, None)];
+        while let Some((child, depth, parent)) = stack.pop() {
+            let Some(node) = nodes.get_mut(child) else {
+                continue;
+            };
+            if node.index == index {
+                let rebuilt = Self::rebuild_subtree(nodes, child, columns, |i| i != index, depth, builder);
+                return match parent {
+                    None => (rebuilt, true),
+                    Some((parent, left)) => {
+                        nodes.set_child(parent, left, rebuilt);
+                        (id, true)
+                    }
+                };
+            }
+            if let Some(i) = node.bucket.iter().position(|i| *i == index) {
+                node.bucket.remove(i);
+                return (id, true);
+            }
+
+            // Points equal to the split may be on both sides, the left one is searched first
+            let (axis, split) = (node.axis, node.split);
+            if position[axis] >= split {
+                stack.push((node.right, depth + 1, Some((child, false))));
+            }
+            if position[axis] <= split {
+                stack.push((node.left, depth + 1, Some((child, true))));
+            }
+        }
+        (id, false)
+    }
+
+    /// Replaces every stored index `i` of the subtree rooted at `id` by `new_indices[i]`.
+    fn remap_indices(nodes: &mut NodeArena<DIM, S>, id: NodeId, new_indices: &[usize]){
+        let mut stack = vec![id];
+        while let Some(id) = stack.pop() {
+            if let Some(node) = nodes.get_mut(id) {
+                node.index = new_indices[node.index];
+                for index in &mut node.bucket {
+                    *index = new_indices[*index];
+                }
+                stack.push(node.left);
+                stack.push(node.right);
+            }
+        }
+    }
+}
+
+impl<const DIM: usize, S: Scalar> NodeArena<DIM, S> {
+    /// Returns the number of nodes of the tree and the bytes they use, buckets and allocation overheads included.
+    ///
+    /// The arena is counted whole, released nodes and spare capacity included, as a single allocation.
+    fn memory_usage(&self) -> (usize, usize) {
+        if self.nodes.capacity() == 0 {
+            return (0, 0);
+        }
+        let mut bytes = self.nodes.capacity() * std::mem::size_of::<Node<DIM, S>>() + ALLOCATION_OVERHEAD;
+        if self.free.capacity() > 0 {
+            bytes += self.free.capacity() * std::mem::size_of::<NodeId>() + ALLOCATION_OVERHEAD;
+        }
+        for node in &self.nodes {
+            if node.bucket.capacity() > 0 {
+                bytes += node.bucket.capacity() * std::mem::size_of::<usize>() + ALLOCATION_OVERHEAD;
+            }
+        }
+        (self.len(), bytes)
+    }
 }
 
-impl<const DIM:usize,POINT:KdTreePoint<DIM>> KdTree<DIM,POINT>{
+/// Entry points of the queries, shared by `KdTree` and `KdTreeRef` which only differ by how they store the points.
+///
+/// The queries only read the coordinates of the points from the columns, the points themselves
+/// are only visited by `in_aabb_indices` which goes through `traverse`.
+impl<const DIM: usize, S: Scalar> Node<DIM, S> {
+    /// Returns the index of the nearest point to `coord` in the tree of `nodes`.
+    fn nearest_index(nodes: &NodeArena<DIM, S>, columns: &Columns<'_, DIM, S>, coord: &[S;DIM], stats: &mut QueryStats) -> Option<usize> {
+        Self::nearest(nodes, nodes.root, columns, coord, &mut SearchStack::new(), stats).map(|(index, _)| index)
+    }
+
+    /// Returns the indices of the `k` nearest points to `coord` in the tree of `nodes`, the nearest first.
+    fn nearest_k_indices(nodes: &NodeArena<DIM, S>, columns: &Columns<'_, DIM, S>, coord: &[S;DIM], k: usize, stats: &mut QueryStats) -> Vec<usize> {
+        let mut best = Vec::with_capacity(k.min(columns.len()) + 1);
+        Self::nearest_k_indices_with(nodes, columns, coord, k, &mut best, stats)
+    }
+
+    /// Like `nearest_k_indices`, collecting the candidates into `best` which is cleared first, so that batches reuse it.
+    fn nearest_k_indices_with(nodes: &NodeArena<DIM, S>, columns: &Columns<'_, DIM, S>, coord: &[S;DIM], k: usize, best: &mut Vec<(usize, S)>, stats: &mut QueryStats) -> Vec<usize> {
+        Self::nearest_k_candidates(nodes, columns, coord, k, best, &mut SearchStack::new(), stats);
+        best.iter().map(|(index, _)| *index).collect()
+    }
+
+    /// Replaces the content of `best` by the `k` nearest points to `coord` and their squared distances, the nearest first.
+    ///
+    /// The search runs with `stack`, so that workspaces reuse it.
+    #[allow(clippy::too_many_arguments)]
+    fn nearest_k_candidates(nodes: &NodeArena<DIM, S>, columns: &Columns<'_, DIM, S>, coord: &[S;DIM], k: usize, best: &mut Vec<(usize, S)>, stack: &mut SearchStack<S>, stats: &mut QueryStats) {
+        best.clear();
+        if k > 0 {
+            Self::nearest_k(nodes, nodes.root, columns, coord, k, best, stack, stats);
+        }
+    }
+
+    /// Returns the indices of the points within `radius` of `coord` in the tree of `nodes`.
+    fn within_radius_indices(nodes: &NodeArena<DIM, S>, columns: &Columns<'_, DIM, S>, coord: &[S;DIM], radius: S, stats: &mut QueryStats) -> Vec<usize> {
+        let mut found = Vec::new();
+        Self::within_radius_into(nodes, columns, coord, radius, &mut found, &mut SearchStack::new(), stats);
+        found
+    }
+
+    /// Like `within_radius_indices`, pushing the indices into `found` and searching with `stack`, so that workspaces reuse them.
+    #[allow(clippy::too_many_arguments)]
+    fn within_radius_into(nodes: &NodeArena<DIM, S>, columns: &Columns<'_, DIM, S>, coord: &[S;DIM], radius: S, found: &mut Vec<usize>, stack: &mut SearchStack<S>, stats: &mut QueryStats) {
+        if radius >= S::ZERO {
+            Self::within_radius(nodes, nodes.root, columns, coord, radius * radius, found, stack, stats);
+        }
+    }
+
+    /// Returns the indices of the points inside the box `[min, max]` in the tree of `nodes`.
+    fn in_aabb_indices<POINT:KdTreePoint<DIM, S>>(nodes: &NodeArena<DIM, S>, columns: &Columns<'_, DIM, S>, values: &[POINT], min: &[S;DIM], max: &[S;DIM], stats: &mut QueryStats) -> Vec<usize> {
+        let mut visitor = AabbVisitor { min, max, found: Vec::new() };
+        if let Some(root) = nodes.root() {
+            root.traverse(nodes, values, 0, &mut visitor, stats);
+        }
+        visitor.found.retain(|i| !columns.is_tombstone(*i));
+        visitor.found
+    }
+
+    /// Returns the height of the tree of `nodes`, walking it with an explicit stack so that any depth fits.
+    fn tree_height(nodes: &NodeArena<DIM, S>) -> usize {
+        let mut height = 0;
+        let mut stack = vec![(nodes.root, 1)];
+        while let Some((id, depth)) = stack.pop() {
+            if let Some(node) = nodes.get(id) {
+                height = height.max(depth);
+                stack.push((node.left, depth + 1));
+                stack.push((node.right, depth + 1));
+            }
+        }
+        height
+    }
+}
+
+impl<const DIM:usize,POINT:KdTreePoint<DIM, S>, S: Scalar> KdTree<DIM,POINT,S>{
+
+    ///Constructs an empty Kd-Tree, with the default construction options
+    pub fn new() -> Self {
+        Self::from(Vec::new())
+    }
+
+    ///Constructs a balanced Kd-Tree from an iterator of points, reserving `capacity` points up front
+    ///
+    /// The points are collected once and the tree is built like with `From<Vec<POINT>>`,
+    /// useful when the iterator can't tell its size.
+    pub fn from_iter_with_capacity<I: IntoIterator<Item = POINT>>(iter: I, capacity: usize) -> Self {
+        let mut points = Vec::with_capacity(capacity);
+        points.extend(iter);
+        Self::from(points)
+    }
+
+    ///Constructs an empty Kd-Tree able to hold `capacity` points without reallocating
+    pub fn with_capacity(capacity: usize) -> Self {
+        let mut kd_tree = KdTreeBuilder::new().build(Vec::with_capacity(capacity));
+        kd_tree.reserve(capacity);
+        kd_tree
+    }
+
+    ///Reserves capacity for at least `additional` more points
+    ///
+    /// The points, their nodes and the copies of their coordinates are all reserved, so that as
+    /// many `add_point` calls don't reallocate.
+    pub fn reserve(&mut self, additional: usize) {
+        self.points.reserve(additional);
+        self.nodes.nodes.reserve(additional);
+        self.store.reserve(additional);
+    }
+
+    ///Returns the number of points the tree can hold without reallocating its storage
+    ///
+    /// The points it holds plus the ones that can be added before the points, the nodes or the
+    /// copies of the coordinates reallocate, whichever has the least room.
+    pub fn capacity(&self) -> usize {
+        let len = self.points.len();
+        let free_nodes = self.nodes.nodes.capacity() - self.nodes.nodes.len() + self.nodes.free.len();
+        len + (self.points.capacity() - len).min(free_nodes).min(self.store.capacity().saturating_sub(len))
+    }
+
+    ///Compacts the storage of the tree and releases its spare capacity, returning the new index of every point
+    ///
+    /// The tombstones left by `remove_lazy` are dropped by `compact_tombstones`, the nodes released
+    /// by the removals are dropped and the remaining ones are stored again in pre-order, the
+    /// buffers of the points, their coordinates and the rebuilds are shrunk to fit. The answers of
+    /// the queries are unchanged. `mapping[old]` is the index of the point previously at `old`,
+    /// `REMOVED_INDEX` for the dropped tombstones. Without tombstones, every point keeps its index.
+    pub fn shrink_to_fit(&mut self) -> Vec<usize> {
+        let mapping = self.compact_tombstones_mapped();
+        self.nodes.compact();
+        self.points.shrink_to_fit();
+        self.store.shrink_to_fit();
+        self.scratch = Vec::new();
+        mapping
+    }
+
+    ///Constructs a Kd-Tree from a vector of points, failing if any coordinate is NaN or infinite
+    pub fn try_from_points(points: Vec<POINT>) -> Result<Self, BuildError> {
+        for (index, point) in points.iter().enumerate() {
+            check_finite(&point.to_kdtree_point(), index)?;
+        }
+        Ok(Self::from(points))
+    }
+
+    ///Replaces the points of the tree and rebuilds it, reusing the allocated storage
+    ///
+    /// The result is identical to constructing a new tree with the same options. The points
+    /// buffer and the entries used during construction are kept and reused by the next calls,
+    /// so rebuilding with a steady number of points doesn't reallocate them.
+    pub fn rebuild_from<I: IntoIterator<Item = POINT>>(&mut self, points: I) {
+        self.nodes.clear();
+        self.points.clear();
+        self.points.extend(points);
+        self.store.clear_tombstones();
+        self.rebuild();
+    }
+
+    /// Rebuilds the node structure from the stored points.
+    fn rebuild(&mut self) {
+        self.store.assign(&self.points);
+        self.bounds = Aabb::of_columns(&self.columns());
+        self.nodes.clear();
+        if DIM == 0 {
+            return;
+        }
+        self.scratch.clear();
+        self.scratch.extend(Columns::new(&self.store, &self.points).entries(0..self.points.len()));
+        self.nodes.nodes.reserve_exact(self.points.len());
+        self.nodes.root = Node::construct_kdtree(&mut self.scratch, 0, &self.builder, &mut self.nodes);
+    }
 
     ///Returns a reference to the nearest POINT using given coordinates
-    pub fn nearest_by_coord(&self, coord :&[f64;DIM]) ->Option<&POINT>{
-        let index = self.root.as_ref().and_then(|n|
-            n.nearest(coord, 0, None)
-            .map(|b|b.point.index))?;
+    ///
+    /// Among the points at the same distance, the one of smallest index is returned whatever the
+    /// layout of the nodes, see `nearest_all_at_min_distance` to get all of them. A NaN coordinate
+    /// gives an unspecified point, see [`KdTree::try_nearest_by_coord`].
+    ///
+    /// Coordinates large enough for the squared distances to overflow, beyond about `1e153` for
+    /// `f64`, switch the search to `RobustEuclidean` so that the neighbors are still ranked. The
+    /// same goes for the other nearest neighbor and radius queries, batched or not, except the
+    /// `_with_stats` ones.
+    pub fn nearest_by_coord(&self, coord :&[S;DIM]) ->Option<&POINT>{
+        if self.distances_may_overflow(coord) {
+            return self.nearest_by_coord_with_metric(coord, &RobustEuclidean);
+        }
+        self.nearest_by_coord_with_stats(coord).0
+    }
 
-        Some(&self.points[index])
-        
+    ///Returns a reference to the nearest POINT using given coordinates, along with statistics on the search
+    ///
+    /// The squared distances are always computed plainly, even for coordinates making them overflow.
+    pub fn nearest_by_coord_with_stats(&self, coord :&[S;DIM]) ->(Option<&POINT>, QueryStats){
+        let mut stats = QueryStats::default();
+        let index = Node::nearest_index(&self.nodes, &self.columns(), coord, &mut stats);
+        (index.map(|i| &self.points[i]), stats)
     }
 
     ///Returns a reference to the nearest POINT using another POINT
     pub fn nearest(&self,target:&POINT)->Option<&POINT>{
-        let target = &target.as_kdtree_point();
+        self.nearest_by_coord(&target.to_kdtree_point())
+    }
+
+    ///Returns references to the `k` nearest POINTs to the given coordinates, the nearest first
+    ///
+    /// Fewer points are returned if the tree holds less than `k` points. Points at the same
+    /// distance come by increasing index, like in `nearest_by_coord`.
+    pub fn nearest_k(&self, coord: &[S;DIM], k: usize) -> Vec<&POINT>{
+        if self.distances_may_overflow(coord) {
+            return self.nearest_k_with_metric(coord, k, &RobustEuclidean);
+        }
+        Node::nearest_k_indices(&self.nodes, &self.columns(), coord, k, &mut QueryStats::default())
+            .into_iter()
+            .map(|i| &self.points[i])
+            .collect()
+    }
 
+    ///Returns the indices of every point at the distance of the nearest one to the given coordinates, increasing
+    ///
+    /// The first index is the one of the point returned by `nearest_by_coord`. Empty on an empty tree.
+    pub fn nearest_all_at_min_distance(&self, coord: &[S;DIM]) -> Vec<usize>{
+        let mut stats = QueryStats::default();
+        let Some(nearest) = Node::nearest_index(&self.nodes, &self.columns(), coord, &mut stats) else {
+            return Vec::new();
+        };
+        // No point is nearer, the ones within the distance of the nearest are exactly at it
+        let mut found = Vec::new();
+        let squared_distance = self.columns().squared_distance(nearest, coord);
+        Node::within_radius(&self.nodes, self.nodes.root, &self.columns(), coord, squared_distance, &mut found, &mut SearchStack::new(), &mut stats);
+        found.sort_unstable();
+        found
+    }
 
-        let index = self.root.as_ref().and_then(|n|
-            n.nearest(target, 0, None)
-            .map(|b|b.point.index))?;
+    ///Like `nearest_by_coord`, failing if any coordinate of the query is NaN
+    ///
+    /// # Errors:
+    /// - `BuildError::NanQuery` if a coordinate of `coord` is NaN.
+    pub fn try_nearest_by_coord(&self, coord: &[S;DIM]) -> Result<Option<&POINT>, BuildError>{
+        check_query(coord)?;
+        Ok(self.nearest_by_coord(coord))
+    }
 
-        Some(&self.points[index])
-        
+    ///Like `nearest_k`, failing if any coordinate of the query is NaN
+    ///
+    /// # Errors:
+    /// - `BuildError::NanQuery` if a coordinate of `coord` is NaN.
+    pub fn try_nearest_k(&self, coord: &[S;DIM], k: usize) -> Result<Vec<&POINT>, BuildError>{
+        check_query(coord)?;
+        Ok(self.nearest_k(coord, k))
     }
 
+    ///Like `within_radius`, failing if any coordinate of the query is NaN
+    ///
+    /// # Errors:
+    /// - `BuildError::NanQuery` if a coordinate of `coord` is NaN.
+    pub fn try_within_radius(&self, coord: &[S;DIM], radius: S) -> Result<Vec<&POINT>, BuildError>{
+        check_query(coord)?;
+        Ok(self.within_radius(coord, radius))
+    }
+
+    ///Returns the index of the nearest point to each query, in the order of the queries
+    ///
+    /// Every query gives `None` on an empty tree. See `nearest_batch_par` with the `rayon` feature,
+    /// and `nearest_batch_coherent` for queries following each other in space.
+    pub fn nearest_batch(&self, queries: &[[S;DIM]]) -> Vec<Option<usize>>{
+        queries.iter().map(|coord| self.nearest_index(coord)).collect()
+    }
+
+    ///Returns the indices of the `k` nearest points to each query, the nearest first, in the order of the queries
+    ///
+    /// The candidates and search buffers are shared by all the queries. See `nearest_k_batch_par`
+    /// with the `rayon` feature.
+    pub fn nearest_k_batch(&self, queries: &[[S;DIM]], k: usize) -> Vec<Vec<usize>>{
+        let (mut best, mut stack) = (Vec::new(), SearchStack::new());
+        queries.iter()
+            .map(|coord| {
+                self.nearest_k_candidates(coord, k, &mut best, &mut stack);
+                best.iter().map(|(index, _)| *index).collect()
+            })
+            .collect()
+    }
+
+    ///Adds a point to the tree
+    ///
+    /// A point stored deeper than `log(size) / log(4 / 3)`, which happens when adding points in
+    /// sorted order, rebuilds the subtree of the lowest node whose child holds more than 3/4 of its
+    /// points, as in a scapegoat tree: the depth stays logarithmic for an amortized logarithmic cost.
+    ///
+    /// Coordinates are not validated, see [`KdTree::try_add_point`], a NaN coordinate being placed
+    /// like in the construction. Like with `From`, the points
+    /// of a tree of dimension 0 are stored in no node and never found by the queries.
     pub fn add_point(&mut self, point: POINT) {
-        let position = *point.as_kdtree_point();
         let index = self.points.len();
+        self.store.push(&point.to_kdtree_point());
         self.points.push(point);
-    
-        let new_node = Node {
-            point: Point { position, index },
-            left: None,
-            right: None,
-        };
-    
-        if let Some(root) = &mut self.root {
-            root.add_node(new_node, 0);
+        self.insert_node(index);
+    }
+
+    ///Adds a point to the tree, failing if any of its coordinates is NaN or infinite
+    ///
+    /// The reported index is the one the point would have had.
+    pub fn try_add_point(&mut self, point: POINT) -> Result<(), BuildError> {
+        check_finite(&point.to_kdtree_point(), self.points.len())?;
+        self.add_point(point);
+        Ok(())
+    }
+
+    ///Returns a reference to the POINT of the given index, None if there is no such point
+    ///
+    /// The tombstones left by `remove_lazy` are still stored, so they are still returned.
+    pub fn get(&self, index: usize) -> Option<&POINT> {
+        self.points.get(index)
+    }
+
+    ///Returns the coordinates the tree uses for the point of the given index, None if there is no such point
+    ///
+    /// They are read once from the point when it is stored and updated when it is moved through
+    /// the tree, so they are the ones `to_kdtree_point` gave at that time.
+    pub fn position(&self, index: usize) -> Option<[S;DIM]> {
+        (index < self.points.len()).then(|| self.columns().position(index))
+    }
+
+    ///Returns the index of a point at exactly the given coordinates, found through the tree
+    ///
+    /// The smallest index is returned if several points share these coordinates, tombstones
+    /// excluded. `-0.` and `0.` are the same coordinate.
+    pub fn find_index(&self, coord: &[S;DIM]) -> Option<usize> {
+        Node::in_aabb_indices(&self.nodes, &self.columns(), &self.points, coord, coord, &mut QueryStats::default())
+            .into_iter()
+            .min()
+    }
+
+    ///Returns the stored points in index order, tombstones included
+    pub fn points(&self) -> &[POINT] {
+        &self.points
+    }
+
+    ///Returns a guard giving mutable access to the POINT of the given index
+    ///
+    /// If the coordinates of the point are changed through the guard, its node is moved
+    /// when the guard is dropped, see [`PointGuard`].
+    pub fn get_mut(&mut self, index: usize) -> Option<PointGuard<'_, DIM, POINT, S>> {
+        if index >= self.points.len() {
+            return None;
+        }
+        Some(PointGuard::new(self, index))
+    }
+
+    /// Returns the coordinates of the points, as read by the queries.
+    fn columns(&self) -> Columns<'_, DIM, S> {
+        Columns::new(&self.store, &self.points)
+    }
+
+    fn insert_node(&mut self, index: usize) {
+        let position = self.columns().position(index);
+        match &mut self.bounds {
+            Some(bounds) => bounds.extend(&position),
+            None => self.bounds = Some(Aabb::from_point(&position)),
+        }
+        // Like `From`, the points of a tree of dimension 0 are stored in no node
+        if DIM == 0 {
+            return;
+        }
+        if self.nodes.root != NONE {
+            let root = self.nodes.root;
+            let depth = Node::add_node(&mut self.nodes, root, &Columns::new(&self.store, &self.points), index, self.builder.leaf_size);
+            // Deep insertions, like the ones of sorted points, rebuild the subtree of an unbalanced node
+            if depth as f64 > (self.points.len() as f64).ln() / (1. / MAX_CHILD_SHARE).ln() {
+                let path = Node::path_to(&self.nodes, root, &self.columns(), index);
+                Node::rebuild_scapegoat(&mut self.nodes, &path, &Columns::new(&self.store, &self.points), &self.builder);
+            }
         } else {
-            self.root = Some(Box::new(new_node));
+            self.nodes.root = self.nodes.push(Node::leaf(&self.columns(), index, 0));
         }
     }
 
+    /// Moves the node of the point `index`, previously at `old_position`, to the current position of the point.
+    fn relocate(&mut self, index: usize, old_position: &[S;DIM]) {
+        if DIM == 0 {
+            return;
+        }
+        self.store.set(index, &self.points[index].to_kdtree_point());
+        let root = self.nodes.root;
+        self.nodes.root = Node::without_index(&mut self.nodes, root, &Columns::new(&self.store, &self.points), index, old_position, 0, &self.builder).0;
+        self.insert_node(index);
+        // The box may only shrink if the old position was on its boundary
+        if !self.bounds.is_some_and(|bounds| bounds.strictly_contains(old_position)) {
+            self.bounds = Aabb::of_columns(&self.columns());
+        }
+    }
+
+    ///Returns references to every POINT within `radius` of the given coordinates (boundary included)
+    pub fn within_radius(&self, coord: &[S;DIM], radius: S) -> Vec<&POINT>{
+        self.within_radius_indices(coord, radius)
+            .into_iter()
+            .map(|i| &self.points[i])
+            .collect()
+    }
+
+    ///Returns references to every POINT within `radius` of the given coordinates, along with statistics on the search
+    pub fn within_radius_with_stats(&self, coord: &[S;DIM], radius: S) -> (Vec<&POINT>, QueryStats){
+        let mut stats = QueryStats::default();
+        let found = Node::within_radius_indices(&self.nodes, &self.columns(), coord, radius, &mut stats);
+        (found.into_iter().map(|i| &self.points[i]).collect(), stats)
+    }
+
+    ///Removes and returns every POINT within `radius` of the given coordinates (boundary included)
+    ///
+    /// The remaining points keep their relative order, so the index of a point
+    /// decreases by the number of removed points that were stored before it.
+    pub fn remove_within_radius(&mut self, coord: &[S;DIM], radius: S) -> Vec<POINT>{
+        let found = self.within_radius_indices(coord, radius);
+        self.remove_indices(&found)
+    }
+
+    fn within_radius_indices(&self, coord: &[S;DIM], radius: S) -> Vec<usize>{
+        let mut found = Vec::new();
+        self.within_radius_into(coord, radius, &mut found, &mut SearchStack::new());
+        found
+    }
+
+    ///Returns references to every POINT inside the box `[min, max]` (boundary included)
+    ///
+    /// A box with `min[axis] > max[axis]` on any axis is empty.
+    pub fn points_in_aabb(&self, min: &[S;DIM], max: &[S;DIM]) -> Vec<&POINT>{
+        self.in_aabb_indices(min, max)
+            .into_iter()
+            .map(|i| &self.points[i])
+            .collect()
+    }
+
+    ///Returns references to every POINT inside the box `[min, max]`, along with statistics on the search
+    pub fn points_in_aabb_with_stats(&self, min: &[S;DIM], max: &[S;DIM]) -> (Vec<&POINT>, QueryStats){
+        let mut stats = QueryStats::default();
+        let found = Node::in_aabb_indices(&self.nodes, &self.columns(), &self.points, min, max, &mut stats);
+        (found.into_iter().map(|i| &self.points[i]).collect(), stats)
+    }
+
+    ///Removes and returns every POINT inside the box `[min, max]` (boundary included)
+    ///
+    /// An inverted box (`min[axis] > max[axis]` on any axis) removes nothing.
+    /// Indices of the remaining points shift like with [`KdTree::remove_within_radius`].
+    pub fn remove_in_aabb(&mut self, min: &[S;DIM], max: &[S;DIM]) -> Vec<POINT>{
+        let found = self.in_aabb_indices(min, max);
+        self.remove_indices(&found)
+    }
+
+    ///Keeps only the POINTs inside the box `[min, max]` (boundary included), returns the removed ones
+    ///
+    /// An inverted box (`min[axis] > max[axis]` on any axis) removes nothing.
+    /// Indices of the remaining points shift like with [`KdTree::remove_within_radius`].
+    pub fn crop_to_aabb(&mut self, min: &[S;DIM], max: &[S;DIM]) -> Vec<POINT>{
+        let mut found = Vec::new();
+        if (0..DIM).all(|axis| min[axis] <= max[axis]) {
+            if let Some(root) = self.nodes.root() {
+                root.outside_aabb(&self.nodes, &self.columns(), min, max, &mut found);
+            }
+        }
+        self.remove_indices(&found)
+    }
+
+    fn in_aabb_indices(&self, min: &[S;DIM], max: &[S;DIM]) -> Vec<usize>{
+        Node::in_aabb_indices(&self.nodes, &self.columns(), &self.points, min, max, &mut QueryStats::default())
+    }
+
+    /// Removes the points of the given indices, repairs the nodes and returns the removed points in index order.
+    fn remove_indices(&mut self, indices: &[usize]) -> Vec<POINT>{
+        if indices.is_empty() {
+            return Vec::new();
+        }
+
+        let mut removed = vec![false; self.points.len()];
+        for i in indices {
+            removed[*i] = true;
+        }
+
+        let root = self.nodes.root;
+        self.nodes.root = Node::without(&mut self.nodes, root, &Columns::new(&self.store, &self.points), &removed, 0, &self.builder);
+        self.store.remove(&removed);
+
+        // Compact the points and shift the indices of the remaining ones
+        let mut new_indices = Vec::with_capacity(self.points.len());
+        let mut kept = Vec::with_capacity(self.points.len() - indices.len());
+        let mut removed_points = Vec::with_capacity(indices.len());
+        for (point, is_removed) in self.points.drain(..).zip(removed) {
+            new_indices.push(kept.len());
+            if is_removed {
+                removed_points.push(point);
+            } else {
+                kept.push(point);
+            }
+        }
+        self.points = kept;
+
+        let root = self.nodes.root;
+        Node::remap_indices(&mut self.nodes, root, &new_indices);
+        self.bounds = Aabb::of_columns(&self.columns());
+        removed_points
+    }
+
+    ///Returns true if the tree holds no point, like `size() == 0`
+    ///
+    /// The points of a tree of dimension 0 are in no node but still count.
     pub fn is_empty(&self)->bool{
-        self.root.is_none()
+        self.size() == 0
     }
 
+    ///Returns the number of points of the tree, without the tombstones left by `remove_lazy`
     pub fn size(&self)->usize{
-        self.points.len()
+        self.points.len() - self.store.tombstone_count
     }
 
+    ///Returns the height of the tree, the number of nodes on its longest branch from the root
+    ///
+    /// An empty tree has a height of 0 and a single node a height of 1. A tree of dimension 0 has
+    /// no node, so a height of 0 whatever its size.
     pub fn height(&self)->usize{
-        self.root.as_ref()
-           .map(|r|r.height(0))
-           .unwrap_or(0)
+        Node::tree_height(&self.nodes)
     }
-}
\ No newline at end of file
+
+    ///Returns an estimate of the memory used by the tree, split between the nodes and the stored points
+    ///
+    /// The nodes only hold the indices of the points. The queries read the coordinates of arrays
+    /// in place, the ones of the other points are copied once into one array per axis, counted in
+    /// `other_bytes`. Every heap allocation is counted with an estimated allocator overhead of
+    /// `ALLOCATION_OVERHEAD` bytes. Heap memory owned by the POINTs themselves (e.g. a `String`
+    /// payload) is not counted.
+    pub fn memory_usage(&self)->MemoryUsage{
+        let (node_count, node_bytes) = self.nodes.memory_usage();
+        MemoryUsage {
+            node_count,
+            node_bytes,
+            points_len: self.points.len(),
+            points_capacity: self.points.capacity(),
+            point_size: std::mem::size_of::<POINT>(),
+            other_bytes: std::mem::size_of::<Self>() + self.store.memory_usage() + self.scratch.capacity() * std::mem::size_of::<BuildEntry<DIM, S>>(),
+        }
+    }
+}
+
+impl<const DIM:usize,POINT:KdTreePoint<DIM, S> + PartialEq, S: Scalar> KdTree<DIM,POINT,S>{
+    ///Returns true if both trees hold the same points at the same indices with the same node layout
+    ///
+    /// Stricter than `==`, which only compares the points regardless of their order, this is
+    /// what a serialization round trip should preserve.
+    pub fn structurally_equal(&self, other: &Self) -> bool {
+        self.points == other.points && self.nodes == other.nodes
+    }
+}
+
+impl<const DIM:usize, S: Scalar> KdTree<DIM,[S;DIM],S>{
+
+    ///Constructs a Kd-Tree from one slice of coordinates per axis, `columns[axis][i]` being a coordinate of the point `i`
+    ///
+    /// The rows are gathered directly into the points of the tree, which is the only allocation
+    /// besides the nodes. Coordinates are not validated, like with `From<Vec<POINT>>`.
+    ///
+    /// # Errors:
+    /// - `BuildError::ColumnLengthMismatch` if the columns don't all have the same length.
+    pub fn from_coordinate_columns(columns: [&[S]; DIM]) -> Result<Self, BuildError> {
+        let len = columns.first().map_or(0, |c| c.len());
+        if let Some(axis) = columns.iter().position(|c| c.len() != len) {
+            return Err(BuildError::ColumnLengthMismatch { axis, expected: len, found: columns[axis].len() });
+        }
+        Ok(Self::from_iter_with_capacity((0..len).map(|i| std::array::from_fn(|axis| columns[axis][i])), len))
+    }
+}
+
+impl<const DIM:usize,POINT:KdTreePointMut<DIM, S>, S: Scalar> KdTree<DIM,POINT,S>{
+
+    ///Applies `f` to the coordinates of every stored POINT and keeps the tree queryable
+    ///
+    /// The new coordinates are written back into the POINTs. If every point still lies on the
+    /// same side of the splits of its ancestors, the node structure is kept as it is: this is
+    /// always the case for transforms preserving the order on each axis, like translations and
+    /// positive uniform scalings. Otherwise (rotations, mirroring, ...) the tree is rebuilt.
+    /// Trees built with `SplitRule::SlidingMidpoint` are always rebuilt, their splits don't
+    /// lie on the stored points so they can't be moved along with them.
+    ///
+    /// # Returns:
+    /// - `true` if the tree had to be rebuilt.
+    pub fn transform_points<F: Fn(&mut [S;DIM])>(&mut self, f: F) -> bool {
+        for (index, point) in self.points.iter_mut().enumerate() {
+            let mut position = point.to_kdtree_point();
+            f(&mut position);
+            point.set_kdtree_point(&position);
+            self.store.set(index, &point.to_kdtree_point());
+        }
+        self.bounds = Aabb::of_columns(&self.columns());
+
+        if self.nodes.root == NONE {
+            return false;
+        }
+
+        if self.builder.split_rule == SplitRule::Median {
+            let root = self.nodes.root;
+            Node::reset_splits(&mut self.nodes, root, &Columns::new(&self.store, &self.points));
+            let root = &self.nodes.nodes[root as usize];
+            if root.is_valid(&self.nodes, &self.columns()) {
+                return false;
+            }
+        }
+        self.rebuild();
+        true
+    }
+}
+
@@ -1,6 +1,23 @@
 #[cfg(test)]
 pub(crate) mod kdtree_tests {
     use crate::data_struct_utils::kd_tree::*;
+    use crate::data_struct_utils::kd_tree;
+
+    /// Deterministic pseudo-random points in [0, 100)^DIM (xorshift64)
+    fn random_points<const DIM: usize>(n: usize, seed: u64) -> Vec<[f64; DIM]> {
+        let mut state = seed.max(1);
+        let mut next = || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state >> 11) as f64 / (1u64 << 53) as f64 * 100.
+        };
+        (0..n).map(|_| std::array::from_fn(|_| next())).collect()
+    }
+
+    fn squared_distance<const DIM: usize>(a: &[f64; DIM], b: &[f64; DIM]) -> f64 {
+        a.iter().zip(b).map(|(x, y)| (x - y) * (x - y)).sum()
+    }
 
     #[test]
     fn test_kdtree_construction() {
@@ -47,7 +64,7 @@ pub(crate) mod kdtree_tests {
 
         let kdtree = KdTree::<2, [f64; 2]>::from(points.clone());
         assert!(!kdtree.is_empty());
-        println!("KD-Tree Root: {:#?}", kdtree.root);
+        println!("KD-Tree: {:?}\n{}", kdtree, kdtree.debug_structure(None));
 
         // Test nearest neighbor search by another point
         let target = [2.0, 3.0];
@@ -104,6 +121,59 @@ pub(crate) mod kdtree_tests {
         assert!(nearest.is_none());
     }
 
+    #[test]
+    fn test_zero_dim_size(){
+        let points: Vec<[f64; 0]> = vec![[]; 5];
+        let mut kdtree = KdTree::from(points.clone());
+        assert!(!kdtree.is_empty());
+        assert_eq!(kdtree.size(), 5);
+        assert_eq!(kdtree.height(), 0);
+        assert_eq!(kdtree.iter().count(), kdtree.size());
+        assert_eq!(kdtree.iter_entries().len(), kdtree.size());
+
+        let by_ref = KdTreeBuilder::new().build_ref(&points);
+        assert!(!by_ref.is_empty());
+        assert_eq!((by_ref.size(), by_ref.height()), (5, 0));
+        let implicit = kdtree.clone().freeze_implicit();
+        assert!(!implicit.is_empty());
+        assert_eq!((implicit.size(), implicit.height(), implicit.points().len()), (5, 0, 5));
+
+        let mut bytes = Vec::new();
+        kdtree.write_binary(&mut bytes).unwrap();
+        let read = KdTree::<0, [f64; 0]>::read_binary(&bytes[..]).unwrap();
+        assert_eq!((read.is_empty(), read.size(), read.height()), (false, 5, 0));
+        let restored = KdTree::from_parts(kdtree.clone().into_parts()).unwrap();
+        assert_eq!((restored.is_empty(), restored.size()), (false, 5));
+
+        assert!(kdtree.remove_lazy(0).is_removed());
+        assert_eq!(kdtree.size(), 4);
+        kdtree.rebuild_from(Vec::new());
+        assert!(kdtree.is_empty());
+        assert_eq!((kdtree.size(), kdtree.iter().count()), (0, 0));
+        assert!(KdTreeBuilder::new().build_ref::<0, [f64; 0], f64>(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_zero_dim_add_point(){
+        let mut kdtree: KdTree<0, [f64; 0]> = KdTree::from(Vec::new());
+        for _ in 0..10 {
+            kdtree.add_point([]);
+        }
+        assert!(kdtree.try_add_point([]).is_ok());
+        assert_eq!(kdtree.points.len(), 11);
+        assert!(kdtree.nearest_by_coord(&[]).is_none());
+        assert!(kdtree.nearest_k(&[], 3).is_empty());
+        assert!(kdtree.within_radius(&[], 1.).is_empty());
+        assert!(kdtree.check_invariants().is_ok());
+
+        // Moving a point through the guard doesn't create a node either
+        drop(kdtree.get_mut(3));
+        kdtree.rebuild_from(vec![[]; 4]);
+        kdtree.add_point([]);
+        assert!(kdtree.nearest_by_coord(&[]).is_none());
+        assert!(kdtree.check_invariants().is_ok());
+    }
+
 
     #[test]
     fn test_add(){
@@ -138,4 +208,3964 @@ pub(crate) mod kdtree_tests {
         let nearest = kd_tree.nearest(&[10.,10.]);
         assert_eq!(nearest, Some(&[9.,5.]));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_within_radius(){
+        let points = random_points::<2>(300, 7);
+        let kd_tree = KdTree::from(points.clone());
+
+        let center = [40., 60.];
+        let mut found = kd_tree.within_radius(&center, 15.);
+        let linear = LinearSearch::from(points);
+        let mut expected = linear.within_radius(&center, 15.);
+        found.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        expected.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(found, expected);
+
+        assert!(kd_tree.within_radius(&center, -1.).is_empty());
+    }
+
+    #[test]
+    fn test_remove_within_radius(){
+        let mut points = random_points::<2>(500, 42);
+        let mut kd_tree = KdTree::from(points.clone());
+        let centers = random_points::<2>(20, 3);
+        let queries = random_points::<2>(20, 11);
+
+        for center in &centers {
+            let removed = kd_tree.remove_within_radius(center, 10.);
+            assert!(removed.iter().all(|p| squared_distance(p, center) <= 100.));
+            assert!(kd_tree.within_radius(center, 10.).is_empty());
+
+            points.retain(|p| squared_distance(p, center) > 100.);
+            assert_eq!(kd_tree.size(), points.len());
+
+            let reference = KdTree::from(points.clone());
+            for query in &queries {
+                let nearest = kd_tree.nearest_by_coord(query).unwrap();
+                let expected = reference.nearest_by_coord(query).unwrap();
+                assert_eq!(squared_distance(nearest, query), squared_distance(expected, query));
+            }
+        }
+    }
+
+    #[test]
+    fn test_remove_within_radius_edge_cases(){
+        let points = random_points::<2>(100, 5);
+        let mut kd_tree = KdTree::from(points.clone());
+
+        // Nothing matches
+        assert!(kd_tree.remove_within_radius(&[1000., 1000.], 1.).is_empty());
+        assert_eq!(kd_tree.size(), 100);
+
+        // Everything matches
+        let removed = kd_tree.remove_within_radius(&[50., 50.], 1000.);
+        assert_eq!(removed, points);
+        assert!(kd_tree.is_empty());
+        assert_eq!(kd_tree.size(), 0);
+        assert_eq!(kd_tree.nearest_by_coord(&[0., 0.]), None);
+
+        kd_tree.add_point([1., 1.]);
+        assert_eq!(kd_tree.nearest_by_coord(&[0., 0.]), Some(&[1., 1.]));
+    }
+
+    #[test]
+    fn test_points_in_aabb(){
+        let points = random_points::<3>(300, 9);
+        let kd_tree = KdTree::from(points.clone());
+
+        let (min, max) = ([10., 20., 30.], [60., 50., 90.]);
+        let mut found = kd_tree.points_in_aabb(&min, &max);
+        let linear = LinearSearch::from(points);
+        let mut expected = linear.points_in_aabb(&min, &max);
+        found.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        expected.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(found, expected);
+    }
+
+    #[test]
+    fn test_remove_in_aabb(){
+        let points = random_points::<2>(400, 13);
+        let mut kd_tree = KdTree::from(points.clone());
+
+        let removed = kd_tree.remove_in_aabb(&[20., 20.], &[70., 40.]);
+        let remaining = points.iter()
+            .filter(|p| !(20. <= p[0] && p[0] <= 70. && 20. <= p[1] && p[1] <= 40.))
+            .copied()
+            .collect::<Vec<_>>();
+        assert_eq!(removed.len() + remaining.len(), points.len());
+        assert_eq!(kd_tree.size(), remaining.len());
+        assert!(kd_tree.points_in_aabb(&[20., 20.], &[70., 40.]).is_empty());
+
+        let reference = KdTree::from(remaining);
+        for query in random_points::<2>(30, 17) {
+            let nearest = kd_tree.nearest_by_coord(&query).unwrap();
+            let expected = reference.nearest_by_coord(&query).unwrap();
+            assert_eq!(squared_distance(nearest, &query), squared_distance(expected, &query));
+        }
+
+        // Inverted boxes remove nothing
+        assert!(kd_tree.remove_in_aabb(&[70., 40.], &[20., 20.]).is_empty());
+        assert!(kd_tree.crop_to_aabb(&[70., 40.], &[20., 20.]).is_empty());
+        assert_eq!(kd_tree.size(), points.len() - removed.len());
+    }
+
+    #[test]
+    fn test_crop_to_aabb(){
+        let points = random_points::<2>(1000, 21);
+        let mut kd_tree = KdTree::from(points.clone());
+
+        let (min, max) = ([25., 10.], [75., 60.]);
+        let removed = kd_tree.crop_to_aabb(&min, &max);
+        assert!(!removed.is_empty());
+        assert_eq!(removed.len() + kd_tree.size(), points.len());
+
+        // Bounding box of the survivors is inside the crop box
+        let survivors = kd_tree.points_in_aabb(&[f64::NEG_INFINITY; 2], &[f64::INFINITY; 2]);
+        assert_eq!(survivors.len(), kd_tree.size());
+        for axis in 0..2 {
+            let low = survivors.iter().map(|p| p[axis]).fold(f64::INFINITY, f64::min);
+            let high = survivors.iter().map(|p| p[axis]).fold(f64::NEG_INFINITY, f64::max);
+            assert!(min[axis] <= low && high <= max[axis]);
+        }
+        assert!(removed.iter().all(|p| p[0] < min[0] || p[0] > max[0] || p[1] < min[1] || p[1] > max[1]));
+    }
+
+    #[test]
+    fn test_transform_points_translation(){
+        let points = random_points::<2>(200, 31);
+        let mut kd_tree = KdTree::from(points.clone());
+        let queries = random_points::<2>(20, 37);
+        let before = queries.iter()
+            .map(|q| *kd_tree.nearest_by_coord(q).unwrap())
+            .collect::<Vec<_>>();
+
+        let rebuilt = kd_tree.transform_points(|p| {
+            p[0] = p[0] * 2. + 10.;
+            p[1] = p[1] * 2. - 5.;
+        });
+        assert!(!rebuilt);
+
+        for (query, old) in queries.iter().zip(before) {
+            let moved_query = [query[0] * 2. + 10., query[1] * 2. - 5.];
+            let nearest = kd_tree.nearest_by_coord(&moved_query).unwrap();
+            assert_eq!(nearest, &[old[0] * 2. + 10., old[1] * 2. - 5.]);
+        }
+    }
+
+    #[test]
+    fn test_transform_points_rotation(){
+        let points = random_points::<2>(200, 41);
+        let mut kd_tree = KdTree::from(points);
+
+        let (sin, cos) = 1f64.sin_cos();
+        let rebuilt = kd_tree.transform_points(|p| {
+            *p = [p[0] * cos - p[1] * sin, p[0] * sin + p[1] * cos];
+        });
+        assert!(rebuilt);
+
+        let rotated = kd_tree.points_in_aabb(&[f64::NEG_INFINITY; 2], &[f64::INFINITY; 2])
+            .into_iter()
+            .copied()
+            .collect::<Vec<_>>();
+        assert_eq!(rotated.len(), 200);
+        for query in random_points::<2>(20, 43) {
+            let nearest = kd_tree.nearest_by_coord(&query).unwrap();
+            let expected = rotated.iter()
+                .map(|p| squared_distance(p, &query))
+                .fold(f64::INFINITY, f64::min);
+            assert_eq!(squared_distance(nearest, &query), expected);
+        }
+    }
+
+    #[test]
+    fn test_transform_tuple_points(){
+        let mut kd_tree = KdTree::from(vec![(1., 2.), (3., 4.), (5., 6.)]);
+        kd_tree.transform_points(|p| p[0] += 1.);
+        assert_eq!(kd_tree.nearest_by_coord(&[4., 4.]), Some(&(4., 4.)));
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct NamedPoint {
+        coords: [f64; 2],
+        name: String,
+    }
+
+    impl KdTreePoint<2> for NamedPoint {
+        fn to_kdtree_point(&self) -> [f64; 2] {
+            self.coords
+        }
+    }
+
+    fn named_points(n: usize, seed: u64) -> Vec<NamedPoint> {
+        random_points::<2>(n, seed)
+            .into_iter()
+            .enumerate()
+            .map(|(i, coords)| NamedPoint { coords, name: format!("point {i}") })
+            .collect()
+    }
+
+    #[test]
+    fn test_get_mut_moves_point(){
+        let mut kd_tree = KdTree::from(named_points(100, 47));
+
+        kd_tree.get_mut(10).unwrap().coords = [500., 500.];
+        assert_eq!(kd_tree.nearest_by_coord(&[490., 490.]).unwrap().name, "point 10");
+
+        kd_tree.get_mut(10).unwrap().coords = [-500., -500.];
+        assert_eq!(kd_tree.nearest_by_coord(&[-490., -490.]).unwrap().name, "point 10");
+        assert!(kd_tree.within_radius(&[500., 500.], 20.).is_empty());
+        assert_eq!(kd_tree.size(), 100);
+
+        for query in random_points::<2>(20, 53) {
+            let nearest = kd_tree.nearest_by_coord(&query).unwrap();
+            let expected = kd_tree.points.iter()
+                .map(|p| squared_distance(&p.coords, &query))
+                .fold(f64::INFINITY, f64::min);
+            assert_eq!(squared_distance(&nearest.coords, &query), expected);
+        }
+    }
+
+    #[test]
+    fn test_get_mut_without_moving(){
+        let mut kd_tree = KdTree::from(named_points(50, 59));
+        let structure = format!("{:?}", kd_tree.nodes);
+
+        kd_tree.get_mut(3).unwrap().name = String::from("renamed");
+        assert_eq!(format!("{:?}", kd_tree.nodes), structure);
+        assert_eq!(kd_tree.points[3].name, "renamed");
+
+        assert!(kd_tree.get_mut(50).is_none());
+    }
+
+    #[test]
+    fn test_from_iterator(){
+        let points = random_points::<2>(500, 61);
+
+        let from_vec = KdTree::from(points.clone());
+        let collected = points.iter().copied().collect::<KdTree<2, _>>();
+        // An iterator with an unknown size hint
+        let filtered = points.iter().copied().filter(|_| true).collect::<KdTree<2, _>>();
+        let with_capacity = KdTree::from_iter_with_capacity(points.iter().copied().filter(|_| true), 500);
+
+        for kd_tree in [&collected, &filtered, &with_capacity] {
+            assert_eq!(kd_tree.size(), from_vec.size());
+            assert_eq!(kd_tree.height(), from_vec.height());
+            for query in random_points::<2>(20, 67) {
+                assert_eq!(kd_tree.nearest_by_coord(&query), from_vec.nearest_by_coord(&query));
+            }
+        }
+        assert!(from_vec.height() <= 2 * 9);
+    }
+
+    #[test]
+    fn test_try_from_points(){
+        let points = random_points::<3>(50, 71);
+        assert!(KdTree::try_from_points(points.clone()).is_ok());
+
+        for (index, axis, value) in [(0, 0, f64::NAN), (17, 2, f64::INFINITY), (49, 1, f64::NEG_INFINITY)] {
+            let mut invalid = points.clone();
+            invalid[index][axis] = value;
+            match KdTree::try_from_points(invalid) {
+                Err(BuildError::NonFiniteCoordinate { index: i, axis: a, value: v }) => {
+                    assert_eq!((i, a), (index, axis));
+                    assert!(v.is_nan() && value.is_nan() || v == value);
+                }
+                other => panic!("unexpected result {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_try_add_point(){
+        let mut kd_tree = KdTree::try_from_points(vec![(1., 2.), (3., 4.)]).unwrap();
+
+        assert_eq!(kd_tree.try_add_point((5., 6.)), Ok(()));
+        assert_eq!(
+            kd_tree.try_add_point((f64::NEG_INFINITY, 6.)),
+            Err(BuildError::NonFiniteCoordinate { index: 3, axis: 0, value: f64::NEG_INFINITY })
+        );
+        assert!(matches!(
+            kd_tree.try_add_point((0., f64::NAN)),
+            Err(BuildError::NonFiniteCoordinate { index: 3, axis: 1, .. })
+        ));
+        assert_eq!(kd_tree.size(), 3);
+        assert_eq!(kd_tree.nearest_by_coord(&[-100., 0.]), Some(&(1., 2.)));
+    }
+
+    /// x in [0, 10) with a single far outlier, y in [0, 100): the largest extent is on x
+    /// while the largest variance is on y
+    fn outlier_points() -> Vec<[f64; 2]> {
+        let mut points = random_points::<2>(1000, 73);
+        for p in points.iter_mut() {
+            p[0] /= 10.;
+        }
+        points[500][0] = 200.;
+        points
+    }
+
+    #[test]
+    fn test_axis_strategies(){
+        let points = outlier_points();
+        let queries = random_points::<2>(200, 79);
+
+        let strategies = [AxisStrategy::RoundRobin, AxisStrategy::MaxSpread, AxisStrategy::MaxVariance];
+        let mut visits = Vec::new();
+        for strategy in strategies {
+            let kd_tree = KdTreeBuilder::new().axis_strategy(strategy).build(points.clone());
+            assert_eq!(kd_tree.size(), points.len());
+
+            let mut visited = 0;
+            for query in &queries {
+                let (nearest, stats) = kd_tree.nearest_by_coord_with_stats(query);
+                let expected = points.iter()
+                    .map(|p| squared_distance(p, query))
+                    .fold(f64::INFINITY, f64::min);
+                assert_eq!(squared_distance(nearest.unwrap(), query), expected);
+                visited += stats.nodes_visited;
+            }
+            visits.push(visited);
+        }
+        assert_ne!(visits[0], visits[1]);
+        assert_ne!(visits[0], visits[2]);
+        assert_ne!(visits[1], visits[2]);
+    }
+
+    #[test]
+    fn test_axis_strategy_kept_on_rebuild(){
+        let points = outlier_points();
+        let mut kd_tree = KdTreeBuilder::new().axis_strategy(AxisStrategy::MaxVariance).build(points);
+        assert_eq!(kd_tree.nodes.root().unwrap().axis, 1);
+
+        kd_tree.remove_within_radius(&[5., 50.], 1000.);
+        kd_tree.add_point([0., 0.]);
+        kd_tree.add_point([100., 1.]);
+        kd_tree.add_point([50., 2.]);
+        kd_tree.remove_within_radius(&[0., 0.], 1.);
+        assert_eq!(kd_tree.nodes.root().unwrap().axis, 0);
+        assert_eq!(kd_tree.builder.axis_strategy, AxisStrategy::MaxVariance);
+        assert_eq!(kd_tree.nearest_by_coord(&[60., 0.]), Some(&[50., 2.]));
+    }
+
+    fn sorted<const DIM: usize>(mut points: Vec<&[f64; DIM]>) -> Vec<&[f64; DIM]> {
+        points.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        points
+    }
+
+    #[test]
+    fn test_leaf_buckets(){
+        let points = random_points::<3>(2000, 83);
+        let queries = random_points::<3>(50, 89);
+        let reference = KdTree::from(points.clone());
+
+        for leaf_size in [4, 8, 32] {
+            let mut kd_tree = KdTreeBuilder::new().leaf_size(leaf_size).build(points.clone());
+            assert!(kd_tree.height() < reference.height());
+            assert_eq!(kd_tree.size(), reference.size());
+
+            for query in &queries {
+                let nearest = kd_tree.nearest_by_coord(query).unwrap();
+                let expected = reference.nearest_by_coord(query).unwrap();
+                assert_eq!(squared_distance(nearest, query), squared_distance(expected, query));
+
+                assert_eq!(sorted(kd_tree.within_radius(query, 12.)), sorted(reference.within_radius(query, 12.)));
+
+                let min = query.map(|x| x - 10.);
+                let max = query.map(|x| x + 15.);
+                assert_eq!(sorted(kd_tree.points_in_aabb(&min, &max)), sorted(reference.points_in_aabb(&min, &max)));
+            }
+
+            kd_tree.remove_within_radius(&queries[0], 20.);
+            let mut remaining = points.clone();
+            remaining.retain(|p| squared_distance(p, &queries[0]) > 400.);
+            let reference = KdTree::from(remaining);
+            for query in &queries {
+                let nearest = kd_tree.nearest_by_coord(query).unwrap();
+                let expected = reference.nearest_by_coord(query).unwrap();
+                assert_eq!(squared_distance(nearest, query), squared_distance(expected, query));
+            }
+        }
+    }
+
+    #[test]
+    fn test_add_point_fills_buckets(){
+        let mut kd_tree = KdTreeBuilder::new().leaf_size(4).build(Vec::<[f64; 2]>::new());
+        for point in random_points::<2>(4, 97) {
+            kd_tree.add_point(point);
+        }
+        assert_eq!(kd_tree.height(), 1);
+        assert_eq!(kd_tree.nodes.root().unwrap().bucket.len(), 3);
+
+        let points = random_points::<2>(300, 101);
+        for point in &points[4..] {
+            kd_tree.add_point(*point);
+        }
+        for query in random_points::<2>(30, 103) {
+            let nearest = kd_tree.nearest_by_coord(&query).unwrap();
+            let expected = points.iter().map(|p| squared_distance(p, &query)).fold(f64::INFINITY, f64::min);
+            assert_eq!(squared_distance(nearest, &query), expected);
+        }
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_parallel_construction(){
+        let points = random_points::<3>(50_000, 107);
+
+        let sequential = KdTree::from(points.clone());
+        let parallel = KdTree::from_par(points.clone());
+        assert_eq!(parallel.nodes, sequential.nodes);
+
+        let builder = KdTreeBuilder::new().axis_strategy(AxisStrategy::MaxSpread).leaf_size(8);
+        let sequential = builder.build(points.clone());
+        let parallel = builder.build_par(points);
+        assert_eq!(parallel.nodes, sequential.nodes);
+
+        for query in random_points::<3>(50, 109) {
+            assert_eq!(parallel.nearest_by_coord(&query), sequential.nearest_by_coord(&query));
+        }
+    }
+
+    #[test]
+    fn test_kd_tree_ref(){
+        let mut arena = random_points::<2>(500, 113);
+
+        let found = {
+            let kd_tree = KdTreeRef::from_slice(&arena);
+            let owned = KdTree::from(arena.clone());
+            assert_eq!(kd_tree.size(), owned.size());
+            assert_eq!(kd_tree.height(), owned.height());
+
+            for query in random_points::<2>(30, 127) {
+                assert_eq!(kd_tree.nearest_by_coord(&query), owned.nearest_by_coord(&query));
+                assert_eq!(sorted(kd_tree.within_radius(&query, 10.)), sorted(owned.within_radius(&query, 10.)));
+                let max = query.map(|x| x + 20.);
+                assert_eq!(sorted(kd_tree.points_in_aabb(&query, &max)), sorted(owned.points_in_aabb(&query, &max)));
+            }
+
+            // The results borrow the slice, not the tree
+            let moved = kd_tree.clone();
+            drop(kd_tree);
+            moved.nearest_by_coord(&[50., 50.]).unwrap()
+        };
+        assert!(arena.contains(found));
+
+        arena.push([1000., 1000.]);
+        assert_eq!(KdTreeRef::from_slice(&arena).nearest_by_coord(&[900., 900.]), Some(&[1000., 1000.]));
+    }
+
+    #[test]
+    fn test_with_capacity(){
+        let points = random_points::<2>(100, 131);
+
+        let mut kd_tree = KdTree::with_capacity(100);
+        assert!(kd_tree.is_empty());
+        let capacity = kd_tree.capacity();
+        assert!(capacity >= 100);
+        for point in &points {
+            kd_tree.add_point(*point);
+        }
+        assert_eq!(kd_tree.capacity(), capacity);
+
+        kd_tree.reserve(50);
+        let capacity = kd_tree.capacity();
+        assert!(capacity >= 150);
+        for point in random_points::<2>(50, 137) {
+            kd_tree.add_point(point);
+        }
+        assert_eq!(kd_tree.capacity(), capacity);
+
+        // The nodes and the copied coordinates are reserved with the points
+        let mut tuples: KdTree<2, (f64, f64)> = KdTree::with_capacity(100);
+        assert!(tuples.nodes.nodes.capacity() >= 100);
+        assert!(tuples.store.columns.iter().all(|column| column.capacity() >= 100));
+        for point in &points {
+            tuples.add_point((point[0], point[1]));
+        }
+        tuples.reserve(50);
+        assert!(tuples.capacity() >= 150);
+        let (nodes, columns) = (tuples.nodes.nodes.capacity(), tuples.store.columns.each_ref().map(Vec::capacity));
+        assert!(nodes >= 150 && columns.iter().all(|&capacity| capacity >= 150));
+        for point in random_points::<2>(50, 137) {
+            tuples.add_point((point[0], point[1]));
+        }
+        assert_eq!((tuples.nodes.nodes.capacity(), tuples.store.columns.each_ref().map(Vec::capacity)), (nodes, columns));
+
+        // Arrays are read in place, without columns to reserve
+        let mut arrays: KdTree<2, [f64; 2]> = KdTree::with_capacity(100);
+        arrays.reserve(200);
+        assert!(arrays.nodes.nodes.capacity() >= 200 && arrays.store.columns.iter().all(|column| column.capacity() == 0));
+
+        let mut reference = KdTree::from(Vec::new());
+        for point in &points {
+            reference.add_point(*point);
+        }
+        for query in random_points::<2>(20, 139) {
+            let expected = reference.nearest_by_coord(&query).unwrap();
+            assert!(squared_distance(kd_tree.nearest_by_coord(&query).unwrap(), &query) <= squared_distance(expected, &query));
+        }
+    }
+
+    #[test]
+    fn test_rebuild_from(){
+        let builder = KdTreeBuilder::new().leaf_size(4);
+        let mut kd_tree = builder.build(random_points::<2>(1000, 149));
+        let mut capacity = None;
+
+        for frame in 0..20 {
+            let points = random_points::<2>(1000 - frame % 3, 151 + frame as u64);
+            kd_tree.rebuild_from(points.iter().copied());
+
+            let reference = builder.build(points);
+            assert_eq!(kd_tree.nodes, reference.nodes);
+            for query in random_points::<2>(10, 157) {
+                assert_eq!(kd_tree.nearest_by_coord(&query), reference.nearest_by_coord(&query));
+            }
+
+            // No reallocation once the buffers are large enough
+            let current = (kd_tree.capacity(), kd_tree.scratch.capacity());
+            assert_eq!(*capacity.get_or_insert(current), current);
+        }
+    }
+
+    fn clustered_points() -> Vec<[f64; 2]> {
+        let mut points = random_points::<2>(2000, 163);
+        for (i, p) in points.iter_mut().enumerate() {
+            // Tight clusters around a few centers, with some scattered points
+            if i % 10 != 0 {
+                let center = [(i % 3) as f64 * 40., (i % 4) as f64 * 30.];
+                p[0] = center[0] + p[0] / 100.;
+                p[1] = center[1] + p[1] / 100.;
+            }
+        }
+        points
+    }
+
+    #[test]
+    fn test_sliding_midpoint(){
+        let points = clustered_points();
+        let queries = random_points::<2>(200, 167);
+        let median = KdTreeBuilder::new().build(points.clone());
+        let sliding = KdTreeBuilder::new().split_rule(SplitRule::SlidingMidpoint).build(points.clone());
+        assert_eq!(sliding.size(), points.len());
+
+        let mut visited = (0, 0);
+        for query in &queries {
+            assert_eq!(median.nearest_by_coord(query), sliding.nearest_by_coord(query));
+
+            let (expected, median_stats) = median.within_radius_with_stats(query, 5.);
+            let (found, sliding_stats) = sliding.within_radius_with_stats(query, 5.);
+            assert_eq!(sorted(found), sorted(expected));
+            visited.0 += median_stats.nodes_visited;
+            visited.1 += sliding_stats.nodes_visited;
+
+            let min = [query[0] - 10., query[1] - 5.];
+            let max = [query[0] + 10., query[1] + 5.];
+            assert_eq!(sorted(sliding.points_in_aabb(&min, &max)), sorted(median.points_in_aabb(&min, &max)));
+        }
+        assert!(visited.1 < visited.0, "{visited:?}");
+    }
+
+    #[test]
+    fn test_sliding_midpoint_updates(){
+        let builder = KdTreeBuilder::new().split_rule(SplitRule::SlidingMidpoint);
+
+        // Duplicates fall back on the median split instead of chaining
+        let kd_tree = builder.build(vec![[1., 1.]; 1024]);
+        assert!(kd_tree.height() <= 11);
+
+        let mut kd_tree = builder.build(clustered_points());
+        for point in random_points::<2>(100, 173) {
+            kd_tree.add_point(point);
+        }
+        kd_tree.remove_within_radius(&[40., 30.], 1.);
+        assert!(kd_tree.transform_points(|p| p[0] += 1.));
+
+        let points = kd_tree.points.clone();
+        for query in random_points::<2>(50, 179) {
+            let expected = points.iter().map(|p| squared_distance(p, &query)).fold(f64::INFINITY, f64::min);
+            assert_eq!(squared_distance(kd_tree.nearest_by_coord(&query).unwrap(), &query), expected);
+            let found = kd_tree.within_radius(&query, 20.).len();
+            assert_eq!(found, points.iter().filter(|p| squared_distance(p, &query) <= 400.).count());
+        }
+    }
+
+    #[test]
+    fn test_from_pairs(){
+        let points = random_points::<2>(200, 181);
+        let mut kd_tree = KdTree::from_pairs(points.iter().enumerate().map(|(i, p)| (*p, i.to_string())).collect());
+        kd_tree.add_point(PairPoint::new([1000., 1000.], "far".to_string()));
+
+        for (i, point) in points.iter().enumerate() {
+            let found = kd_tree.nearest_by_coord(point).unwrap();
+            assert_eq!(found.coord(), point);
+            assert_eq!(found.payload(), &i.to_string());
+        }
+        assert_eq!(kd_tree.nearest_by_coord(&[900., 900.]).map(|p| p.payload().as_str()), Some("far"));
+
+        let found: Vec<_> = kd_tree.within_radius(&[1000., 1000.], 1.).into_iter().map(|p| p.payload()).collect();
+        assert_eq!(found, vec!["far"]);
+
+        kd_tree.get_mut(3).unwrap().payload_mut().push('!');
+        let point = kd_tree.nearest_by_coord(&points[3]).unwrap().clone();
+        assert_eq!(point.into_inner(), (points[3], "3!".to_string()));
+    }
+
+    #[test]
+    fn test_deterministic_duplicates(){
+        let mut points = vec![[1., 2., 3.]; 500];
+        points.extend(random_points::<3>(100, 191).into_iter().map(|p| [p[0].round(), 2., p[2].round()]));
+
+        for builder in [KdTreeBuilder::new(), KdTreeBuilder::new().split_rule(SplitRule::SlidingMidpoint).leaf_size(3)] {
+            let mut first = builder.build(points.clone());
+            let mut second = builder.build(points.clone());
+            assert_eq!(first.nodes, second.nodes);
+
+            for kd_tree in [&mut first, &mut second] {
+                for point in points.iter().take(50) {
+                    kd_tree.add_point(*point);
+                }
+                kd_tree.get_mut(7).unwrap()[1] = 5.;
+            }
+            assert_eq!(first.nodes, second.nodes);
+        }
+
+        // Identical points are ordered by index
+        fn in_order(nodes: &NodeArena<3, f64>, id: NodeId, found: &mut Vec<usize>) {
+            if let Some(node) = nodes.get(id) {
+                in_order(nodes, node.left, found);
+                found.push(node.index);
+                in_order(nodes, node.right, found);
+            }
+        }
+        let mut found = Vec::new();
+        let kd_tree = KdTree::from(vec![[1., 2., 3.]; 500]);
+        in_order(&kd_tree.nodes, kd_tree.nodes.root, &mut found);
+        assert_eq!(found, (0..500).collect::<Vec<_>>());
+
+        // A point equal to the root goes after it, as its index is greater
+        let mut kd_tree = KdTree::from(vec![[1., 1.]]);
+        kd_tree.add_point([1., 1.]);
+        let root = kd_tree.nodes.root().unwrap();
+        assert_eq!(root.left, NONE);
+        assert_eq!(kd_tree.nodes.get(root.right).unwrap().index, 1);
+    }
+
+    #[test]
+    fn test_memory_usage(){
+        let n = 1_000_000;
+        let points = random_points::<3>(n, 193);
+        let kd_tree = KdTree::from(points.clone());
+        let coords = n * std::mem::size_of::<[f64; 3]>();
+
+        // The points, plus one node per point made of its index, split metadata, bucket and two
+        // 32-bit links, all the nodes being in a single allocation. The coordinates are read in
+        // place from the points, never copied
+        let node = std::mem::size_of::<usize>() * 7;
+        assert_eq!(std::mem::size_of::<Node<3, f64>>(), node);
+        let usage = kd_tree.memory_usage();
+        assert_eq!(usage, MemoryUsage {
+            node_count: n,
+            node_bytes: n * node + ALLOCATION_OVERHEAD,
+            points_len: n,
+            points_capacity: n,
+            point_size: std::mem::size_of::<[f64; 3]>(),
+            other_bytes: std::mem::size_of::<KdTree<3, [f64; 3]>>(),
+        });
+        assert_eq!(usage.total(), std::mem::size_of::<KdTree<3, [f64; 3]>>() + coords + ALLOCATION_OVERHEAD + n * node + ALLOCATION_OVERHEAD);
+        assert_eq!(usage.to_string(), "1000000 points of 24 bytes (1000000 allocated): 22.89 MiB, 1000000 nodes: 53.41 MiB, total 76.29 MiB");
+
+        // The usage grows linearly with the number of points
+        let half = KdTree::from(points[..n / 2].to_vec()).memory_usage();
+        assert_eq!((half.node_bytes - ALLOCATION_OVERHEAD) * 2, usage.node_bytes - ALLOCATION_OVERHEAD);
+        assert_eq!(KdTree::<3, [f64; 3]>::new().memory_usage().total(), std::mem::size_of::<KdTree<3, [f64; 3]>>());
+
+        for query in random_points::<3>(20, 197) {
+            let expected = points.iter().map(|p| squared_distance(p, &query)).fold(f64::INFINITY, f64::min);
+            assert_eq!(squared_distance(kd_tree.nearest_by_coord(&query).unwrap(), &query), expected);
+        }
+    }
+
+    #[test]
+    fn test_from_coordinate_columns(){
+        let points = random_points::<3>(500, 199);
+        let columns: Vec<Vec<f64>> = (0..3).map(|axis| points.iter().map(|p| p[axis]).collect()).collect();
+
+        let kd_tree = KdTree::from_coordinate_columns([&columns[0], &columns[1], &columns[2]]).unwrap();
+        let reference = KdTree::from(points.clone());
+        assert_eq!(kd_tree.points, points);
+        for query in random_points::<3>(50, 211) {
+            assert_eq!(kd_tree.nearest_by_coord(&query), reference.nearest_by_coord(&query));
+            assert_eq!(sorted(kd_tree.within_radius(&query, 20.)), sorted(reference.within_radius(&query, 20.)));
+        }
+
+        assert!(KdTree::<2, [f64; 2]>::from_coordinate_columns([&[], &[]]).unwrap().is_empty());
+        assert_eq!(
+            KdTree::from_coordinate_columns([&columns[0], &columns[1][1..], &columns[2]]).unwrap_err(),
+            BuildError::ColumnLengthMismatch { axis: 1, expected: 500, found: 499 }
+        );
+    }
+
+    #[test]
+    fn test_from_flat(){
+        let points = random_points::<3>(300, 223);
+        let kd_tree = KdTree::<3, FlatPoint<3>>::from_flat(points.concat()).unwrap();
+        let reference = KdTree::from(points.clone());
+        assert_eq!(kd_tree.size(), 300);
+        for query in random_points::<3>(50, 227) {
+            let found = kd_tree.nearest_by_coord(&query).unwrap();
+            assert_eq!(Some(found.coord()), reference.nearest_by_coord(&query));
+            assert_eq!(found.coord(), &points[found.index()]);
+        }
+
+        assert!(KdTree::<3, FlatPoint<3>>::from_flat(Vec::new()).unwrap().is_empty());
+        let kd_tree = KdTree::<2, FlatPoint<2>>::from_flat(vec![1., 2.]).unwrap();
+        let found = kd_tree.nearest_by_coord(&[0., 0.]).unwrap();
+        assert_eq!((found.index(), found.coord()), (0, &[1., 2.]));
+
+        assert_eq!(KdTree::<3, FlatPoint<3>>::from_flat(vec![1., 2.]).unwrap_err(), BuildError::FlatLengthMismatch { len: 2, dim: 3 });
+        assert!(KdTree::<0, FlatPoint<0>>::from_flat(vec![1.]).is_err());
+    }
+
+    #[test]
+    fn test_sampled_median(){
+        let points = random_points::<3>(20000, 229);
+        let builder = KdTreeBuilder::new().median_mode(MedianMode::Sampled { sample_size: 64 });
+        let kd_tree = builder.build(points.clone());
+        let reference = KdTree::from(points.clone());
+
+        // Not perfectly balanced but still logarithmic, an exact tree has a height of 15
+        assert!(kd_tree.height() <= 2 * 15, "{}", kd_tree.height());
+        assert_eq!(kd_tree.nodes, builder.build(points.clone()).nodes);
+        assert_ne!(kd_tree.nodes, reference.nodes);
+
+        for query in random_points::<3>(100, 233) {
+            assert_eq!(kd_tree.nearest_by_coord(&query), reference.nearest_by_coord(&query));
+            assert_eq!(sorted(kd_tree.within_radius(&query, 10.)), sorted(reference.within_radius(&query, 10.)));
+        }
+    }
+
+    fn scalar_points<S: Scalar>(n: usize, seed: u64) -> Vec<[S; 3]> {
+        random_points::<3>(n, seed).into_iter().map(|p| p.map(S::from_f64)).collect()
+    }
+
+    fn brute_force_nearest<S: Scalar>(points: &[[S; 3]], query: &[S; 3]) -> S {
+        let nearest = LinearSearch::from(points.to_vec()).nearest_by_coord(query).copied().unwrap();
+        nearest.iter().zip(query).fold(S::ZERO, |acc, (x, y)| acc + (*x - *y) * (*x - *y))
+    }
+
+    fn check_scalar_queries<S: Scalar>(){
+        let points = scalar_points::<S>(1000, 239);
+        let queries = scalar_points::<S>(50, 241);
+        let distance = |p: &[S; 3], q: &[S; 3]| p.iter().zip(q).fold(S::ZERO, |acc, (x, y)| acc + (*x - *y) * (*x - *y));
+
+        for builder in [KdTreeBuilder::new(), KdTreeBuilder::new().leaf_size(8).split_rule(SplitRule::SlidingMidpoint)] {
+            let mut kd_tree = builder.build(points[..500].to_vec());
+            for point in &points[500..] {
+                kd_tree.add_point(*point);
+            }
+            let radius = S::from_f64(15.);
+            for query in &queries {
+                assert_eq!(distance(kd_tree.nearest_by_coord(query).unwrap(), query), brute_force_nearest(&points, query));
+                assert_eq!(
+                    kd_tree.within_radius(query, radius).len(),
+                    points.iter().filter(|p| distance(p, query) <= radius * radius).count()
+                );
+                let min = query.map(|x| x - radius);
+                let max = query.map(|x| x + radius);
+                assert_eq!(
+                    kd_tree.points_in_aabb(&min, &max).len(),
+                    points.iter().filter(|p| (0..3).all(|axis| min[axis] <= p[axis] && p[axis] <= max[axis])).count()
+                );
+            }
+
+            let removed = kd_tree.remove_within_radius(&queries[0], S::from_f64(30.));
+            kd_tree.transform_points(|p| p[0] = S::ZERO - p[0]);
+            let remaining: Vec<_> = kd_tree.points.clone();
+            assert_eq!(remaining.len() + removed.len(), points.len());
+            for query in &queries {
+                assert_eq!(distance(kd_tree.nearest_by_coord(query).unwrap(), query), brute_force_nearest(&remaining, query));
+            }
+        }
+    }
+
+    #[test]
+    fn test_scalar_types(){
+        check_scalar_queries::<f32>();
+        check_scalar_queries::<f64>();
+
+        let kd_tree: KdTree<2, (f32, f32), f32> = KdTree::from(vec![(0., 0.), (1., 1.), (5., 5.)]);
+        assert_eq!(kd_tree.nearest_by_coord(&[4., 4.]), Some(&(5., 5.)));
+        assert_eq!(KdTree::try_from_points(vec![[1f32, f32::INFINITY]]).unwrap_err(), BuildError::NonFiniteCoordinate { index: 0, axis: 1, value: f64::INFINITY });
+    }
+
+    struct Station {
+        position: (f32, f32),
+    }
+
+    // Implemented through the crate root path, it is the same trait as the one of the module
+    impl crate::KdTreePoint<2, f32> for Station {
+        fn to_kdtree_point(&self) -> [f32; 2] {
+            [self.position.0, self.position.1]
+        }
+    }
+
+    #[test]
+    fn test_crate_root_traits(){
+        let kd_tree: KdTree<2, Station, f32> = KdTree::from(vec![Station { position: (0., 0.) }, Station { position: (3., 4.) }]);
+        assert_eq!(kd_tree.nearest_by_coord(&[2., 3.]).map(|s| s.position), Some((3., 4.)));
+        assert_eq!(kd_tree.nearest(&Station { position: (-1., 0.) }).map(|s| s.position), Some((0., 0.)));
+    }
+
+    fn check_metric<M: Metric<3>>(metric: M){
+        let points = random_points::<3>(1000, 251);
+        let kd_tree = KdTreeBuilder::new().leaf_size(4).build(points.clone());
+        let kd_tree_ref = KdTreeRef::from_slice(&points);
+
+        for query in random_points::<3>(100, 257) {
+            let distance = |p: &[f64; 3]| metric.distance(p, &query);
+            let expected = points.iter().map(distance).fold(f64::INFINITY, f64::min);
+            assert_eq!(kd_tree.nearest_by_coord_with_metric(&query, &metric).map(distance), Some(expected));
+            assert_eq!(kd_tree_ref.nearest_by_coord_with_metric(&query, &metric).map(distance), Some(expected));
+
+            let mut expected: Vec<_> = points.iter().filter(|p| distance(p) <= 12.).collect();
+            expected.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            assert_eq!(sorted(kd_tree.within_radius_with_metric(&query, 12., &metric)), expected);
+        }
+    }
+
+    #[test]
+    fn test_metrics(){
+        check_metric(Euclidean);
+        check_metric(Manhattan);
+        check_metric(Chebyshev);
+        check_metric(Minkowski(3.));
+        check_metric(Minkowski(1.5));
+
+        // Under L1 [4, 0] is nearer to the origin (4 < 6), under L∞ [3, 3] is (3 < 4)
+        let kd_tree = KdTree::from(vec![[4., 0.], [3., 3.]]);
+        assert_eq!(kd_tree.nearest_by_coord_with_metric(&[0., 0.], &Manhattan), Some(&[4., 0.]));
+        assert_eq!(kd_tree.nearest_by_coord_with_metric(&[0., 0.], &Chebyshev), Some(&[3., 3.]));
+        assert!(kd_tree.within_radius_with_metric(&[0., 0.], -1., &Euclidean).is_empty());
+    }
+
+    fn check_haversine(points: &[[f64; 2]], queries: &[[f64; 2]], radius: f64){
+        let metric = Haversine::default();
+        for builder in [KdTreeBuilder::new(), KdTreeBuilder::new().leaf_size(8).axis_strategy(AxisStrategy::MaxSpread)] {
+            let kd_tree = builder.build(points.to_vec());
+            for query in queries {
+                let distance = |p: &[f64; 2]| metric.distance(p, query);
+                let expected = points.iter().map(distance).fold(f64::INFINITY, f64::min);
+                assert_eq!(kd_tree.nearest_by_coord_with_metric(query, &metric).map(distance), Some(expected), "{query:?}");
+
+                let mut expected: Vec<_> = points.iter().filter(|p| distance(p) <= radius).collect();
+                expected.sort_by(|a, b| a.partial_cmp(b).unwrap());
+                assert_eq!(sorted(kd_tree.within_radius_with_metric(query, radius, &metric)), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_haversine(){
+        let metric = Haversine::default();
+        // One degree of latitude, and neighbors across the antimeridian
+        assert!((metric.distance(&[0., 0.], &[1., 0.]) - 111.195).abs() < 1e-3);
+        assert!((metric.distance(&[0., 179.5], &[0., -179.5]) - 111.195).abs() < 1e-3);
+
+        let to_geo = |p: [f64; 2]| [p[0] * 1.8 - 90., p[1] * 3.6 - 180.];
+        let points: Vec<_> = random_points::<2>(2000, 263).into_iter().map(to_geo).collect();
+
+        // Queries straddling the antimeridian, whose planar nearest lies on the wrong side
+        let queries: Vec<_> = random_points::<2>(50, 269).into_iter()
+            .map(|p| [p[0] * 1.6 - 80., if p[1] < 50. { 179.9 } else { -179.9 }])
+            .collect();
+        let kd_tree = KdTree::from(vec![[0., 170.], [0., -179.]]);
+        assert_eq!(kd_tree.nearest_by_coord_with_metric(&[0., 179.], &metric), Some(&[0., -179.]));
+        check_haversine(&points, &queries, 500.);
+
+        // A cluster around the north pole, where longitudes are close to meaningless
+        let cluster: Vec<_> = random_points::<2>(1000, 271).into_iter()
+            .map(|p| [90. - p[0] / 50., p[1] * 3.6 - 180.])
+            .collect();
+        let queries: Vec<_> = random_points::<2>(50, 277).into_iter()
+            .map(|p| [89. + p[0] / 100., p[1] * 3.6 - 180.])
+            .collect();
+        check_haversine(&cluster, &queries, 50.);
+    }
+
+    #[cfg(feature = "glam")]
+    #[test]
+    fn test_glam_points(){
+        use glam::{DVec3, Vec2, Vec3};
+
+        let points: Vec<Vec3> = scalar_points::<f32>(500, 281).into_iter().map(Vec3::from_array).collect();
+        let kd_tree = KdTree::from(points.clone());
+        for query in scalar_points::<f32>(30, 283).into_iter().map(Vec3::from_array) {
+            let expected = points.iter().map(|p| p.distance_squared(query)).fold(f32::INFINITY, f32::min);
+            assert_eq!(kd_tree.nearest_by_vec3(query).unwrap().distance_squared(query), expected);
+            assert_eq!(kd_tree.within_radius_vec3(query, 20.).len(), points.iter().filter(|p| p.distance_squared(query) <= 400.).count());
+        }
+
+        let mut kd_tree = KdTree::from(vec![DVec3::ZERO, DVec3::ONE]);
+        kd_tree.transform_points(|p| p[2] += 10.);
+        assert_eq!(kd_tree.nearest_by_dvec3(DVec3::new(0., 0., 10.)), Some(&DVec3::new(0., 0., 10.)));
+
+        let kd_tree = KdTree::from(vec![Vec2::X, Vec2::Y]);
+        assert_eq!(kd_tree.nearest_by_vec2(Vec2::new(0.1, 0.9)), Some(&Vec2::Y));
+    }
+
+    #[cfg(feature = "nalgebra")]
+    #[test]
+    fn test_nalgebra_points(){
+        use nalgebra::{DMatrix, Point2, Point3, SVector};
+
+        let points: Vec<Point3<f64>> = random_points::<3>(300, 293).into_iter().map(Point3::from).collect();
+        let kd_tree = KdTree::from(points.clone());
+        for query in random_points::<3>(30, 307) {
+            let expected = points.iter().map(|p| squared_distance(&p.coords.into(), &query)).fold(f64::INFINITY, f64::min);
+            assert_eq!(squared_distance(&kd_tree.nearest_by_coord(&query).unwrap().coords.into(), &query), expected);
+        }
+
+        let mut kd_tree = KdTree::from(vec![Point2::new(0., 0.), Point2::new(1., 1.)]);
+        kd_tree.add_point(Point2::new(5., 5.));
+        assert_eq!(kd_tree.nearest(&Point2::new(4., 4.)), Some(&Point2::new(5., 5.)));
+
+        let matrix = DMatrix::from_column_slice(2, 3, &[0., 0., 1., 1., 5., 5.]);
+        let kd_tree = KdTree::<2, SVector<f64, 2>>::from_matrix_columns(&matrix).unwrap();
+        assert_eq!(kd_tree.nearest_by_coord(&[4., 4.]), Some(&SVector::from([5., 5.])));
+        assert_eq!(
+            KdTree::<3, SVector<f64, 3>>::from_matrix_columns(&matrix).unwrap_err(),
+            BuildError::DimensionMismatch { expected: 3, found: 2 }
+        );
+    }
+
+    #[cfg(feature = "mint")]
+    #[test]
+    fn test_mint_points(){
+        use mint::{Point2, Vector3};
+
+        let kd_tree = KdTree::from(vec![Point2 { x: 0., y: 0. }, Point2 { x: 3., y: 4. }, Point2 { x: 10., y: 0. }]);
+        assert_eq!(kd_tree.nearest_by_coord(&[2., 3.]), Some(&Point2 { x: 3., y: 4. }));
+        assert_eq!(kd_tree.within_radius(&[0., 0.], 5.).len(), 2);
+
+        let kd_tree: KdTree<3, Vector3<f32>, f32> = scalar_points::<f32>(100, 311).into_iter().map(Vector3::from).collect();
+        let query = [50f32, 50., 50.];
+        let expected = brute_force_nearest(&scalar_points::<f32>(100, 311), &query);
+        let found: [f32; 3] = (*kd_tree.nearest_by_coord(&query).unwrap()).into();
+        assert_eq!(found.iter().zip(&query).map(|(x, y)| (x - y) * (x - y)).sum::<f32>(), expected);
+        assert_eq!(kd_tree.within_radius(&query, 1000.).len(), 100);
+    }
+
+    #[cfg(feature = "cgmath")]
+    #[test]
+    fn test_cgmath_points(){
+        use cgmath::{Point3, Vector2};
+
+        let mut kd_tree = KdTree::from(vec![Vector2::new(0., 0.), Vector2::new(3., 4.), Vector2::new(10., 0.)]);
+        assert_eq!(kd_tree.nearest_by_coord(&[9., 1.]), Some(&Vector2::new(10., 0.)));
+        assert_eq!(kd_tree.within_radius(&[0., 0.], 5.).len(), 2);
+        kd_tree.get_mut(2).unwrap().x = 1.;
+        assert_eq!(kd_tree.within_radius(&[0., 0.], 5.).len(), 3);
+
+        let kd_tree = KdTree::from(vec![Point3::new(1., 2., 3.), Point3::new(-1., -2., -3.)]);
+        assert_eq!(kd_tree.nearest(&Point3::new(0., -1., 0.)), Some(&Point3::new(-1., -2., -3.)));
+        assert_eq!(kd_tree.within_radius(&[0., 0., 0.], 4.).len(), 2);
+    }
+
+    #[test]
+    fn test_integer_points(){
+        let cells: Vec<[i32; 2]> = random_points::<2>(500, 313).into_iter().map(|p| [p[0] as i32 - 50, p[1] as i32 - 50]).collect();
+        let kd_tree = KdTree::<2, [i32; 2]>::from(cells.clone());
+
+        for query in random_points::<2>(100, 317) {
+            let query = [query[0] - 50.25, query[1] - 50.75];
+            let distance = |c: &[i32; 2]| squared_distance(&c.map(f64::from), &query);
+            let expected = cells.iter().map(distance).fold(f64::INFINITY, f64::min);
+            assert_eq!(distance(kd_tree.nearest_by_coord(&query).unwrap()), expected);
+            assert_eq!(kd_tree.within_radius(&query, 5.).len(), cells.iter().filter(|c| distance(c) <= 25.).count());
+        }
+
+        let kd_tree = KdTree::from(vec![[0u8, 0, 0], [255, 255, 255]]);
+        assert_eq!(kd_tree.nearest_by_coord(&[200., 180., 250.]), Some(&[255, 255, 255]));
+        let kd_tree = KdTree::from(vec![[-3i64, 7, 1 << 40], [0, 0, 0]]);
+        assert_eq!(kd_tree.nearest_by_coord(&[0., 0., 1e12]), Some(&[-3, 7, 1 << 40]));
+    }
+
+    #[test]
+    fn test_kd_tree_map(){
+        let keys = random_points::<2>(200, 331);
+        let mut map: KdTreeMap<2, usize> = keys.iter().enumerate().map(|(i, k)| (*k, i)).collect();
+        assert_eq!(map.len(), 200);
+
+        // Exact collisions replace the value instead of adding an entry
+        assert_eq!(map.insert(keys[10], 1000), Some(10));
+        assert_eq!(map.insert([-1., -1.], 2000), None);
+        assert_eq!(map.len(), 201);
+        assert_eq!(map.get(&keys[10]), Some(&1000));
+        assert_eq!(map.get(&[keys[10][0], keys[10][1] + 1e-9]), None);
+
+        let (key, value) = map.get_nearest_mut(&[-0.5, -0.5]).unwrap();
+        assert_eq!(key, &[-1., -1.]);
+        *value += 1;
+        assert_eq!(map.get_nearest(&[-2., -2.]), Some((&[-1., -1.], &2001)));
+
+        // Removed entries are no longer found, the nearest query falls back on the remaining ones
+        for key in &keys[..100] {
+            assert!(map.remove(key).is_some());
+        }
+        assert_eq!(map.remove(&keys[0]), None);
+        assert_eq!(map.len(), 101);
+        for query in random_points::<2>(50, 337) {
+            let expected = keys[100..].iter().chain([[-1., -1.]].iter())
+                .map(|k| squared_distance(k, &query))
+                .fold(f64::INFINITY, f64::min);
+            let (key, value) = map.get_nearest(&query).unwrap();
+            assert_eq!(squared_distance(key, &query), expected);
+            assert_eq!(map.get(key), Some(value));
+            assert_eq!(map.within_radius(&query, 10.).len(), map.iter().filter(|(k, _)| squared_distance(k, &query) <= 100.).count());
+        }
+
+        // NaN keys are matched by bits instead of being duplicated, signed zeros are one key
+        let mut map: KdTreeMap<2, usize> = keys.iter().enumerate().map(|(i, k)| (*k, i)).collect();
+        assert_eq!(map.insert([f64::NAN, 5.], 1), None);
+        assert_eq!(map.insert([f64::NAN, 5.], 2), Some(1));
+        assert_eq!(map.insert([f64::NAN, 6.], 3), None);
+        assert_eq!(map.insert([-f64::NAN, 5.], 4), None);
+        assert_eq!(map.len(), 203);
+        assert_eq!(map.get(&[f64::NAN, 5.]), Some(&2));
+        assert_eq!(map.remove(&[f64::NAN, 5.]), Some(2));
+        assert_eq!(map.get(&[f64::NAN, 5.]), None);
+        assert_eq!(map.get(&[f64::NAN, 6.]), Some(&3));
+        assert_eq!(map.insert([0., -0.], 5), None);
+        assert_eq!(map.insert([-0., 0.], 6), Some(5));
+        assert_eq!(map.len(), 203);
+    }
+
+    #[test]
+    fn test_pointer_points(){
+        use std::rc::Rc;
+        use std::sync::Arc;
+
+        let named = named_points(100, 347);
+        let shared: Vec<Arc<NamedPoint>> = named.iter().cloned().map(Arc::new).collect();
+        let mut kd_tree = KdTree::from(shared[1..].to_vec());
+        kd_tree.add_point(shared[0].clone());
+        for point in &named {
+            let found: &Arc<NamedPoint> = kd_tree.nearest_by_coord(&point.coords).unwrap();
+            assert!(Arc::ptr_eq(found, shared.iter().find(|p| p.name == point.name).unwrap()));
+        }
+        let mut found: Vec<_> = kd_tree.within_radius(&named[0].coords, 30.).into_iter().map(|p| p.name.clone()).collect();
+        let mut expected: Vec<_> = named.iter().filter(|p| squared_distance(&p.coords, &named[0].coords) <= 900.).map(|p| p.name.clone()).collect();
+        found.sort();
+        expected.sort();
+        assert_eq!(found, expected);
+        assert_eq!(Arc::strong_count(&shared[0]), 2);
+
+        let kd_tree = KdTree::from(named.iter().collect::<Vec<&NamedPoint>>());
+        assert_eq!(kd_tree.nearest(&&named[3]).unwrap().name, named[3].name);
+        let kd_tree = KdTree::from(vec![Box::new([1., 1.]), Box::new([5., 5.])]);
+        assert_eq!(kd_tree.nearest_by_coord(&[4., 4.]).map(|p| **p), Some([5., 5.]));
+        let kd_tree = KdTree::from(vec![Rc::new((1f32, 1f32)), Rc::new((5., 5.))]);
+        assert_eq!(kd_tree.nearest_by_coord(&[0., 0.]).map(|p| **p), Some((1., 1.)));
+    }
+
+    #[test]
+    fn test_tuple_points(){
+        // The coordinates follow the order of the fields, tuples and arrays convert into each other
+        fn check<const DIM: usize, T: KdTreePointMut<DIM> + Into<[f64; DIM]> + From<[f64; DIM]> + Copy + PartialEq + std::fmt::Debug>(tuple: T) {
+            let coords: [f64; DIM] = tuple.into();
+            assert_eq!(tuple.to_kdtree_point(), coords);
+            let mut moved = tuple;
+            moved.set_kdtree_point(&coords.map(|x| x * 2.));
+            assert_eq!(moved, T::from(coords.map(|x| x * 2.)));
+        }
+        check((1., 2.));
+        check((1., 2., 3.));
+        check((1., 2., 3., 4.));
+        check((1., 2., 3., 4., 5.));
+        check((1., 2., 3., 4., 5., 6.));
+        check((1., 2., 3., 4., 5., 6., 7.));
+        check((1., 2., 3., 4., 5., 6., 7., 8.));
+        assert_eq!((1f32, 2f32, 3f32).to_kdtree_point(), [1f32, 2., 3.]);
+
+        let points: Vec<(f64, f64, f64, f64, f64, f64)> = random_points::<6>(200, 349).into_iter().map(Into::into).collect();
+        let kd_tree = KdTree::from(points.clone());
+        for query in random_points::<6>(20, 353) {
+            let expected = points.iter().map(|p| squared_distance(&(*p).into(), &query)).fold(f64::INFINITY, f64::min);
+            assert_eq!(squared_distance(&(*kd_tree.nearest_by_coord(&query).unwrap()).into(), &query), expected);
+        }
+    }
+
+    #[test]
+    fn test_dyn_kd_tree(){
+        let points = random_points::<3>(500, 359);
+        let kd_tree = KdTree::from(points.clone());
+        let dyn_tree = DynKdTree::from_rows(3, points.iter().map(|p| p.to_vec()).collect()).unwrap();
+        assert_eq!(dyn_tree.len(), 500);
+        assert_eq!(dyn_tree.dim(), 3);
+
+        for query in random_points::<3>(50, 361) {
+            let nearest = dyn_tree.nearest(&query).unwrap().unwrap();
+            assert_eq!(dyn_tree.point(nearest).unwrap(), kd_tree.nearest_by_coord(&query).unwrap());
+
+            let mut found: Vec<&[f64]> = dyn_tree.within_radius(&query, 15.).unwrap().into_iter().map(|i| dyn_tree.point(i).unwrap()).collect();
+            found.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            let expected: Vec<&[f64]> = sorted(kd_tree.within_radius(&query, 15.)).into_iter().map(|p| p.as_slice()).collect();
+            assert_eq!(found, expected);
+
+            let mut by_distance: Vec<usize> = (0..points.len()).collect();
+            by_distance.sort_by(|&a, &b| squared_distance(&points[a], &query).partial_cmp(&squared_distance(&points[b], &query)).unwrap());
+            by_distance.truncate(7);
+            assert_eq!(dyn_tree.nearest_k(&query, 7).unwrap(), by_distance);
+        }
+
+        // Flat buffers give the same tree as rows, the lengths are checked
+        let flat = DynKdTree::from_flat(3, points.iter().flatten().copied().collect()).unwrap();
+        assert_eq!(flat.nearest_k(&[0.5, 0.5, 0.5], 5).unwrap(), dyn_tree.nearest_k(&[0.5, 0.5, 0.5], 5).unwrap());
+        assert_eq!(dyn_tree.nearest_k(&[0.5, 0.5, 0.5], 600).unwrap().len(), 500);
+        assert_eq!(dyn_tree.nearest(&[0.5, 0.5]), Err(BuildError::DimensionMismatch { expected: 3, found: 2 }));
+        assert_eq!(DynKdTree::from_flat(3, vec![0.; 7]).unwrap_err(), BuildError::FlatLengthMismatch { len: 7, dim: 3 });
+        assert_eq!(DynKdTree::from_rows(2, vec![vec![0., 1.], vec![0.]]).unwrap_err(), BuildError::DimensionMismatch { expected: 2, found: 1 });
+        assert_eq!(DynKdTree::from_flat(4, Vec::new()).unwrap().nearest(&[0.; 4]), Ok(None));
+    }
+
+    #[test]
+    fn test_metric_transform(){
+        let l = [[2., 0., 0.], [0.5, 1., 0.], [-0.3, 0.8, 0.5]];
+        // M = L Lᵀ, the distances are checked against it directly
+        let m: [[f64; 3]; 3] = std::array::from_fn(|i| std::array::from_fn(|j| (0..3).map(|k| l[i][k] * l[j][k]).sum()));
+        let mahalanobis = |a: &[f64; 3], b: &[f64; 3]| {
+            let d: [f64; 3] = std::array::from_fn(|i| a[i] - b[i]);
+            (0..3).map(|i| (0..3).map(|j| d[i] * m[i][j] * d[j]).sum::<f64>()).sum::<f64>().sqrt()
+        };
+
+        let points = random_points::<3>(400, 367);
+        let kd_tree = KdTree::with_metric_transform(points.clone(), l).unwrap();
+        assert_eq!(kd_tree.size(), 400);
+        for query in random_points::<3>(50, 373) {
+            let expected = points.iter().min_by(|a, b| mahalanobis(a, &query).partial_cmp(&mahalanobis(b, &query)).unwrap()).unwrap();
+            assert_eq!(kd_tree.nearest_by_coord(&query), Some(expected));
+            assert!((kd_tree.distance(expected, &query) - mahalanobis(expected, &query)).abs() < 1e-12);
+
+            let found = sorted(kd_tree.within_radius(&query, 0.4));
+            let expected = sorted(points.iter().filter(|p| mahalanobis(p, &query) <= 0.4).collect());
+            assert_eq!(found, expected);
+        }
+
+        let error = KdTree::with_metric_transform(points, [[1., 0., 0.], [f64::INFINITY, 1., 0.], [0., 0., 1.]]);
+        assert_eq!(error.unwrap_err(), BuildError::NonFiniteTransform { row: 1, column: 0, value: f64::INFINITY });
+    }
+
+    #[test]
+    fn test_nearest_k(){
+        let points = random_points::<3>(300, 379);
+        let kd_tree = KdTreeBuilder::new().leaf_size(4).build(points.clone());
+        for query in random_points::<3>(30, 383) {
+            let mut expected: Vec<&[f64; 3]> = points.iter().collect();
+            expected.sort_by(|a, b| squared_distance(a, &query).partial_cmp(&squared_distance(b, &query)).unwrap());
+            expected.truncate(10);
+            assert_eq!(kd_tree.nearest_k(&query, 10), expected);
+            assert_eq!(KdTreeRef::from_slice(&points).nearest_k(&query, 10), expected);
+        }
+        assert_eq!(kd_tree.nearest_k(&[0.; 3], 0).len(), 0);
+        assert_eq!(kd_tree.nearest_k(&[0.; 3], 1000).len(), 300);
+    }
+
+    #[test]
+    fn test_cosine_kd_tree(){
+        let unit = |v: [f64; 3]| {
+            let length = squared_distance(&v, &[0.; 3]).sqrt();
+            v.map(|x| x / length)
+        };
+        let mut vectors: Vec<[f64; 3]> = random_points::<3>(300, 389).into_iter().map(|v| unit(v.map(|x| x - 0.5))).collect();
+        // Near-duplicate directions, with different lengths
+        vectors.push([1., 1e-9, 0.]);
+        vectors.push([2., 0., 1e-9]);
+        vectors.push([0.5, -1e-9, 0.]);
+
+        let kd_tree = CosineKdTree::try_from_points(vectors.clone()).unwrap();
+        let queries = random_points::<3>(50, 397).into_iter().map(|v| v.map(|x| (x - 0.5) * 3.)).chain([[1., 0., 0.]]);
+        for query in queries {
+            let similarity = |v: &[f64; 3]| {
+                let v = unit(*v);
+                v.iter().zip(unit(query)).map(|(a, b)| a * b).sum::<f64>()
+            };
+            let mut expected: Vec<&[f64; 3]> = vectors.iter().collect();
+            expected.sort_by(|a, b| similarity(b).partial_cmp(&similarity(a)).unwrap());
+
+            let (nearest, cosine) = kd_tree.nearest_cosine(&query).unwrap();
+            assert!((similarity(nearest) - similarity(expected[0])).abs() < 1e-12);
+            assert!((cosine - similarity(expected[0])).abs() < 1e-12);
+            let found = kd_tree.nearest_k_cosine(&query, 5);
+            assert_eq!(found.len(), 5);
+            for ((point, cosine), expected) in found.into_iter().zip(expected) {
+                assert!((similarity(point) - similarity(expected)).abs() < 1e-12);
+                assert!((cosine - similarity(expected)).abs() < 1e-12);
+            }
+        }
+
+        assert_eq!(CosineKdTree::try_from_points(vec![[1., 0.], [0., 0.]]).unwrap_err(), BuildError::ZeroVector { index: 1 });
+        let mut kd_tree = CosineKdTree::default();
+        assert_eq!(kd_tree.add_point([0., 0.]), Err(BuildError::ZeroVector { index: 0 }));
+        kd_tree.add_point([0., 2.]).unwrap();
+        assert_eq!(kd_tree.nearest_cosine(&[1., 1.]).map(|(p, _)| *p), Some([0., 2.]));
+        assert_eq!(kd_tree.nearest_cosine(&[0., 0.]), None);
+    }
+
+    #[test]
+    fn test_kd_tree_equality(){
+        let points = named_points(200, 401);
+        let mut shuffled = points.clone();
+        shuffled.reverse();
+        shuffled.swap(3, 150);
+
+        let kd_tree = KdTree::from(points.clone());
+        let other = KdTree::from(shuffled);
+        assert!(kd_tree == other);
+        assert!(!kd_tree.structurally_equal(&other));
+        assert!(kd_tree.structurally_equal(&KdTree::from(points.clone())));
+
+        // Same coordinates are not enough, the whole points are compared
+        let mut renamed = points.clone();
+        renamed[10].name = "renamed".to_string();
+        assert!(kd_tree != KdTree::from(renamed));
+        let mut duplicated = points.clone();
+        duplicated[10] = duplicated[11].clone();
+        assert!(kd_tree != KdTree::from(duplicated));
+        assert!(kd_tree != KdTree::from(points[1..].to_vec()));
+
+        // Same points, different layout
+        let bucketed = KdTreeBuilder::new().leaf_size(8).build(points);
+        assert!(kd_tree == bucketed);
+        assert!(!kd_tree.structurally_equal(&bucketed));
+    }
+
+    #[test]
+    fn test_empty_kd_tree(){
+        #[derive(Default)]
+        struct Scene {
+            points: KdTree<2, [f64; 2]>,
+        }
+        let scene = Scene::default();
+        let kd_tree = &scene.points;
+        assert!(kd_tree.is_empty());
+        assert_eq!(kd_tree.size(), 0);
+        assert_eq!(kd_tree.height(), 0);
+        assert_eq!(kd_tree.nearest_by_coord(&[0., 0.]), None);
+        assert_eq!(kd_tree.nearest(&[0., 0.]), None);
+        assert!(kd_tree.nearest_k(&[0., 0.], 3).is_empty());
+        assert!(kd_tree.within_radius(&[0., 0.], 10.).is_empty());
+        assert!(kd_tree.points_in_aabb(&[-10., -10.], &[10., 10.]).is_empty());
+
+        // Growing a new tree gives exactly the tree grown from an empty vector
+        let mut kd_tree = KdTree::new();
+        let mut from_empty = KdTree::from(Vec::new());
+        for point in random_points::<2>(100, 409) {
+            kd_tree.add_point(point);
+            from_empty.add_point(point);
+        }
+        assert!(kd_tree.structurally_equal(&from_empty));
+        assert!(!kd_tree.is_empty());
+        assert_eq!(kd_tree.size(), 100);
+    }
+
+    #[test]
+    fn test_iter_prefix(){
+        assert_eq!(KdTree::<2, [f64; 2]>::new().iter_prefix().count(), 0);
+        assert_eq!(KdTree::from(vec![[1., 2.]]).iter_prefix().collect::<Vec<_>>(), vec![&[1., 2.]]);
+
+        let points = random_points::<3>(300, 419);
+        for leaf_size in [1, 6] {
+            let mut kd_tree = KdTreeBuilder::new().leaf_size(leaf_size).build(points[..200].to_vec());
+            for point in &points[200..] {
+                kd_tree.add_point(*point);
+            }
+            let root = kd_tree.nodes.root().unwrap().index;
+            assert_eq!(kd_tree.iter_prefix().next(), Some(&points[root]));
+            assert_eq!(sorted(kd_tree.iter_prefix().collect()), sorted(points.iter().collect()));
+        }
+    }
+
+    #[test]
+    fn test_iter_in_order(){
+        assert_eq!(KdTree::<2, [f64; 2]>::new().iter_in_order().count(), 0);
+
+        let mut values: Vec<[f64; 1]> = random_points::<1>(200, 421);
+        values.extend([[0.5], [0.5], [0.25]]);
+        let mut kd_tree = KdTree::from(values.clone());
+        for value in random_points::<1>(50, 431) {
+            kd_tree.add_point(value);
+            values.push(value);
+        }
+        let in_order: Vec<f64> = kd_tree.iter_in_order().map(|p| p[0]).collect();
+        values.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(in_order, values.iter().map(|p| p[0]).collect::<Vec<_>>());
+
+        let points = random_points::<3>(300, 433);
+        let kd_tree = KdTreeBuilder::new().leaf_size(5).build(points.clone());
+        let in_order: Vec<_> = kd_tree.iter_in_order().collect();
+        assert_eq!(sorted(in_order.clone()), sorted(points.iter().collect()));
+        assert_eq!(in_order, KdTreeBuilder::new().leaf_size(5).build(points.clone()).iter_in_order().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_iter_post_order(){
+        assert_eq!(KdTree::<2, [f64; 2]>::new().iter_post_order().count(), 0);
+
+        // Root 4, its children 2 and 6, the leaves 1, 3, 5 and 7
+        let kd_tree = KdTree::from((1..=7).map(|x| [x as f64]).collect::<Vec<_>>());
+        let post_order: Vec<(f64, usize)> = kd_tree.iter_post_order().map(|(p, depth)| (p[0], depth)).collect();
+        assert_eq!(post_order, vec![(1., 2), (3., 2), (2., 1), (5., 2), (7., 2), (6., 1), (4., 0)]);
+
+        // Subtree sizes computed from the items, the children being found on the stack before their parent
+        let subtree_sizes = |kd_tree: &KdTree<3, [f64; 3]>| {
+            let mut stack: Vec<(usize, usize)> = Vec::new();
+            for (_, depth) in kd_tree.iter_post_order() {
+                let mut size = 1;
+                while stack.last().is_some_and(|&(d, _)| d > depth) {
+                    size += stack.pop().unwrap().1;
+                }
+                stack.push((depth, size));
+            }
+            stack
+        };
+        let points = random_points::<3>(300, 439);
+        let mut kd_tree = KdTree::from(points[..100].to_vec());
+        for point in &points[100..] {
+            kd_tree.add_point(*point);
+        }
+        assert_eq!(kd_tree.iter_post_order().count(), kd_tree.size());
+        assert_eq!(subtree_sizes(&kd_tree), vec![(0, 300)]);
+        assert_eq!(sorted(kd_tree.iter_post_order().map(|(p, _)| p).collect()), sorted(points.iter().collect()));
+
+        let bucketed = KdTreeBuilder::new().leaf_size(7).build(points.clone());
+        assert_eq!(subtree_sizes(&bucketed), vec![(0, 300)]);
+    }
+
+    #[test]
+    fn test_iter_bfs(){
+        assert_eq!(KdTree::<2, [f64; 2]>::new().iter_bfs().count(), 0);
+
+        let kd_tree = KdTree::from((1..=7).map(|x| [x as f64]).collect::<Vec<_>>());
+        let levels: Vec<(usize, f64)> = kd_tree.iter_bfs().map(|(depth, p)| (depth, p[0])).collect();
+        assert_eq!(levels, vec![(0, 4.), (1, 2.), (1, 6.), (2, 1.), (2, 3.), (2, 5.), (2, 7.)]);
+        assert_eq!(kd_tree.height(), 3);
+
+        let points = random_points::<2>(500, 443);
+        let mut kd_tree = KdTree::from(points[..300].to_vec());
+        for point in &points[300..] {
+            kd_tree.add_point(*point);
+        }
+        let depths: Vec<usize> = kd_tree.iter_bfs().map(|(depth, _)| depth).collect();
+        assert!(depths.is_sorted());
+        assert_eq!(depths.last().unwrap() + 1, kd_tree.height());
+        assert_eq!(sorted(kd_tree.iter_bfs().map(|(_, p)| p).collect()), sorted(points.iter().collect()));
+    }
+
+    #[test]
+    fn test_into_iter(){
+        let points = named_points(200, 449);
+        let mut kd_tree = KdTree::from(points.clone());
+        let extra = named_points(20, 457);
+        for point in extra.clone() {
+            kd_tree.add_point(point);
+        }
+        let expected: Vec<NamedPoint> = points.into_iter().chain(extra).collect();
+
+        assert_eq!((&kd_tree).into_iter().collect::<Vec<_>>(), expected.iter().collect::<Vec<_>>());
+        let mut count = 0;
+        for point in &kd_tree {
+            assert_eq!(point, &expected[count]);
+            count += 1;
+        }
+        assert_eq!(count, 220);
+        assert_eq!(kd_tree.into_iter().collect::<Vec<_>>(), expected);
+    }
+
+    #[test]
+    fn test_iter_and_iter_mut(){
+        let points = random_points::<2>(300, 461);
+        let mut kd_tree = KdTree::from(points.clone());
+        assert_eq!(kd_tree.iter().len(), 300);
+        assert_eq!(kd_tree.iter().collect::<Vec<_>>(), points.iter().collect::<Vec<_>>());
+
+        // Mirror every point, which breaks the order of the splits
+        let moved = kd_tree.iter_mut_then_rebuild(|points| {
+            points.map(|point| *point = [1. - point[0], point[1] * 2.]).count()
+        });
+        assert_eq!(moved, 300);
+        let mirrored: Vec<[f64; 2]> = points.iter().map(|p| [1. - p[0], p[1] * 2.]).collect();
+        assert_eq!(kd_tree.iter().collect::<Vec<_>>(), mirrored.iter().collect::<Vec<_>>());
+        for query in random_points::<2>(50, 463) {
+            let expected = mirrored.iter().map(|p| squared_distance(p, &query)).fold(f64::INFINITY, f64::min);
+            assert_eq!(squared_distance(kd_tree.nearest_by_coord(&query).unwrap(), &query), expected);
+        }
+    }
+
+    #[test]
+    fn test_iterator_lengths(){
+        let points = random_points::<2>(100, 467);
+        let kd_tree = KdTreeBuilder::new().leaf_size(3).build(points.clone());
+
+        let mut iter = kd_tree.iter();
+        assert_eq!(iter.len(), 100);
+        iter.nth(9);
+        iter.next_back();
+        assert_eq!(iter.len(), 89);
+        assert_eq!(kd_tree.iter().rev().take(5).collect::<Vec<_>>(), points.iter().rev().take(5).collect::<Vec<_>>());
+
+        // Alternating ends meet in the middle without yielding a point twice
+        let mut iter = kd_tree.iter();
+        let mut seen = Vec::new();
+        while let Some(point) = iter.next() {
+            seen.push(point);
+            seen.extend(iter.next_back());
+        }
+        assert_eq!(iter.next(), None);
+        assert_eq!(sorted(seen), sorted(points.iter().collect()));
+
+        let mut consuming = kd_tree.clone().into_iter();
+        consuming.next();
+        assert_eq!(consuming.len(), 99);
+        assert_eq!(consuming.next_back(), points.last().copied());
+
+        let mut prefix = kd_tree.iter_prefix();
+        let mut in_order = kd_tree.iter_in_order();
+        let mut post_order = kd_tree.iter_post_order();
+        let mut bfs = kd_tree.iter_bfs();
+        for consumed in 0..=100 {
+            assert_eq!(prefix.len(), 100 - consumed);
+            assert_eq!(in_order.size_hint(), (100 - consumed, Some(100 - consumed)));
+            assert_eq!(post_order.len(), 100 - consumed);
+            assert_eq!(bfs.len(), 100 - consumed);
+            assert_eq!(prefix.next().is_some(), consumed < 100);
+            in_order.next();
+            post_order.next();
+            bfs.next();
+        }
+        assert_eq!(prefix.next(), None);
+        assert_eq!(KdTree::<0, [f64; 0]>::from(vec![[]; 4]).iter_prefix().len(), 0);
+    }
+
+    #[test]
+    fn test_iter_entries(){
+        let points = named_points(150, 479);
+        let kd_tree = KdTree::from(points.clone());
+        assert_eq!(kd_tree.iter_entries().len(), 150);
+        for (entry, point) in kd_tree.iter_entries().zip(&points) {
+            assert_eq!(entry.point(), point);
+            assert_eq!(entry.position(), point.to_kdtree_point());
+            assert_eq!(kd_tree.iter().nth(entry.index()), Some(point));
+        }
+        assert_eq!(kd_tree.iter_entries().next_back().map(|e| e.index()), Some(149));
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_par_iter(){
+        use rayon::prelude::*;
+
+        let points = random_points::<3>(5000, 487);
+        let mut kd_tree = KdTree::from(points.clone());
+        let score = |p: &[f64; 3]| p[0] * 3. + p[1] * p[2];
+        let sequential: f64 = kd_tree.iter().map(score).sum();
+        let parallel: f64 = kd_tree.par_iter().map(score).sum();
+        assert!((sequential - parallel).abs() <= sequential.abs() * 1e-12);
+        assert_eq!(kd_tree.par_iter().collect::<Vec<_>>(), kd_tree.iter().collect::<Vec<_>>());
+
+        kd_tree.par_iter_mut_then_rebuild(|points| points.for_each(|p| p[0] = -p[0]));
+        for query in random_points::<3>(20, 491) {
+            let expected = points.iter().map(|p| squared_distance(&[-p[0], p[1], p[2]], &query)).fold(f64::INFINITY, f64::min);
+            assert_eq!(squared_distance(kd_tree.nearest_by_coord(&query).unwrap(), &query), expected);
+        }
+    }
+
+    #[test]
+    fn test_traverse(){
+        // Counts the points of a region, skipping the subtrees outside it, and stops after `limit` matches
+        struct RegionCount {
+            min: [f64; 2],
+            max: [f64; 2],
+            count: usize,
+            limit: usize,
+            visited: usize,
+        }
+        impl KdVisitor<2, [f64; 2]> for RegionCount {
+            fn visit(&mut self, _index: usize, point: &[f64; 2], _depth: usize, axis: usize, split: f64) -> TraversalControl {
+                self.visited += 1;
+                if (0..2).all(|i| self.min[i] <= point[i] && point[i] <= self.max[i]) {
+                    self.count += 1;
+                    if self.count == self.limit {
+                        return TraversalControl::Stop;
+                    }
+                }
+                match (self.min[axis] <= split, self.max[axis] >= split) {
+                    (true, true) => TraversalControl::Continue,
+                    (false, true) => TraversalControl::SkipLeft,
+                    (true, false) => TraversalControl::SkipRight,
+                    (false, false) => TraversalControl::SkipBoth,
+                }
+            }
+        }
+
+        let kd_tree = KdTreeBuilder::new().leaf_size(4).build(random_points::<2>(2000, 499));
+        for corners in random_points::<4>(30, 503) {
+            let min = [corners[0].min(corners[1]), corners[2].min(corners[3])];
+            let max = [corners[0].max(corners[1]), corners[2].max(corners[3])];
+            let mut visitor = RegionCount { min, max, count: 0, limit: usize::MAX, visited: 0 };
+            kd_tree.traverse(&mut visitor);
+            assert_eq!(visitor.count, kd_tree.points_in_aabb(&min, &max).len());
+            assert!(visitor.visited < 2000 || visitor.count > 1000);
+
+            let mut limited = RegionCount { min, max, count: 0, limit: 3, visited: 0 };
+            kd_tree.traverse(&mut limited);
+            assert_eq!(limited.count, visitor.count.min(3));
+        }
+
+        let mut everything = RegionCount { min: [f64::NEG_INFINITY; 2], max: [f64::INFINITY; 2], count: 0, limit: usize::MAX, visited: 0 };
+        kd_tree.traverse(&mut everything);
+        assert_eq!((everything.count, everything.visited), (2000, 2000));
+    }
+
+    #[test]
+    fn test_balance_report(){
+        let kd_tree = KdTree::from(random_points::<2>(1000, 509));
+        let report = kd_tree.balance_report();
+        assert_eq!(report, BalanceReport { size: 1000, height: 10, ideal_height: 10, largest_root_subtree: 500, smallest_root_subtree: 499 });
+        assert!(report.is_balanced(1.));
+        assert_eq!(report.to_string(), "1000 points, height 10 (ideal 10), root subtrees of 500 and 499 points");
+
+        let mut grown = KdTree::new();
+        for x in 0..200 {
+            grown.add_point([x as f64, x as f64]);
+        }
+        // Sorted insertions only rebuild the lowest unbalanced subtrees, the root keeps its empty side
+        let report = grown.balance_report();
+        assert_eq!((report.height, report.ideal_height), (19, 8));
+        assert_eq!((report.largest_root_subtree, report.smallest_root_subtree), (199, 0));
+        assert!(!report.is_balanced(2.));
+        assert!(report.is_balanced(2.5));
+
+        assert_eq!(KdTree::<2, [f64; 2]>::new().balance_report(), BalanceReport::default());
+    }
+
+    #[test]
+    fn test_depth_stats(){
+        let kd_tree = KdTree::from((1..=7).map(|x| [x as f64]).collect::<Vec<_>>());
+        assert_eq!(kd_tree.depth_stats(), DepthStats {
+            min_leaf_depth: 2,
+            max_leaf_depth: 2,
+            mean_leaf_depth: 2.,
+            node_count: 7,
+            nodes_per_level: vec![1, 2, 4],
+        });
+
+        let mut chain = KdTree::new();
+        for x in 0..5 {
+            chain.add_point([x as f64]);
+        }
+        assert_eq!(chain.depth_stats(), DepthStats {
+            min_leaf_depth: 4,
+            max_leaf_depth: 4,
+            mean_leaf_depth: 4.,
+            node_count: 5,
+            nodes_per_level: vec![1; 5],
+        });
+
+        let kd_tree = KdTree::from((1..=6).map(|x| [x as f64]).collect::<Vec<_>>());
+        let stats = kd_tree.depth_stats();
+        assert_eq!((stats.min_leaf_depth, stats.max_leaf_depth, stats.node_count), (2, 2, 6));
+        assert_eq!(stats.nodes_per_level, vec![1, 2, 3]);
+        assert_eq!(stats.max_leaf_depth + 1, kd_tree.height());
+        assert_eq!(KdTree::<1, [f64; 1]>::new().depth_stats(), DepthStats::default());
+    }
+
+    #[test]
+    fn test_bounding_box(){
+        let mut kd_tree = KdTree::from(vec![[1., 5.], [-2., 3.], [4., -1.], [0., 0.]]);
+        assert_eq!(kd_tree.bounding_box(), Some(Aabb::new([-2., -1.], [4., 5.])));
+        kd_tree.add_point([10., 2.]);
+        assert_eq!(kd_tree.bounding_box(), Some(Aabb::new([-2., -1.], [10., 5.])));
+
+        // Moving or removing a point on the boundary shrinks the box
+        kd_tree.get_mut(4).unwrap()[0] = 3.;
+        assert_eq!(kd_tree.bounding_box(), Some(Aabb::new([-2., -1.], [4., 5.])));
+        kd_tree.get_mut(3).unwrap()[1] = 1.;
+        assert_eq!(kd_tree.bounding_box(), Some(Aabb::new([-2., -1.], [4., 5.])));
+        kd_tree.remove_within_radius(&[1., 5.], 0.5);
+        assert_eq!(kd_tree.bounding_box(), Some(Aabb::new([-2., -1.], [4., 3.])));
+        kd_tree.transform_points(|p| p[0] *= 2.);
+        assert_eq!(kd_tree.bounding_box(), Some(Aabb::new([-4., -1.], [8., 3.])));
+        kd_tree.remove_in_aabb(&[-10., -10.], &[10., 10.]);
+        assert_eq!(kd_tree.bounding_box(), None);
+        assert_eq!(KdTree::<2, [f64; 2]>::new().bounding_box(), None);
+
+        let points = random_points::<3>(500, 521);
+        let aabb = KdTree::from(points.clone()).bounding_box().unwrap();
+        assert!(points.iter().all(|p| aabb.contains(p)));
+        assert!(!aabb.contains(&aabb.max.map(|x| x + 1e-9)));
+        assert!(aabb.expand(1e-6).contains(&aabb.max.map(|x| x + 1e-9)));
+        let unit = Aabb::new([0.; 3], [1.; 3]);
+        assert_eq!(unit.union(&Aabb::from_point(&[2., -1., 0.5])), Aabb::new([0., -1., 0.], [2., 1., 1.]));
+        assert_eq!(unit.expand(0.5), Aabb::new([-0.5; 3], [1.5; 3]));
+    }
+
+    #[test]
+    fn test_render_ascii(){
+        let kd_tree = KdTree::from(vec![[3., 4.], [1., 1.], [5., 2.], [2., 7.], [6., 6.], [4., 0.], [0., 3.]]);
+        assert_eq!(kd_tree.to_string(), "\
+axis=0 [3.0, 4.0] #0
+├── L axis=1 [0.0, 3.0] #6
+│   ├── L axis=0 [1.0, 1.0] #1
+│   └── R axis=0 [2.0, 7.0] #3
+└── R axis=1 [5.0, 2.0] #2
+    ├── L axis=0 [4.0, 0.0] #5
+    └── R axis=0 [6.0, 6.0] #4
+");
+        assert_eq!(kd_tree.render_ascii(Some(1)), "\
+axis=0 [3.0, 4.0] #0
+├── L axis=1 [0.0, 3.0] #6
+│   └── … 2 more nodes
+└── R axis=1 [5.0, 2.0] #2
+    └── … 2 more nodes
+");
+        assert_eq!(kd_tree.render_ascii(Some(0)), "axis=0 [3.0, 4.0] #0\n└── … 6 more nodes\n");
+        assert_eq!(KdTree::<2, [f64; 2]>::new().to_string(), "(empty)\n");
+
+        let bucketed = KdTreeBuilder::new().leaf_size(3).build(vec![[1.], [2.], [3.]]);
+        assert_eq!(bucketed.render_ascii(None), "axis=0 [1.0] #0 + [2.0] #1 + [3.0] #2\n");
+    }
+
+    #[test]
+    fn test_to_dot(){
+        let kd_tree = KdTree::from(vec![[3., 4.], [1., 1.], [5., 2.], [2., 7.], [6., 6.], [4., 0.], [0., 3.]]);
+        let dot = kd_tree.to_dot(DotOptions::new().precision(1).highlight([0, 2]));
+        assert!(dot.starts_with("digraph KdTree {\n"));
+        assert!(dot.ends_with("}\n"));
+        assert_eq!(dot.matches('{').count(), dot.matches('}').count());
+        assert_eq!(dot.lines().filter(|l| l.contains("[label=\"#")).count(), 7);
+        assert_eq!(dot.lines().filter(|l| l.contains(" -> ")).count(), 6);
+        assert!(dot.contains("    n0 [label=\"#0 (3.0, 4.0)\\naxis=0\", style=filled, fillcolor=orange];\n"));
+        assert!(dot.contains("    n6 [label=\"#6 (0.0, 3.0)\\naxis=1\"];\n"));
+        assert!(dot.contains("    n0 -> n6 [label=\"L\"];\n"));
+        assert!(dot.contains("    n0 -> n2 [label=\"R\", style=dashed];\n"));
+        assert_eq!(dot.matches("fillcolor").count(), 2);
+
+        let dot = kd_tree.to_dot(DotOptions::new().coordinates(false));
+        assert!(dot.contains("    n0 [label=\"#0\\naxis=0\"];\n"));
+        assert_eq!(KdTree::<2, [f64; 2]>::new().to_dot(DotOptions::default()), "digraph KdTree {\n    node [shape=box];\n}\n");
+    }
+
+    #[test]
+    fn test_check_invariants(){
+        let points = random_points::<2>(300, 523);
+        let mut kd_tree = KdTreeBuilder::new().leaf_size(3).build(points[..200].to_vec());
+        for point in &points[200..] {
+            kd_tree.add_point(*point);
+        }
+        kd_tree.remove_within_radius(&[50., 50.], 10.);
+        assert_eq!(kd_tree.check_invariants(), Ok(()));
+        assert_eq!(KdTreeBuilder::new().split_rule(SplitRule::SlidingMidpoint).build(points.clone()).check_invariants(), Ok(()));
+        assert_eq!(KdTree::<2, [f64; 2]>::new().check_invariants(), Ok(()));
+
+        // Corrupted copies of a tree whose root has two children
+        let kd_tree = KdTree::from(points.clone());
+        let root = kd_tree.nodes.root().unwrap();
+        let (left, right) = (kd_tree.nodes.get(root.left).unwrap().index, kd_tree.nodes.get(root.right).unwrap().index);
+
+        let mut corrupted = kd_tree.clone();
+        corrupted.nodes.get_mut(root.left).unwrap().index = right;
+        assert_eq!(corrupted.check_invariants(), Err(InvariantViolation::MisplacedPoint { path: "L".into(), index: right, axis: root.axis }));
+
+        let mut corrupted = kd_tree.clone();
+        corrupted.nodes.get_mut(kd_tree.nodes.get(root.right).unwrap().right).unwrap().index = 1000;
+        assert_eq!(corrupted.check_invariants(), Err(InvariantViolation::IndexOutOfRange { path: "RR".into(), index: 1000, len: 300 }));
+
+        let mut corrupted = kd_tree.clone();
+        corrupted.nodes.get_mut(root.left).unwrap().bucket.push(left);
+        assert_eq!(corrupted.check_invariants(), Err(InvariantViolation::DuplicateIndex { path: "L".into(), index: left }));
+
+        let mut corrupted = kd_tree.clone();
+        corrupted.nodes.get_mut(kd_tree.nodes.root).unwrap().right = NONE;
+        assert!(matches!(corrupted.check_invariants(), Err(InvariantViolation::CountMismatch { points: 300, .. })));
+
+        let mut corrupted = kd_tree.clone();
+        corrupted.points[root.index] = [f64::NAN, 0.];
+        assert_eq!(corrupted.check_invariants(), Err(InvariantViolation::NonFinitePosition { path: "".into(), index: root.index, axis: 0 }));
+        let mut corrupted = kd_tree.clone();
+        corrupted.points[root.index][1] = f64::INFINITY;
+        assert_eq!(corrupted.check_invariants(), Err(InvariantViolation::NonFinitePosition { path: "".into(), index: root.index, axis: 1 }));
+
+        // The coordinates copied from points which are not arrays must follow them
+        let tuples = KdTree::from(points.iter().map(|&p| <(f64, f64)>::from(p)).collect::<Vec<_>>());
+        assert_eq!(tuples.check_invariants(), Ok(()));
+        let mut corrupted = tuples.clone();
+        corrupted.points[42].1 += 1e-9;
+        assert_eq!(corrupted.check_invariants(), Err(InvariantViolation::StaleCoordinates { index: 42, axis: 1 }));
+        let mut corrupted = tuples.clone();
+        corrupted.store.columns[0].pop();
+        assert_eq!(corrupted.check_invariants(), Err(InvariantViolation::StaleCoordinates { index: 299, axis: 0 }));
+    }
+
+    #[test]
+    fn test_axis_stats(){
+        let kd_tree = KdTree::from(vec![[1., 7., -2.], [3., 7., 0.], [5., 7., 8.], [7., 7., 2.]]);
+        assert_eq!(kd_tree.axis_stats(), [
+            AxisStats { min: 1., max: 7., mean: 4., variance: 5. },
+            AxisStats { min: 7., max: 7., mean: 7., variance: 0. },
+            AxisStats { min: -2., max: 8., mean: 2., variance: 14. },
+        ]);
+        assert_eq!(kd_tree.largest_spread_axis(), Some(2));
+
+        // Large offsets don't ruin the variance, constant axes stay exactly 0
+        let points: Vec<[f64; 2]> = random_points::<2>(1000, 541).into_iter().map(|p| [p[0] + 1e9, 1e9 + 0.1]).collect();
+        let stats = KdTree::from(points.clone()).axis_stats();
+        let mean = points.iter().map(|p| p[0] - 1e9).sum::<f64>() / 1000.;
+        let variance = points.iter().map(|p| (p[0] - 1e9 - mean).powi(2)).sum::<f64>() / 1000.;
+        assert!((stats[0].variance - variance).abs() < variance * 1e-6);
+        assert_eq!(stats[1].variance, 0.);
+        assert_eq!(stats[1].spread(), 0.);
+
+        assert_eq!(KdTree::<2, [f64; 2]>::new().axis_stats(), [AxisStats::default(); 2]);
+        assert_eq!(KdTree::<2, [f64; 2]>::new().largest_spread_axis(), None);
+    }
+
+    #[test]
+    fn test_debug_summary(){
+        // Points without Debug are fine, only the summary is printed
+        struct Opaque([f64; 3]);
+        impl KdTreePoint<3> for Opaque {
+            fn to_kdtree_point(&self) -> [f64; 3] {
+                self.0
+            }
+        }
+        let kd_tree: KdTree<3, Opaque> = random_points::<3>(1000, 547).into_iter().map(Opaque).collect();
+        assert_eq!(format!("{kd_tree:?}"), "KdTree { dim: 3, size: 1000, height: 10 }");
+
+        let kd_tree = KdTree::from((1..=7).map(|x| [x as f64]).collect::<Vec<_>>());
+        assert_eq!(kd_tree.debug_structure(None), "\
+Node { index: 3, axis: 0, split: 4.0, bucket: [] }
+    left: Node { index: 1, axis: 0, split: 2.0, bucket: [] }
+        left: Node { index: 0, axis: 0, split: 1.0, bucket: [] }
+        right: Node { index: 2, axis: 0, split: 3.0, bucket: [] }
+    right: Node { index: 5, axis: 0, split: 6.0, bucket: [] }
+        left: Node { index: 4, axis: 0, split: 5.0, bucket: [] }
+        right: Node { index: 6, axis: 0, split: 7.0, bucket: [] }
+");
+        assert_eq!(kd_tree.debug_structure(Some(0)), "Node { index: 3, axis: 0, split: 4.0, bucket: [] }\n    …\n");
+        assert_eq!(KdTree::<1, [f64; 1]>::new().debug_structure(None), "(empty)\n");
+    }
+
+    #[test]
+    fn test_structure_histogram(){
+        let kd_tree = KdTree::from(random_points::<3>(1000, 557));
+        let histogram = kd_tree.structure_histogram();
+        assert_eq!(histogram.nodes_per_depth, kd_tree.depth_stats().nodes_per_level);
+        assert_eq!(histogram.nodes_per_depth.iter().sum::<usize>(), 1000);
+        // Round robin splits every level on one axis, only the deepest partial level breaks the balance
+        let deepest = *histogram.nodes_per_depth.last().unwrap();
+        let (min, max) = (histogram.splits_per_axis.iter().min().unwrap(), histogram.splits_per_axis.iter().max().unwrap());
+        assert!(max - min <= deepest);
+        assert!(histogram.splits_per_axis.iter().sum::<usize>() < 1000);
+
+        // Axes chosen from the data are counted as well
+        let points: Vec<[f64; 2]> = random_points::<2>(500, 563).into_iter().map(|p| [p[0] * 100., p[1]]).collect();
+        let histogram = KdTreeBuilder::new().axis_strategy(AxisStrategy::MaxSpread).build(points).structure_histogram();
+        assert!(histogram.splits_per_axis[0] > histogram.splits_per_axis[1]);
+        assert_eq!(KdTree::<2, [f64; 2]>::new().structure_histogram(), StructureHistogram { splits_per_axis: [0; 2], nodes_per_depth: Vec::new() });
+    }
+
+    #[test]
+    fn test_parts_round_trip(){
+        let points = random_points::<3>(400, 569);
+        let mut kd_tree = KdTreeBuilder::new().leaf_size(4).build(points[..300].to_vec());
+        for point in &points[300..] {
+            kd_tree.add_point(*point);
+        }
+        let restored = KdTree::from_parts(kd_tree.clone().into_parts()).unwrap();
+        assert!(restored.structurally_equal(&kd_tree));
+        assert_eq!(restored.bounding_box(), kd_tree.bounding_box());
+        for target in random_points::<3>(50, 571) {
+            assert_eq!(restored.nearest_by_coord(&target), kd_tree.nearest_by_coord(&target));
+            assert_eq!(restored.nearest_k(&target, 5), kd_tree.nearest_k(&target, 5));
+            assert_eq!(restored.within_radius(&target, 15.), kd_tree.within_radius(&target, 15.));
+        }
+        let empty = KdTree::<3, [f64; 3]>::from_parts(KdTree::new().into_parts()).unwrap();
+        assert!(empty.is_empty());
+
+        // Forged structures are refused
+        let parts = KdTree::from(points.clone()).into_parts();
+        let mut forged = parts.clone();
+        forged.nodes.pop();
+        assert_eq!(KdTree::from_parts(forged).err(), Some(InvariantViolation::TruncatedStructure { nodes: 399 }));
+        let mut forged = parts.clone();
+        forged.nodes.push(forged.nodes[0].clone());
+        assert_eq!(KdTree::from_parts(forged).err(), Some(InvariantViolation::TrailingNodes { used: 400, nodes: 401 }));
+        let mut forged = parts.clone();
+        forged.nodes[0].axis = 3;
+        assert_eq!(KdTree::from_parts(forged).err(), Some(InvariantViolation::AxisOutOfRange { path: "".into(), axis: 3, dim: 3 }));
+        let mut forged = parts.clone();
+        forged.nodes[1].index = 400;
+        assert!(matches!(KdTree::from_parts(forged), Err(InvariantViolation::IndexOutOfRange { index: 400, .. })));
+        let mut forged = parts;
+        forged.points.truncate(10);
+        assert!(KdTree::from_parts(forged).is_err());
+    }
+
+    #[test]
+    fn test_binary_round_trip(){
+        let points = random_points::<3>(500, 577);
+        let mut kd_tree = KdTreeBuilder::new().leaf_size(6).axis_strategy(AxisStrategy::MaxSpread).build(points[..400].to_vec());
+        for point in &points[400..] {
+            kd_tree.add_point(*point);
+        }
+        let mut dump = Vec::new();
+        kd_tree.write_binary(&mut dump).unwrap();
+        assert_eq!(dump[..4], BINARY_MAGIC);
+        let restored = KdTree::<3, [f64; 3]>::read_binary(dump.as_slice()).unwrap();
+        assert!(restored.structurally_equal(&kd_tree));
+        assert_eq!(restored.builder, kd_tree.builder);
+        for target in random_points::<3>(50, 587) {
+            assert_eq!(restored.nearest_by_coord(&target), kd_tree.nearest_by_coord(&target));
+            assert_eq!(restored.within_radius(&target, 15.), kd_tree.within_radius(&target, 15.));
+        }
+
+        let mut empty = Vec::new();
+        KdTree::<2, [f64; 2]>::new().write_binary(&mut empty).unwrap();
+        assert!(KdTree::<2, [f64; 2]>::read_binary(empty.as_slice()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_binary_corrupted(){
+        let mut dump = Vec::new();
+        KdTree::from(random_points::<2>(100, 593)).write_binary(&mut dump).unwrap();
+        let read = |dump: &[u8]| KdTree::<2, [f64; 2]>::read_binary(dump).err();
+
+        let mut corrupted = dump.clone();
+        corrupted[0] = b'X';
+        assert_eq!(read(&corrupted), Some(DecodeError::BadMagic { found: *b"XDTR" }));
+        let mut corrupted = dump.clone();
+        corrupted[4..6].copy_from_slice(&(BINARY_VERSION + 1).to_le_bytes());
+        assert_eq!(read(&corrupted), Some(DecodeError::UnsupportedVersion { found: BINARY_VERSION + 1, supported: BINARY_VERSION }));
+        assert_eq!(KdTree::<3, [f64; 3]>::read_binary(dump.as_slice()).err(), Some(DecodeError::DimensionMismatch { expected: 3, found: 2 }));
+        for len in [0, 3, 10, 40, dump.len() / 2, dump.len() - 1] {
+            assert_eq!(read(&dump[..len]), Some(DecodeError::Truncated));
+        }
+        // A point index of the root past the end of the points, the nodes are the last 100 * 29 bytes
+        let root = dump.len() - 100 * 29;
+        let mut corrupted = dump.clone();
+        corrupted[root..root + 8].copy_from_slice(&1000u64.to_le_bytes());
+        assert!(matches!(read(&corrupted), Some(DecodeError::InvalidStructure(InvariantViolation::IndexOutOfRange { index: 1000, .. }))));
+        let mut corrupted = dump;
+        corrupted[18] = 7;
+        assert_eq!(read(&corrupted), Some(DecodeError::InvalidField { field: "axis strategy", value: 7 }));
+    }
+
+    #[test]
+    fn test_from_csv(){
+        let csv = "name;x;y;z\na ; 1.5; 2;-3\n\nb;4;5e-1;6;extra\n";
+        let kd_tree = KdTree::<2, [f64; 2]>::from_csv(csv.as_bytes(), CsvOptions::new().delimiter(';').has_header(true).columns([3, 1])).unwrap();
+        assert_eq!(kd_tree.iter().copied().collect::<Vec<_>>(), vec![[-3., 1.5], [6., 4.]]);
+        let kd_tree = KdTree::<3, [f64; 3]>::from_csv("1,2,3,4\n5,6,7,8".as_bytes(), CsvOptions::new()).unwrap();
+        assert_eq!(kd_tree.size(), 2);
+        assert_eq!(kd_tree.nearest_by_coord(&[5., 6., 6.]), Some(&[5., 6., 7.]));
+
+        let read = |csv: &str| KdTree::<2, [f64; 2]>::from_csv(csv.as_bytes(), CsvOptions::new().has_header(true)).err();
+        assert_eq!(read("x,y\n1,2\n3\n"), Some(CsvError::MissingColumn { line: 3, column: 1, found: 1 }));
+        assert_eq!(read("x,y\n1,2\n\n3,four\n"), Some(CsvError::InvalidNumber { line: 4, column: 1, field: "four".into() }));
+        assert_eq!(read("x,y\ninf,2\n"), Some(CsvError::InvalidNumber { line: 2, column: 0, field: "inf".into() }));
+        assert!(read("x,y\n").is_none());
+    }
+
+    #[test]
+    fn test_csv_round_trip(){
+        let kd_tree = KdTree::from(random_points::<3>(300, 599));
+        let mut csv = Vec::new();
+        kd_tree.write_points_csv(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert!(csv.starts_with("index,x0,x1,x2\n0,"));
+        assert_eq!(csv.lines().count(), 301);
+
+        let restored = KdTree::<3, [f64; 3]>::from_csv(csv.as_bytes(), CsvOptions::new().has_header(true).columns([1, 2, 3])).unwrap();
+        assert!(restored.iter().eq(kd_tree.iter()));
+        for target in random_points::<3>(50, 601) {
+            assert_eq!(restored.nearest_k(&target, 3), kd_tree.nearest_k(&target, 3));
+        }
+    }
+
+    /// Minimal JSON value, parsed to check the exports
+    #[derive(Debug, Clone, PartialEq)]
+    enum Json {
+        Null,
+        Bool(bool),
+        Number(f64),
+        String(String),
+        Array(Vec<Json>),
+        Object(Vec<(String, Json)>),
+    }
+
+    impl Json {
+        fn get(&self, key: &str) -> &Json {
+            match self {
+                Json::Object(fields) => &fields.iter().find(|(k, _)| k == key).unwrap().1,
+                _ => panic!("not an object"),
+            }
+        }
+    }
+
+    /// Parses a JSON value from `s` (without escapes in strings), panicking on invalid input
+    fn parse_json(s: &str) -> Json {
+        fn value(s: &[u8], i: &mut usize) -> Json {
+            while s[*i].is_ascii_whitespace() { *i += 1; }
+            let start = *i;
+            match s[*i] {
+                b'{' | b'[' => {
+                    let object = s[*i] == b'{';
+                    let (mut fields, mut items) = (Vec::new(), Vec::new());
+                    *i += 1;
+                    loop {
+                        while s[*i].is_ascii_whitespace() { *i += 1; }
+                        if s[*i] == b'}' || s[*i] == b']' { *i += 1; break; }
+                        if s[*i] == b',' { *i += 1; continue; }
+                        if object {
+                            let Json::String(key) = value(s, i) else { panic!("invalid key") };
+                            while s[*i].is_ascii_whitespace() { *i += 1; }
+                            assert_eq!(s[*i], b':');
+                            *i += 1;
+                            fields.push((key, value(s, i)));
+                        } else {
+                            items.push(value(s, i));
+                        }
+                    }
+                    if object { Json::Object(fields) } else { Json::Array(items) }
+                }
+                b'"' => {
+                    *i += 1;
+                    while s[*i] != b'"' { *i += 1; }
+                    *i += 1;
+                    Json::String(String::from_utf8(s[start + 1..*i - 1].to_vec()).unwrap())
+                }
+                b'n' => { *i += 4; Json::Null }
+                b't' => { *i += 4; Json::Bool(true) }
+                b'f' => { *i += 5; Json::Bool(false) }
+                _ => {
+                    while *i < s.len() && matches!(s[*i], b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9') { *i += 1; }
+                    Json::Number(std::str::from_utf8(&s[start..*i]).unwrap().parse().unwrap())
+                }
+            }
+        }
+        let mut i = 0;
+        let json = value(s.as_bytes(), &mut i);
+        assert!(s[i..].trim().is_empty());
+        json
+    }
+
+    #[test]
+    fn test_to_visualization_json(){
+        let kd_tree = KdTree::from(random_points::<2>(200, 607));
+        let json = parse_json(&kd_tree.to_visualization_json());
+        assert_eq!(json.get("dim"), &Json::Number(2.));
+        assert_eq!(json.get("root"), &Json::Number(0.));
+        let aabb = kd_tree.bounding_box().unwrap();
+        assert_eq!(json.get("bounding_box").get("min"), &Json::Array(aabb.min.iter().map(|x| Json::Number(*x)).collect()));
+
+        let Json::Array(nodes) = json.get("nodes") else { panic!("nodes is not an array") };
+        assert_eq!(nodes.len(), 200);
+        let root = kd_tree.nodes.root().unwrap();
+        assert_eq!(nodes[0].get("index"), &Json::Number(root.index as f64));
+        assert_eq!(nodes[0].get("depth"), &Json::Number(0.));
+        // Every node but the root is the child of exactly one node, one level deeper
+        let mut parents = vec![0; 200];
+        for node in nodes {
+            for child in [node.get("left"), node.get("right")] {
+                if let Json::Number(id) = child {
+                    parents[*id as usize] += 1;
+                    let Json::Number(depth) = node.get("depth") else { panic!() };
+                    assert_eq!(nodes[*id as usize].get("depth"), &Json::Number(depth + 1.));
+                }
+            }
+            let Json::Number(index) = node.get("index") else { panic!() };
+            let position = kd_tree.points[*index as usize];
+            assert_eq!(node.get("position"), &Json::Array(position.iter().map(|x| Json::Number(*x)).collect()));
+        }
+        assert_eq!(parents[0], 0);
+        assert!(parents[1..].iter().all(|&count| count == 1));
+
+        let json = parse_json(&KdTree::<2, [f64; 2]>::new().to_visualization_json());
+        assert_eq!((json.get("root"), json.get("bounding_box"), json.get("nodes")), (&Json::Null, &Json::Null, &Json::Array(Vec::new())));
+    }
+
+    #[cfg(feature = "ffi")]
+    #[test]
+    fn test_ffi(){
+        use crate::data_struct_utils::kd_tree::kd_tree_ffi::*;
+        use std::ptr::{null, null_mut};
+
+        let points = random_points::<2>(200, 613);
+        let coords: Vec<f64> = points.iter().flatten().copied().collect();
+        let kd_tree = KdTree::from(points.clone());
+        unsafe {
+            let handle = kdtree2_new(coords.as_ptr(), 200);
+            assert!(!handle.is_null());
+            let mut index = usize::MAX;
+            assert!(kdtree2_nearest(handle, 30., 70., &mut index));
+            assert_eq!(Some(&points[index]), kd_tree.nearest_by_coord(&[30., 70.]));
+
+            let expected = kd_tree.within_radius_indices(&[50., 50.], 20.);
+            assert!(expected.len() > 4);
+            let mut found = vec![0; 4];
+            assert_eq!(kdtree2_within_radius(handle, 50., 50., 20., found.as_mut_ptr(), 4), expected.len());
+            assert!(found.iter().all(|i| expected.contains(i)));
+            found.resize(expected.len(), 0);
+            assert_eq!(kdtree2_within_radius(handle, 50., 50., 20., found.as_mut_ptr(), found.len()), expected.len());
+            found.sort();
+            let mut expected = expected;
+            expected.sort();
+            assert_eq!(found, expected);
+            assert_eq!(kdtree2_within_radius(handle, 50., 50., 20., null_mut(), 0), expected.len());
+
+            // Null pointers and invalid inputs are tolerated
+            assert!(!kdtree2_nearest(handle, 0., 0., null_mut()));
+            assert!(!kdtree2_nearest(null(), 0., 0., &mut index));
+            assert_eq!(kdtree2_within_radius(null(), 0., 0., 1., found.as_mut_ptr(), 1), 0);
+            assert!(kdtree2_new(null(), 3).is_null());
+            assert!(kdtree2_new([0., f64::NAN].as_ptr(), 1).is_null());
+            // Lengths whose coordinates overflow `usize` or `isize::MAX` bytes are refused before reading
+            let buffer = [0.; 3];
+            assert!(kdtree2_new(buffer.as_ptr(), usize::MAX / 2 + 1).is_null());
+            assert!(kdtree3_new(buffer.as_ptr(), usize::MAX / 3 + 1).is_null());
+            assert!(kdtree2_new(buffer.as_ptr(), isize::MAX as usize / 16 + 1).is_null());
+            assert!(kdtree3_new(buffer.as_ptr(), isize::MAX as usize / 24 + 1).is_null());
+            kdtree2_free(handle);
+            kdtree2_free(null_mut());
+
+            let empty = kdtree3_new(null(), 0);
+            assert!(!empty.is_null());
+            assert!(!kdtree3_nearest(empty, 0., 0., 0., &mut index));
+            kdtree3_free(empty);
+            let handle = kdtree3_new([0., 0., 0., 1., 1., 1., 2., 2., 2.].as_ptr(), 3);
+            assert!(kdtree3_nearest(handle, 1.2, 0.9, 1.1, &mut index));
+            assert_eq!(index, 1);
+            assert_eq!(kdtree3_within_radius(handle, 0., 0., 0., 2., null_mut(), 0), 2);
+            kdtree3_free(handle);
+        }
+    }
+
+    #[test]
+    fn test_from_ply(){
+        let ply = "ply\nformat ascii 1.0\ncomment tiny cloud\nelement vertex 3\nproperty float x\nproperty float y\nproperty float z\nproperty uchar red\nelement face 1\nproperty list uchar int vertex_indices\nend_header\n0 0 0 255\n1 2 3 0\n-1 0.5 2 12\n3 0 1 2\n";
+        let kd_tree = KdTree::from_ply(ply.as_bytes()).unwrap();
+        assert_eq!(kd_tree.iter().copied().collect::<Vec<_>>(), vec![[0., 0., 0.], [1., 2., 3.], [-1., 0.5, 2.]]);
+
+        // Binary little-endian, with a list and a color before the coordinates
+        let points = random_points::<3>(100, 617);
+        let mut ply = b"ply\nformat binary_little_endian 1.0\nelement camera 1\nproperty double focal\nelement vertex 100\nproperty list uchar float normal\nproperty uchar red\nproperty double x\nproperty float y\nproperty double z\nend_header\n".to_vec();
+        ply.extend(50f64.to_le_bytes());
+        for (i, point) in points.iter().enumerate() {
+            let normals = i % 3;
+            ply.push(normals as u8);
+            for _ in 0..normals {
+                ply.extend(1f32.to_le_bytes());
+            }
+            ply.push(i as u8);
+            ply.extend(point[0].to_le_bytes());
+            ply.extend((point[1] as f32).to_le_bytes());
+            ply.extend(point[2].to_le_bytes());
+        }
+        let kd_tree = KdTree::from_ply(ply.as_slice()).unwrap();
+        assert_eq!(kd_tree.size(), 100);
+        for (read, point) in kd_tree.iter().zip(&points) {
+            assert_eq!(*read, [point[0], point[1] as f32 as f64, point[2]]);
+        }
+        assert_eq!(KdTree::from_ply(&ply[..ply.len() - 4]).err(), Some(PointCloudError::Truncated { element: "vertex".into(), index: 99 }));
+    }
+
+    #[test]
+    fn test_from_ply_errors(){
+        let header = |lines: &str| KdTree::from_ply(format!("ply\n{lines}end_header\n").as_bytes()).err();
+        assert!(matches!(header("format binary_big_endian 1.0\n"), Some(PointCloudError::InvalidHeader { line: 2, .. })));
+        assert!(matches!(header("format ascii 1.0\nelement vertex many\n"), Some(PointCloudError::InvalidHeader { line: 3, .. })));
+        assert!(matches!(header("format ascii 1.0\nproperty float x\n"), Some(PointCloudError::InvalidHeader { line: 3, .. })));
+        assert!(matches!(header("element vertex 0\n"), Some(PointCloudError::InvalidHeader { line: 3, .. })));
+        assert_eq!(header("format ascii 1.0\nelement vertex 0\nproperty float x\nproperty float y\n"), Some(PointCloudError::MissingVertexCoordinates));
+        assert!(matches!(KdTree::from_ply("format ascii 1.0\n".as_bytes()), Err(PointCloudError::InvalidHeader { line: 1, .. })));
+        assert!(matches!(KdTree::from_ply("ply\nformat ascii 1.0\n".as_bytes()), Err(PointCloudError::InvalidHeader { line: 3, .. })));
+
+        let body = |rows: &str| KdTree::from_ply(format!("ply\nformat ascii 1.0\nelement vertex 2\nproperty float x\nproperty float y\nproperty float z\nend_header\n{rows}").as_bytes()).err();
+        assert!(matches!(body("0 0 0\n1 one 1\n"), Some(PointCloudError::InvalidElement { index: 1, .. })));
+        assert!(matches!(body("0 0\n"), Some(PointCloudError::InvalidElement { index: 0, .. })));
+        assert!(matches!(body("0 0 0\n0 nan 0\n"), Some(PointCloudError::InvalidElement { index: 1, .. })));
+        assert_eq!(body("0 0 0\n"), Some(PointCloudError::Truncated { element: "vertex".into(), index: 1 }));
+        assert_eq!(body("0 0 0\n1 1 1\n"), None);
+    }
+
+    #[test]
+    fn test_from_xyz(){
+        let xyz = "# x y z r g b\n1 2 3 255 0 0\n\n  4.5\t-1  0  \n7 8 9 0.5\n";
+        let kd_tree = KdTree::from_xyz(xyz.as_bytes()).unwrap();
+        assert_eq!(kd_tree.iter().copied().collect::<Vec<_>>(), vec![[1., 2., 3.], [4.5, -1., 0.], [7., 8., 9.]]);
+        assert_eq!(KdTree::from_xyz("1 2 3\n4 5\n".as_bytes()).err(), Some(PointCloudError::MissingCoordinates { line: 2, found: 2 }));
+        assert_eq!(KdTree::from_xyz("1 2 3\n4 5 x 6\n".as_bytes()).err(), Some(PointCloudError::InvalidLine { line: 2, column: 2, field: "x".into() }));
+    }
+
+    #[test]
+    fn test_from_geojson_points(){
+        let geojson = r#"{
+            "type": "FeatureCollection",
+            "features": [
+                {"type": "Feature", "id": 1, "geometry": {"type": "Point", "coordinates": [2.35, 48.85]}, "properties": {"name": "Paris"}},
+                {"type": "Feature", "geometry": {"type": "LineString", "coordinates": [[0, 0], [1, 1]]}, "properties": null},
+                {"type": "Feature", "id": "lyon", "geometry": {"type": "Point", "coordinates": [4.83, 45.76, 170]}, "properties": {"name": "Lyon é\n", "tags": [true, null]}},
+                {"type": "Feature", "geometry": null, "properties": {}}
+            ]
+        }"#;
+        let kd_tree = KdTree::from_geojson_points(geojson, GeoJsonOptions::new()).unwrap();
+        assert_eq!(kd_tree.size(), 2);
+        let lyon = kd_tree.nearest_by_coord(&[5., 45.]).unwrap();
+        assert_eq!((lyon.lon(), lyon.lat()), (4.83, 45.76));
+        assert_eq!(lyon.id, Some(JsonValue::String("lyon".into())));
+        assert_eq!(lyon.properties.get("name").and_then(JsonValue::as_str), Some("Lyon é\n"));
+        assert_eq!(lyon.properties.get("tags"), Some(&JsonValue::Array(vec![JsonValue::Bool(true), JsonValue::Null])));
+        let paris = kd_tree.nearest_by_coord(&[2., 49.]).unwrap();
+        assert_eq!(paris.id, Some(JsonValue::Number(1.)));
+
+        assert_eq!(
+            KdTree::from_geojson_points(geojson, GeoJsonOptions::new().skip_non_points(false)).err(),
+            Some(GeoJsonError::NonPointGeometry { feature: 1, kind: "LineString".into() })
+        );
+    }
+
+    #[test]
+    fn test_geojson_haversine(){
+        // Fiji and Samoa lie on both sides of the antimeridian, New Caledonia farther on the planar side
+        let geojson = r#"{
+            "type": "FeatureCollection",
+            "features": [
+                {"type": "Feature", "id": "noumea", "geometry": {"type": "Point", "coordinates": [166.45, -22.27]}, "properties": null},
+                {"type": "Feature", "id": "apia", "geometry": {"type": "Point", "coordinates": [-171.77, -13.83]}, "properties": null},
+                {"type": "Feature", "id": "suva", "geometry": {"type": "Point", "coordinates": [178.44, -18.14]}, "properties": null}
+            ]
+        }"#;
+        let kd_tree = KdTree::from_geojson_points(geojson, GeoJsonOptions::new()).unwrap();
+        let metric = HaversineLonLat::default();
+        let id = |feature: Option<&GeoFeature>| feature.and_then(|feature| feature.id.clone());
+        let query = [-179.9, -15.];
+        assert_eq!(id(kd_tree.nearest_by_coord(&query)), Some(JsonValue::String("apia".into())));
+        assert_eq!(id(kd_tree.nearest_by_coord_with_metric(&query, &metric)), Some(JsonValue::String("suva".into())));
+        let query = [172., -20.];
+        assert_eq!(id(kd_tree.nearest_by_coord_with_metric(&query, &metric)), Some(JsonValue::String("noumea".into())));
+
+        // Suva to Apia is about 1150 km, to Noumea about 1350 km
+        let suva = kd_tree.nearest_by_coord(&[178., -18.]).unwrap();
+        let names = |features: Vec<&GeoFeature>| features.iter().map(|f| f.id.as_ref().and_then(JsonValue::as_str).unwrap().to_string()).collect::<Vec<_>>();
+        let mut found = names(kd_tree.within_radius_with_metric(&suva.to_kdtree_point(), 1200., &metric));
+        found.sort();
+        assert_eq!(found, ["apia", "suva"]);
+        assert_eq!(names(kd_tree.nearest_k_with_metric(&suva.to_kdtree_point(), 3, &metric)), ["suva", "apia", "noumea"]);
+
+        // The same distances as `Haversine` on swapped coordinates
+        let to_geo = |p: [f64; 2]| [p[0] * 3.6 - 180., p[1] * 1.8 - 90.];
+        let points: Vec<_> = random_points::<2>(500, 1277).into_iter().map(to_geo).collect();
+        let lon_lat = KdTree::from(points.clone());
+        let lat_lon = KdTree::from(points.iter().map(|p| [p[1], p[0]]).collect::<Vec<_>>());
+        for query in random_points::<2>(50, 1279).into_iter().map(to_geo) {
+            assert_eq!(metric.distance(&points[0], &query), Haversine::default().distance(&[points[0][1], points[0][0]], &[query[1], query[0]]));
+            let found = lon_lat.nearest_by_coord_with_metric(&query, &metric).unwrap();
+            assert_eq!(lat_lon.nearest_by_coord_with_metric(&[query[1], query[0]], &Haversine::default()), Some(&[found[1], found[0]]));
+        }
+    }
+
+    #[test]
+    fn test_from_geojson_errors(){
+        let read = |input: &str| KdTree::from_geojson_points(input, GeoJsonOptions::new()).err();
+        assert!(matches!(read(r#"{"type": "FeatureCollection", "features": [}"#), Some(GeoJsonError::InvalidJson { offset: 43, .. })));
+        assert!(matches!(read(r#"{"type": "FeatureCollection" "features": []}"#), Some(GeoJsonError::InvalidJson { offset: 29, .. })));
+        assert!(matches!(read(r#"{"type": "FeatureCollection", "features": []} x"#), Some(GeoJsonError::InvalidJson { .. })));
+        assert!(matches!(read(r#"{"name": "unterminated}"#), Some(GeoJsonError::InvalidJson { .. })));
+        assert_eq!(read(r#"{"type": "Feature", "features": []}"#), Some(GeoJsonError::NotAFeatureCollection));
+        assert_eq!(read(r#"[1, 2]"#), Some(GeoJsonError::NotAFeatureCollection));
+
+        let feature = |geometry: &str| read(&format!(r#"{{"type": "FeatureCollection", "features": [{{"type": "Feature", "geometry": {{"type": "Point", "coordinates": [0, 0]}}}}, {{"type": "Feature"{geometry}}}]}}"#));
+        assert!(matches!(feature(""), Some(GeoJsonError::InvalidFeature { feature: 1, .. })));
+        assert!(matches!(feature(r#", "geometry": {"type": "Point"}"#), Some(GeoJsonError::InvalidFeature { feature: 1, .. })));
+        assert!(matches!(feature(r#", "geometry": {"type": "Point", "coordinates": [1]}"#), Some(GeoJsonError::InvalidFeature { feature: 1, .. })));
+        assert!(matches!(feature(r#", "geometry": {"type": "Point", "coordinates": ["1", 2]}"#), Some(GeoJsonError::InvalidFeature { feature: 1, .. })));
+        assert_eq!(feature(r#", "geometry": {"type": "Point", "coordinates": [1, 2]}"#), None);
+    }
+
+    #[test]
+    fn test_prebuilt_order(){
+        let points = random_points::<3>(1000, 619);
+        let kd_tree = KdTree::from(points.clone());
+        let order = kd_tree.to_build_order();
+        assert_eq!(order, kd_tree.iter_prefix().map(|p| points.iter().position(|q| q == p).unwrap()).collect::<Vec<_>>());
+
+        PARTITIONS.with(|count| count.set(0));
+        let restored = KdTree::from_prebuilt_order(points.clone(), &order).unwrap();
+        assert_eq!(PARTITIONS.with(|count| count.get()), 0);
+        assert!(restored.structurally_equal(&kd_tree));
+        for target in random_points::<3>(50, 631) {
+            assert_eq!(restored.nearest_k(&target, 4), kd_tree.nearest_k(&target, 4));
+            assert_eq!(restored.within_radius(&target, 15.), kd_tree.within_radius(&target, 15.));
+        }
+        PARTITIONS.with(|count| count.set(0));
+        let _ = KdTree::from(points.clone());
+        assert!(PARTITIONS.with(|count| count.get()) > 0);
+
+        // The order only depends on the points
+        let modified = KdTreeBuilder::new().leaf_size(8).build(points.clone());
+        assert_eq!(modified.to_build_order(), order);
+
+        let mut forged = order.clone();
+        forged.pop();
+        assert_eq!(KdTree::from_prebuilt_order(points.clone(), &forged).err(), Some(RebuildError::LengthMismatch { order: 999, points: 1000 }));
+        forged.push(order[0]);
+        assert_eq!(KdTree::from_prebuilt_order(points.clone(), &forged).err(), Some(RebuildError::DuplicateIndex { position: 999, index: order[0] }));
+        forged[999] = 1000;
+        assert_eq!(KdTree::from_prebuilt_order(points.clone(), &forged).err(), Some(RebuildError::IndexOutOfRange { position: 999, index: 1000 }));
+        let identity: Vec<usize> = (0..1000).collect();
+        assert!(matches!(KdTree::from_prebuilt_order(points, &identity), Err(RebuildError::InvalidOrder(_))));
+        assert!(KdTree::<3, [f64; 3]>::from_prebuilt_order(Vec::new(), &[]).unwrap().is_empty());
+    }
+
+    /// Returns a version 1.0 `.npy` file with the given header dict, padded like NumPy does, followed by `data`
+    fn npy_file(dict: &str, data: &[u8]) -> Vec<u8> {
+        let mut header = dict.to_string();
+        while !(10 + header.len() + 1).is_multiple_of(64) {
+            header.push(' ');
+        }
+        header.push('\n');
+        let mut file = b"\x93NUMPY\x01\x00".to_vec();
+        file.extend((header.len() as u16).to_le_bytes());
+        file.extend(header.as_bytes());
+        file.extend(data);
+        file
+    }
+
+    #[test]
+    fn test_from_npy(){
+        let points = random_points::<3>(100, 641);
+        let data: Vec<u8> = points.iter().flatten().flat_map(|x| x.to_le_bytes()).collect();
+        let file = npy_file("{'descr': '<f8', 'fortran_order': False, 'shape': (100, 3), }", &data);
+        assert_eq!(file.len() % 64, (100 * 3 * 8) % 64);
+        let kd_tree = KdTree::<3, [f64; 3]>::from_npy(file.as_slice()).unwrap();
+        assert!(kd_tree.iter().eq(points.iter()));
+
+        let data: Vec<u8> = [1f32, 2., 3.5, -4.].iter().flat_map(|x| x.to_le_bytes()).collect();
+        let file = npy_file("{'descr': '<f4', 'fortran_order': False, 'shape': (2, 2), }", &data);
+        assert_eq!(KdTree::<2, [f64; 2]>::from_npy(file.as_slice()).unwrap().iter().copied().collect::<Vec<_>>(), vec![[1., 2.], [3.5, -4.]]);
+        let empty = npy_file("{'descr': '<f8', 'fortran_order': False, 'shape': (0, 2), }", &[]);
+        assert!(KdTree::<2, [f64; 2]>::from_npy(empty.as_slice()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_from_npy_errors(){
+        let read = |dict: &str, data: &[u8]| KdTree::<2, [f64; 2]>::from_npy(npy_file(dict, data).as_slice()).err();
+        let data = [0u8; 32];
+        assert_eq!(read("{'descr': '<f8', 'fortran_order': True, 'shape': (2, 2), }", &data), Some(NpyError::FortranOrder));
+        assert_eq!(read("{'descr': '<i8', 'fortran_order': False, 'shape': (2, 2), }", &data), Some(NpyError::UnsupportedDtype { descr: "<i8".into() }));
+        assert_eq!(read("{'descr': '>f8', 'fortran_order': False, 'shape': (2, 2), }", &data), Some(NpyError::UnsupportedDtype { descr: ">f8".into() }));
+        assert_eq!(read("{'descr': '<f8', 'fortran_order': False, 'shape': (2, 3), }", &data), Some(NpyError::ShapeMismatch { shape: vec![2, 3], dim: 2 }));
+        assert_eq!(read("{'descr': '<f8', 'fortran_order': False, 'shape': (4,), }", &data), Some(NpyError::ShapeMismatch { shape: vec![4], dim: 2 }));
+        assert_eq!(read("{'descr': '<f8', 'fortran_order': False, 'shape': (3, 2), }", &data), Some(NpyError::Truncated));
+        assert!(matches!(read("{'descr': '<f8', 'shape': (2, 2), }", &data), Some(NpyError::InvalidHeader { .. })));
+        let nan: Vec<u8> = [0., 1., f64::NAN, 2.].iter().flat_map(|x: &f64| x.to_le_bytes()).collect();
+        assert_eq!(read("{'descr': '<f8', 'fortran_order': False, 'shape': (2, 2), }", &nan), Some(NpyError::NonFiniteCoordinate { row: 1, column: 0 }));
+
+        let mut file = npy_file("{'descr': '<f8', 'fortran_order': False, 'shape': (2, 2), }", &data);
+        file[6] = 4;
+        assert_eq!(KdTree::<2, [f64; 2]>::from_npy(file.as_slice()).err(), Some(NpyError::UnsupportedVersion { major: 4, minor: 0 }));
+        assert_eq!(KdTree::<2, [f64; 2]>::from_npy(&b"PK\x03\x04 not an npy"[..]).err(), Some(NpyError::BadMagic));
+        assert_eq!(KdTree::<2, [f64; 2]>::from_npy(&b"\x93NUMPY\x01"[..]).err(), Some(NpyError::Truncated));
+    }
+
+    #[test]
+    fn test_write_indices_npy(){
+        let mut file = Vec::new();
+        write_indices_npy(&[3, 1, 4], &mut file).unwrap();
+        let mut expected = b"\x93NUMPY\x01\x00\x76\x00{'descr': '<u8', 'fortran_order': False, 'shape': (3,), }".to_vec();
+        expected.resize(127, b' ');
+        expected.push(b'\n');
+        for index in [3u64, 1, 4] {
+            expected.extend(index.to_le_bytes());
+        }
+        assert_eq!(file, expected);
+    }
+
+    #[test]
+    fn test_clone_parity(){
+        let points = random_points::<3>(2000, 643);
+        let queries = random_points::<3>(30, 647);
+        let builder = KdTreeBuilder::new().leaf_size(4);
+        let mut original = builder.build(points.clone());
+        let mut clone = original.clone();
+
+        let same_answers = |a: &KdTree<3, [f64; 3]>, b: &KdTree<3, [f64; 3]>| {
+            for query in &queries {
+                assert_eq!(a.nearest_by_coord(query), b.nearest_by_coord(query));
+                assert_eq!(a.nearest_k(query, 7), b.nearest_k(query, 7));
+                assert_eq!(a.within_radius(query, 15.), b.within_radius(query, 15.));
+            }
+            assert!(a.structurally_equal(b));
+            assert_eq!(a.height(), b.height());
+        };
+        same_answers(&original, &clone);
+
+        // The same modifications keep both trees identical
+        for kd_tree in [&mut original, &mut clone] {
+            for point in random_points::<3>(300, 653) {
+                kd_tree.add_point(point);
+            }
+            kd_tree.remove_within_radius(&[50., 50., 50.], 20.);
+            kd_tree.get_mut(11).unwrap()[0] = 99.;
+        }
+        same_answers(&original, &clone);
+        assert_eq!(original.check_invariants(), Ok(()));
+
+        // The clone is deep: changing it leaves the original as it was
+        let expected: Vec<Vec<[f64; 3]>> = queries.iter().map(|q| original.nearest_k(q, 5).into_iter().copied().collect()).collect();
+        clone.remove_in_aabb(&[0., 0., 0.], &[60., 60., 60.]);
+        clone.rebuild_from(random_points::<3>(100, 659));
+        let found: Vec<Vec<[f64; 3]>> = queries.iter().map(|q| original.nearest_k(q, 5).into_iter().copied().collect()).collect();
+        assert_eq!(found, expected);
+        assert!(!original.structurally_equal(&clone));
+        assert_eq!(original.check_invariants(), Ok(()));
+        assert_eq!(clone.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn test_freeze_implicit(){
+        let queries = random_points::<3>(40, 661);
+        for (len, seed) in [(0, 0), (1, 663), (2, 667), (3, 673), (7, 677), (8, 683), (100, 691), (1000, 701), (1023, 709)] {
+            let points = random_points::<3>(len, seed);
+            let reference = KdTree::from(points.clone());
+            let implicit = KdTreeBuilder::new().leaf_size(6).build(points.clone()).freeze_implicit();
+            assert_eq!((implicit.size(), implicit.is_empty()), (len, len == 0));
+            assert_eq!(implicit.height(), reference.height());
+
+            // Every node lies on the side of the splits of its ancestors
+            for position in 1..len {
+                let (mut child, mut parent) = (position, (position - 1) / 2);
+                loop {
+                    let axis = (usize::BITS - 1 - (parent + 1).leading_zeros()) as usize % 3;
+                    let (x, split) = (implicit.points()[position][axis], implicit.points()[parent][axis]);
+                    assert!(if child % 2 == 1 { x <= split } else { x >= split });
+                    if parent == 0 {
+                        break;
+                    }
+                    (child, parent) = (parent, (parent - 1) / 2);
+                }
+            }
+
+            for query in &queries {
+                assert_eq!(implicit.nearest_by_coord(query), reference.nearest_by_coord(query));
+                assert_eq!(implicit.nearest_k(query, 5), reference.nearest_k(query, 5));
+                assert_eq!(implicit.nearest_k(query, 0), Vec::<&[f64; 3]>::new());
+                let mut found = implicit.within_radius(query, 20.);
+                let mut expected = reference.within_radius(query, 20.);
+                found.sort_by(|a, b| a.partial_cmp(b).unwrap());
+                expected.sort_by(|a, b| a.partial_cmp(b).unwrap());
+                assert_eq!(found, expected);
+            }
+        }
+
+        // Duplicated coordinates, on both sides of the splits
+        let points: Vec<[f64; 2]> = (0..200).map(|i| [(i % 5) as f64, (i % 3) as f64]).collect();
+        let implicit = KdTree::from(points.clone()).freeze_implicit();
+        for query in [[2., 1.], [0.5, 0.5], [4., 2.]] {
+            assert_eq!(implicit.within_radius(&query, 1.).len(), KdTree::from(points.clone()).within_radius(&query, 1.).len());
+            assert_eq!(squared_distance(implicit.nearest_by_coord(&query).unwrap(), &query), squared_distance(KdTree::from(points.clone()).nearest_by_coord(&query).unwrap(), &query));
+        }
+        assert!(KdTree::<0, [f64; 0]>::from(vec![[]; 3]).freeze_implicit().nearest_by_coord(&[]).is_none());
+    }
+
+    #[test]
+    fn test_coordinate_columns_follow_the_points(){
+        // Tuples are copied into the columns, which must follow every modification
+        type Tuple = (f64, f64, f64);
+        let tuples = |points: Vec<[f64; 3]>| points.into_iter().map(Tuple::from).collect::<Vec<_>>();
+        let in_sync = |tree: &KdTree<3, Tuple>| tree.store == ColumnStore::of_points(&tree.points) && tree.store.columns[0].len() == tree.size();
+        let mut kd_tree = KdTree::from(tuples(random_points::<3>(500, 719)));
+        assert!(in_sync(&kd_tree));
+
+        for point in tuples(random_points::<3>(100, 727)) {
+            kd_tree.add_point(point);
+        }
+        assert!(in_sync(&kd_tree));
+
+        *kd_tree.get_mut(42).unwrap() = (50., 50., 50.);
+        *kd_tree.get_mut(0).unwrap() = (-1., 200., 3.);
+        assert!(in_sync(&kd_tree));
+        assert_eq!(kd_tree.nearest_by_coord(&[-1., 199., 3.]), Some(&(-1., 200., 3.)));
+
+        kd_tree.remove_within_radius(&[50., 50., 50.], 15.);
+        kd_tree.crop_to_aabb(&[0., 0., 0.], &[90., 90., 90.]);
+        assert!(in_sync(&kd_tree));
+
+        kd_tree.transform_points(|p| p[1] += 10.);
+        assert!(in_sync(&kd_tree));
+        kd_tree.transform_points(|p| p[2] = -p[2]);
+        assert!(in_sync(&kd_tree));
+        kd_tree.iter_mut_then_rebuild(|points| points.for_each(|p| p.0 *= 2.));
+        assert!(in_sync(&kd_tree));
+        kd_tree.rebuild_from(tuples(random_points::<3>(50, 733)));
+        assert!(in_sync(&kd_tree));
+
+        let points = kd_tree.iter().map(|p| p.to_kdtree_point()).collect::<Vec<_>>();
+        for query in random_points::<3>(20, 739) {
+            let expected = points.iter().map(|p| squared_distance(p, &query)).fold(f64::INFINITY, f64::min);
+            assert_eq!(squared_distance(&kd_tree.nearest_by_coord(&query).unwrap().to_kdtree_point(), &query), expected);
+        }
+
+        // Arrays are read in place, through the same modifications
+        let mut kd_tree = KdTree::from(random_points::<3>(500, 719));
+        *kd_tree.get_mut(42).unwrap() = [50., 50., 50.];
+        kd_tree.remove_within_radius(&[20., 20., 20.], 15.);
+        kd_tree.transform_points(|p| p[1] += 10.);
+        kd_tree.add_point([-1., 200., 3.]);
+        assert!(kd_tree.store.columns.iter().all(Vec::is_empty));
+        assert_eq!(kd_tree.store.len, kd_tree.size());
+        assert_eq!(kd_tree.check_invariants(), Ok(()));
+        assert_eq!(kd_tree.nearest_by_coord(&[-1., 199., 3.]), Some(&[-1., 200., 3.]));
+        assert_eq!(kd_tree.nearest_by_coord(&[50., 61., 50.]), Some(&[50., 60., 50.]));
+    }
+
+    /// Compares a brute-force scan reading the coordinates through the points with one reading
+    /// the columns axis by axis, run with `cargo test --release -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    fn bench_coordinate_columns_scan(){
+        let n = 1 << 16;
+        let points: Vec<(f64, f64, f64)> = random_points::<3>(n, 743).into_iter().map(|[x, y, z]| (x, y, z)).collect();
+        let kd_tree: KdTree<3, (f64, f64, f64)> = KdTree::from(points.clone());
+        let queries = random_points::<3>(200, 751);
+
+        // Both scans compute the distances of blocks of points small enough to stay in the cache,
+        // then take their minimum over four lanes, only the way the coordinates are read differs
+        const BLOCK: usize = 256;
+        let block_min = |nearest: f64, distances: &[f64; BLOCK]| {
+            let mut lanes = [nearest; 4];
+            for chunk in distances.chunks_exact(4) {
+                for (lane, d) in lanes.iter_mut().zip(chunk) {
+                    *lane = if *d < *lane { *d } else { *lane };
+                }
+            }
+            lanes.into_iter().fold(f64::INFINITY, f64::min)
+        };
+
+        let start = std::time::Instant::now();
+        let through_points: Vec<f64> = queries.iter().map(|query| {
+            let mut nearest = f64::INFINITY;
+            for block in points.chunks_exact(BLOCK) {
+                let mut distances = [0.; BLOCK];
+                for (distance, p) in distances.iter_mut().zip(block) {
+                    *distance = squared_distance(&p.to_kdtree_point(), query);
+                }
+                nearest = block_min(nearest, &distances);
+            }
+            nearest
+        }).collect();
+        let points_time = start.elapsed();
+
+        let start = std::time::Instant::now();
+        let columns = &kd_tree.store.columns;
+        let through_columns: Vec<f64> = queries.iter().map(|query| {
+            let mut nearest = f64::INFINITY;
+            for start in (0..n).step_by(BLOCK) {
+                let mut distances = [0.; BLOCK];
+                for (column, y) in columns.iter().zip(query) {
+                    for (distance, x) in distances.iter_mut().zip(&column[start..start + BLOCK]) {
+                        *distance += (x - y) * (x - y);
+                    }
+                }
+                nearest = block_min(nearest, &distances);
+            }
+            nearest
+        }).collect();
+        let columns_time = start.elapsed();
+
+        assert_eq!(through_points, through_columns);
+        eprintln!("{n} points, {} queries: {points_time:?} through the points, {columns_time:?} through the columns", queries.len());
+    }
+
+    #[test]
+    fn test_construction_converts_each_point_once(){
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static CONVERSIONS: AtomicUsize = AtomicUsize::new(0);
+
+        /// Point computing its coordinates on every call, like the tuples
+        #[derive(Debug, Clone, PartialEq)]
+        struct CountingPoint(f64, f64, f64);
+
+        impl KdTreePoint<3> for CountingPoint {
+            fn to_kdtree_point(&self) -> [f64; 3] {
+                CONVERSIONS.fetch_add(1, Ordering::Relaxed);
+                [self.0, self.1, self.2]
+            }
+        }
+
+        let n = 5000;
+        let points: Vec<CountingPoint> = random_points::<3>(n, 757).into_iter().map(|[x, y, z]| CountingPoint(x, y, z)).collect();
+        let conversions = |build: &dyn Fn()| {
+            CONVERSIONS.store(0, Ordering::Relaxed);
+            build();
+            CONVERSIONS.load(Ordering::Relaxed)
+        };
+
+        let builders = [
+            KdTreeBuilder::new(),
+            KdTreeBuilder::new().leaf_size(8).axis_strategy(AxisStrategy::MaxSpread),
+            KdTreeBuilder::new().axis_strategy(AxisStrategy::MaxVariance).median_mode(MedianMode::Sampled { sample_size: 64 }),
+            KdTreeBuilder::new().split_rule(SplitRule::SlidingMidpoint),
+        ];
+        for builder in builders {
+            assert_eq!(conversions(&|| { builder.build::<3, _, f64>(points.clone()); }), n);
+            assert_eq!(conversions(&|| { builder.build_ref::<3, _, f64>(&points); }), n);
+            #[cfg(feature = "rayon")]
+            assert_eq!(conversions(&|| { builder.build_par::<3, _, f64>(points.clone()); }), n);
+        }
+
+        let mut kd_tree = KdTree::from(points.clone());
+        let rebuilt = conversions(&|| {
+            let mut tree = kd_tree.clone();
+            tree.rebuild_from(points[..n / 2].iter().cloned());
+        });
+        assert_eq!(rebuilt, n / 2);
+        assert_eq!(conversions(&|| { kd_tree.clone().freeze_implicit(); }), 0);
+
+        // The tree built from the cached coordinates is the one of the same points as arrays
+        let arrays = KdTree::from(random_points::<3>(n, 757));
+        assert!(kd_tree.iter_prefix().map(|p| [p.0, p.1, p.2]).eq(arrays.iter_prefix().copied()));
+        assert_eq!(kd_tree.bounding_box(), arrays.bounding_box());
+        kd_tree.add_point(CountingPoint(-1., -1., -1.));
+        assert_eq!(kd_tree.nearest_by_coord(&[-2., -2., -2.]), Some(&CountingPoint(-1., -1., -1.)));
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_parallel_batch_queries(){
+        let points = random_points::<3>(20_000, 761);
+        let kd_tree = KdTree::from(points.clone());
+        let queries = random_points::<3>(5000, 769);
+
+        let nearest = kd_tree.nearest_batch(&queries);
+        assert_eq!(kd_tree.nearest_batch_par(&queries), nearest);
+        for (query, index) in queries.iter().zip(&nearest).step_by(50) {
+            assert_eq!(Some(&points[index.unwrap()]), kd_tree.nearest_by_coord(query));
+        }
+
+        let nearest_k = kd_tree.nearest_k_batch(&queries, 7);
+        assert_eq!(kd_tree.nearest_k_batch_par(&queries, 7), nearest_k);
+        for (query, indices) in queries.iter().zip(&nearest_k).step_by(50) {
+            assert_eq!(indices.iter().map(|i| &points[*i]).collect::<Vec<_>>(), kd_tree.nearest_k(query, 7));
+        }
+        assert!(kd_tree.nearest_k_batch_par(&queries, 0).iter().all(Vec::is_empty));
+
+        // No queries, and queries on an empty tree
+        assert!(kd_tree.nearest_batch_par(&[]).is_empty());
+        assert!(kd_tree.nearest_k_batch_par(&[], 3).is_empty());
+        let empty = KdTree::<3, [f64; 3]>::new();
+        assert_eq!(empty.nearest_batch_par(&queries[..10]), vec![None; 10]);
+        assert_eq!(empty.nearest_k_batch_par(&queries[..10], 3), vec![Vec::<usize>::new(); 10]);
+    }
+
+    #[test]
+    fn test_shrink_to_fit(){
+        let mut kd_tree = KdTreeBuilder::new().leaf_size(4).build(Vec::new());
+        for point in random_points::<3>(4000, 773) {
+            kd_tree.add_point(point);
+        }
+        let queries = random_points::<3>(30, 787);
+        let check = |kd_tree: &KdTree<3, [f64; 3]>| {
+            let reference = KdTree::from(kd_tree.iter().copied().collect::<Vec<_>>());
+            for query in &queries {
+                let nearest = kd_tree.nearest_by_coord(query).unwrap();
+                assert_eq!(squared_distance(nearest, query), squared_distance(reference.nearest_by_coord(query).unwrap(), query));
+                let mut found = kd_tree.within_radius(query, 15.);
+                let mut expected = reference.within_radius(query, 15.);
+                found.sort_by(|a, b| a.partial_cmp(b).unwrap());
+                expected.sort_by(|a, b| a.partial_cmp(b).unwrap());
+                assert_eq!(found, expected);
+            }
+            assert_eq!(kd_tree.check_invariants(), Ok(()));
+        };
+
+        for (round, center) in random_points::<3>(5, 797).into_iter().enumerate() {
+            kd_tree.remove_within_radius(&center, 20.);
+            check(&kd_tree);
+            let before = kd_tree.clone();
+            let usage = kd_tree.memory_usage();
+
+            let mapping = kd_tree.shrink_to_fit();
+            assert_eq!(mapping, (0..kd_tree.size()).collect::<Vec<_>>());
+            assert!(kd_tree.structurally_equal(&before));
+            assert!(kd_tree.nodes.free.is_empty());
+            assert_eq!(kd_tree.nodes.nodes.len(), kd_tree.nodes.len());
+            assert_eq!(kd_tree.capacity(), kd_tree.size());
+            let shrunk = kd_tree.memory_usage();
+            assert_eq!(shrunk.node_count, usage.node_count);
+            assert!(shrunk.total() < usage.total(), "round {round}: {shrunk} not below {usage}");
+            check(&kd_tree);
+
+            // The compacted tree keeps working with insertions
+            for point in random_points::<3>(100, 809 + round as u64) {
+                kd_tree.add_point(point);
+            }
+            check(&kd_tree);
+        }
+
+        let mut empty = KdTree::<3, [f64; 3]>::with_capacity(100);
+        assert!(empty.shrink_to_fit().is_empty());
+        assert_eq!(empty.memory_usage().total(), std::mem::size_of::<KdTree<3, [f64; 3]>>());
+    }
+
+    #[test]
+    fn test_incremental_region_distance(){
+        fn check<const DIM: usize>(points: Vec<[f64; DIM]>, queries: Vec<[f64; DIM]>, radius: f64) -> (usize, usize) {
+            let kd_tree = KdTree::from(points.clone());
+            let mut visited = (0, 0);
+            for query in &queries {
+                let mut distances: Vec<f64> = points.iter().map(|p| squared_distance(p, query)).collect();
+                distances.select_nth_unstable_by(9, |a, b| a.partial_cmp(b).unwrap());
+                distances[..10].sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+                let (nearest, stats) = kd_tree.nearest_by_coord_with_stats(query);
+                assert_eq!(squared_distance(nearest.unwrap(), query), distances[0]);
+                visited.0 += stats.nodes_visited;
+                let nearest_k: Vec<f64> = kd_tree.nearest_k(query, 10).into_iter().map(|p| squared_distance(p, query)).collect();
+                assert_eq!(nearest_k, distances[..10]);
+
+                let (found, stats) = kd_tree.within_radius_with_stats(query, radius);
+                assert_eq!(found.len(), distances.iter().filter(|d| **d <= radius * radius).count());
+                visited.1 += stats.nodes_visited;
+            }
+            (visited.0 / queries.len(), visited.1 / queries.len())
+        }
+
+        // Pruning on the split distance alone visits about 1040 nodes per nearest query and 4975
+        // per radius query in 8-D, 19350 per nearest query in 16-D
+        let (nearest, radius) = check(random_points::<8>(20_000, 811), random_points::<8>(200, 821), 40.);
+        assert!(nearest < 500 && radius < 2000, "{nearest} {radius}");
+        let (nearest, _) = check(random_points::<16>(20_000, 811), random_points::<16>(40, 823), 100.);
+        assert!(nearest < 9000, "{nearest}");
+
+        // Points on the splits, at the boundary of the regions
+        let grid: Vec<[f64; 8]> = (0..4096).map(|i| std::array::from_fn(|axis| ((i >> axis) % 3) as f64)).collect();
+        check(grid.clone(), grid.iter().step_by(37).map(|p| p.map(|x| x + 0.5)).collect(), 1.);
+        check(grid.clone(), grid.into_iter().step_by(41).collect(), 1.);
+    }
+
+    #[test]
+    fn test_one_distance_per_visited_point(){
+        let points = random_points::<3>(5000, 827);
+        let distances = |f: &dyn Fn() -> usize| {
+            DISTANCES.with(|count| count.set(0));
+            let visited = f();
+            (DISTANCES.with(|count| count.get()), visited)
+        };
+
+        // Nodes hold a single point, the distance of each visited node is computed once
+        let kd_tree = KdTree::from(points);
+        for query in random_points::<3>(50, 829) {
+            let (computed, visited) = distances(&|| kd_tree.nearest_by_coord_with_stats(&query).1.nodes_visited);
+            assert_eq!(computed, visited);
+            let (computed, visited) = distances(&|| kd_tree.within_radius_with_stats(&query, 10.).1.nodes_visited);
+            assert_eq!(computed, visited);
+            let (computed, visited) = distances(&|| {
+                let mut stats = QueryStats::default();
+                Node::nearest_k_indices(&kd_tree.nodes, &kd_tree.columns(), &query, 5, &mut stats);
+                stats.nodes_visited
+            });
+            assert_eq!(computed, visited);
+        }
+    }
+
+    #[test]
+    fn test_construction_keeps_the_indices(){
+        // Many equal coordinates, the entries are sorted on their index when their coordinates tie
+        let points: Vec<[f64; 2]> = random_points::<2>(3000, 857).into_iter().map(|[x, y]| [(x / 10.).round(), y]).collect();
+        let builders = [
+            KdTreeBuilder::new(),
+            KdTreeBuilder::new().leaf_size(6).axis_strategy(AxisStrategy::MaxSpread),
+            KdTreeBuilder::new().split_rule(SplitRule::SlidingMidpoint).axis_strategy(AxisStrategy::MaxVariance),
+            KdTreeBuilder::new().median_mode(MedianMode::Sampled { sample_size: 32 }),
+        ];
+        for builder in builders {
+            let kd_tree = builder.build(points.clone());
+            assert!(kd_tree.check_invariants().is_ok());
+            let mut indices = Vec::new();
+            kd_tree.nodes.root().unwrap().collect_indices(&kd_tree.nodes, &mut indices);
+            indices.sort();
+            assert_eq!(indices, (0..points.len()).collect::<Vec<_>>());
+            // Every index reported by a query is the one of its point
+            let queries = random_points::<2>(20, 859);
+            for (query, found) in queries.iter().zip(kd_tree.nearest_k_batch(&queries, 8)) {
+                let mut expected: Vec<f64> = points.iter().map(|p| squared_distance(p, query)).collect();
+                expected.sort_by(f64::total_cmp);
+                let found: Vec<f64> = found.iter().map(|i| squared_distance(&points[*i], query)).collect();
+                assert_eq!(found, expected[..8]);
+            }
+        }
+    }
+
+    /// Times the construction of a tree of a million points, run with `cargo test --release -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    fn bench_construction(){
+        let points = random_points::<3>(1_000_000, 863);
+        let mut times = Vec::new();
+        for _ in 0..5 {
+            let start = std::time::Instant::now();
+            let kd_tree = KdTree::from(points.clone());
+            times.push(start.elapsed());
+            assert_eq!(kd_tree.size(), points.len());
+        }
+        times.sort();
+        println!("construction of {} points: {:?} (median of {})", points.len(), times[times.len() / 2], times.len());
+    }
+
+    /// Squared distance summed by the generic fold, the reference of the unrolled kernels.
+    fn folded_squared_distance<const DIM: usize>(a: &[f64; DIM], b: &[f64; DIM]) -> f64 {
+        a.iter().zip(b).fold(0., |acc, (x, y)| acc + (x - y) * (x - y))
+    }
+
+    fn check_small_dimension<const DIM: usize>(seed: u64){
+        let mut points = random_points::<DIM>(500, seed);
+        // Values whose rounding depends on the order of the additions
+        points.push(std::array::from_fn(|axis| [1e16, 1., -1e16, 3.5, 0.1][axis % 5]));
+        points.push(std::array::from_fn(|axis| if axis == 0 { -0. } else { 1e-300 }));
+        let queries = random_points::<DIM>(30, seed + 2);
+        let kd_tree = KdTree::from(points.clone());
+
+        for query in queries.iter().chain(&points[points.len() - 2..]) {
+            for (i, p) in points.iter().enumerate() {
+                let expected = folded_squared_distance(p, query);
+                assert_eq!(kd_tree::squared_distance(p, query).to_bits(), expected.to_bits());
+                assert_eq!(kd_tree.columns().squared_distance(i, query).to_bits(), expected.to_bits());
+                assert_eq!(Metric::<DIM>::distance(&Euclidean, p, query).to_bits(), expected.sqrt().to_bits());
+            }
+            let nearest = points.iter().map(|p| folded_squared_distance(p, query)).fold(f64::INFINITY, f64::min);
+            assert_eq!(squared_distance(kd_tree.nearest_by_coord(query).unwrap(), query), nearest);
+            let found = kd_tree.within_radius(query, 20.).len();
+            assert_eq!(found, points.iter().filter(|p| folded_squared_distance(p, query) <= 400.).count());
+        }
+    }
+
+    #[test]
+    fn test_unrolled_distances(){
+        check_small_dimension::<1>(867);
+        check_small_dimension::<2>(871);
+        check_small_dimension::<3>(877);
+        check_small_dimension::<4>(881);
+        check_small_dimension::<5>(883);
+    }
+
+    /// Compares the unrolled squared distance to the generic fold on 3-D points, run with
+    /// `cargo test --release -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    fn bench_unrolled_distances(){
+        let points = random_points::<3>(1 << 16, 887);
+        let queries = random_points::<3>(200, 907);
+        let time = |distance: fn(&[f64; 3], &[f64; 3]) -> f64| {
+            let start = std::time::Instant::now();
+            let total: f64 = queries.iter().map(|query| {
+                points.iter().map(|p| distance(std::hint::black_box(p), query)).fold(f64::INFINITY, f64::min)
+            }).sum();
+            (start.elapsed(), total)
+        };
+        let (folded, folded_total) = time(folded_squared_distance::<3>);
+        let (unrolled, unrolled_total) = time(kd_tree::squared_distance::<3, f64>);
+        assert_eq!(folded_total.to_bits(), unrolled_total.to_bits());
+        println!("generic fold: {folded:?}, unrolled: {unrolled:?}");
+    }
+
+    #[test]
+    fn test_remove_lazy(){
+        let points = random_points::<3>(2000, 911);
+        let mut kd_tree = KdTreeBuilder::new().leaf_size(4).build(points.clone());
+        assert_eq!(kd_tree.remove_lazy(points.len()), LazyRemoval::NotFound);
+
+        // Below the compaction threshold, the removed points stay stored as tombstones
+        let removed: Vec<usize> = (0..points.len()).step_by(7).collect();
+        for i in &removed {
+            assert!(kd_tree.remove_lazy(*i).is_removed());
+        }
+        assert_eq!(kd_tree.remove_lazy(removed[0]), LazyRemoval::NotFound);
+        assert_eq!(kd_tree.tombstone_count(), removed.len());
+        assert_eq!(kd_tree.size(), points.len() - removed.len());
+        let live: Vec<[f64; 3]> = (0..points.len()).filter(|i| i % 7 != 0).map(|i| points[i]).collect();
+
+        let check = |kd_tree: &KdTree<3, [f64; 3]>| {
+            let is_live = |p: &&[f64; 3]| live.contains(p);
+            for query in random_points::<3>(30, 919) {
+                let mut expected: Vec<f64> = live.iter().map(|p| squared_distance(p, &query)).collect();
+                expected.sort_by(f64::total_cmp);
+                assert_eq!(squared_distance(kd_tree.nearest_by_coord(&query).unwrap(), &query), expected[0]);
+                let nearest_k = kd_tree.nearest_k(&query, 10);
+                assert!(nearest_k.iter().all(is_live));
+                assert_eq!(nearest_k.iter().map(|p| squared_distance(p, &query)).collect::<Vec<_>>(), expected[..10]);
+                let within = kd_tree.within_radius(&query, 15.);
+                assert!(within.iter().all(is_live));
+                assert_eq!(within.len(), expected.iter().filter(|d| **d <= 225.).count());
+                let (min, max) = (query.map(|x| x - 10.), query.map(|x| x + 10.));
+                let in_box = kd_tree.points_in_aabb(&min, &max);
+                assert!(in_box.iter().all(is_live));
+                assert_eq!(in_box.len(), live.iter().filter(|p| (0..3).all(|axis| min[axis] <= p[axis] && p[axis] <= max[axis])).count());
+                let nearest = kd_tree.nearest_by_coord_with_metric(&query, &Manhattan).unwrap();
+                let manhattan = |p: &[f64; 3]| Metric::<3>::distance(&Manhattan, p, &query);
+                assert_eq!(manhattan(nearest), live.iter().map(manhattan).fold(f64::INFINITY, f64::min));
+                assert!(kd_tree.within_radius_with_metric(&query, 20., &Manhattan).iter().all(is_live));
+            }
+        };
+        check(&kd_tree);
+        assert!(kd_tree.check_invariants().is_ok());
+
+        // Compaction removes the tombstones for good without changing the answers
+        let compacted = kd_tree.compact_tombstones();
+        assert_eq!(compacted, removed.iter().map(|i| points[*i]).collect::<Vec<_>>());
+        assert_eq!(kd_tree.tombstone_count(), 0);
+        assert_eq!(kd_tree.points, live);
+        assert!(kd_tree.check_invariants().is_ok());
+        check(&kd_tree);
+
+        // Past a quarter of the live points, the tombstones are compacted automatically
+        let mut kd_tree = KdTree::from(points[..100].to_vec());
+        for i in 0..20 {
+            assert!(kd_tree.remove_lazy(i).is_removed());
+        }
+        assert_eq!(kd_tree.tombstone_count(), 20);
+        let mapping = match kd_tree.remove_lazy(20) {
+            LazyRemoval::Compacted { mapping } => mapping,
+            removal => panic!("no compaction: {removal:?}"),
+        };
+        assert_eq!(mapping[..21], [REMOVED_INDEX; 21]);
+        assert_eq!(mapping[21..], (0..79).collect::<Vec<_>>());
+        assert_eq!((kd_tree.tombstone_count(), kd_tree.size()), (0, 79));
+        assert_eq!(kd_tree.points, &points[21..100]);
+
+        // A tree of tombstones is empty, and new points are live
+        let mut kd_tree = KdTree::from(points[..1].to_vec());
+        assert!(kd_tree.remove_lazy(0).is_removed());
+        assert!(kd_tree.is_empty());
+        assert_eq!(kd_tree.nearest_by_coord(&[0.; 3]), None);
+    }
+
+    #[test]
+    fn test_clone_and_drop_deep_chain(){
+        // A chain of nodes, each one being the right child of the previous one
+        let chain = |n: usize| {
+            let mut kd_tree = KdTree::<1, [f64; 1]>::new();
+            kd_tree.points = (0..n).map(|i| [i as f64]).collect();
+            kd_tree.store = ColumnStore::of_points(&kd_tree.points);
+            kd_tree.nodes.nodes = (0..n).map(|i| {
+                let right = if i + 1 < n { (i + 1) as NodeId } else { NONE };
+                Node { index: i, axis: 0, split: i as f64, bucket: Vec::new(), left: NONE, right }
+            }).collect();
+            kd_tree.nodes.root = 0;
+            kd_tree
+        };
+
+        // Sorted insertions rebuild the unbalanced subtrees instead of giving that chain
+        let mut inserted = KdTree::new();
+        for i in 0..1000 {
+            inserted.add_point([i as f64]);
+        }
+        assert!(inserted.height() < 30);
+        assert_eq!(chain(1000).height(), 1000);
+
+        // The nodes are stored in a single Vec: cloning, comparing and dropping them never
+        // recurses along the links, and the height is found with an explicit stack
+        let kd_tree = chain(300_000);
+        assert_eq!(kd_tree.height(), 300_000);
+        let clone = kd_tree.clone();
+        assert!(clone.nodes == kd_tree.nodes);
+        assert_eq!(clone.points, kd_tree.points);
+        drop(kd_tree);
+        drop(clone);
+    }
+
+    #[cfg(feature = "simd")]
+    fn check_simd_distances<const DIM: usize, S: Scalar>(seed: u64) {
+        let mut points: Vec<[S; DIM]> = random_points::<DIM>(400, seed).into_iter().map(|p| p.map(S::from_f64)).collect();
+        for (i, special) in [S::INFINITY, S::NEG_INFINITY, S::from_f64(1e300), S::from_f64(-0.)].into_iter().enumerate() {
+            points[i * 7][i % DIM] = special;
+        }
+        let queries: Vec<[S; DIM]> = random_points::<DIM>(20, seed + 1).into_iter().map(|p| p.map(S::from_f64)).chain([[S::INFINITY; DIM]]).collect();
+        let same = |a: S, b: S| a.to_f64().to_bits() == b.to_f64().to_bits() || (a.to_f64().is_nan() && b.to_f64().is_nan());
+
+        for query in &queries {
+            for lanes in points.chunks_exact(4) {
+                let coords = std::array::from_fn(|axis| std::array::from_fn(|lane| lanes[lane][axis]));
+                let distances = S::squared_distances_x4(&coords, query);
+                for (p, distance) in lanes.iter().zip(distances) {
+                    assert!(same(distance, kd_tree::squared_distance(p, query)));
+                }
+            }
+        }
+
+        // Leaf buckets are scanned 4 points at a time
+        let kd_tree = KdTreeBuilder::new().leaf_size(13).build(points.clone());
+        for query in &queries[..20] {
+            let expected = points.iter().map(|p| kd_tree::squared_distance(p, query)).fold(S::INFINITY, S::min);
+            assert!(same(kd_tree::squared_distance(kd_tree.nearest_by_coord(query).unwrap(), query), expected));
+            let squared_radius = S::from_f64(900.);
+            let found = kd_tree.within_radius(query, S::from_f64(30.)).len();
+            assert_eq!(found, points.iter().filter(|p| kd_tree::squared_distance(p, query) <= squared_radius).count());
+        }
+    }
+
+    #[cfg(feature = "simd")]
+    #[test]
+    fn test_simd_distances(){
+        check_simd_distances::<1, f64>(929);
+        check_simd_distances::<2, f64>(937);
+        check_simd_distances::<3, f64>(941);
+        check_simd_distances::<3, f32>(947);
+        check_simd_distances::<7, f32>(953);
+    }
+
+    /// Compares a brute-force scan of the columns 4 points at a time to one point at a time,
+    /// run with `cargo test --release --features simd -- --ignored --nocapture`.
+    #[cfg(feature = "simd")]
+    #[test]
+    #[ignore]
+    fn bench_simd_scan(){
+        let kd_tree = KdTree::from(random_points::<3>(1 << 16, 967));
+        let queries = random_points::<3>(200, 971);
+        let start = std::time::Instant::now();
+        let one_by_one: Vec<f64> = queries.iter().map(|query| {
+            (0..kd_tree.size()).map(|i| kd_tree.columns().squared_distance(i, query)).fold(f64::INFINITY, f64::min)
+        }).collect();
+        let scalar = start.elapsed();
+        let start = std::time::Instant::now();
+        let by_lanes: Vec<f64> = queries.iter().map(|query| {
+            let mut nearest = f64::INFINITY;
+            kd_tree.columns().for_each_squared_distance(0..kd_tree.size(), query, |_, distance| nearest = nearest.min(distance));
+            nearest
+        }).collect();
+        let simd = start.elapsed();
+        assert_eq!(one_by_one, by_lanes);
+        println!("one by one: {scalar:?}, 4 at a time: {simd:?}");
+    }
+
+    /// Allocator of the tests counting the allocations of each thread.
+    struct CountingAllocator;
+
+    thread_local! {
+        static ALLOCATIONS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+    }
+
+    unsafe impl std::alloc::GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: std::alloc::Layout) -> *mut u8 {
+            let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+            unsafe { std::alloc::System.alloc(layout) }
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: std::alloc::Layout) {
+            unsafe { std::alloc::System.dealloc(ptr, layout) }
+        }
+
+        unsafe fn realloc(&self, ptr: *mut u8, layout: std::alloc::Layout, new_size: usize) -> *mut u8 {
+            let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+            unsafe { std::alloc::System.realloc(ptr, layout, new_size) }
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+    #[test]
+    fn test_query_workspace(){
+        fn assert_send<T: Send>() {}
+        assert_send::<QueryWorkspace>();
+
+        let points = random_points::<3>(3000, 977);
+        let kd_tree = KdTreeBuilder::new().leaf_size(6).build(points);
+        let queries = random_points::<3>(100, 983);
+        let mut ws = QueryWorkspace::new();
+        for query in &queries {
+            let found: Vec<&[f64; 3]> = kd_tree.nearest_k_with(&mut ws, query, 9).iter().map(|i| &kd_tree.points[*i]).collect();
+            assert_eq!(found, kd_tree.nearest_k(query, 9));
+            let found: Vec<&[f64; 3]> = kd_tree.within_radius_with(&mut ws, query, 12.).iter().map(|i| &kd_tree.points[*i]).collect();
+            assert_eq!(found, kd_tree.within_radius(query, 12.));
+        }
+        assert!(kd_tree.nearest_k_with(&mut ws, &queries[0], 0).is_empty());
+        assert!(kd_tree.within_radius_with(&mut ws, &queries[0], -1.).is_empty());
+
+        // Once the buffers have grown, the queries don't allocate
+        let allocations = ALLOCATIONS.with(|count| count.get());
+        let mut total = 0;
+        for query in &queries {
+            total += kd_tree.nearest_k_with(&mut ws, query, 9).len();
+            total += kd_tree.within_radius_with(&mut ws, query, 12.).len();
+        }
+        assert_eq!(ALLOCATIONS.with(|count| count.get()), allocations);
+        assert!(total > 900);
+        // Unlike the plain queries
+        let _ = kd_tree.nearest_k(&queries[0], 9);
+        assert!(ALLOCATIONS.with(|count| count.get()) > allocations);
+    }
+
+    #[test]
+    fn test_nearest_batch_coherent(){
+        let mut points = random_points::<2>(3000, 991);
+        // Duplicates, so that the ties must be broken like nearest_by_coord does
+        points.extend_from_within(..200);
+        let random_queries = random_points::<2>(500, 997);
+        let scanline: Vec<[f64; 2]> = (0..40).flat_map(|y| (0..40).map(move |x| [x as f64 * 2.5, y as f64 * 2.5])).collect();
+
+        let mut lazy = KdTree::from(points.clone());
+        for i in (0..points.len()).step_by(7) {
+            lazy.remove_lazy(i);
+        }
+        let trees = [KdTree::from(points.clone()), KdTreeBuilder::new().leaf_size(8).build(points.clone()), lazy];
+        for kd_tree in &trees {
+            for queries in [&random_queries, &scanline, &points] {
+                let (found, stats) = kd_tree.nearest_batch_coherent_with_stats(queries);
+                assert_eq!(found, kd_tree.nearest_batch(queries));
+                for (query, index) in queries.iter().zip(&found) {
+                    assert!(std::ptr::eq(kd_tree.nearest_by_coord(query).unwrap(), &kd_tree.points[index.unwrap()]));
+                }
+                assert_eq!(kd_tree.nearest_batch_coherent(queries), found);
+
+                let visited: usize = queries.iter().map(|query| kd_tree.nearest_by_coord_with_stats(query).1.nodes_visited).sum();
+                assert!(stats.nodes_visited <= visited);
+                if std::ptr::eq(queries, &scanline) {
+                    // Neighbouring queries share most of their paths
+                    assert!(stats.nodes_visited * 4 < visited * 3, "{} vs {visited}", stats.nodes_visited);
+                }
+            }
+        }
+
+        let empty: KdTree<2, [f64; 2]> = KdTree::from(Vec::new());
+        assert_eq!(empty.nearest_batch_coherent(&scanline[..3]), vec![None; 3]);
+        assert!(trees[0].nearest_batch_coherent(&[]).is_empty());
+    }
+
+
+    #[test]
+    fn test_equal_coordinates_placement(){
+        // Every point shares x = 5 with all the others and y or z with some of them
+        let points: Vec<[f64; 3]> = (0..300).map(|i| [5., (i % 17) as f64, (i / 17) as f64 + if i % 3 == 0 { 0.5 } else { 0. }]).collect();
+        let first = points[..100].to_vec();
+
+        let mut trees = Vec::new();
+        for builder in [KdTreeBuilder::new(), KdTreeBuilder::new().leaf_size(4), KdTreeBuilder::new().split_rule(SplitRule::SlidingMidpoint)] {
+            trees.push(builder.build(points.clone()));
+            let mut added = builder.build(first.clone());
+            let mut empty = builder.build(Vec::new());
+            for p in &points[100..] {
+                added.add_point(*p);
+            }
+            for p in &points {
+                empty.add_point(*p);
+            }
+            trees.push(added);
+            trees.push(empty);
+        }
+        // Points moved back and forth are inserted again with a small index
+        let mut moved = KdTree::from(points.clone());
+        for i in (0..points.len()).step_by(5) {
+            moved.get_mut(i).unwrap()[0] = 9.;
+            moved.get_mut(i).unwrap()[0] = 5.;
+        }
+        trees.push(moved);
+
+        for kd_tree in &trees {
+            assert!(kd_tree.check_invariants().is_ok());
+            for (i, p) in points.iter().enumerate() {
+                let (nearest, stats) = kd_tree.nearest_by_coord_with_stats(p);
+                assert!(std::ptr::eq(nearest.unwrap(), &kd_tree.points[i]), "{p:?}");
+                assert!(stats.nodes_visited <= kd_tree.points.len());
+                assert_eq!(kd_tree.nearest_k(p, 1)[0], p);
+                assert!(kd_tree.within_radius(p, 0.).contains(&p));
+                assert_eq!(kd_tree.points_in_aabb(p, p), vec![p]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_nan_coordinates(){
+        let mut points = random_points::<2>(2000, 1009);
+        for i in (0..points.len()).step_by(3) {
+            points[i][i % 2] = f64::NAN;
+        }
+
+        // A NaN point in the build set is rejected by the fallible constructor
+        assert!(matches!(KdTree::try_from_points(points.clone()), Err(BuildError::NonFiniteCoordinate { index: 0, axis: 0, .. })));
+        // and the infallible one builds the same tree every time, which check_invariants reports
+        let kd_tree = KdTree::from(points.clone());
+        let layout = |kd_tree: &KdTree<2, [f64; 2]>| kd_tree.nodes.nodes.iter().map(|node| (node.index, node.left, node.right)).collect::<Vec<_>>();
+        assert_eq!(layout(&kd_tree), layout(&KdTree::from(points.clone())));
+        assert!(matches!(kd_tree.check_invariants(), Err(InvariantViolation::NonFinitePosition { .. })));
+        let implicit = KdTree::from(points.clone()).freeze_implicit();
+        assert_eq!(implicit.points().len(), points.len());
+
+        // A NaN inserted is rejected by try_add_point, and placed like in the construction by add_point
+        let mut finite: KdTree<2, [f64; 2]> = KdTree::from(random_points::<2>(100, 1013));
+        assert!(matches!(finite.try_add_point([1., f64::NAN]), Err(BuildError::NonFiniteCoordinate { index: 100, axis: 1, .. })));
+        assert_eq!(finite.points.len(), 100);
+        assert!(finite.check_invariants().is_ok());
+        finite.add_point([f64::NAN, 1.]);
+        finite.add_point([f64::NAN, f64::NAN]);
+        assert!(matches!(finite.check_invariants(), Err(InvariantViolation::NonFinitePosition { index: 100, axis: 0, .. })));
+
+        // A NaN query is rejected by the fallible queries, the other ones give some point
+        let tree = KdTree::from(random_points::<2>(100, 1019));
+        for query in [[f64::NAN, 1.], [1., f64::NAN], [f64::NAN; 2]] {
+            let axis = if query[0].is_nan() { 0 } else { 1 };
+            assert_eq!(tree.try_nearest_by_coord(&query), Err(BuildError::NanQuery { axis }));
+            assert_eq!(tree.try_nearest_k(&query, 3), Err(BuildError::NanQuery { axis }));
+            assert_eq!(tree.try_within_radius(&query, 10.), Err(BuildError::NanQuery { axis }));
+            assert!(tree.nearest_by_coord(&query).is_some());
+            assert!(tree.within_radius(&query, 10.).is_empty());
+        }
+        let query = [50., 50.];
+        assert_eq!(tree.try_nearest_by_coord(&query), Ok(tree.nearest_by_coord(&query)));
+        assert_eq!(tree.try_nearest_k(&query, 5), Ok(tree.nearest_k(&query, 5)));
+        assert_eq!(tree.try_within_radius(&query, 20.), Ok(tree.within_radius(&query, 20.)));
+        assert_eq!(BuildError::NanQuery { axis: 1 }.to_string(), "query has a NaN coordinate on axis 1");
+    }
+
+    #[test]
+    fn test_prune_boundary_below_one(){
+        // Offsets below 1 have a square smaller than their absolute value: comparing the absolute
+        // offset to a squared distance would prune the far side holding the nearest point
+        let kd_tree = KdTree::from(vec![[0., 0.], [0.5, 0.9], [0.55, 0.]]);
+        // The best of the near side is at a squared distance 0.09 above the offset 0.2 of the split,
+        // the far side point is nearer
+        let (nearest, stats) = kd_tree.nearest_by_coord_with_stats(&[0.3, 0.]);
+        assert_eq!(nearest, Some(&[0.55, 0.]));
+        assert_eq!(stats.nodes_visited, 3);
+
+        // Queries whose near side is empty still fall back to the points found above
+        let kd_tree = KdTree::from(vec![[0.5, 0.5], [0.1, 0.1]]);
+        assert_eq!(kd_tree.nearest_by_coord(&[0.9, 0.9]), Some(&[0.5, 0.5]));
+
+        let points: Vec<[f64; 2]> = random_points::<2>(1000, 1021).into_iter().map(|p| p.map(|x| x / 100.)).collect();
+        let kd_tree = KdTree::from(points.clone());
+        for query in random_points::<2>(300, 1031).into_iter().map(|p| p.map(|x| x / 100.)) {
+            let nearest = points.iter().map(|p| squared_distance(p, &query)).fold(f64::INFINITY, f64::min);
+            assert_eq!(squared_distance(kd_tree.nearest_by_coord(&query).unwrap(), &query), nearest);
+        }
+    }
+
+
+    #[test]
+    fn test_prune_boundary_lattice(){
+        // Integer lattice with duplicates: many points lie on the split planes, at distances
+        // exactly equal to the offsets of the queries to the splits
+        let mut points: Vec<[f64; 3]> = (0..216).map(|i| [(i % 6) as f64, (i / 6 % 6) as f64, (i / 36) as f64]).collect();
+        points.extend_from_within(..100);
+        points.extend_from_within(50..80);
+        let queries: Vec<[f64; 3]> = (0..343).map(|i| [(i % 7) as f64 - 0.5, (i / 7 % 7) as f64 * 0.5, (i / 49) as f64 - 0.5]).chain(points.iter().copied()).collect();
+
+        let trees = [KdTree::from(points.clone()), KdTreeBuilder::new().leaf_size(5).build(points.clone()), KdTreeBuilder::new().split_rule(SplitRule::SlidingMidpoint).build(points.clone())];
+        for kd_tree in &trees {
+            let implicit = kd_tree.clone().freeze_implicit();
+            for query in &queries {
+                let mut distances: Vec<f64> = points.iter().map(|p| squared_distance(p, query)).collect();
+                distances.sort_by(f64::total_cmp);
+
+                assert_eq!(squared_distance(kd_tree.nearest_by_coord(query).unwrap(), query), distances[0]);
+                assert_eq!(squared_distance(implicit.nearest_by_coord(query).unwrap(), query), distances[0]);
+                for k in [2, 7, 30] {
+                    let found: Vec<f64> = kd_tree.nearest_k(query, k).iter().map(|p| squared_distance(p, query)).collect();
+                    assert_eq!(found, distances[..k]);
+                    let found: Vec<f64> = implicit.nearest_k(query, k).iter().map(|p| squared_distance(p, query)).collect();
+                    assert_eq!(found, distances[..k]);
+                }
+                // Radii reaching exactly the lattice points include them
+                for radius in [1., 2f64.sqrt(), 1.5] {
+                    let expected = distances.iter().filter(|d| **d <= radius * radius).count();
+                    assert_eq!(kd_tree.within_radius(query, radius).len(), expected);
+                    assert_eq!(implicit.within_radius(query, radius).len(), expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_nearest_ties_smallest_index(){
+        // Quantized points: every position is stored several times
+        let base: Vec<[f64; 2]> = random_points::<2>(400, 1033).into_iter().map(|p| p.map(|x| (x / 10.).round())).collect();
+        let queries: Vec<[f64; 2]> = (0..121).map(|i| [(i % 11) as f64, (i / 11) as f64]).chain(random_points::<2>(100, 1039).into_iter().map(|p| p.map(|x| x / 10.))).collect();
+
+        let mut state = 1049u64;
+        for shuffle in 0..4 {
+            let mut points = base.clone();
+            for i in (1..points.len()).rev().take(if shuffle == 0 { 0 } else { points.len() }) {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                points.swap(i, (state % (i as u64 + 1)) as usize);
+            }
+
+            let mut added = KdTree::from(Vec::new());
+            for p in &points {
+                added.add_point(*p);
+            }
+            let trees = [
+                KdTree::from(points.clone()),
+                added,
+                KdTreeBuilder::new().leaf_size(6).build(points.clone()),
+                KdTreeBuilder::new().split_rule(SplitRule::SlidingMidpoint).axis_strategy(AxisStrategy::MaxSpread).build(points.clone()),
+            ];
+            let by_ref = KdTreeRef::<2, [f64; 2]>::from_slice(&points);
+            let mut workspace = QueryWorkspace::new();
+            for query in &queries {
+                let distances: Vec<f64> = points.iter().map(|p| squared_distance(p, query)).collect();
+                let min = distances.iter().copied().fold(f64::INFINITY, f64::min);
+                let ties: Vec<usize> = (0..points.len()).filter(|i| distances[*i] == min).collect();
+                let mut order: Vec<usize> = (0..points.len()).collect();
+                order.sort_by(|a, b| distances[*a].total_cmp(&distances[*b]).then(a.cmp(b)));
+
+                let manhattan: Vec<f64> = points.iter().map(|p| Metric::<2>::distance(&Manhattan, p, query)).collect();
+                let min_manhattan = manhattan.iter().copied().fold(f64::INFINITY, f64::min);
+                let first_manhattan = (0..points.len()).find(|i| manhattan[*i] == min_manhattan).unwrap();
+
+                for kd_tree in &trees {
+                    assert!(std::ptr::eq(kd_tree.nearest_by_coord(query).unwrap(), &kd_tree.points[ties[0]]));
+                    assert_eq!(kd_tree.nearest_batch(&[*query]), vec![Some(ties[0])]);
+                    assert_eq!(kd_tree.nearest_batch_coherent(&[*query]), vec![Some(ties[0])]);
+                    assert_eq!(kd_tree.nearest_all_at_min_distance(query), ties);
+                    assert_eq!(kd_tree.nearest_k_with(&mut workspace, query, 12), &order[..12]);
+                    assert!(std::ptr::eq(kd_tree.nearest_by_coord_with_metric(query, &Manhattan).unwrap(), &kd_tree.points[first_manhattan]));
+                }
+                assert!(std::ptr::eq(by_ref.nearest_by_coord(query).unwrap(), &points[ties[0]]));
+            }
+        }
+        assert!(KdTree::<2, [f64; 2]>::from(Vec::new()).nearest_all_at_min_distance(&[0., 0.]).is_empty());
+    }
+
+    #[test]
+    fn test_add_sorted_points(){
+        let mut kd_tree = KdTree::new();
+        for i in 0..200_000 {
+            kd_tree.add_point([i as f64, i as f64 * 0.5]);
+        }
+        // Within the depth bound of the rebuilds, log(n) / log(4/3)
+        assert!(kd_tree.height() <= 44, "{}", kd_tree.height());
+        assert!(kd_tree.check_invariants().is_ok());
+        assert_eq!(kd_tree.nearest_by_coord(&[123_456.2, 61_728.]), Some(&[123_456., 61_728.]));
+        assert_eq!(kd_tree.nearest_by_coord(&[-5., -5.]), Some(&[0., 0.]));
+        assert_eq!(kd_tree.nearest_k(&[1e9, 1e9], 2), vec![&[199_999., 99_999.5], &[199_998., 99_999.]]);
+
+        // Decreasing points, with buckets and tombstones
+        let mut kd_tree = KdTreeBuilder::new().leaf_size(8).build(Vec::new());
+        for i in (0..50_000).rev() {
+            kd_tree.add_point([0., i as f64]);
+            if i % 10 == 0 {
+                kd_tree.remove_lazy(kd_tree.points.len() - 1);
+            }
+        }
+        assert!(kd_tree.height() <= 40, "{}", kd_tree.height());
+        assert!(kd_tree.check_invariants().is_ok());
+        assert_eq!(kd_tree.nearest_by_coord(&[0., 10.1]), Some(&[0., 11.]));
+    }
+
+    #[test]
+    fn test_infinite_coordinates(){
+        let inf = f64::INFINITY;
+        let mut points = random_points::<2>(300, 1051);
+        for (i, p) in [[inf, 5.], [-inf, 50.], [20., inf], [inf, inf], [-inf, -inf], [inf, -inf]].into_iter().enumerate() {
+            points[i * 37] = p;
+        }
+        assert!(matches!(KdTree::try_from_points(points.clone()), Err(BuildError::NonFiniteCoordinate { index: 0, axis: 0, value }) if value == inf));
+        let mut finite = KdTree::from(random_points::<2>(10, 1061));
+        assert!(finite.try_add_point([1., -inf]).is_err());
+
+        let mut added = KdTree::from(Vec::new());
+        for p in &points {
+            added.add_point(*p);
+        }
+        let trees = [KdTree::from(points.clone()), added, KdTreeBuilder::new().leaf_size(4).build(points.clone()), KdTreeBuilder::new().split_rule(SplitRule::SlidingMidpoint).build(points.clone())];
+        // Finite queries, infinite ones, and mixed ones
+        let queries = random_points::<2>(50, 1063).into_iter()
+            .chain([[inf, 0.], [-inf, 30.], [10., inf], [inf, inf], [-inf, inf], [0., -inf]])
+            .collect::<Vec<_>>();
+        for query in &queries {
+            let distances: Vec<f64> = points.iter().map(|p| kd_tree::squared_distance(p, query)).collect();
+            // NaN distances last, then by index
+            let mut order: Vec<usize> = (0..points.len()).collect();
+            order.sort_by(|a, b| distances[*a].is_nan().cmp(&distances[*b].is_nan())
+                .then(distances[*a].partial_cmp(&distances[*b]).unwrap_or(std::cmp::Ordering::Equal))
+                .then(a.cmp(b)));
+            for kd_tree in &trees {
+                assert!(std::ptr::eq(kd_tree.nearest_by_coord(query).unwrap(), &kd_tree.points[order[0]]), "{query:?}");
+                assert_eq!(kd_tree.nearest_k_batch(&[*query], 10), vec![order[..10].to_vec()]);
+                assert_eq!(kd_tree.nearest_all_at_min_distance(query).first(), Some(&order[0]));
+                for radius in [30., inf] {
+                    let expected = distances.iter().filter(|d| **d <= radius * radius).count();
+                    assert_eq!(kd_tree.within_radius(query, radius).len(), expected, "{query:?} {radius}");
+                }
+            }
+            let implicit = trees[0].clone().freeze_implicit();
+            assert_eq!(kd_tree::squared_distance(implicit.nearest_by_coord(query).unwrap(), query).to_bits(), distances[order[0]].to_bits());
+            assert_eq!(implicit.within_radius(query, inf).len(), distances.iter().filter(|d| !d.is_nan()).count());
+        }
+    }
+
+    #[test]
+    fn test_height_values(){
+        let height = |points: Vec<[f64; 2]>| {
+            let by_ref = KdTreeBuilder::new().build_ref(&points).height();
+            let kd_tree = KdTree::from(points.clone());
+            assert_eq!(kd_tree.height(), by_ref);
+            assert_eq!(kd_tree.clone().freeze_implicit().height(), by_ref);
+            by_ref
+        };
+        assert_eq!(height(Vec::new()), 0);
+        assert_eq!(height(vec![[1., 2.]]), 1);
+        assert_eq!(height(vec![[1., 2.], [3., 4.]]), 2);
+        assert_eq!(height((0..7).map(|i| [i as f64, (i * 3 % 7) as f64]).collect()), 3);
+        assert_eq!(height((0..8).map(|i| [i as f64, 0.]).collect()), 4);
+
+        // Two nodes added under the root, then a chain of sorted points too short to be rebuilt
+        let mut kd_tree = KdTree::from(vec![[5., 5.]]);
+        kd_tree.add_point([1., 1.]);
+        kd_tree.add_point([9., 9.]);
+        assert_eq!(kd_tree.height(), 2);
+        let mut chain = KdTree::new();
+        for x in 0..4 {
+            chain.add_point([x as f64, x as f64]);
+        }
+        assert_eq!(chain.height(), 4);
+        assert_eq!(chain.depth_stats().max_leaf_depth + 1, chain.height());
+
+        // Buckets don't add levels
+        assert_eq!(KdTreeBuilder::new().leaf_size(8).build(random_points::<2>(8, 1069)).height(), 1);
+        assert_eq!(KdTreeBuilder::new().leaf_size(8).build(random_points::<2>(9, 1069)).height(), 2);
+    }
+
+    #[test]
+    fn test_node_size_independent_of_dimension(){
+        // Nodes hold an index and the split value, never a copy of the coordinates
+        assert_eq!(std::mem::size_of::<Node<2, f64>>(), std::mem::size_of::<Node<16, f64>>());
+        let small = KdTree::from(random_points::<2>(1000, 1087)).memory_usage();
+        let large = KdTree::from(random_points::<16>(1000, 1091)).memory_usage();
+        assert_eq!((small.node_count, small.node_bytes), (large.node_count, large.node_bytes));
+        // The coordinates of arrays are only stored in the points, the total is the points and the nodes
+        assert_eq!(large.other_bytes, std::mem::size_of::<KdTree<16, [f64; 16]>>());
+        assert_eq!(large.total(), std::mem::size_of::<KdTree<16, [f64; 16]>>() + 16 * 1000 * std::mem::size_of::<f64>() + ALLOCATION_OVERHEAD + large.node_bytes);
+        // Other points are copied once, in one column per axis
+        let tuples = KdTree::from(random_points::<3>(1000, 1091).into_iter().map(<(f64, f64, f64)>::from).collect::<Vec<_>>()).memory_usage();
+        assert_eq!(tuples.other_bytes, std::mem::size_of::<KdTree<3, (f64, f64, f64)>>() + 3 * (1000 * std::mem::size_of::<f64>() + ALLOCATION_OVERHEAD));
+    }
+
+    #[test]
+    fn test_add_or_update(){
+        let coords = random_points::<2>(200, 1093);
+        let mut kd_tree = KdTree::from(coords.iter().enumerate().map(|(i, c)| PairPoint::new(*c, i)).collect::<Vec<_>>());
+
+        for round in 1..20 {
+            let result = kd_tree.add_or_update(PairPoint::new(coords[17], 1000 + round));
+            let old = if round == 1 { 17 } else { 999 + round };
+            assert_eq!(result, UpsertResult::Updated { index: 17, old: PairPoint::new(coords[17], old) });
+            assert_eq!(kd_tree.size(), 200);
+            assert_eq!(kd_tree.nearest_by_coord(&coords[17]).unwrap().payload(), &(1000 + round));
+        }
+        assert_eq!(kd_tree.add_or_update(PairPoint::new([-1., -1.], 5)), UpsertResult::Inserted { index: 200 });
+        assert_eq!(kd_tree.add_or_update(PairPoint::new([-1., -1.], 6)), UpsertResult::Updated { index: 200, old: PairPoint::new([-1., -1.], 5) });
+        assert_eq!(kd_tree.size(), 201);
+        assert!(kd_tree.check_invariants().is_ok());
+
+        // Duplicates added before: the smallest index is updated
+        kd_tree.add_point(PairPoint::new(coords[3], 7));
+        assert!(matches!(kd_tree.add_or_update(PairPoint::new(coords[3], 8)), UpsertResult::Updated { index: 3, .. }));
+        // A tombstone is not updated, a zero of another sign is
+        kd_tree.remove_lazy(50);
+        assert_eq!(kd_tree.add_or_update(PairPoint::new(coords[50], 9)), UpsertResult::Inserted { index: 202 });
+        kd_tree.add_point(PairPoint::new([0., 5.], 10));
+        assert!(matches!(kd_tree.add_or_update(PairPoint::new([-0., 5.], 11)), UpsertResult::Updated { index: 203, .. }));
+        assert!(kd_tree.check_invariants().is_ok());
+        assert_eq!(kd_tree.nearest_by_coord(&[0., 5.]).unwrap().payload(), &11);
+    }
+
+    #[test]
+    fn test_point_by_value(){
+        // A custom point only implementing `to_kdtree_point`, the deprecated method is bridged
+        #[derive(Debug, Clone, Copy, PartialEq)]
+        struct Sample { x: f64, y: f64, z: f64 }
+        impl KdTreePoint<3> for Sample {
+            fn to_kdtree_point(&self) -> [f64; 3] {
+                [self.x, self.y, self.z]
+            }
+        }
+        // A point written against the old signature keeps working through the bridge
+        struct Legacy([f64; 3]);
+        impl KdTreePoint<3> for Legacy {
+            fn as_kdtree_point(&self) -> std::borrow::Cow<'_, [f64; 3]> {
+                std::borrow::Cow::Borrowed(&self.0)
+            }
+        }
+        let sample = Sample { x: 1., y: 2., z: 3. };
+        assert_eq!(sample.to_kdtree_point(), [1., 2., 3.]);
+        #[allow(deprecated)]
+        let old = sample.as_kdtree_point().into_owned();
+        assert_eq!(old, [1., 2., 3.]);
+        assert_eq!(Legacy([4., 5., 6.]).to_kdtree_point(), [4., 5., 6.]);
+        #[allow(deprecated)]
+        let borrowed = matches!(Legacy([4., 5., 6.]).as_kdtree_point(), std::borrow::Cow::Borrowed([4., 5., 6.]));
+        assert!(borrowed);
+
+        // Arrays, tuples, custom and legacy points give the same results
+        let arrays = random_points::<3>(2000, 1097);
+        let array_tree = KdTree::from(arrays.clone());
+        let tuple_tree: KdTree<3, (f64, f64, f64)> = arrays.iter().map(|&p| p.into()).collect();
+        let sample_tree: KdTree<3, Sample> = arrays.iter().map(|&[x, y, z]| Sample { x, y, z }).collect();
+        let legacy_tree: KdTree<3, Legacy> = arrays.iter().map(|&p| Legacy(p)).collect();
+        for query in random_points::<3>(100, 1103) {
+            let expected = array_tree.nearest_by_coord(&query).copied();
+            assert_eq!(tuple_tree.nearest_by_coord(&query).map(|p| p.to_kdtree_point()), expected);
+            assert_eq!(sample_tree.nearest_by_coord(&query).map(|p| p.to_kdtree_point()), expected);
+            assert_eq!(legacy_tree.nearest_by_coord(&query).map(|p| p.0), expected);
+
+            let expected: Vec<[f64; 3]> = array_tree.nearest_k(&query, 10).into_iter().copied().collect();
+            assert_eq!(tuple_tree.nearest_k(&query, 10).into_iter().map(|p| (*p).into()).collect::<Vec<[f64; 3]>>(), expected);
+            assert_eq!(sample_tree.nearest_k(&query, 10).into_iter().map(|p| p.to_kdtree_point()).collect::<Vec<_>>(), expected);
+
+            let expected: Vec<[f64; 3]> = array_tree.within_radius(&query, 0.1).into_iter().copied().collect();
+            assert_eq!(tuple_tree.within_radius(&query, 0.1).into_iter().map(|p| (*p).into()).collect::<Vec<[f64; 3]>>(), expected);
+            assert_eq!(legacy_tree.within_radius(&query, 0.1).into_iter().map(|p| p.0).collect::<Vec<_>>(), expected);
+        }
+    }
+
+    #[test]
+    fn test_linear_search(){
+        let mut points = random_points::<3>(1500, 1109);
+        // Duplicates and points at the same distance of the lattice queries exercise the ties
+        points.extend(points[..100].to_vec());
+        points.extend((0..200).map(|i| [(i % 7) as f64 * 10., (i / 7 % 5) as f64 * 10., (i / 35) as f64 * 10.]));
+        let linear = LinearSearch::from(points.clone());
+        let queries: Vec<[f64; 3]> = random_points::<3>(200, 1117).into_iter()
+            .chain((0..50).map(|i| [(i % 7) as f64 * 10. + 5., (i / 7 % 5) as f64 * 10., 5.]))
+            .collect();
+        let builders = [
+            KdTreeBuilder::new(),
+            KdTreeBuilder::new().leaf_size(8).axis_strategy(AxisStrategy::MaxSpread),
+            KdTreeBuilder::new().leaf_size(4).split_rule(SplitRule::SlidingMidpoint),
+        ];
+        for builder in builders {
+            let mut kd_tree = builder.build(points[..800].to_vec());
+            for point in &points[800..] {
+                kd_tree.add_point(*point);
+            }
+            for query in &queries {
+                // Points at the same distance differ, so the values also check the tie rule
+                assert_eq!(kd_tree.nearest_by_coord(query), linear.nearest_by_coord(query));
+                assert_eq!(kd_tree.nearest_k(query, 20), linear.nearest_k(query, 20));
+                let mut found = kd_tree.within_radius(query, 12.);
+                found.sort_by(|a, b| a.partial_cmp(b).unwrap());
+                let mut expected = linear.within_radius(query, 12.);
+                expected.sort_by(|a, b| a.partial_cmp(b).unwrap());
+                assert_eq!(found, expected);
+                let (min, max) = (query.map(|x| x - 10.), query.map(|x| x + 10.));
+                let mut found = kd_tree.points_in_aabb(&min, &max);
+                found.sort_by(|a, b| a.partial_cmp(b).unwrap());
+                let mut expected = linear.points_in_aabb(&min, &max);
+                expected.sort_by(|a, b| a.partial_cmp(b).unwrap());
+                assert_eq!(found, expected);
+            }
+        }
+
+        let mut linear = LinearSearch::<2, [f64; 2]>::new();
+        assert!(linear.is_empty());
+        assert_eq!(linear.nearest_by_coord(&[0., 0.]), None);
+        assert!(linear.nearest_k(&[0., 0.], 3).is_empty());
+        linear.add_point([1., 1.]);
+        linear.add_point([-1., -1.]);
+        linear.add_point([3., 3.]);
+        assert_eq!(linear.size(), 3);
+        // Ties give the smallest index, within_radius keeps the index order
+        assert_eq!(linear.nearest(&[0., 0.]), Some(&[1., 1.]));
+        assert_eq!(linear.nearest_k(&[0., 0.], 5), vec![&[1., 1.], &[-1., -1.], &[3., 3.]]);
+        assert_eq!(linear.within_radius(&[2., 2.], 1.5), vec![&[1., 1.], &[3., 3.]]);
+        assert!(linear.within_radius(&[2., 2.], -1.).is_empty());
+        assert!(linear.points_in_aabb(&[2., 2.], &[0., 0.]).is_empty());
+        assert_eq!(linear.points(), &[[1., 1.], [-1., -1.], [3., 3.]]);
+    }
+
+    #[test]
+    fn test_point_access(){
+        let points = random_points::<3>(500, 1123);
+        let mut kd_tree = KdTreeBuilder::new().leaf_size(4).build(points[..300].to_vec());
+        for point in &points[300..] {
+            kd_tree.add_point(*point);
+        }
+        let tuples: KdTree<3, (f64, f64, f64)> = points.iter().map(|&p| p.into()).collect();
+        for (i, point) in points.iter().enumerate() {
+            assert_eq!(kd_tree.get(i), Some(point));
+            assert_eq!(kd_tree[i], *point);
+            assert_eq!(kd_tree.position(i), Some(kd_tree.get(i).unwrap().to_kdtree_point()));
+            assert_eq!(tuples.position(i), Some(tuples.get(i).unwrap().to_kdtree_point()));
+            assert_eq!(kd_tree.find_index(point), Some(i));
+        }
+        assert_eq!(kd_tree.points(), &points[..]);
+        assert_eq!(kd_tree.get(500), None);
+        assert_eq!(kd_tree.position(500), None);
+        assert_eq!(kd_tree.find_index(&[-1., -1., -1.]), None);
+
+        // Duplicates give the smallest index, tombstones are skipped
+        kd_tree.add_point(points[7]);
+        assert_eq!(kd_tree.find_index(&points[7]), Some(7));
+        kd_tree.remove_lazy(7);
+        assert_eq!(kd_tree.get(7), Some(&points[7]));
+        assert_eq!(kd_tree.find_index(&points[7]), Some(500));
+
+        // Moving a point through its guard updates its position
+        *kd_tree.get_mut(3).unwrap() = [-5., 0., 0.];
+        assert_eq!(kd_tree.position(3), Some([-5., 0., 0.]));
+        assert_eq!(kd_tree.find_index(&[-5., 0., 0.]), Some(3));
+        assert_eq!(kd_tree.find_index(&points[3]), None);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_index_out_of_range(){
+        let kd_tree = KdTree::from(random_points::<2>(10, 1129));
+        let _ = kd_tree[10];
+    }
+
+    #[test]
+    fn test_nearest_k_with_metric(){
+        let points = random_points::<3>(1000, 1151);
+        let kd_tree = KdTreeBuilder::new().leaf_size(4).build(points.clone());
+        let manhattan = |p: &[f64; 3], q: &[f64; 3]| Metric::<3>::distance(&Manhattan, p, q);
+        for query in random_points::<3>(50, 1153) {
+            let found = kd_tree.nearest_k_with_metric(&query, 10, &Manhattan);
+            let mut expected = points.clone();
+            expected.sort_by(|a, b| manhattan(a, &query).total_cmp(&manhattan(b, &query)));
+            assert_eq!(found.iter().map(|p| manhattan(p, &query)).collect::<Vec<_>>(), expected[..10].iter().map(|p| manhattan(p, &query)).collect::<Vec<_>>());
+        }
+        assert!(kd_tree.nearest_k_with_metric(&[0.; 3], 0, &Manhattan).is_empty());
+        assert_eq!(kd_tree.nearest_k_with_metric(&[0.; 3], 2000, &Manhattan).len(), 1000);
+    }
+
+    #[test]
+    fn test_extreme_coordinates(){
+        // Around 1e200 every plain squared distance overflows to infinity
+        let points: Vec<[f64; 3]> = random_points::<3>(2000, 1163).into_iter().map(|p| p.map(|x| (x - 50.) * 1e198)).collect();
+        let queries: Vec<[f64; 3]> = random_points::<3>(100, 1171).into_iter().map(|p| p.map(|x| (x - 50.) * 1e198)).collect();
+        assert_eq!(kd_tree::squared_distance(&points[0], &queries[0]), f64::INFINITY);
+
+        let robust = |p: &[f64; 3], q: &[f64; 3]| Metric::<3>::distance(&RobustEuclidean, p, q);
+        let kd_tree = KdTree::from(points.clone());
+        for query in &queries {
+            let mut expected = points.clone();
+            expected.sort_by(|a, b| robust(a, query).total_cmp(&robust(b, query)));
+            assert!(robust(&expected[0], query).is_finite());
+            assert_eq!(kd_tree.nearest_by_coord(query), Some(&expected[0]));
+            assert_eq!(kd_tree.nearest_k(query, 5), expected[..5].iter().collect::<Vec<_>>());
+
+            let radius = robust(&expected[20], query);
+            let mut found = kd_tree.within_radius(query, radius);
+            found.sort_by(|a, b| robust(a, query).total_cmp(&robust(b, query)));
+            assert_eq!(found, expected[..=20].iter().collect::<Vec<_>>());
+        }
+
+        // The batched, workspace, coherent and linear queries switch too
+        let nearest: Vec<Option<usize>> = queries.iter().map(|query| {
+            (0..points.len()).reduce(|best, i| if robust(&points[i], query) < robust(&points[best], query) { i } else { best })
+        }).collect();
+        let nearest_k: Vec<Vec<usize>> = queries.iter().map(|query| {
+            let mut by_distance: Vec<usize> = (0..points.len()).collect();
+            by_distance.sort_by(|&a, &b| robust(&points[a], query).total_cmp(&robust(&points[b], query)));
+            by_distance.truncate(5);
+            by_distance
+        }).collect();
+        assert_eq!(kd_tree.nearest_batch(&queries), nearest);
+        assert_eq!(kd_tree.nearest_batch_coherent(&queries), nearest);
+        assert_eq!(kd_tree.nearest_k_batch(&queries, 5), nearest_k);
+        #[cfg(feature = "rayon")]
+        {
+            assert_eq!(kd_tree.nearest_batch_par(&queries), nearest);
+            assert_eq!(kd_tree.nearest_k_batch_par(&queries, 5), nearest_k);
+        }
+        let linear = LinearSearch::from(points.clone());
+        let mut map: KdTreeMap<3, usize> = points.iter().copied().zip(0..).collect();
+        let mut ws = QueryWorkspace::new();
+        for (t, query) in queries.iter().enumerate() {
+            assert_eq!(kd_tree.nearest_k_with(&mut ws, query, 5), nearest_k[t]);
+            let expected = &points[nearest[t].unwrap()];
+            assert_eq!(linear.nearest_by_coord(query), Some(expected));
+            assert_eq!(linear.nearest_k(query, 5), nearest_k[t].iter().map(|&i| &points[i]).collect::<Vec<_>>());
+            assert_eq!(map.get_nearest_mut(query).map(|(coord, value)| (*coord, *value)), Some((*expected, nearest[t].unwrap())));
+
+            let radius = robust(&points[nearest_k[t][4]], query);
+            let mut found = kd_tree.within_radius_with(&mut ws, query, radius).to_vec();
+            found.sort_by(|&a, &b| robust(&points[a], query).total_cmp(&robust(&points[b], query)));
+            assert_eq!(found, nearest_k[t]);
+            assert_eq!(sorted(linear.within_radius(query, radius)), sorted(nearest_k[t].iter().map(|&i| &points[i]).collect()));
+        }
+
+        // Sentinels at the largest finite value are ranked like any other point
+        let mut kd_tree = KdTree::from(vec![[f64::MAX, 0.], [-f64::MAX, 0.], [0., f64::MAX]]);
+        assert_eq!(kd_tree.nearest_by_coord(&[1e300, 0.]), Some(&[f64::MAX, 0.]));
+        assert_eq!(kd_tree.nearest_by_coord(&[-1e300, 1e300]), Some(&[-f64::MAX, 0.]));
+        kd_tree.add_point([1., 1.]);
+        assert_eq!(kd_tree.nearest_by_coord(&[1e10, 1e10]), Some(&[1., 1.]));
+        assert_eq!(kd_tree.nearest_k(&[0., 1e308], 2), vec![&[0., f64::MAX], &[1., 1.]]);
+
+        // The robust distance matches the plain one where it doesn't overflow
+        for (p, q) in random_points::<3>(100, 1181).iter().zip(random_points::<3>(100, 1187).iter()) {
+            let plain = Metric::<3>::distance(&Euclidean, p, q);
+            assert!((robust(p, q) - plain).abs() <= plain * 1e-15);
+        }
+        assert_eq!(robust(&[1., 2., 3.], &[1., 2., 3.]), 0.);
+        assert_eq!(robust(&[f64::INFINITY, 0., 0.], &[0., 0., 0.]), f64::INFINITY);
+        assert!(robust(&[f64::INFINITY, 0., 0.], &[f64::INFINITY, 0., 0.]).is_nan());
+    }
+
+    #[test]
+    fn test_remove_lazy_held_indices(){
+        // Indices held by the caller, updated whenever a removal compacts the tombstones
+        let points = random_points::<2>(3, 1193);
+        let mut kd_tree = KdTree::from(points.clone());
+        let mut held: Vec<usize> = (0..3).collect();
+        // With 3 points, the first removal already compacts
+        match kd_tree.remove_lazy(0) {
+            LazyRemoval::Compacted { mapping } => {
+                assert_eq!(mapping, vec![REMOVED_INDEX, 0, 1]);
+                held = held.into_iter().map(|i| mapping[i]).collect();
+            }
+            removal => panic!("no compaction: {removal:?}"),
+        }
+        assert_eq!(held, vec![REMOVED_INDEX, 0, 1]);
+        for (i, index) in held.iter().enumerate().skip(1) {
+            assert_eq!(kd_tree.get(*index), Some(&points[i]));
+        }
+
+        // Over many removals, the held indices keep designating the same points
+        let points = random_points::<2>(500, 1201);
+        let mut kd_tree = KdTree::from(points.clone());
+        let mut held: Vec<usize> = (0..points.len()).collect();
+        let mut compactions = 0;
+        for original in (0..points.len()).step_by(3) {
+            match kd_tree.remove_lazy(held[original]) {
+                LazyRemoval::NotFound => panic!("point {original} not found"),
+                LazyRemoval::Buried => {}
+                LazyRemoval::Compacted { mapping } => {
+                    compactions += 1;
+                    for index in held.iter_mut().filter(|i| **i != REMOVED_INDEX) {
+                        *index = mapping[*index];
+                    }
+                }
+            }
+            held[original] = REMOVED_INDEX;
+            for (i, index) in held.iter().enumerate().filter(|(_, i)| **i != REMOVED_INDEX) {
+                assert_eq!(kd_tree[*index], points[i]);
+                assert_eq!(kd_tree.position(*index), Some(points[i]));
+            }
+        }
+        assert!(compactions > 0);
+        assert_eq!(kd_tree.remove_lazy(kd_tree.points.len()), LazyRemoval::NotFound);
+    }
+
+    #[test]
+    fn test_binary_tombstones(){
+        let points = random_points::<3>(400, 1213);
+        let mut kd_tree = KdTreeBuilder::new().leaf_size(4).build(points.clone());
+        assert_eq!(kd_tree.remove_lazy(17), LazyRemoval::Buried);
+        assert_eq!(kd_tree.remove_lazy(250), LazyRemoval::Buried);
+
+        let mut bytes = Vec::new();
+        kd_tree.write_binary(&mut bytes).unwrap();
+        let read = KdTree::<3, [f64; 3]>::read_binary(&bytes[..]).unwrap();
+        assert_eq!((read.size(), read.tombstone_count()), (kd_tree.size(), 0));
+        assert!(!read.points.contains(&points[17]) && !read.points.contains(&points[250]));
+        assert_eq!(read.find_index(&points[17]), None);
+        for query in random_points::<3>(30, 1217) {
+            assert_eq!(read.nearest_by_coord(&query), kd_tree.nearest_by_coord(&query));
+            assert_eq!(read.nearest_k(&query, 5), kd_tree.nearest_k(&query, 5));
+        }
+        // The dumped tree itself keeps its tombstones
+        assert_eq!(kd_tree.tombstone_count(), 2);
+        let mut compacted = kd_tree.clone();
+        compacted.compact_tombstones();
+        assert!(read.structurally_equal(&compacted));
+    }
+
+    #[test]
+    fn test_shrink_to_fit_tombstones(){
+        let points = random_points::<3>(1000, 1223);
+        let mut kd_tree = KdTreeBuilder::new().leaf_size(4).build(points.clone());
+        // Below the compaction threshold, the tombstones stay until the storage is compacted
+        let removed: Vec<usize> = (0..points.len()).step_by(9).collect();
+        for i in &removed {
+            assert_eq!(kd_tree.remove_lazy(*i), LazyRemoval::Buried);
+        }
+        let live: Vec<[f64; 3]> = (0..points.len()).filter(|i| i % 9 != 0).map(|i| points[i]).collect();
+        let usage = kd_tree.memory_usage();
+
+        let mapping = kd_tree.shrink_to_fit();
+        assert_eq!(mapping.len(), points.len());
+        for (old, new) in mapping.iter().enumerate() {
+            if old % 9 == 0 {
+                assert_eq!(*new, REMOVED_INDEX);
+            } else {
+                assert_eq!(kd_tree[*new], points[old]);
+            }
+        }
+        assert_eq!((kd_tree.tombstone_count(), kd_tree.size()), (0, live.len()));
+        assert_eq!(kd_tree.points, live);
+        assert_eq!(kd_tree.capacity(), live.len());
+        assert!(kd_tree.memory_usage().total() < usage.total());
+        assert_eq!(kd_tree.check_invariants(), Ok(()));
+
+        let reference = KdTree::from(live.clone());
+        for query in random_points::<3>(30, 1229) {
+            assert_eq!(kd_tree.nearest_by_coord(&query), reference.nearest_by_coord(&query));
+            assert_eq!(kd_tree.nearest_k(&query, 10), reference.nearest_k(&query, 10));
+        }
+    }
+
+    #[test]
+    fn test_geojson_nesting_depth(){
+        let read = |input: &str| KdTree::from_geojson_points(input, GeoJsonOptions::new()).err();
+        // Deep nesting is refused instead of overflowing the stack
+        assert!(matches!(read(&"[".repeat(1_000_000)), Some(GeoJsonError::InvalidJson { offset: 128, .. })));
+        assert!(matches!(read(&"{\"a\":".repeat(1_000_000)), Some(GeoJsonError::InvalidJson { .. })));
+
+        // Nesting up to the limit is fine, properties included
+        let nested = format!("{}1{}", "[".repeat(120), "]".repeat(120));
+        let geojson = format!(r#"{{"type": "FeatureCollection", "features": [
+            {{"type": "Feature", "geometry": {{"type": "Point", "coordinates": [1, 2]}}, "properties": {{"deep": {nested}}}}}
+        ]}}"#);
+        let kd_tree = KdTree::from_geojson_points(&geojson, GeoJsonOptions::new()).unwrap();
+        assert_eq!(kd_tree.size(), 1);
+        let too_deep = geojson.replace(&nested, &format!("{}1{}", "[".repeat(130), "]".repeat(130)));
+        assert!(matches!(read(&too_deep), Some(GeoJsonError::InvalidJson { .. })));
+    }
+
+    #[test]
+    fn test_parts_deep_chain(){
+        // A chain of a million nodes, each one being the right child of the previous one
+        let n = 1_000_000;
+        let chain = |n: usize| KdTreeParts {
+            points: (0..n).map(|i| [i as f64]).collect::<Vec<[f64; 1]>>(),
+            nodes: (0..n).map(|i| PartsNode { index: i, axis: 0, split: i as f64, bucket: Vec::new(), left: false, right: i + 1 < n }).collect(),
+            builder: KdTreeBuilder::new(),
+        };
+        // A valid chain is restored and checked without overflowing the stack
+        let kd_tree = KdTree::from_parts(chain(n)).unwrap();
+        assert_eq!((kd_tree.size(), kd_tree.height()), (n, n));
+        assert_eq!(kd_tree.nearest_by_coord_with_metric(&[12.2], &Euclidean), Some(&[12.]));
+        let mut bytes = Vec::new();
+        kd_tree.write_binary(&mut bytes).unwrap();
+        let read = KdTree::<1, [f64; 1]>::read_binary(&bytes[..]).unwrap();
+        assert!(read.structurally_equal(&kd_tree));
+
+        // A forged one is refused, with the path of the offending node
+        let mut forged = chain(n);
+        forged.points[n - 1] = [-1.];
+        match KdTree::from_parts(forged) {
+            Err(InvariantViolation::MisplacedPoint { path, index, axis: 0 }) => {
+                assert_eq!((path.len(), index), (n - 1, n - 1));
+                assert!(path.chars().all(|c| c == 'R'));
+            }
+            result => panic!("not refused: {:?}", result.err()),
+        }
+        let mut truncated = chain(n);
+        truncated.nodes.pop();
+        assert_eq!(KdTree::from_parts(truncated).err(), Some(InvariantViolation::TruncatedStructure { nodes: n - 1 }));
+        let mut forged = chain(1000);
+        forged.nodes[999].axis = 1;
+        assert_eq!(KdTree::from_parts(forged).err(), Some(InvariantViolation::AxisOutOfRange { path: "R".repeat(999), axis: 1, dim: 1 }));
+        let mut forged = chain(1000);
+        forged.nodes[500].left = true;
+        forged.nodes[998].right = true;
+        assert!(matches!(KdTree::from_parts(forged), Err(InvariantViolation::TruncatedStructure { .. })));
+    }
+
+    #[test]
+    fn test_parts_infinite_coordinates(){
+        // Trees of points with infinite coordinates break the finiteness invariant but are placed
+        // like the other ones, they round trip
+        let mut points = random_points::<2>(200, 1231);
+        points.extend([[f64::INFINITY, 0.], [f64::NEG_INFINITY, 5.], [3., f64::INFINITY], [f64::INFINITY, f64::NEG_INFINITY]]);
+        let kd_tree = KdTreeBuilder::new().leaf_size(4).build(points.clone());
+        assert!(matches!(kd_tree.check_invariants(), Err(InvariantViolation::NonFinitePosition { .. })));
+        assert_eq!(kd_tree.check_structure(), Ok(()));
+        let restored = KdTree::from_parts(kd_tree.clone().into_parts()).unwrap();
+        assert!(restored.structurally_equal(&kd_tree));
+        let mut bytes = Vec::new();
+        kd_tree.write_binary(&mut bytes).unwrap();
+        assert!(KdTree::<2, [f64; 2]>::read_binary(&bytes[..]).unwrap().structurally_equal(&kd_tree));
+
+        // NaN is still refused
+        let mut parts = kd_tree.into_parts();
+        parts.points[parts.nodes[0].index] = [f64::NAN, 0.];
+        assert!(matches!(KdTree::from_parts(parts), Err(InvariantViolation::NonFinitePosition { axis: 0, .. })));
+    }
+
+    #[test]
+    fn test_kd_tree_equality_nan(){
+        // Points equal by their id only, so that points with NaN coordinates can be equal
+        #[derive(Debug, Clone)]
+        struct Tagged {
+            coords: [f64; 2],
+            id: usize,
+        }
+        impl PartialEq for Tagged {
+            fn eq(&self, other: &Self) -> bool {
+                self.id == other.id
+            }
+        }
+        impl KdTreePoint<2> for Tagged {
+            fn to_kdtree_point(&self) -> [f64; 2] {
+                self.coords
+            }
+        }
+
+        let mut points: Vec<Tagged> = random_points::<2>(300, 1233).into_iter().enumerate()
+            .map(|(id, coords)| Tagged { coords, id })
+            .collect();
+        for i in (0..300).step_by(7) {
+            points[i].coords[i % 2] = f64::NAN;
+        }
+        for i in (3..300).step_by(11) {
+            points[i].coords = points[i - 1].coords;
+        }
+        let mut shuffled = points.clone();
+        shuffled.reverse();
+        shuffled.swap(5, 200);
+        shuffled.swap(17, 90);
+        let kd_tree = KdTree::from(points.clone());
+        assert!(kd_tree == KdTree::from(shuffled.clone()));
+        shuffled[42].id = 1000;
+        assert!(kd_tree != KdTree::from(shuffled));
+
+        // Signed zeros are equal coordinates
+        let lhs = KdTree::from(vec![[-0., 1.], [2., 0.], [f64::INFINITY, 3.]]);
+        let rhs = KdTree::from(vec![[f64::INFINITY, 3.], [0., 1.], [2., -0.]]);
+        assert!(lhs == rhs);
+    }
+
+    #[test]
+    fn test_dyn_kd_tree_duplicates(){
+        // Coordinates on a small grid, so that many points are at the same distance of the queries
+        let points: Vec<[f64; 3]> = random_points::<3>(600, 1235).into_iter()
+            .map(|p| p.map(|x| (x / 25.).floor()))
+            .collect();
+        let kd_tree = KdTree::from(points.clone());
+        let dyn_tree = DynKdTree::from_rows(3, points.iter().map(|p| p.to_vec()).collect()).unwrap();
+        let mut queries: Vec<[f64; 3]> = random_points::<3>(60, 1237).into_iter().map(|p| p.map(|x| (x / 25.).floor())).collect();
+        queries.extend([[0.5, 1., 2.], [f64::INFINITY, 0., 1.], [1., f64::NEG_INFINITY, f64::INFINITY]]);
+        for query in queries {
+            for k in [1, 5, 40] {
+                assert_eq!(dyn_tree.nearest_k(&query, k).unwrap(), kd_tree.nearest_k_batch(&[query], k)[0], "{query:?} {k}");
+            }
+            assert_eq!(dyn_tree.nearest(&query).unwrap(), kd_tree.nearest_k_batch(&[query], 1)[0].first().copied());
+            let mut found = dyn_tree.within_radius(&query, 1.5).unwrap();
+            found.sort();
+            let mut expected: Vec<usize> = (0..points.len()).filter(|&i| squared_distance(&points[i], &query) <= 2.25).collect();
+            expected.sort();
+            assert_eq!(found, expected);
+        }
+
+        // NaN coordinates have a place in the construction, the other points are still found
+        let mut rows: Vec<Vec<f64>> = points.iter().map(|p| p.to_vec()).collect();
+        rows[10][0] = f64::NAN;
+        rows[20][1] = f64::NAN;
+        let with_nan = DynKdTree::from_rows(3, rows.clone()).unwrap();
+        assert_eq!(with_nan.len(), 600);
+        assert_eq!(DynKdTree::from_rows(3, rows).unwrap().nearest_k(&[3., 3., 3.], 20).unwrap(), with_nan.nearest_k(&[3., 3., 3.], 20).unwrap());
+        assert_eq!(with_nan.nearest(points[30].as_slice()).unwrap().map(|i| with_nan.point(i).unwrap()), Some(points[30].as_slice()));
+    }
+
+    #[test]
+    fn test_queries_on_deep_chain(){
+        // A chain of a million nodes restored by `from_parts`, each one being the right child of the previous one
+        let n = 1_000_000;
+        let parts = KdTreeParts {
+            points: (0..n).map(|i| [i as f64]).collect::<Vec<[f64; 1]>>(),
+            nodes: (0..n).map(|i| PartsNode { index: i, axis: 0, split: i as f64, bucket: Vec::new(), left: false, right: i + 1 < n }).collect(),
+            builder: KdTreeBuilder::new(),
+        };
+        let mut kd_tree = KdTree::from_parts(parts).unwrap();
+        assert_eq!(kd_tree.height(), n);
+
+        // The queries walk the whole chain without overflowing the stack
+        assert_eq!(kd_tree.nearest_by_coord(&[999_998.7]), Some(&[999_999.]));
+        assert_eq!(kd_tree.nearest_by_coord(&[-5.]), Some(&[0.]));
+        assert_eq!(kd_tree.within_radius(&[999_998.], 1.), vec![&[999_997.], &[999_998.], &[999_999.]]);
+
+        // So do the removals, which repair the chain and shift the indices
+        assert_eq!(kd_tree.remove_within_radius(&[999_999.], 0.5), vec![[999_999.]]);
+        assert_eq!(kd_tree.size(), n - 1);
+        assert_eq!(kd_tree.nearest_by_coord(&[2e6]), Some(&[999_998.]));
+        assert_eq!(kd_tree.check_invariants(), Ok(()));
+
+        // And every other traversal
+        assert_eq!(kd_tree.nearest_k(&[999_997.2], 3), vec![&[999_997.], &[999_998.], &[999_996.]]);
+        assert_eq!(kd_tree.points_in_aabb(&[999_990.], &[999_992.]), vec![&[999_990.], &[999_991.], &[999_992.]]);
+        assert_eq!(kd_tree.nearest_batch_coherent(&[[999_997.7], [-5.], [500_000.2]]), vec![Some(999_998), Some(0), Some(500_000)]);
+        assert_eq!(kd_tree.nearest_by_coord_with_metric(&[999_997.7], &Manhattan), Some(&[999_998.]));
+        assert_eq!(kd_tree.nearest_k_with_metric(&[999_997.2], 2, &Chebyshev), vec![&[999_997.], &[999_998.]]);
+        assert_eq!(kd_tree.within_radius_with_metric(&[999_997.], 1., &Manhattan).len(), 3);
+        assert_eq!(kd_tree.nearest_k_batch(&[[999_998.]], 1), vec![vec![999_998]]);
+        assert_eq!(kd_tree.nearest_k_with(&mut QueryWorkspace::new(), &[999_998.], 1), [999_998]);
+        // The splits follow the points without a rebuild
+        assert!(!kd_tree.transform_points(|p| p[0] += 1.));
+        assert_eq!((kd_tree.height(), kd_tree.nearest_by_coord(&[2e6])), (n - 1, Some(&[999_999.])));
+        assert_eq!(kd_tree.crop_to_aabb(&[1.], &[999_990.]).len(), 9);
+        assert_eq!(kd_tree.size(), n - 10);
+        // Moving a point rebuilds the end of the chain below it
+        *kd_tree.get_mut(500_000).unwrap() = [-1.];
+        assert!(kd_tree.height() < 500_100);
+        assert_eq!(kd_tree.nearest_by_coord(&[-0.6]), Some(&[-1.]));
+        assert_eq!(kd_tree.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn test_extreme_coordinates_other_trees(){
+        // The borrowing, implicit, dynamic and C trees switch to `RobustEuclidean` like `KdTree`
+        let points: Vec<[f64; 3]> = random_points::<3>(1000, 1249).into_iter().map(|p| p.map(|x| (x - 50.) * 1e198)).collect();
+        let queries: Vec<[f64; 3]> = random_points::<3>(50, 1259).into_iter().map(|p| p.map(|x| (x - 50.) * 1e198)).collect();
+        assert_eq!(kd_tree::squared_distance(&points[0], &queries[0]), f64::INFINITY);
+
+        let robust = |p: &[f64; 3], q: &[f64; 3]| Metric::<3>::distance(&RobustEuclidean, p, q);
+        let kd_tree_ref = KdTreeRef::from_slice(&points);
+        let implicit = KdTree::from(points.clone()).freeze_implicit();
+        let dyn_tree = DynKdTree::from_rows(3, points.iter().map(|p| p.to_vec()).collect()).unwrap();
+        for query in &queries {
+            let mut by_distance: Vec<usize> = (0..points.len()).collect();
+            by_distance.sort_by(|&a, &b| robust(&points[a], query).total_cmp(&robust(&points[b], query)).then(a.cmp(&b)));
+            let expected: Vec<&[f64; 3]> = by_distance[..5].iter().map(|&i| &points[i]).collect();
+            let radius = robust(&points[by_distance[20]], query);
+            let within: Vec<&[f64; 3]> = sorted(by_distance[..=20].iter().map(|&i| &points[i]).collect());
+
+            assert_eq!(kd_tree_ref.nearest_by_coord(query), Some(expected[0]));
+            assert_eq!(kd_tree_ref.nearest(query), Some(expected[0]));
+            assert_eq!(kd_tree_ref.nearest_k(query, 5), expected);
+            assert_eq!(sorted(kd_tree_ref.within_radius(query, radius)), within);
+
+            assert_eq!(implicit.nearest_by_coord(query), Some(expected[0]));
+            assert_eq!(implicit.nearest(query), Some(expected[0]));
+            assert_eq!(implicit.nearest_k(query, 5), expected);
+            assert_eq!(sorted(implicit.within_radius(query, radius)), within);
+
+            assert_eq!(dyn_tree.nearest(query).unwrap(), Some(by_distance[0]));
+            assert_eq!(dyn_tree.nearest_k(query, 5).unwrap(), by_distance[..5]);
+            let found: Vec<&[f64; 3]> = dyn_tree.within_radius(query, radius).unwrap().into_iter().map(|i| &points[i]).collect();
+            assert_eq!(sorted(found), within);
+        }
+
+        #[cfg(feature = "ffi")]
+        unsafe {
+            use crate::data_struct_utils::kd_tree::kd_tree_ffi::*;
+            let coords: Vec<f64> = points.iter().flatten().copied().collect();
+            let handle = kdtree3_new(coords.as_ptr(), points.len());
+            for query in &queries {
+                let expected = (0..points.len()).reduce(|best, i| if robust(&points[i], query) < robust(&points[best], query) { i } else { best });
+                let mut index = usize::MAX;
+                assert!(kdtree3_nearest(handle, query[0], query[1], query[2], &mut index));
+                assert_eq!(Some(index), expected);
+            }
+            kdtree3_free(handle);
+        }
+    }
+}
@@ -1 +1,8 @@
-pub mod kd_tree;
\ No newline at end of file
+pub mod kd_tree;
+
+///Former location of the point traits, they are now defined once in `kd_tree::kd_tree_traits`
+/// for every scalar type and re-exported from the crate root
+#[deprecated(note = "use `my_rust_utils::KdTreePoint` or `data_struct_utils::kd_tree::KdTreePoint` instead")]
+pub mod kd_tree_traits {
+    pub use super::kd_tree::kd_tree_traits::{KdTreePoint, KdTreePointMut};
+}
@@ -6,3 +6,5 @@
 
 ///Some data struct that I have implemented
 pub mod data_struct_utils;
+
+pub use data_struct_utils::kd_tree::{KdTreePoint, KdTreePointMut};